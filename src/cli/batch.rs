@@ -0,0 +1,88 @@
+use crate::error::CliError;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// A `.toml` manifest: `repos = ["../service-a", "../service-b"]`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TomlManifest {
+    repos: Vec<PathBuf>,
+}
+
+/// The repository paths listed in `manifest`, either as a TOML `repos` array (for
+/// `.toml` files) or one per line, skipping blank lines and `#` comments. Relative
+/// paths are resolved against the manifest's own directory.
+pub fn load_manifest(manifest: &Path) -> Result<Vec<PathBuf>, CliError> {
+    let content = std::fs::read_to_string(manifest).map_err(|e| CliError::FileSystem {
+        message: format!("Failed to read manifest {}: {}", manifest.display(), e),
+        suggestion: "Check that the --manifest path exists and is readable".to_string(),
+    })?;
+
+    let repos = if manifest.extension().is_some_and(|ext| ext == "toml") {
+        toml::from_str::<TomlManifest>(&content)
+            .map_err(|e| CliError::FileSystem {
+                message: format!("Invalid manifest {}: {}", manifest.display(), e),
+                suggestion: "List the repositories as repos = [\"path\", ...]".to_string(),
+            })?
+            .repos
+    } else {
+        content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(PathBuf::from)
+            .collect()
+    };
+
+    if repos.is_empty() {
+        return Err(CliError::FileSystem {
+            message: format!("Manifest {} lists no repositories", manifest.display()),
+            suggestion: "Add one repository path per line".to_string(),
+        });
+    }
+
+    let base = manifest.parent().unwrap_or(Path::new(""));
+    Ok(repos.into_iter().map(|repo| base.join(repo)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_load_manifest_from_lines_and_toml() {
+        let dir = TempDir::new().unwrap();
+        let lines = dir.path().join("repos.txt");
+        std::fs::write(
+            &lines,
+            "# platform repos\nservice-a\n\n  /srv/service-b  \n",
+        )
+        .unwrap();
+        let toml = dir.path().join("repos.toml");
+        std::fs::write(&toml, "repos = [\"service-a\", \"/srv/service-b\"]\n").unwrap();
+
+        let expected = vec![
+            dir.path().join("service-a"),
+            PathBuf::from("/srv/service-b"),
+        ];
+        assert_eq!(load_manifest(&lines).unwrap(), expected);
+        assert_eq!(load_manifest(&toml).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_load_manifest_rejects_empty_or_missing_file() {
+        let dir = TempDir::new().unwrap();
+        let empty = dir.path().join("repos.txt");
+        std::fs::write(&empty, "# nothing yet\n").unwrap();
+
+        assert!(matches!(
+            load_manifest(&empty),
+            Err(CliError::FileSystem { .. })
+        ));
+        assert!(matches!(
+            load_manifest(&dir.path().join("missing.txt")),
+            Err(CliError::FileSystem { .. })
+        ));
+    }
+}
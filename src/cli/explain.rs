@@ -0,0 +1,260 @@
+use crate::config::*;
+use crate::git::subtree;
+use crate::lockfile::{LOCKFILE_COMMIT_MESSAGE, LOCKFILE_NAME};
+use crate::types::CommandContext;
+
+/// Operations that `explain` can describe
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Init,
+    Update,
+    Remove,
+}
+
+impl Operation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operation::Init => "init",
+            Operation::Update => "update",
+            Operation::Remove => "remove",
+        }
+    }
+}
+
+/// One line of the generated script: a git invocation or an explanatory comment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    Git(Vec<String>),
+    Comment(String),
+}
+
+fn git(args: &[&str]) -> Step {
+    Step::Git(args.iter().map(|arg| arg.to_string()).collect())
+}
+
+fn comment(text: &str) -> Step {
+    Step::Comment(text.to_string())
+}
+
+fn upstream_branch(context: &CommandContext) -> &str {
+    context
+        .upstream_branch
+        .as_deref()
+        .unwrap_or(CLAUDE_UPSTREAM_BRANCH)
+}
+
+fn upstream_ref(context: &CommandContext) -> String {
+    remote_branch_ref(&context.remote_name, upstream_branch(context))
+}
+
+/// The fetch init and update run to bring in `branch` of the upstream remote
+pub fn fetch_args(context: &CommandContext, branch: &str) -> Vec<String> {
+    let mut args = vec!["fetch".to_string()];
+    if !context.full_history {
+        args.push("--depth=1".to_string());
+    }
+    args.push(context.remote_name.clone());
+    if !context.fetch_all {
+        args.push(branch_fetch_refspec(&context.remote_name, branch));
+    }
+    args
+}
+
+fn fetch_step(context: &CommandContext) -> Step {
+    Step::Git(fetch_args(context, upstream_branch(context)))
+}
+
+fn default_branch_note(context: &CommandContext) -> String {
+    match &context.upstream_branch {
+        Some(branch) => format!("'{}' is the configured upstream branch", branch),
+        None => format!(
+            "'{}' stands for the upstream default branch from 'git ls-remote --symref {} HEAD'",
+            CLAUDE_UPSTREAM_BRANCH, context.remote_name
+        ),
+    }
+}
+
+/// Recording the synced revisions, which init and update finish with
+pub fn lockfile_steps() -> Vec<Step> {
+    vec![
+        comment(&format!(
+            "Write {} with the synced revisions, then:",
+            LOCKFILE_NAME
+        )),
+        git(&["add", "--", LOCKFILE_NAME]),
+        git(&["commit", "-m", LOCKFILE_COMMIT_MESSAGE, "--", LOCKFILE_NAME]),
+    ]
+}
+
+/// The ordered steps `operation` performs for `context`, built from the same
+/// argument builders the git managers use
+pub fn steps(context: &CommandContext, operation: Operation) -> Vec<Step> {
+    match operation {
+        Operation::Init => {
+            let mut steps = vec![
+                git(&["remote", "add", &context.remote_name, &context.repo_url]),
+                comment(&default_branch_note(context)),
+                fetch_step(context),
+                git(&[
+                    "branch",
+                    "-f",
+                    &context.tracking_branch,
+                    &upstream_ref(context),
+                ]),
+                git(&["checkout", &context.tracking_branch]),
+                Step::Git(subtree::split_args(
+                    &context.upstream_prefix,
+                    &context.work_branch,
+                )),
+                comment("Back to the branch you started on:"),
+                git(&["checkout", "-"]),
+                Step::Git(subtree::add_args(
+                    &context.prefix,
+                    &context.work_branch,
+                    true,
+                    context.subtree_message.as_deref(),
+                )),
+                comment("With --strip-firewall, firewall changes are committed here"),
+            ];
+            steps.extend(lockfile_steps());
+            steps
+        }
+        Operation::Update => {
+            let mut steps = vec![
+                comment(&default_branch_note(context)),
+                fetch_step(context),
+                git(&["checkout", &context.tracking_branch]),
+                comment("With --locked, reset to the upstream-commit in the lockfile instead"),
+                git(&["reset", "--hard", &upstream_ref(context)]),
+                comment("Skipped when the branch doesn't exist yet:"),
+                git(&["branch", "-D", &context.updated_branch()]),
+                Step::Git(subtree::split_args(
+                    &context.upstream_prefix,
+                    &context.updated_branch(),
+                )),
+                comment("Back to the branch you started on:"),
+                git(&["checkout", "-"]),
+                Step::Git(subtree::merge_args(
+                    &context.prefix,
+                    &context.updated_branch(),
+                    context.subtree_message.as_deref(),
+                )),
+                comment("With --strip-firewall, firewall changes are committed here"),
+            ];
+            steps.extend(lockfile_steps());
+            steps
+        }
+        Operation::Remove => vec![
+            git(&["remote", "remove", &context.remote_name]),
+            git(&["branch", "-D", &context.tracking_branch]),
+            comment("These branches may not exist, so failures are ignored"),
+            git(&["branch", "-D", &context.work_branch]),
+            git(&["branch", "-D", &context.updated_branch()]),
+            comment("Skipped with --keep-files:"),
+            Step::Comment(format!("rm -rf {}", shell_quote(&context.prefix))),
+            git(&["add", &context.prefix]),
+            comment("Skipped with --no-commit:"),
+            git(&["commit", "-m", REMOVE_COMMIT_MESSAGE, "--", &context.prefix]),
+        ],
+    }
+}
+
+/// Render the steps as a copy-pasteable shell script
+pub fn script(context: &CommandContext, operation: Operation) -> String {
+    let mut script = format!(
+        "#!/bin/sh\n# Git commands run by 'devcontainer-sync {}'\nset -e\n",
+        operation.name()
+    );
+
+    for step in steps(context, operation) {
+        match step {
+            Step::Git(args) => script.push_str(&format!("{}\n", command_line(&args))),
+            Step::Comment(text) => script.push_str(&format!("# {}\n", text)),
+        }
+    }
+
+    script
+}
+
+/// A git invocation as a shell command line
+pub fn command_line(args: &[String]) -> String {
+    let args: Vec<String> = args.iter().map(|arg| shell_quote(arg)).collect();
+    format!("git {}", args.join(" "))
+}
+
+/// Quote an argument for POSIX shells, leaving plain words untouched
+pub fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@%+".contains(c));
+
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn context() -> CommandContext {
+        CommandContext::new(PathBuf::from("."), false)
+    }
+
+    #[test]
+    fn test_init_script_uses_resolved_settings() {
+        let context = context()
+            .with_repo_url("https://example.com/fork.git".to_string())
+            .with_prefix("tools\\devcontainer".to_string());
+
+        let script = script(&context, Operation::Init);
+
+        assert!(script.contains("git remote add claude https://example.com/fork.git\n"));
+        assert!(script.contains("git subtree split --prefix=.devcontainer -b devcontainer\n"));
+        assert!(
+            script.contains("git subtree add --prefix=tools/devcontainer --squash devcontainer\n")
+        );
+    }
+
+    #[test]
+    fn test_update_script_merges_updated_branch() {
+        let script = script(&context(), Operation::Update);
+
+        assert!(script.contains("git reset --hard refs/remotes/claude/main\n"));
+        assert!(script
+            .contains("git subtree merge --prefix=.devcontainer --squash devcontainer-updated\n"));
+    }
+
+    #[test]
+    fn test_fetch_is_narrowed_unless_fetch_all() {
+        let script_narrow = script(&context(), Operation::Update);
+        assert!(script_narrow
+            .contains("git fetch --depth=1 claude +refs/heads/main:refs/remotes/claude/main\n"));
+
+        let script_all = script(&context().with_fetch_all(true), Operation::Update);
+        assert!(script_all.contains("git fetch --depth=1 claude\n"));
+    }
+
+    #[test]
+    fn test_fetch_is_shallow_unless_full_history() {
+        let script_full = script(&context().with_full_history(true), Operation::Init);
+        assert!(
+            script_full.contains("git fetch claude +refs/heads/main:refs/remotes/claude/main\n")
+        );
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("claude/main"), "claude/main");
+        assert_eq!(
+            shell_quote("Remove devcontainer configuration"),
+            "'Remove devcontainer configuration'"
+        );
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote(""), "''");
+    }
+}
@@ -1,15 +1,408 @@
+pub mod batch;
+pub mod explain;
+pub mod output;
+
 use crate::config::*;
-use crate::customizer::{DefaultDevcontainerCustomizer, DevcontainerCustomizer};
+use crate::customizer::FirewallRemovalResult;
+use crate::customizer::{
+    customization_commit_message, repo_relative, DefaultDevcontainerCustomizer,
+    DevcontainerCustomizer, FeatureProfile,
+};
 use crate::error::CliError;
+use crate::git::subtree;
 use crate::git::{
-    BranchManager, GitBranchManager, GitExecutor, GitRemoteManager, GitRepositoryValidator,
-    GitSubtreeManager, RemoteManager, RepositoryValidator, SubtreeManager, SystemGitExecutor,
+    ensure_git_available, ensure_supported_git, BranchManager, GitBranchManager, GitExecutor,
+    GitRemoteManager, GitRepositoryValidator, GitSubtreeManager, RemoteManager,
+    RepositoryValidator, Split, SubtreeManager, SystemGitExecutor,
 };
-use crate::types::CommandContext;
+use crate::lockfile::{Lockfile, LOCKFILE_COMMIT_MESSAGE, LOCKFILE_NAME};
+use crate::types::{
+    BatchEntry, ColorChoice, CommandContext, DryRunPlan, InitReport, OperationResult, Severity,
+    StripPreview, UpdateSummary, Warning,
+};
+use output::{json_result, porcelain_record, print_result, OutputFormat, Progress};
 use std::env;
+use std::io::IsTerminal;
+use std::rc::Rc;
+
+/// Which parts of what init sets up are in place
+struct SyncPresence {
+    remote: bool,
+    tracking_branch: bool,
+    tracked: bool,
+}
+
+impl SyncPresence {
+    fn initialized(&self) -> bool {
+        self.remote && self.tracking_branch && self.tracked
+    }
+}
+
+/// Human-readable warning line, colored by severity when `color` allows it on stdout
+fn render_warning(warning: &Warning, color: ColorChoice) -> String {
+    let line = format!("{}: {}", warning.severity, warning.message);
+    if !color.enabled(std::io::stdout().is_terminal()) {
+        return line;
+    }
+
+    let color = match warning.severity {
+        Severity::Info => "36",
+        Severity::Warn => "33",
+        Severity::Error => "31",
+    };
+    format!("\x1b[{}m{}\x1b[0m", color, line)
+}
+
+/// Handle a failed `git subtree merge`. When it stopped on conflicts, `force` resolves
+/// them in favour of upstream and otherwise the merge is aborted, so the index is never
+/// left conflicted. Returns the resolved paths; any other failure is passed through.
+fn settle_merge_conflict<S: SubtreeManager>(
+    subtree_manager: &S,
+    prefix: &str,
+    force: bool,
+    error: CliError,
+) -> Result<Vec<String>, CliError> {
+    let conflicted = subtree_manager.conflicted_paths()?;
+    if conflicted.is_empty() {
+        return Err(error);
+    }
+
+    if force {
+        subtree_manager.resolve_conflicts_with_upstream(&conflicted)?;
+        Ok(conflicted)
+    } else {
+        subtree_manager.abort_merge()?;
+        Err(CliError::merge_conflict(prefix, &conflicted))
+    }
+}
+
+/// Every file (not directory) under `dir`, recursively
+fn collect_files(
+    dir: &std::path::Path,
+    files: &mut Vec<std::path::PathBuf>,
+) -> Result<(), CliError> {
+    let entries = std::fs::read_dir(dir).map_err(|e| CliError::FileSystem {
+        message: format!("Failed to read directory {}: {}", dir.display(), e),
+        suggestion: "Check directory permissions".to_string(),
+    })?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        // Symlinked directories are kept as links rather than followed
+        if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// A git command a dry run would run, as a shell command line
+fn planned_git(args: &[&str]) -> String {
+    explain::command_line(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+}
+
+/// An `explain` step as a line of a dry run's plan
+fn planned_step(step: &explain::Step) -> String {
+    match step {
+        explain::Step::Git(args) => explain::command_line(args),
+        explain::Step::Comment(text) => format!("# {}", text),
+    }
+}
+
+/// A dry run's result: `header`, then the `planned` changes and the firewall `strip` it
+/// would commit, kept whole for display and listed line by line in `changes`
+fn dry_run_result(
+    header: &str,
+    planned: Vec<String>,
+    strip: Option<StripPreview>,
+) -> OperationResult {
+    let mut result = OperationResult::success(header.to_string());
+    for line in &planned {
+        result.add_change(line.clone());
+    }
+    if let Some(strip) = &strip {
+        for file in &strip.files {
+            result.add_change(format!("Would change {}", file));
+        }
+        if let Some(message) = &strip.message {
+            result.add_change(format!("Would commit: {}", message));
+        }
+    }
+    result.plan = Some(DryRunPlan { planned, strip });
+    result
+}
+
+/// A directory under the system temp dir that is deleted when dropped
+struct ScratchDir {
+    path: std::path::PathBuf,
+}
+
+impl ScratchDir {
+    fn new(purpose: &str) -> Result<Self, CliError> {
+        let path = env::temp_dir().join(format!(
+            "devcontainer-sync-{}-{}",
+            purpose,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&path);
+        let scratch = Self { path };
+        std::fs::create_dir_all(&scratch.path).map_err(|e| scratch.write_error(e))?;
+        Ok(scratch)
+    }
+
+    fn write_error(&self, error: std::io::Error) -> CliError {
+        CliError::FileSystem {
+            message: format!(
+                "Failed to write to scratch directory {}: {}",
+                self.path.display(),
+                error
+            ),
+            suggestion: "Check that the system temp directory is writable".to_string(),
+        }
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Refs under a private namespace that are deleted when dropped, however the command exits
+struct TemporaryRefs<'a> {
+    executor: &'a SystemGitExecutor,
+    working_dir: &'a std::path::Path,
+    namespace: &'static str,
+}
+
+impl Drop for TemporaryRefs<'_> {
+    fn drop(&mut self) {
+        let Ok(refs) = self.executor.execute_git_command(
+            &["for-each-ref", "--format=%(refname)", self.namespace],
+            self.working_dir,
+        ) else {
+            return;
+        };
+        for name in refs.lines() {
+            let _ = self
+                .executor
+                .execute_git_command(&["update-ref", "-d", name], self.working_dir);
+        }
+    }
+}
+
+/// Checks the branch a command started on back out if the command fails (or is
+/// interrupted) after switching away from it
+struct BranchRestore<'a> {
+    executor: SystemGitExecutor,
+    working_dir: &'a std::path::Path,
+    /// The branch name, or the commit when HEAD was detached
+    start: String,
+    detached: bool,
+    armed: bool,
+}
+
+impl BranchRestore<'_> {
+    fn current_branch(
+        executor: &SystemGitExecutor,
+        working_dir: &std::path::Path,
+    ) -> Option<String> {
+        executor
+            .execute_git_command(&["symbolic-ref", "--short", "-q", "HEAD"], working_dir)
+            .ok()
+            .map(|branch| branch.trim().to_string())
+    }
+
+    fn current_commit(
+        executor: &SystemGitExecutor,
+        working_dir: &std::path::Path,
+    ) -> Result<String, CliError> {
+        executor
+            .execute_git_command(&["rev-parse", "--verify", "HEAD"], working_dir)
+            .map(|commit| commit.trim().to_string())
+    }
+
+    /// What the command returns to before changing the user's tree: the starting
+    /// branch, or its commit as a detached HEAD
+    fn starting_branch(&self) -> &str {
+        &self.start
+    }
+
+    /// The branch name, or the abbreviated commit of a detached HEAD
+    fn short_name(&self) -> &str {
+        if self.detached {
+            &self.start[..self.start.len().min(7)]
+        } else {
+            &self.start
+        }
+    }
+
+    /// The starting point as progress output names it
+    fn describe(&self) -> String {
+        if self.detached {
+            format!("detached HEAD at {}", self.short_name())
+        } else {
+            format!("{} branch", self.start)
+        }
+    }
+
+    /// The command finished; leave HEAD where it is
+    fn disarm(mut self) {
+        self.armed = false;
+    }
+}
+
+impl Drop for BranchRestore<'_> {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        let returned = if self.detached {
+            Self::current_branch(&self.executor, self.working_dir).is_none()
+                && Self::current_commit(&self.executor, self.working_dir).ok()
+                    == Some(self.start.clone())
+        } else {
+            Self::current_branch(&self.executor, self.working_dir).as_deref()
+                == Some(self.start.as_str())
+        };
+        if !returned {
+            let _ = self
+                .executor
+                .execute_git_command(&["checkout", &self.start], self.working_dir);
+        }
+    }
+}
+
+/// Stashes uncommitted changes, untracked files included, for the length of a command
+/// and pops them again when dropped, whether the command succeeded or not
+struct Autostash<E: GitExecutor> {
+    executor: E,
+    working_dir: std::path::PathBuf,
+    /// The stash commit created, if there was anything to stash
+    stash: Option<String>,
+}
+
+impl<E: GitExecutor> Autostash<E> {
+    fn push(executor: E, working_dir: std::path::PathBuf) -> Result<Self, CliError> {
+        // With nothing to stash, `git stash push` succeeds without creating an entry
+        let top = |executor: &E| {
+            executor
+                .execute_git_command(&["rev-parse", "-q", "--verify", "refs/stash"], &working_dir)
+                .ok()
+                .map(|sha| sha.trim().to_string())
+        };
+        let before = top(&executor);
+        executor.execute_git_command(
+            &[
+                "stash",
+                "push",
+                "--include-untracked",
+                "-m",
+                AUTOSTASH_MESSAGE,
+            ],
+            &working_dir,
+        )?;
+        let after = top(&executor);
+        let stash = after.filter(|sha| before.as_ref() != Some(sha));
+
+        Ok(Self {
+            executor,
+            working_dir,
+            stash,
+        })
+    }
+}
+
+impl<E: GitExecutor> Drop for Autostash<E> {
+    fn drop(&mut self) {
+        let Some(stash) = self.stash.take() else {
+            return;
+        };
+        // A pop that conflicts keeps the entry, so the changes are never lost
+        if self
+            .executor
+            .execute_git_command(&["stash", "pop"], &self.working_dir)
+            .is_err()
+        {
+            eprintln!(
+                "⚠️  Could not re-apply your stashed changes; they are kept in stash@{{0}} ({}). Resolve any conflicts, then run 'git stash drop'",
+                stash
+            );
+        }
+    }
+}
+
+/// Records what `init` has created so far and, unless disarmed, removes it again when
+/// dropped, so a failed init leaves no half-configured remote or branches behind
+struct InitRollback<E: GitExecutor> {
+    executor: E,
+    working_dir: std::path::PathBuf,
+    remote: Option<String>,
+    branches: Vec<String>,
+}
+
+impl<E: GitExecutor> InitRollback<E> {
+    fn new(executor: E, working_dir: std::path::PathBuf) -> Self {
+        Self {
+            executor,
+            working_dir,
+            remote: None,
+            branches: Vec::new(),
+        }
+    }
+
+    fn remote_added(&mut self, name: &str) {
+        self.remote = Some(name.to_string());
+    }
+
+    fn branch_created(&mut self, name: &str) {
+        self.branches.push(name.to_string());
+    }
+
+    /// Init got far enough that its changes should stay
+    fn disarm(mut self) {
+        self.remote = None;
+        self.branches.clear();
+    }
+}
+
+impl<E: GitExecutor> Drop for InitRollback<E> {
+    fn drop(&mut self) {
+        // Best effort: the original error is what the user needs to see
+        for branch in self.branches.iter().rev() {
+            let _ = self
+                .executor
+                .execute_git_command(&["branch", "-D", branch], &self.working_dir);
+        }
+        if let Some(remote) = self.remote.take() {
+            let _ = self
+                .executor
+                .execute_git_command(&["remote", "remove", &remote], &self.working_dir);
+        }
+    }
+}
+
+/// The customizer a command strips firewall configurations with
+enum ActiveCustomizer<'a> {
+    Default(DefaultDevcontainerCustomizer),
+    Injected(&'a dyn DevcontainerCustomizer),
+}
+
+impl<'a> std::ops::Deref for ActiveCustomizer<'a> {
+    type Target = dyn DevcontainerCustomizer + 'a;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            ActiveCustomizer::Default(customizer) => customizer,
+            ActiveCustomizer::Injected(customizer) => *customizer,
+        }
+    }
+}
 
 pub struct CliApp {
     context: CommandContext,
+    customizer: Option<Rc<dyn DevcontainerCustomizer>>,
 }
 
 impl CliApp {
@@ -17,597 +410,4274 @@ impl CliApp {
         let working_dir = env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("."));
         let context = CommandContext::new(working_dir, verbose);
 
-        Self { context }
+        Self {
+            context,
+            customizer: None,
+        }
+    }
+
+    /// Strip firewall configurations with `customizer` instead of the built-in one
+    ///
+    /// The package, git path, blame-ignore and script selection settings only configure
+    /// the built-in customizer; an injected one is used as given.
+    pub fn with_customizer(mut self, customizer: Box<dyn DevcontainerCustomizer>) -> Self {
+        self.customizer = Some(Rc::from(customizer));
+        self
+    }
+
+    pub fn with_working_dir(mut self, working_dir: std::path::PathBuf) -> Self {
+        self.context.working_dir = working_dir;
+        self
+    }
+
+    pub fn working_dir(&self) -> &std::path::Path {
+        &self.context.working_dir
+    }
+
+    /// Apply the settings from `.devcontainer-sync.toml`; builders called afterwards win
+    pub fn with_config(mut self, config: &SyncConfig) -> Self {
+        if let Some(repo_url) = &config.repo_url {
+            self = self.with_repo_url(repo_url.clone());
+        }
+        if let Some(remote_name) = &config.remote_name {
+            self = self.with_remote_name(remote_name.clone());
+        }
+        if let Some(tracking_branch) = &config.tracking_branch {
+            self = self.with_tracking_branch(tracking_branch.clone());
+        }
+        if let Some(prefix) = &config.prefix {
+            self = self.with_prefix(prefix.clone());
+        }
+        if let Some(default_branch) = &config.default_branch {
+            self = self.with_upstream_branch(default_branch.clone());
+        }
+        self
+    }
+
+    /// Treat `home_dir` as the user's home directory, which init won't sync into
+    pub fn with_home_dir(mut self, home_dir: std::path::PathBuf) -> Self {
+        self.context.home_dir = Some(home_dir);
+        self
+    }
+
+    pub fn with_repo_url(mut self, repo_url: String) -> Self {
+        self.context = self.context.with_repo_url(repo_url);
+        self
+    }
+
+    pub fn with_git_path(mut self, git_path: std::path::PathBuf) -> Self {
+        self.context = self.context.with_git_path(git_path);
+        self
+    }
+
+    /// Authenticate to an HTTPS upstream (e.g. a private fork) with `token`
+    pub fn with_token(mut self, token: String) -> Self {
+        self.context = self.context.with_token(token);
+        self
+    }
+
+    /// Fail early with a friendly message when the git binary cannot be run
+    pub fn ensure_git_available(&self) -> Result<(), CliError> {
+        let version = ensure_git_available(&self.context.git_path)?;
+        log::debug!("Using {}", version);
+        Ok(())
+    }
+
+    pub fn with_format(mut self, format: OutputFormat) -> Self {
+        self.context = self.context.with_format(format);
+        self
+    }
+
+    pub fn with_prefix(mut self, prefix: String) -> Self {
+        self.context = self.context.with_prefix(prefix);
+        self
+    }
+
+    pub fn with_upstream_prefix(mut self, upstream_prefix: String) -> Self {
+        self.context = self.context.with_upstream_prefix(upstream_prefix);
+        self
+    }
+
+    pub fn with_keep_packages(mut self, keep_packages: Vec<String>) -> Self {
+        self.context = self.context.with_keep_packages(keep_packages);
+        self
+    }
+
+    pub fn with_extra_packages(mut self, extra_packages: Vec<String>) -> Self {
+        self.context = self.context.with_extra_packages(extra_packages);
+        self
+    }
+
+    pub fn with_blame_ignore(mut self, blame_ignore: bool) -> Self {
+        self.context = self.context.with_blame_ignore(blame_ignore);
+        self
+    }
+
+    pub fn with_fetch_all(mut self, fetch_all: bool) -> Self {
+        self.context = self.context.with_fetch_all(fetch_all);
+        self
+    }
+
+    pub fn with_full_history(mut self, full_history: bool) -> Self {
+        self.context = self.context.with_full_history(full_history);
+        self
+    }
+
+    pub fn with_incremental_strip(mut self, incremental_strip: bool) -> Self {
+        self.context = self.context.with_incremental_strip(incremental_strip);
+        self
+    }
+
+    pub fn with_ignore_missing(mut self, ignore_missing: bool) -> Self {
+        self.context = self.context.with_ignore_missing(ignore_missing);
+        self
+    }
+
+    pub fn with_check_updates(mut self, check_updates: bool) -> Self {
+        self.context = self.context.with_check_updates(check_updates);
+        self
+    }
+
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.context = self.context.with_dry_run(dry_run);
+        self
+    }
+
+    pub fn with_subtree_message(mut self, message: Option<String>) -> Self {
+        self.context = self.context.with_subtree_message(message);
+        self
+    }
+
+    pub fn with_select_scripts(mut self, select_scripts: bool) -> Self {
+        self.context = self.context.with_select_scripts(select_scripts);
+        self
+    }
+
+    pub fn with_preview_diff(mut self, preview_diff: bool) -> Self {
+        self.context = self.context.with_preview_diff(preview_diff);
+        self
+    }
+
+    pub fn with_tracking_branch(mut self, tracking_branch: String) -> Self {
+        self.context = self.context.with_tracking_branch(tracking_branch);
+        self
+    }
+
+    pub fn with_work_branch(mut self, work_branch: String) -> Self {
+        self.context = self.context.with_work_branch(work_branch);
+        self
+    }
+
+    pub fn with_allow_dirty(mut self, allow_dirty: bool) -> Self {
+        self.context = self.context.with_allow_dirty(allow_dirty);
+        self
+    }
+
+    pub fn with_autostash(mut self, autostash: bool) -> Self {
+        self.context = self.context.with_autostash(autostash);
+        self
+    }
+
+    pub fn with_no_progress(mut self, no_progress: bool) -> Self {
+        self.context = self.context.with_no_progress(no_progress);
+        self
+    }
+
+    pub fn with_color(mut self, color: ColorChoice) -> Self {
+        self.context = self.context.with_color(color);
+        self
+    }
+
+    pub fn with_remote_name(mut self, remote_name: String) -> Self {
+        self.context = self.context.with_remote_name(remote_name);
+        self
+    }
+
+    pub fn with_upstream_branch(mut self, upstream_branch: String) -> Self {
+        self.context = self.context.with_upstream_branch(upstream_branch);
+        self
+    }
+
+    /// Kill git commands that run longer than `timeout`; zero lets them run indefinitely
+    pub fn with_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.context = self.context.with_timeout(timeout);
+        self
+    }
+
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.context = self.context.with_retries(retries);
+        self
+    }
+
+    pub fn with_retry_delay(mut self, retry_delay: std::time::Duration) -> Self {
+        self.context = self.context.with_retry_delay(retry_delay);
+        self
+    }
+
+    /// Run `init`, returning what it produced; a dry run produces nothing and returns `None`
+    pub fn init(
+        &self,
+        strip_firewall: bool,
+        assume_yes: bool,
+        force: bool,
+    ) -> Result<Option<InitReport>, CliError> {
+        self.init_with_result(strip_firewall, assume_yes, force)
+            .map(|(report, _)| report)
     }
 
-    pub fn init(&self, strip_firewall: bool) -> Result<(), CliError> {
+    /// Run `init` and return what it did, leaving the summary to the caller; only
+    /// progress is printed, in the format set with `with_format` (`Silent` for none).
+    /// Pass `assume_yes` when there is no one to answer a prompt.
+    pub fn run_init(
+        &self,
+        strip_firewall: bool,
+        assume_yes: bool,
+        force: bool,
+    ) -> Result<OperationResult, CliError> {
+        self.init_with_result(strip_firewall, assume_yes, force)
+            .map(|(_, result)| result)
+    }
+
+    /// Run `update` and return what it did, like `run_init`
+    pub fn run_update(
+        &self,
+        backup: bool,
+        force: bool,
+        strip_firewall: bool,
+        locked: bool,
+        migrate_prefix: Option<&str>,
+        reinit_subtree: bool,
+    ) -> Result<OperationResult, CliError> {
+        self.update(
+            backup,
+            force,
+            strip_firewall,
+            locked,
+            migrate_prefix,
+            reinit_subtree,
+        )
+    }
+
+    /// Run `remove` and return what it did, like `run_init`
+    pub fn run_remove(
+        &self,
+        keep_files: bool,
+        no_commit: bool,
+        preserve: &[String],
+    ) -> Result<OperationResult, CliError> {
+        self.remove(keep_files, no_commit, preserve)
+    }
+
+    fn init_with_result(
+        &self,
+        strip_firewall: bool,
+        assume_yes: bool,
+        force: bool,
+    ) -> Result<(Option<InitReport>, OperationResult), CliError> {
         // Update context with strip_firewall flag
         let context = self.context.clone().with_strip_firewall(strip_firewall);
+        let progress = Progress::for_context(&context);
 
-        if context.verbose {
-            println!("Initializing devcontainer sync from Claude Code repository...");
-            if strip_firewall {
-                println!("Firewall stripping enabled - will remove firewall configurations");
-            }
+        log::debug!("Initializing devcontainer sync from Claude Code repository...");
+        if strip_firewall {
+            log::debug!("Firewall stripping enabled - will remove firewall configurations");
         }
 
         // Validate that we're in a git repository
-        let validator = GitRepositoryValidator::new(context.working_dir.clone());
+        let validator = self.validator();
         validator.validate_git_repository(&context.working_dir)?;
+        if !force {
+            self.guard_against_unsafe_location()?;
+        }
+        validator.validate_writable()?;
 
         // Validate that the repository has commits
         validator.validate_has_commits()?;
 
-        // Check if .devcontainer already exists and prompt for confirmation
-        let devcontainer_path = context.working_dir.join(DEVCONTAINER_PREFIX);
-        if devcontainer_path.exists() {
-            println!("Warning: .devcontainer directory already exists.");
-            println!("This will overwrite existing devcontainer configurations.");
-            print!("Continue? (y/N): ");
+        // git subtree split walks the full history, which a shallow clone doesn't have
+        validator.validate_not_shallow()?;
+
+        // A dry run notes each change here, in order, instead of making it
+        let dry_run = context.dry_run;
+        let mut planned = Vec::new();
+
+        // Stashed before the other guards are set up so it is popped last, once the starting
+        // branch is checked out again
+        let _autostash = if dry_run { None } else { self.autostash()? };
+        let stash_planned = self.plan_autostash(&mut planned)?;
+
+        // Init checks out other branches, which would carry or clobber uncommitted work.
+        // A dry run that would have stashed it has nothing left to check.
+        if !force && !context.allow_dirty && !stash_planned {
+            validator.validate_clean_working_tree(&context.prefix)?;
+        }
+
+        // Check if .devcontainer already exists and prompt for confirmation; a dry run
+        // overwrites nothing, so it doesn't ask
+        let devcontainer_path = context.working_dir.join(&context.prefix);
+        if devcontainer_path.exists() && !assume_yes && !dry_run {
             use std::io::{self, Write};
-            io::stdout().flush().unwrap();
+            // A prompt written into redirected stdout would leave the user waiting on
+            // input for a question they never saw
+            let mut prompt: Box<dyn Write> = if io::stdout().is_terminal() {
+                Box::new(io::stdout())
+            } else {
+                Box::new(io::stderr())
+            };
+            let _ = write!(
+                prompt,
+                "Warning: {} directory already exists.\nThis will overwrite existing devcontainer configurations.\nContinue? (y/N): ",
+                context.prefix
+            );
+            let _ = prompt.flush();
 
             let mut input = String::new();
-            io::stdin()
-                .read_line(&mut input)
-                .map_err(|e| CliError::FileSystem {
-                    message: format!("Failed to read user input: {}", e),
-                    suggestion: "Try running the command again".to_string(),
-                })?;
+            let bytes_read =
+                io::stdin()
+                    .read_line(&mut input)
+                    .map_err(|e| CliError::FileSystem {
+                        message: format!("Failed to read user input: {}", e),
+                        suggestion: "Try running the command again".to_string(),
+                    })?;
+
+            // EOF means nobody is there to answer, which is not the same as "no"
+            if bytes_read == 0 {
+                let _ = writeln!(prompt);
+                return Err(CliError::Repository {
+                    message: format!(
+                        "No input available to confirm overwriting {} (stdin is closed)",
+                        context.prefix
+                    ),
+                    suggestion: "Use --yes to skip confirmation in non-interactive contexts"
+                        .to_string(),
+                });
+            }
 
             let input = input.trim().to_lowercase();
             if input != "y" && input != "yes" {
                 return Err(CliError::Repository {
                     message: "Operation cancelled by user".to_string(),
                     suggestion:
-                        "Use --force flag to skip confirmation or backup existing files first"
+                        "Use --yes flag to skip confirmation or backup existing files first"
                             .to_string(),
                 });
             }
         }
 
-        // Create Git operation managers
-        let executor = SystemGitExecutor::new();
-        let remote_manager = GitRemoteManager::new(executor, self.context.working_dir.clone());
-        let executor = SystemGitExecutor::new();
-        let branch_manager = GitBranchManager::new(executor, self.context.working_dir.clone());
-        let executor = SystemGitExecutor::new();
-        let subtree_manager = GitSubtreeManager::new(executor, self.context.working_dir.clone());
+        // Create Git operation managers, sharing one executor
+        let executor = self.executor();
+        let remote_manager = GitRemoteManager::new(&executor, self.context.working_dir.clone());
+        let branch_manager = GitBranchManager::new(&executor, self.context.working_dir.clone());
+        let subtree_manager = GitSubtreeManager::new(&executor, self.context.working_dir.clone());
+
+        self.warn_about_other_subtree_prefixes(&subtree_manager)?;
+        if !force {
+            self.guard_against_branch_in_use(&context.tracking_branch, "--tracking-branch")?;
+            self.guard_against_foreign_tracking_branch(&context.tracking_branch)?;
+            self.guard_against_branch_in_use(&context.work_branch, "--work-branch")?;
+        }
+        if validator.check_existing_remote(&self.context.remote_name)? {
+            return Err(self.existing_remote_error(&remote_manager));
+        }
+
+        // Declared first so it is dropped last, once the starting branch is checked out
+        // again and the branches it deletes are no longer in use
+        let mut rollback = InitRollback::new(&executor, context.working_dir.clone());
+        let tracking_branch_existed = validator.check_existing_branch(&context.tracking_branch)?;
+        let split_branch_existed = validator.check_existing_branch(&context.work_branch)?;
+        let branch_restore = self.restore_branch_on_error()?;
+        // A dry run fetches what it would add into refs of its own, deleted on the way out
+        let _refs = dry_run.then(|| TemporaryRefs {
+            executor: &executor,
+            working_dir: &context.working_dir,
+            namespace: DIFF_REF_NAMESPACE,
+        });
 
         // Execute the Git command sequence
 
         // 1. git remote add claude https://github.com/anthropics/claude-code.git
-        if context.verbose {
-            println!("Adding Claude Code remote...");
+        let step = progress.start("add_remote", "Adding Claude Code remote", "Adding remote");
+        if dry_run {
+            planned.push(planned_git(&[
+                "remote",
+                "add",
+                &self.context.remote_name,
+                &context.repo_url,
+            ]));
         } else {
-            print!("Adding remote... ");
-            use std::io::{self, Write};
-            io::stdout().flush().unwrap();
-        }
-        remote_manager.add_remote(CLAUDE_REMOTE_NAME, CLAUDE_REPO_URL)?;
-        if !context.verbose {
-            println!("✓");
+            remote_manager.add_remote(&self.context.remote_name, &context.repo_url)?;
+            rollback.remote_added(&self.context.remote_name);
         }
+        step.done();
 
-        // 2. git fetch claude
-        if context.verbose {
-            println!("Fetching from Claude Code repository...");
+        // 2. git fetch claude +refs/heads/<default branch>:refs/remotes/claude/<default branch>
+        let step = progress.start(
+            "fetch",
+            "Fetching from Claude Code repository",
+            "Fetching repository",
+        );
+        // Without the remote a dry run asks the upstream itself
+        let mut incoming = None;
+        let upstream_branch = if dry_run {
+            let upstream_branch = self.branch_to_sync(&remote_manager, &context.repo_url)?;
+            planned.push(explain::command_line(&explain::fetch_args(
+                &context,
+                &upstream_branch,
+            )));
+            incoming = Some(self.fetch_upstream_ref(&upstream_branch, "latest")?);
+            upstream_branch
         } else {
-            print!("Fetching repository... ");
-            use std::io::{self, Write};
-            io::stdout().flush().unwrap();
-        }
-        remote_manager.fetch_remote(CLAUDE_REMOTE_NAME)?;
-        if !context.verbose {
-            println!("✓");
-        }
+            let upstream_branch = self.upstream_branch(&remote_manager)?;
+            self.fetch_upstream(&remote_manager, &upstream_branch)?;
+            upstream_branch
+        };
+        step.done();
 
-        // 3. git branch -f claude-main claude/main
-        if context.verbose {
-            println!("Creating tracking branch...");
+        // 3. git branch -f claude-main refs/remotes/claude/<default branch>
+        let step = progress.start(
+            "create_branch",
+            "Creating tracking branch",
+            "Creating branch",
+        );
+        let upstream_ref = remote_branch_ref(&self.context.remote_name, &upstream_branch);
+        if dry_run {
+            planned.push(planned_git(&[
+                "branch",
+                "-f",
+                &context.tracking_branch,
+                &upstream_ref,
+            ]));
         } else {
-            print!("Creating branch... ");
-            use std::io::{self, Write};
-            io::stdout().flush().unwrap();
-        }
-        branch_manager.force_create_branch(CLAUDE_BRANCH_NAME, CLAUDE_REMOTE_BRANCH)?;
-        if !context.verbose {
-            println!("✓");
+            branch_manager.force_create_branch(&context.tracking_branch, &upstream_ref)?;
+            if !tracking_branch_existed {
+                rollback.branch_created(&context.tracking_branch);
+            }
         }
+        step.done();
 
         // 4. git checkout claude-main
-        if context.verbose {
-            println!("Switching to Claude branch...");
+        let step = progress.start(
+            "checkout_tracking",
+            "Switching to Claude branch",
+            "Switching branches",
+        );
+        if dry_run {
+            planned.push(planned_git(&["checkout", &context.tracking_branch]));
         } else {
-            print!("Switching branches... ");
-            use std::io::{self, Write};
-            io::stdout().flush().unwrap();
-        }
-        branch_manager.checkout_branch(CLAUDE_BRANCH_NAME)?;
-        if !context.verbose {
-            println!("✓");
+            branch_manager.checkout_branch(&context.tracking_branch)?;
         }
+        step.done();
 
         // 5. git subtree split --prefix=.devcontainer -b devcontainer claude-main
-        if context.verbose {
-            println!("Extracting devcontainer subtree...");
+        let step = progress.start(
+            "split",
+            "Extracting devcontainer subtree",
+            "Extracting devcontainer",
+        );
+        // split -b only fast-forwards an existing branch, so --force replaces it outright
+        let split = if dry_run {
+            if split_branch_existed && force {
+                planned.push(planned_git(&["branch", "-D", &context.work_branch]));
+            }
+            planned.push(explain::command_line(&subtree::split_args(
+                &context.upstream_prefix,
+                &context.work_branch,
+            )));
+            None
         } else {
-            print!("Extracting devcontainer... ");
-            use std::io::{self, Write};
-            io::stdout().flush().unwrap();
-        }
-        subtree_manager.split_subtree(DEVCONTAINER_PREFIX, DEVCONTAINER_BRANCH)?;
-        if !context.verbose {
-            println!("✓");
-        }
+            if split_branch_existed && force {
+                branch_manager.delete_branch(&context.work_branch)?;
+            }
+            // A failed split may still leave the branch behind
+            if !split_branch_existed || force {
+                rollback.branch_created(&context.work_branch);
+            }
+            let split =
+                subtree_manager.split_subtree(&context.upstream_prefix, &context.work_branch)?;
+            self.report_split_warnings(&split);
+            Some(split)
+        };
+        step.done();
 
-        // 6. git checkout master
-        if context.verbose {
-            println!("Returning to master branch...");
+        // 6. git checkout <starting branch>
+        let step = progress.start(
+            "checkout_master",
+            &format!("Returning to {}", branch_restore.describe()),
+            &format!("Returning to {}", branch_restore.short_name()),
+        );
+        if dry_run {
+            planned.push(planned_git(&["checkout", branch_restore.starting_branch()]));
         } else {
-            print!("Returning to master... ");
-            use std::io::{self, Write};
-            io::stdout().flush().unwrap();
-        }
-        branch_manager.checkout_branch(MASTER_BRANCH)?;
-        if !context.verbose {
-            println!("✓");
+            branch_manager.checkout_branch(branch_restore.starting_branch())?;
         }
+        step.done();
 
         // 7. git subtree add --prefix=.devcontainer devcontainer --squash
-        if context.verbose {
-            println!("Adding devcontainer files...");
+        let step = progress.start(
+            "add_subtree",
+            "Adding devcontainer files",
+            "Adding devcontainer files",
+        );
+        let add_args = subtree::add_args(
+            &context.prefix,
+            &context.work_branch,
+            true,
+            context.subtree_message.as_deref(),
+        );
+        if dry_run {
+            planned.push(explain::command_line(&add_args));
         } else {
-            print!("Adding devcontainer files... ");
-            use std::io::{self, Write};
-            io::stdout().flush().unwrap();
-        }
-        subtree_manager.add_subtree(DEVCONTAINER_PREFIX, DEVCONTAINER_BRANCH, true)?;
-        if !context.verbose {
-            println!("✓");
+            subtree_manager.add_subtree(
+                &context.prefix,
+                &context.work_branch,
+                true,
+                context.subtree_message.as_deref(),
+            )?;
+            rollback.disarm();
         }
+        step.done();
 
-        // Apply firewall stripping if requested
-        if context.strip_firewall {
-            if context.verbose {
-                println!("Stripping firewall configurations...");
+        let Some(split) = split else {
+            let incoming = incoming.unwrap_or_default();
+            let strip = if context.strip_firewall {
+                planned.push("# Commit the firewall strip shown below".to_string());
+                Some(self.preview_strip(
+                    &context,
+                    &incoming,
+                    None,
+                    STRIP_FIREWALL_COMMIT_MESSAGE,
+                )?)
             } else {
-                print!("Stripping firewall... ");
-                use std::io::{self, Write};
-                io::stdout().flush().unwrap();
+                None
+            };
+            planned.extend(explain::lockfile_steps().iter().map(planned_step));
+            if stash_planned {
+                planned.push(planned_git(&["stash", "pop"]));
             }
+            let header = format!(
+                "Dry run: init would add {} from {} ({} at {}); nothing is changed",
+                context.prefix,
+                context.repo_url,
+                upstream_branch,
+                &incoming[..incoming.len().min(12)]
+            );
+            return Ok((None, dry_run_result(&header, planned, strip)));
+        };
+        let subtree_split = split.commit.clone();
+
+        // Apply firewall stripping if requested
+        let mut firewall = None;
+        let mut strip_failure = None;
+        if context.strip_firewall {
+            let step = progress.start(
+                "strip_firewall",
+                "Stripping firewall configurations",
+                "Stripping firewall",
+            );
 
-            let customizer =
-                DefaultDevcontainerCustomizer::new(context.working_dir.clone(), context.verbose);
-            let devcontainer_path = context.working_dir.join(DEVCONTAINER_PREFIX);
+            let customizer = self.customizer(&context);
 
             match customizer.strip_firewall_features(&devcontainer_path) {
                 Ok(result) => {
                     if result.has_changes() {
                         // Create a commit for the firewall customizations
-                        let commit_message = "Strip firewall configurations from devcontainer";
-                        let changes: Vec<String> = result
-                            .dockerfile_changes
-                            .iter()
-                            .chain(result.json_changes.iter())
-                            .cloned()
-                            .collect();
-
-                        if let Err(e) = customizer.commit_customizations(&changes, commit_message) {
-                            if context.verbose {
-                                println!(
-                                    "Warning: Failed to commit firewall customizations: {}",
-                                    e
-                                );
-                            }
+                        let commit_message = STRIP_FIREWALL_COMMIT_MESSAGE;
+                        if let Err(e) = customizer.commit_customizations(&result, commit_message) {
+                            log::debug!("Warning: Failed to commit firewall customizations: {}", e);
+                            strip_failure = Some(e);
                         }
 
-                        if context.verbose {
-                            println!("Firewall stripping completed:");
-                            for change in &result.dockerfile_changes {
-                                println!("  - Dockerfile: {}", change);
-                            }
-                            for change in &result.json_changes {
-                                println!("  - devcontainer.json: {}", change);
-                            }
-                            if result.has_warnings() {
-                                println!("Warnings:");
-                                for warning in &result.warnings {
-                                    println!("  ⚠️  {}", warning);
-                                }
+                        log::debug!("Firewall stripping completed:");
+                        for change in &result.dockerfile_changes {
+                            log::debug!("  - Dockerfile: {}", change);
+                        }
+                        for change in &result.json_changes {
+                            log::debug!("  - devcontainer.json: {}", change);
+                        }
+                        for change in &result.compose_changes {
+                            log::debug!("  - {}", change);
+                        }
+                        if result.has_warnings() {
+                            log::debug!("Warnings:");
+                            for warning in &result.warnings {
+                                log::debug!("  ⚠️  {}", render_warning(warning, context.color));
                             }
                         }
-                    } else if context.verbose {
-                        println!("No firewall configurations found to strip");
+                    } else if !result.declined {
+                        log::debug!("No firewall configurations found to strip");
                     }
+                    firewall = Some(result);
                 }
                 Err(e) => {
-                    if context.verbose {
-                        println!("Warning: Firewall stripping failed: {}", e);
-                    } else {
+                    log::debug!("Warning: Firewall stripping failed: {}", e);
+                    // Without --verbose the step line still needs closing off
+                    if !context.verbose && context.format == OutputFormat::Text {
                         println!("⚠️");
                     }
+                    strip_failure = Some(e);
                 }
             }
 
-            if !context.verbose {
-                println!("✓");
-            }
+            step.done();
         }
 
-        // Display summary of changes
-        println!("\n✅ Successfully initialized devcontainer sync!");
-        println!("📁 Created .devcontainer directory with Claude Code configurations");
-        if context.strip_firewall {
-            println!("🔒 Stripped firewall configurations as requested");
-        }
-        println!(
-            "🔗 Added 'claude' remote pointing to https://github.com/anthropics/claude-code.git"
+        // Record the synced revision so teammates can reproduce it with 'update --locked'
+        let step = progress.start(
+            "record_lockfile",
+            &format!("Recording synced revision in {}", LOCKFILE_NAME),
+            "Recording lockfile",
         );
-        println!("🌿 Created tracking branch 'claude-main' for future updates");
-        println!("\nNext steps:");
-        println!("  • Run 'devcontainer-sync update' to get the latest configurations");
-        println!("  • Run 'devcontainer-sync remove' to clean up if no longer needed");
-        Ok(())
-    }
+        self.record_lockfile(&subtree_split)?;
+        step.done();
 
-    pub fn update(&self, backup: bool, _force: bool, strip_firewall: bool) -> Result<(), CliError> {
-        // Update context with strip_firewall flag
-        let context = self.context.clone().with_strip_firewall(strip_firewall);
+        let report = InitReport {
+            remote_added: true,
+            tracking_branch: context.tracking_branch.clone(),
+            subtree_commit: subtree_split,
+            devcontainer_path,
+            firewall,
+        };
 
-        if context.verbose {
-            println!("Updating devcontainer configurations...");
-            if strip_firewall {
-                println!("Firewall stripping enabled - will remove firewall configurations");
-            }
+        let mut result =
+            OperationResult::success("Successfully initialized devcontainer sync".to_string());
+        result.add_change(format!(
+            "Added '{}' remote pointing to {}",
+            self.context.remote_name, context.repo_url
+        ));
+        result.add_change(format!(
+            "Created tracking branch '{}'",
+            report.tracking_branch
+        ));
+        result.add_change(format!(
+            "Added {} from upstream subtree split {}",
+            context.prefix, report.subtree_commit
+        ));
+        self.record_firewall_outcome(
+            &mut result,
+            report.firewall.as_ref(),
+            strip_failure.as_ref(),
+        );
+        result.add_change(format!("Recorded synced revision in {}", LOCKFILE_NAME));
+        for warning in &split.warnings {
+            result.add_warning(format!("git subtree split: {}", warning));
         }
 
-        // Validate that we're in a git repository
-        let validator = GitRepositoryValidator::new(context.working_dir.clone());
+        result.tracking_branch = Some(report.tracking_branch.clone());
+        result.subtree_split = Some(report.subtree_commit.clone());
+
+        progress.finish("init");
+        branch_restore.disarm();
+        Ok((Some(report), result))
+    }
+
+    /// Strip firewall configurations from an existing devcontainer directory
+    ///
+    /// Works offline on any devcontainer, synced or not: only a git repository is
+    /// required, with no remote, tracking branch or prior commits.
+    pub fn strip_firewall(&self, no_commit: bool) -> Result<(), CliError> {
+        let context = &self.context;
+        let progress = Progress::for_context(context);
+
+        let validator = self.validator();
         validator.validate_git_repository(&context.working_dir)?;
 
-        // Create Git operation managers
-        let executor = SystemGitExecutor::new();
-        let remote_manager = GitRemoteManager::new(executor, context.working_dir.clone());
-        let executor = SystemGitExecutor::new();
-        let branch_manager = GitBranchManager::new(executor, context.working_dir.clone());
-        let executor = SystemGitExecutor::new();
-        let subtree_manager = GitSubtreeManager::new(executor, context.working_dir.clone());
+        let devcontainer_path = context.working_dir.join(&context.prefix);
+        if !devcontainer_path.is_dir() {
+            return Err(CliError::FileSystem {
+                message: format!("No {} directory found to strip", context.prefix),
+                suggestion: "Run 'devcontainer-sync init' first, or pass --prefix to point at your devcontainer directory".to_string(),
+            });
+        }
 
-        // Create backup if requested
-        if backup {
-            if context.verbose {
-                println!("Creating backup of existing devcontainer configuration...");
-            } else {
-                print!("Creating backup... ");
-                use std::io::{self, Write};
-                io::stdout().flush().unwrap();
+        let step = progress.start(
+            "strip_firewall",
+            "Stripping firewall configurations",
+            "Stripping firewall",
+        );
+        let customizer = self.customizer(context);
+        let result = customizer.strip_firewall_features(&devcontainer_path)?;
+        if result.has_changes() && !no_commit {
+            customizer.commit_customizations(&result, STRIP_FIREWALL_COMMIT_MESSAGE)?;
+        }
+        step.done();
+
+        progress.finish("strip-firewall");
+        match context.format {
+            OutputFormat::Text => {}
+            OutputFormat::Porcelain => {
+                let touched = result.touched_files().len().to_string();
+                println!("{}", porcelain_record("strip-firewall", "OK", &[&touched]));
+                return Ok(());
             }
-            self.create_backup()?;
-            if !context.verbose {
-                println!("✓");
+            OutputFormat::Ndjson | OutputFormat::Silent => return Ok(()),
+            OutputFormat::Json => {
+                let mut operation = OperationResult::success(format!(
+                    "Stripped firewall configurations from {}",
+                    context.prefix
+                ));
+                self.record_firewall_outcome(&mut operation, Some(&result), None);
+                println!("{}", json_result("strip-firewall", &operation));
+                return Ok(());
             }
         }
 
-        // Execute the Git command sequence for update
-
-        // 1. git fetch claude
-        if context.verbose {
-            println!("Fetching from Claude Code repository...");
+        if result.declined {
+            println!("\n✅ Left firewall configurations in place as declined");
+        } else if !result.has_changes() {
+            println!("\n✅ No firewall configurations found to strip");
         } else {
-            print!("Fetching updates... ");
-            use std::io::{self, Write};
-            io::stdout().flush().unwrap();
-        }
-        remote_manager.fetch_remote(CLAUDE_REMOTE_NAME)?;
-        if !context.verbose {
-            println!("✓");
+            println!(
+                "\n✅ Stripped firewall configurations from {}",
+                context.prefix
+            );
+            for removed in &result.files_removed {
+                println!("  - Removed {}", self.display_path(removed));
+            }
+            for change in result.all_changes() {
+                println!("  - {}", change);
+            }
+            if no_commit {
+                println!("📝 Changes left uncommitted (--no-commit specified)");
+            } else {
+                println!("💾 Changes committed to git history");
+            }
         }
 
-        // 2. git checkout claude-main && git reset --hard claude/main
-        if context.verbose {
-            println!("Updating tracking branch...");
+        // Informational notes are noise unless the user asked for detail
+        let threshold = if context.verbose {
+            Severity::Info
         } else {
-            print!("Updating tracking branch... ");
-            use std::io::{self, Write};
-            io::stdout().flush().unwrap();
+            Severity::Warn
+        };
+        for warning in result.warnings_at_least(threshold) {
+            println!("  ⚠️  {}", render_warning(warning, context.color));
         }
-        branch_manager.checkout_branch(CLAUDE_BRANCH_NAME)?;
+        Ok(())
+    }
 
-        // Reset to latest remote state
-        let executor = SystemGitExecutor::new();
-        executor.execute_git_command(
-            &["reset", "--hard", CLAUDE_REMOTE_BRANCH],
-            &context.working_dir,
-        )?;
-        if !context.verbose {
-            println!("✓");
+    /// Report whether the repository is initialized and how many upstream commits have
+    /// landed since the last sync. Not being initialized is reported, not an error.
+    pub fn status(&self) -> Result<(), CliError> {
+        let context = &self.context;
+        let validator = self.validator();
+        validator.validate_git_repository(&context.working_dir)?;
+
+        let presence = self.sync_presence()?;
+        let (remote_present, branch_present, tracked) =
+            (presence.remote, presence.tracking_branch, presence.tracked);
+
+        if !presence.initialized() {
+            if context.format == OutputFormat::Porcelain {
+                println!("{}", porcelain_record("status", "OK", &["not-initialized"]));
+                return Ok(());
+            }
+            println!("Devcontainer sync is not initialized");
+            let present = |yes: bool| if yes { "present" } else { "missing" };
+            println!(
+                "  '{}' remote: {}",
+                self.context.remote_name,
+                present(remote_present)
+            );
+            println!(
+                "  '{}' branch: {}",
+                &context.tracking_branch,
+                present(branch_present)
+            );
+            println!(
+                "  {} tracked: {}",
+                context.prefix,
+                if tracked { "yes" } else { "no" }
+            );
+            println!("\nRun 'devcontainer-sync init' to start syncing");
+            return Ok(());
         }
 
-        // 3. git subtree split --prefix=.devcontainer -b devcontainer-updated claude-main
-        if context.verbose {
-            println!("Extracting updated devcontainer subtree...");
-        } else {
-            print!("Extracting updates... ");
-            use std::io::{self, Write};
-            io::stdout().flush().unwrap();
+        let remote_manager = GitRemoteManager::new(self.executor(), context.working_dir.clone());
+        let (upstream_branch, behind) = self.upstream_commits_since_sync(&remote_manager)?;
+        let behind = behind.to_string();
+        let behind = behind.as_str();
+
+        if context.format == OutputFormat::Porcelain {
+            println!(
+                "{}",
+                porcelain_record("status", "OK", &["initialized", behind])
+            );
+            return Ok(());
         }
-        subtree_manager.split_subtree(DEVCONTAINER_PREFIX, DEVCONTAINER_UPDATED_BRANCH)?;
-        if !context.verbose {
-            println!("✓");
+        println!("Devcontainer sync is initialized");
+        println!(
+            "  Syncing {} from {} ({})",
+            context.prefix, context.repo_url, upstream_branch
+        );
+        match behind {
+            "0" => println!("  Up to date with upstream"),
+            "1" => {
+                println!("  1 upstream commit since the last sync; run 'devcontainer-sync update'")
+            }
+            count => println!(
+                "  {} upstream commits since the last sync; run 'devcontainer-sync update'",
+                count
+            ),
         }
+        Ok(())
+    }
 
-        // 4. git checkout master && git subtree pull --prefix=.devcontainer devcontainer-updated --squash
-        if context.verbose {
-            println!("Returning to master branch...");
-        } else {
-            print!("Returning to master... ");
-            use std::io::{self, Write};
-            io::stdout().flush().unwrap();
+    /// Which of the remote, tracking branch and tracked devcontainer that init sets up exist
+    fn sync_presence(&self) -> Result<SyncPresence, CliError> {
+        let validator = self.validator();
+        Ok(SyncPresence {
+            remote: validator.check_existing_remote(&self.context.remote_name)?,
+            tracking_branch: validator.check_existing_branch(&self.context.tracking_branch)?,
+            tracked: self.prefix_tracked()?,
+        })
+    }
+
+    /// Converge on an initialized, up-to-date sync: init when nothing is set up, update
+    /// when upstream has moved on, and otherwise do nothing, so it is safe to run repeatedly
+    pub fn ensure(
+        &self,
+        strip_firewall: bool,
+        assume_yes: bool,
+    ) -> Result<OperationResult, CliError> {
+        let context = &self.context;
+        self.validator()
+            .validate_git_repository(&context.working_dir)?;
+        let presence = self.sync_presence()?;
+        let text = context.format == OutputFormat::Text;
+
+        if !presence.remote && !presence.tracking_branch {
+            if text {
+                println!("Devcontainer sync is not initialized; running init");
+            }
+            let result = self.run_init(strip_firewall, assume_yes, false)?;
+            print_result("init", &result, context.format);
+            return Ok(result);
         }
-        branch_manager.checkout_branch(MASTER_BRANCH)?;
-        if !context.verbose {
-            println!("✓");
+        if !presence.initialized() {
+            return Err(CliError::Repository {
+                message: format!(
+                    "Devcontainer sync is only partly set up ('{}' remote, '{}' branch and tracked {} are not all present)",
+                    context.remote_name, context.tracking_branch, context.prefix
+                ),
+                suggestion: "Run 'devcontainer-sync status' to see what is missing, then 'devcontainer-sync remove --ignore-missing --keep-files' to clear it before running ensure again".to_string(),
+            });
         }
 
-        if context.verbose {
-            println!("Updating devcontainer files...");
-        } else {
-            print!("Applying updates... ");
-            use std::io::{self, Write};
-            io::stdout().flush().unwrap();
+        let remote_manager = GitRemoteManager::new(self.executor(), context.working_dir.clone());
+        let (_, behind) = self.upstream_commits_since_sync(&remote_manager)?;
+        if behind > 0 {
+            if text {
+                println!(
+                    "{} upstream commit(s) since the last sync; running update",
+                    behind
+                );
+            }
+            let result = self.run_update(false, false, strip_firewall, false, None, false)?;
+            print_result("update", &result, context.format);
+            return Ok(result);
         }
-        // Use git subtree merge to update the existing subtree
-        let executor = SystemGitExecutor::new();
-        executor.execute_git_command(
+
+        let result = OperationResult::success("Already up to date; nothing to do".to_string());
+        match context.format {
+            OutputFormat::Text => println!("✅ {}", result.message),
+            OutputFormat::Json => println!("{}", json_result("ensure", &result)),
+            OutputFormat::Porcelain => {
+                println!("{}", porcelain_record("ensure", "OK", &["up-to-date"]))
+            }
+            OutputFormat::Ndjson | OutputFormat::Silent => {}
+        }
+        Ok(result)
+    }
+
+    /// Fetch the upstream branch and count the commits on it that the tracking branch,
+    /// which sits at the upstream revision of the last sync, doesn't have yet
+    fn upstream_commits_since_sync(
+        &self,
+        remote_manager: &impl RemoteManager,
+    ) -> Result<(String, usize), CliError> {
+        let context = &self.context;
+        let upstream_branch = self.upstream_branch(remote_manager)?;
+        // Counting needs every commit since the last sync, not just the tip; as the
+        // synced revision is already here, only those commits are transferred
+        self.retry_on_network_error(|| {
+            remote_manager.fetch_remote(&context.remote_name, Some(&upstream_branch))
+        })?;
+        let behind = self.executor().execute_git_command(
             &[
-                "subtree",
-                "merge",
-                "--prefix=.devcontainer",
-                "--squash",
-                DEVCONTAINER_UPDATED_BRANCH,
+                "rev-list",
+                "--count",
+                &format!(
+                    "{}..{}",
+                    &context.tracking_branch,
+                    remote_branch_ref(&context.remote_name, &upstream_branch)
+                ),
             ],
             &context.working_dir,
         )?;
-        if !context.verbose {
-            println!("✓");
+        let behind = behind.trim().parse().map_err(|_| CliError::GitOperation {
+            message: format!("Unexpected output from git rev-list --count: {}", behind),
+            suggestion: "Run 'git rev-list --count' yourself to check the tracking branch"
+                .to_string(),
+        })?;
+        Ok((upstream_branch, behind))
+    }
+
+    /// For `update --check`: report how many upstream commits have landed since the last
+    /// sync without splitting or merging anything; the result carries the count
+    fn check_for_updates(&self) -> Result<OperationResult, CliError> {
+        let context = &self.context;
+        let validator = self.validator();
+        if !validator.check_existing_remote(&context.remote_name)? {
+            return Err(CliError::GitOperation {
+                message: format!("Remote '{}' does not exist", context.remote_name),
+                suggestion: "Run 'devcontainer-sync init' first".to_string(),
+            });
+        }
+        if !validator.check_existing_branch(&context.tracking_branch)? {
+            return Err(CliError::GitOperation {
+                message: format!("Branch '{}' does not exist", context.tracking_branch),
+                suggestion: "Run 'devcontainer-sync init' first".to_string(),
+            });
         }
 
-        // Apply firewall stripping if requested
-        if context.strip_firewall {
-            if context.verbose {
-                println!("Stripping firewall configurations...");
-            } else {
-                print!("Stripping firewall... ");
-                use std::io::{self, Write};
-                io::stdout().flush().unwrap();
-            }
+        let remote_manager = GitRemoteManager::new(self.executor(), context.working_dir.clone());
+        let (_, behind) = self.upstream_commits_since_sync(&remote_manager)?;
+        let mut result = OperationResult::success(match behind {
+            0 => "Up to date with upstream".to_string(),
+            1 => "Updates available: 1 upstream commit since the last sync".to_string(),
+            n => format!(
+                "Updates available: {} upstream commits since the last sync",
+                n
+            ),
+        });
+        result.upstream_commits = Some(behind);
+        Ok(result)
+    }
 
-            let customizer =
-                DefaultDevcontainerCustomizer::new(context.working_dir.clone(), context.verbose);
-            let devcontainer_path = context.working_dir.join(DEVCONTAINER_PREFIX);
+    /// Show how the upstream devcontainer changed between `from` and `to`. Without `from`
+    /// the local devcontainer is compared; without `to`, the upstream default branch.
+    pub fn diff(&self, from: Option<&str>, to: Option<&str>) -> Result<(), CliError> {
+        let working_dir = &self.context.working_dir;
+        self.validator().validate_git_repository(working_dir)?;
 
-            match customizer.strip_firewall_features(&devcontainer_path) {
-                Ok(result) => {
-                    if result.has_changes() {
-                        // Create a commit for the firewall customizations
-                        let commit_message =
-                            "Strip firewall configurations from updated devcontainer";
-                        let changes: Vec<String> = result
-                            .dockerfile_changes
-                            .iter()
-                            .chain(result.json_changes.iter())
-                            .cloned()
-                            .collect();
-
-                        if let Err(e) = customizer.commit_customizations(&changes, commit_message) {
-                            if context.verbose {
-                                println!(
-                                    "Warning: Failed to commit firewall customizations: {}",
-                                    e
-                                );
-                            }
-                        }
+        let executor = self.executor();
+        let _refs = TemporaryRefs {
+            executor: &executor,
+            working_dir,
+            namespace: DIFF_REF_NAMESPACE,
+        };
 
-                        if context.verbose {
-                            println!("Firewall stripping completed:");
-                            for change in &result.dockerfile_changes {
-                                println!("  - Dockerfile: {}", change);
-                            }
-                            for change in &result.json_changes {
-                                println!("  - devcontainer.json: {}", change);
-                            }
-                            if result.has_warnings() {
-                                println!("Warnings:");
-                                for warning in &result.warnings {
-                                    println!("  ⚠️  {}", warning);
-                                }
-                            }
-                        }
-                    } else if context.verbose {
-                        println!("No firewall configurations found to strip");
-                    }
-                }
-                Err(e) => {
-                    if context.verbose {
-                        println!("Warning: Firewall stripping failed: {}", e);
-                    } else {
-                        println!("⚠️");
-                    }
-                }
+        let from_tree = match from {
+            Some(rev) => format!(
+                "{}:{}",
+                self.fetch_upstream_ref(rev, "from")?,
+                self.context.upstream_prefix
+            ),
+            None => format!("HEAD:{}", self.context.prefix),
+        };
+        let to = match to {
+            Some(rev) => rev.to_string(),
+            None => {
+                let remote_manager = GitRemoteManager::new(&executor, working_dir.clone());
+                self.branch_to_sync(&remote_manager, &self.context.repo_url)?
             }
+        };
+        let to_tree = format!(
+            "{}:{}",
+            self.fetch_upstream_ref(&to, "to")?,
+            self.context.upstream_prefix
+        );
 
-            if !context.verbose {
-                println!("✓");
-            }
+        let output = executor.execute_git_command(&["diff", &from_tree, &to_tree], working_dir)?;
+        if output.is_empty() {
+            println!(
+                "No devcontainer changes between {} and {}",
+                from.unwrap_or("the local devcontainer"),
+                to
+            );
+        } else {
+            print!("{}", output);
         }
+        Ok(())
+    }
 
-        // Display summary of changes
-        println!("\n✅ Successfully updated devcontainer configurations!");
-        println!("📁 Updated .devcontainer directory with latest Claude Code configurations");
-        if context.strip_firewall {
-            println!("🔒 Stripped firewall configurations as requested");
+    /// Print every firewall detection pattern in effect, with where it comes from, so
+    /// users can see why content was or wasn't recognized
+    pub fn list_patterns(&self) -> Result<(), CliError> {
+        let customizer =
+            DefaultDevcontainerCustomizer::new(self.context.working_dir.clone(), false)
+                .with_synced_patterns(self.context.working_dir.join(&self.context.prefix));
+        for detection in customizer.detection_patterns(FeatureProfile::Firewall)? {
+            println!("{:<8}  {}", detection.source.to_string(), detection.pattern);
         }
-        if backup {
-            println!("💾 Backup created before update");
-        }
-        println!("🔄 Merged latest changes from Claude Code repository");
-        println!("\nYour devcontainer is now up to date with the latest configurations.");
+
         Ok(())
     }
 
-    pub fn remove(&self, keep_files: bool) -> Result<(), CliError> {
-        if self.context.verbose {
-            println!("Removing devcontainer sync...");
+    /// Describe the built-in defaults: where the devcontainer is synced from and to, and
+    /// what `--strip-firewall` removes. Unlike `status`, this doesn't look at the repository.
+    pub fn info(&self) -> Result<(), CliError> {
+        let profile = FeatureProfile::Firewall;
+        match self.context.format {
+            OutputFormat::Json | OutputFormat::Ndjson => println!(
+                "{}",
+                serde_json::json!({
+                    "repo_url": CLAUDE_REPO_URL,
+                    "remote_name": CLAUDE_REMOTE_NAME,
+                    "tracking_branch": CLAUDE_BRANCH_NAME,
+                    "work_branch": DEVCONTAINER_BRANCH,
+                    "prefix": DEVCONTAINER_PREFIX,
+                    "config_file": CONFIG_FILE_NAME,
+                    "patterns_file": PATTERNS_FILE_NAME,
+                    "lockfile": LOCKFILE_NAME,
+                    "strip_firewall": {
+                        "scripts": profile.script_names(),
+                        "run_args": profile.run_args(),
+                        "packages": profile.packages(),
+                        "patterns": profile.content_patterns(),
+                    },
+                })
+            ),
+            OutputFormat::Text | OutputFormat::Porcelain => {
+                println!("Defaults (override with flags or {}):", CONFIG_FILE_NAME);
+                println!("  Upstream repository: {}", CLAUDE_REPO_URL);
+                println!("  Upstream branch:     the upstream's default branch");
+                println!("  Remote name:         {}", CLAUDE_REMOTE_NAME);
+                println!("  Tracking branch:     {}", CLAUDE_BRANCH_NAME);
+                println!("  Work branch:         {}", DEVCONTAINER_BRANCH);
+                println!("  Prefix:              {}", DEVCONTAINER_PREFIX);
+                println!("  Lockfile:            {}", LOCKFILE_NAME);
+                println!();
+                println!("--strip-firewall removes:");
+                println!("  Scripts:  {}", profile.script_names().join(", "));
+                println!("  runArgs:  {}", profile.run_args().join(", "));
+                println!("  Packages: {}", profile.packages().join(", "));
+                println!(
+                    "  Anything matching these patterns (add more in {}):",
+                    PATTERNS_FILE_NAME
+                );
+                for pattern in profile.content_patterns() {
+                    println!("    {}", pattern);
+                }
+            }
+            OutputFormat::Silent => {}
         }
 
-        // Validate that we're in a git repository
-        let validator = GitRepositoryValidator::new(self.context.working_dir.clone());
-        validator.validate_git_repository(&self.context.working_dir)?;
+        Ok(())
+    }
 
-        // Create Git operation managers
-        let executor = SystemGitExecutor::new();
-        let remote_manager = GitRemoteManager::new(executor, self.context.working_dir.clone());
-        let executor = SystemGitExecutor::new();
-        let branch_manager = GitBranchManager::new(executor, self.context.working_dir.clone());
-        let executor = SystemGitExecutor::new();
-        let subtree_manager = GitSubtreeManager::new(executor, self.context.working_dir.clone());
+    /// List the upstream commits touching the devcontainer since release tag `since`, with
+    /// the ones that change firewall configuration listed separately
+    pub fn changelog(&self, since: &str) -> Result<(), CliError> {
+        let working_dir = &self.context.working_dir;
+        self.validator().validate_git_repository(working_dir)?;
 
-        // Execute the Git command sequence for remove
+        let executor = self.executor();
+        let _refs = TemporaryRefs {
+            executor: &executor,
+            working_dir,
+            namespace: DIFF_REF_NAMESPACE,
+        };
 
-        // 1. git remote remove claude
-        if self.context.verbose {
-            println!("Removing Claude remote...");
-        } else {
-            print!("Removing remote... ");
-            use std::io::{self, Write};
-            io::stdout().flush().unwrap();
+        let since_commit = self
+            .fetch_upstream_ref(&format!("refs/tags/{}", since), "since")
+            .map_err(|_| CliError::Repository {
+                message: format!(
+                    "Release tag '{}' not found at {}",
+                    since, self.context.repo_url
+                ),
+                suggestion:
+                    "Pass a tag that exists upstream, e.g. one listed by 'git ls-remote --tags'"
+                        .to_string(),
+            })?;
+        let remote_manager = GitRemoteManager::new(&executor, working_dir.clone());
+        let upstream_branch = self.branch_to_sync(&remote_manager, &self.context.repo_url)?;
+        let latest_commit = self.fetch_upstream_ref(&upstream_branch, "latest")?;
+
+        let log = executor.execute_git_command(
+            &[
+                "log",
+                "--format=%h %s",
+                &format!("{}..{}", since_commit, latest_commit),
+                "--",
+                &self.context.upstream_prefix,
+            ],
+            working_dir,
+        )?;
+
+        let patterns = DefaultDevcontainerCustomizer::new(working_dir.clone(), false)
+            .with_synced_patterns(working_dir.join(&self.context.prefix));
+        let mut firewall = Vec::new();
+        let mut other = Vec::new();
+        for line in log.lines() {
+            let sha = line.split_whitespace().next().unwrap_or_default();
+            let patch = executor.execute_git_command(
+                &[
+                    "show",
+                    "--format=",
+                    "--unified=0",
+                    sha,
+                    "--",
+                    &self.context.upstream_prefix,
+                ],
+                working_dir,
+            )?;
+            // Only added and removed lines count; file headers catch renamed firewall scripts
+            let changed: Vec<&str> = patch
+                .lines()
+                .filter(|line| line.starts_with('+') || line.starts_with('-'))
+                .collect();
+            if patterns
+                .matches_firewall_patterns(&changed.join("\n"))?
+                .is_empty()
+            {
+                other.push(line);
+            } else {
+                firewall.push(line);
+            }
         }
-        remote_manager.remove_remote(CLAUDE_REMOTE_NAME)?;
-        if !self.context.verbose {
-            println!("✓");
+
+        if firewall.is_empty() && other.is_empty() {
+            println!("No devcontainer changes since {}", since);
+            return Ok(());
+        }
+        println!(
+            "Devcontainer changes since {} ({} commits):",
+            since,
+            firewall.len() + other.len()
+        );
+        if !firewall.is_empty() {
+            println!("\n🔒 Affecting firewall configuration:");
+            for line in &firewall {
+                println!("  {}", line);
+            }
+        }
+        if !other.is_empty() {
+            println!("\n📁 Other changes:");
+            for line in &other {
+                println!("  {}", line);
+            }
         }
+        Ok(())
+    }
 
-        // 2. git branch -D claude-main
-        if self.context.verbose {
-            println!("Deleting tracking branch...");
+    /// Check that the upstream devcontainer still has the layout syncing and stripping
+    /// rely on, by stripping a scratch copy of it, and list what no longer matches
+    pub fn check_upstream(&self) -> Result<(), CliError> {
+        let working_dir = &self.context.working_dir;
+        self.validator().validate_git_repository(working_dir)?;
+
+        let executor = self.executor();
+        let _refs = TemporaryRefs {
+            executor: &executor,
+            working_dir,
+            namespace: DIFF_REF_NAMESPACE,
+        };
+        let remote_manager = GitRemoteManager::new(&executor, working_dir.clone());
+        let upstream_branch = self.branch_to_sync(&remote_manager, &self.context.repo_url)?;
+        println!(
+            "Checking upstream layout at {} ({})...",
+            self.context.repo_url, upstream_branch
+        );
+        let latest_commit = self.fetch_upstream_ref(&upstream_branch, "latest")?;
+        let scratch = self.extract_upstream_devcontainer(&latest_commit, "check-upstream")?;
+
+        let upstream_prefix = &self.context.upstream_prefix;
+        let devcontainer_path = scratch.path.join(upstream_prefix);
+        let mut deviations = Vec::new();
+        if !devcontainer_path.is_dir() {
+            deviations.push(format!("no {} directory", upstream_prefix));
         } else {
-            print!("Removing branches... ");
-            use std::io::{self, Write};
-            io::stdout().flush().unwrap();
+            for expected in ["devcontainer.json", "Dockerfile"] {
+                if !devcontainer_path.join(expected).is_file() {
+                    deviations.push(format!("no {}/{}", upstream_prefix, expected));
+                }
+            }
+
+            let customizer = self.customizer(&self.context);
+            match customizer.strip_firewall_features(&devcontainer_path) {
+                Ok(result) => {
+                    if result.files_removed.is_empty() {
+                        deviations.push(
+                            "no firewall script (init-firewall.sh or a script using iptables)"
+                                .to_string(),
+                        );
+                    }
+                    if devcontainer_path.join("devcontainer.json").is_file()
+                        && result.json_changes.is_empty()
+                    {
+                        deviations.push("devcontainer.json has none of the known firewall settings (NET_ADMIN/NET_RAW runArgs, a firewall postStartCommand)".to_string());
+                    }
+                    if devcontainer_path.join("Dockerfile").is_file()
+                        && result.dockerfile_changes.is_empty()
+                    {
+                        deviations.push(
+                            "Dockerfile installs none of the known firewall packages".to_string(),
+                        );
+                    }
+                    deviations.extend(
+                        result
+                            .warnings_at_least(Severity::Warn)
+                            .into_iter()
+                            .filter(|warning| !warning.code.starts_with("missing-"))
+                            .map(|warning| warning.message.clone()),
+                    );
+                }
+                Err(e) => deviations.push(format!("stripping fails: {}", e)),
+            }
         }
-        branch_manager.delete_branch(CLAUDE_BRANCH_NAME)?;
 
-        // 3. git branch -D devcontainer && git branch -D devcontainer-updated
-        if self.context.verbose {
-            println!("Cleaning up subtree branches...");
+        if deviations.is_empty() {
+            println!("✅ Upstream layout matches expectations");
+            return Ok(());
         }
-        // These branches might not exist, so we ignore errors
-        let _ = branch_manager.delete_branch(DEVCONTAINER_BRANCH);
-        let _ = branch_manager.delete_branch(DEVCONTAINER_UPDATED_BRANCH);
-        if !self.context.verbose {
-            println!("✓");
+        println!("❌ Upstream layout deviates from expectations:");
+        for deviation in &deviations {
+            println!("  - {}", deviation);
         }
+        Err(CliError::Repository {
+            message: format!("Upstream layout has {} deviation(s)", deviations.len()),
+            suggestion: "Syncing or --strip-firewall may no longer work as intended; review the upstream changes with 'devcontainer-sync changelog' before updating".to_string(),
+        })
+    }
 
-        // 4. Remove .devcontainer directory if not keeping files
-        if !keep_files {
-            if self.context.verbose {
-                println!("Removing devcontainer directory...");
-            } else {
-                print!("Removing files... ");
-                use std::io::{self, Write};
-                io::stdout().flush().unwrap();
+    /// Copy the upstream devcontainer at `commit` into a scratch directory, keeping its
+    /// path within the upstream repository
+    fn extract_upstream_devcontainer(
+        &self,
+        commit: &str,
+        purpose: &str,
+    ) -> Result<ScratchDir, CliError> {
+        let executor = self.executor();
+        let working_dir = &self.context.working_dir;
+        let files = executor.execute_git_command(
+            &[
+                "ls-tree",
+                "-r",
+                "--name-only",
+                commit,
+                "--",
+                &self.context.upstream_prefix,
+            ],
+            working_dir,
+        )?;
+        let scratch = ScratchDir::new(purpose)?;
+        for file in files.lines() {
+            let content = executor
+                .execute_git_command(&["show", &format!("{}:{}", commit, file)], working_dir)?;
+            let path = scratch.path.join(file);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| scratch.write_error(e))?;
             }
-            subtree_manager.remove_subtree(DEVCONTAINER_PREFIX)?;
+            std::fs::write(&path, content).map_err(|e| scratch.write_error(e))?;
+        }
+        Ok(scratch)
+    }
 
-            // Commit the removal
-            let executor = SystemGitExecutor::new();
-            executor.execute_git_command(
-                &["commit", "-m", "Remove devcontainer configuration"],
-                &self.context.working_dir,
-            )?;
-            if !self.context.verbose {
-                println!("✓");
+    /// Print the git commands `operation` would run as a shell script, without running them
+    pub fn explain(&self, operation: explain::Operation) -> Result<(), CliError> {
+        print!("{}", explain::script(&self.context, operation));
+        Ok(())
+    }
+
+    /// Run `operation` in each repository listed in `manifest` with these settings and
+    /// that repository's own `.devcontainer-sync.toml`, which `flags` override. Carries on
+    /// past failures and returns every repository's result; the batch fails if any did.
+    pub fn batch(
+        &self,
+        manifest: &std::path::Path,
+        operation: explain::Operation,
+        strip_firewall: bool,
+        flags: &SyncConfig,
+    ) -> Result<OperationResult, CliError> {
+        if strip_firewall && operation == explain::Operation::Remove {
+            return Err(CliError::Repository {
+                message: "--strip-firewall can't be used with 'batch remove'".to_string(),
+                suggestion: "Drop --strip-firewall; remove deletes the devcontainer it would strip"
+                    .to_string(),
+            });
+        }
+        let repos = batch::load_manifest(manifest)?;
+        let format = self.context.format;
+        let mut entries = Vec::new();
+        let mut failed = Vec::new();
+
+        for repo in &repos {
+            if format == OutputFormat::Text {
+                println!("==> {}", repo.display());
             }
+            let app = CliApp {
+                context: self.context.clone(),
+                customizer: self.customizer.clone(),
+            };
+            let result = load_config(repo).and_then(|config| {
+                let app = app
+                    .with_working_dir(repo.clone())
+                    .with_config(&config)
+                    .with_config(flags);
+                match operation {
+                    explain::Operation::Init => app
+                        .init_with_result(strip_firewall, false, false)
+                        .map(|(_, result)| result),
+                    explain::Operation::Update => {
+                        app.update(false, false, strip_firewall, false, None, false)
+                    }
+                    explain::Operation::Remove => app.remove(false, false, &[]),
+                }
+            });
+            let result = match result {
+                Ok(result) => {
+                    // One document or line covers the whole batch, so only text shows
+                    // each repository's summary
+                    if format == OutputFormat::Text {
+                        print_result(operation.name(), &result, format);
+                    }
+                    result
+                }
+                // Ctrl-C stops the whole batch, not just this repository
+                Err(e @ CliError::Interrupted { .. }) => return Err(e),
+                Err(e) => {
+                    if format == OutputFormat::Text {
+                        eprintln!("Error in {}: {}", repo.display(), e);
+                    }
+                    failed.push(repo.display().to_string());
+                    OperationResult::failure(format!("{} failed", operation.name()), e)
+                }
+            };
+            entries.push(BatchEntry {
+                path: repo.clone(),
+                result,
+            });
         }
 
-        // Display summary of changes
-        println!("\n✅ Successfully removed devcontainer sync!");
-        println!("🔗 Removed 'claude' remote");
-        println!("🌿 Deleted tracking branches");
-        if !keep_files {
-            println!("📁 Removed .devcontainer directory and files");
-            println!("💾 Changes committed to git history");
+        let message = format!(
+            "Batch {}: {} succeeded, {} failed",
+            operation.name(),
+            repos.len() - failed.len(),
+            failed.len()
+        );
+        let mut result = if failed.is_empty() {
+            OperationResult::success(message)
         } else {
-            println!("📁 Kept .devcontainer files (--keep-files specified)");
+            OperationResult::failure(
+                message,
+                CliError::batch_failed(operation.name(), &failed, repos.len()),
+            )
+        };
+        result.repos = entries;
+
+        match format {
+            OutputFormat::Text => {
+                println!("{}", result.message);
+                for entry in &result.repos {
+                    match entry.result.errors.first() {
+                        None => println!("  ✅ {}", entry.path.display()),
+                        Some(e) => println!("  ❌ {}: {}", entry.path.display(), e),
+                    }
+                }
+                if let Some(e) = result.errors.first() {
+                    eprintln!("Error: {}", e);
+                }
+            }
+            OutputFormat::Json => println!("{}", json_result("batch", &result)),
+            OutputFormat::Porcelain => {
+                let record = match result.errors.first() {
+                    None => porcelain_record("batch", "OK", &[&result.repos.len().to_string()]),
+                    Some(e) => porcelain_record("batch", "ERR", &[&e.exit_code().to_string()]),
+                };
+                println!("{}", record);
+            }
+            OutputFormat::Ndjson | OutputFormat::Silent => {}
         }
-        println!("\nDevcontainer sync has been completely removed from this repository.");
-        Ok(())
+        Ok(result)
     }
 
-    fn create_backup(&self) -> Result<(), CliError> {
-        let devcontainer_path = self.context.working_dir.join(DEVCONTAINER_PREFIX);
-        let backup_path = self
-            .context
-            .working_dir
-            .join(format!("{}.backup", DEVCONTAINER_PREFIX));
+    /// Run every read-only prerequisite of `init`, including reaching the exact upstream
+    /// branch it would fetch, without changing the repository
+    pub fn check_init(&self) -> Result<(), CliError> {
+        let context = &self.context;
+        println!("Checking init prerequisites...");
 
-        // Check if .devcontainer exists
-        if !devcontainer_path.exists() {
-            return Err(CliError::FileSystem {
-                message: "No .devcontainer directory found to backup".to_string(),
-                suggestion:
-                    "Run 'devcontainer-sync init' first to create devcontainer configuration"
-                        .to_string(),
-            });
-        }
+        let validator = self.validator();
+        let remote_manager = GitRemoteManager::new(self.executor(), context.working_dir.clone());
+        let remote_free = match validator.check_existing_remote(&self.context.remote_name) {
+            Ok(true) => Err(self.existing_remote_error(&remote_manager)),
+            Ok(false) => Ok(()),
+            Err(e) => Err(e),
+        };
 
-        // Remove existing backup if it exists
-        if backup_path.exists() {
-            std::fs::remove_dir_all(&backup_path).map_err(|e| CliError::FileSystem {
-                message: format!("Failed to remove existing backup directory: {}", e),
-                suggestion: "Check file permissions and try again".to_string(),
-            })?;
+        let mut checks = vec![
+            (
+                "git is available".to_string(),
+                ensure_git_available(&context.git_path).map(|_| ()),
+            ),
+            (
+                "inside a git repository".to_string(),
+                validator.validate_git_repository(&context.working_dir),
+            ),
+            (
+                "working directory is writable".to_string(),
+                validator.validate_writable(),
+            ),
+            (
+                "repository has commits".to_string(),
+                validator.validate_has_commits(),
+            ),
+            (
+                "repository has full history".to_string(),
+                validator.validate_not_shallow(),
+            ),
+            (
+                "git subtree is available".to_string(),
+                validator.validate_subtree_available(),
+            ),
+            (
+                format!("remote name '{}' is free", self.context.remote_name),
+                remote_free,
+            ),
+        ];
+        if !context.allow_dirty {
+            checks.push((
+                "working tree is clean".to_string(),
+                validator.validate_clean_working_tree(&context.prefix),
+            ));
         }
+        let upstream_check = match self.branch_to_sync(&remote_manager, &context.repo_url) {
+            Ok(branch) => (
+                format!(
+                    "upstream branch '{}' reachable at {}",
+                    branch, context.repo_url
+                ),
+                remote_manager.check_remote_branch(&context.repo_url, &branch),
+            ),
+            Err(e) => (
+                format!("upstream reachable at {}", context.repo_url),
+                Err(e),
+            ),
+        };
+        let checks = checks.into_iter().chain(std::iter::once(upstream_check));
 
-        // Copy .devcontainer to .devcontainer.backup
-        Self::copy_directory(&devcontainer_path, &backup_path)?;
+        let mut failures = Vec::new();
+        for (name, result) in checks {
+            match result {
+                Ok(()) => println!("  ✓ {}", name),
+                Err(e) => {
+                    println!("  ✗ {}: {}", name, e);
+                    failures.push(e);
+                }
+            }
+        }
 
-        if self.context.verbose {
-            println!("Backup created at: {}", backup_path.display());
+        if failures.is_empty() {
+            println!("\n✅ All checks passed; 'devcontainer-sync init' should succeed");
+            return Ok(());
         }
 
-        Ok(())
+        println!(
+            "\n❌ {} check(s) failed; init would not succeed",
+            failures.len()
+        );
+        Err(failures.remove(0))
     }
 
-    fn copy_directory(src: &std::path::Path, dst: &std::path::Path) -> Result<(), CliError> {
-        std::fs::create_dir_all(dst).map_err(|e| CliError::FileSystem {
-            message: format!("Failed to create backup directory: {}", e),
-            suggestion: "Check file permissions and available disk space".to_string(),
-        })?;
+    /// Diagnose the environment: required checks fail the command, advisory ones (the
+    /// remote being reachable, a clean working tree) are only reported
+    pub fn doctor(&self) -> Result<(), CliError> {
+        let context = &self.context;
+        println!("Checking devcontainer-sync prerequisites...");
 
-        for entry in std::fs::read_dir(src).map_err(|e| CliError::FileSystem {
-            message: format!("Failed to read .devcontainer directory: {}", e),
-            suggestion: "Check file permissions".to_string(),
-        })? {
-            let entry = entry.map_err(|e| CliError::FileSystem {
-                message: format!("Failed to read directory entry: {}", e),
-                suggestion: "Check file permissions".to_string(),
-            })?;
+        let validator = self.validator();
+        let git = ensure_supported_git(&context.git_path);
+        let git_name = match &git {
+            Ok(version) => format!("{} is installed and supported", version),
+            Err(_) => "git is installed and supported".to_string(),
+        };
+        let repository = validator.validate_git_repository(&context.working_dir);
+        let in_repository = git.is_ok() && repository.is_ok();
 
-            let src_path = entry.path();
-            let dst_path = dst.join(entry.file_name());
+        let mut required = vec![
+            (git_name, git.map(|_| ())),
+            ("inside a git repository".to_string(), repository),
+        ];
+        let mut advisory = Vec::new();
+        let mut skipped = Vec::new();
+        if in_repository {
+            required.push((
+                "repository has commits".to_string(),
+                validator.validate_has_commits(),
+            ));
 
-            if src_path.is_dir() {
-                Self::copy_directory(&src_path, &dst_path)?;
+            match validator.check_existing_remote(&self.context.remote_name) {
+                Ok(true) => {
+                    let remote_manager =
+                        GitRemoteManager::new(self.executor(), context.working_dir.clone());
+                    advisory.push((
+                        format!("'{}' remote is reachable", self.context.remote_name),
+                        remote_manager
+                            .default_branch(&self.context.remote_name)
+                            .map(|_| ()),
+                    ));
+                }
+                Ok(false) => skipped.push(format!(
+                    "'{}' remote not configured yet (run 'devcontainer-sync init')",
+                    self.context.remote_name
+                )),
+                Err(e) => advisory.push((format!("'{}' remote", self.context.remote_name), Err(e))),
+            }
+
+            advisory.push((
+                "working tree is clean".to_string(),
+                validator.validate_clean_working_tree(&context.prefix),
+            ));
+        }
+
+        // The first required failure is returned, and main prints its error, so only its
+        // name and suggestion are shown here
+        let returned = required.iter().position(|(_, result)| result.is_err());
+        let report =
+            |name: &str, result: &Result<(), CliError>, note: &str, returned: bool| match result {
+                Ok(()) => println!("  ✓ {}", name),
+                Err(e) => {
+                    if returned {
+                        println!("  ✗ {}{}", name, note);
+                    } else {
+                        println!("  ✗ {}{}: {}", name, note, e);
+                    }
+                    println!("      {}", e.suggestion());
+                }
+            };
+        for (index, (name, result)) in required.iter().enumerate() {
+            report(name, result, "", returned == Some(index));
+        }
+        for (name, result) in &advisory {
+            report(name, result, " (advisory)", false);
+        }
+        for note in &skipped {
+            println!("  - {}", note);
+        }
+
+        let warnings = advisory
+            .iter()
+            .filter(|(_, result)| result.is_err())
+            .count();
+        let mut failures: Vec<CliError> = required
+            .into_iter()
+            .filter_map(|(_, result)| result.err())
+            .collect();
+        if failures.is_empty() {
+            if warnings == 0 {
+                println!("\n✅ No problems found");
             } else {
-                std::fs::copy(&src_path, &dst_path).map_err(|e| CliError::FileSystem {
-                    message: format!("Failed to copy file {}: {}", src_path.display(), e),
-                    suggestion: "Check file permissions and available disk space".to_string(),
-                })?;
+                println!(
+                    "\n⚠️  {} advisory check(s) failed; devcontainer-sync can still run",
+                    warnings
+                );
             }
+            return Ok(());
         }
 
-        Ok(())
+        println!("\n❌ {} required check(s) failed", failures.len());
+        Err(failures.remove(0))
+    }
+
+    /// Fail when the working tree has uncommitted changes, naming the affected files
+    pub fn update(
+        &self,
+        backup: bool,
+        force: bool,
+        strip_firewall: bool,
+        locked: bool,
+        migrate_prefix: Option<&str>,
+        reinit_subtree: bool,
+    ) -> Result<OperationResult, CliError> {
+        // Update context with strip_firewall flag
+        let context = self.context.clone().with_strip_firewall(strip_firewall);
+        let progress = Progress::for_context(&context);
+
+        log::debug!("Updating devcontainer configurations...");
+        if strip_firewall {
+            log::debug!("Firewall stripping enabled - will remove firewall configurations");
+        }
+
+        // Validate that we're in a git repository
+        let validator = self.validator();
+        validator.validate_git_repository(&context.working_dir)?;
+
+        // A check changes nothing, so none of the guards below apply
+        if context.check_updates {
+            return self.check_for_updates();
+        }
+
+        validator.validate_writable()?;
+
+        // git subtree split walks the full history, which a shallow clone doesn't have
+        validator.validate_not_shallow()?;
+
+        // A dry run notes each change here, in order, instead of making it
+        let dry_run = context.dry_run;
+        let mut planned = Vec::new();
+
+        // Stashed before the other guards are set up so it is popped last, once the starting
+        // branch is checked out again
+        let _autostash = if dry_run { None } else { self.autostash()? };
+        let stash_planned = self.plan_autostash(&mut planned)?;
+
+        // Local edits to the devcontainer would conflict with the subtree merge, and
+        // update checks out other branches, which would carry or clobber other changes.
+        // A dry run that would have stashed them has nothing left to check.
+        if !force && !stash_planned {
+            validator.validate_clean_path(migrate_prefix.unwrap_or(&context.prefix))?;
+            if !context.allow_dirty {
+                validator.validate_clean_working_tree(&context.prefix)?;
+            }
+        }
+
+        // Create Git operation managers, sharing one executor
+        let executor = self.executor();
+        let remote_manager = GitRemoteManager::new(&executor, context.working_dir.clone());
+        let branch_manager = GitBranchManager::new(&executor, context.working_dir.clone());
+        let subtree_manager = GitSubtreeManager::new(&executor, context.working_dir.clone());
+        // A dry run fetches what it would merge into refs of its own, deleted on the way out
+        let _refs = dry_run.then(|| TemporaryRefs {
+            executor: &executor,
+            working_dir: &context.working_dir,
+            namespace: DIFF_REF_NAMESPACE,
+        });
+
+        // Read the pinned revision up front so a missing lockfile fails before any changes
+        let lockfile = if locked {
+            let lockfile =
+                Lockfile::read(&context.working_dir)?.ok_or_else(|| CliError::FileSystem {
+                    message: format!("No {} found to sync from", LOCKFILE_NAME),
+                    suggestion: "Run 'devcontainer-sync update' without --locked to create one"
+                        .to_string(),
+                })?;
+            log::debug!(
+                "Using locked upstream revision {}",
+                lockfile.upstream_commit
+            );
+            Some(lockfile)
+        } else {
+            None
+        };
+
+        // Checked before resolving the upstream branch, which would fail less clearly
+        if !validator.check_existing_remote(&self.context.remote_name)? {
+            return Err(CliError::GitOperation {
+                message: format!("Remote '{}' does not exist", self.context.remote_name),
+                suggestion: "Run 'devcontainer-sync init' first".to_string(),
+            });
+        }
+
+        // Create backup if requested
+        if backup {
+            let step = progress.start(
+                "backup",
+                "Creating backup of existing devcontainer configuration",
+                "Creating backup",
+            );
+            if dry_run {
+                let (_, backup_path) = self.backup_paths()?;
+                let backup_dir = explain::shell_quote(&self.display_path(&backup_path));
+                if backup_path.exists() {
+                    planned.push(format!("rm -rf {}", backup_dir));
+                }
+                planned.push(format!(
+                    "cp -R {} {}",
+                    explain::shell_quote(&context.prefix),
+                    backup_dir
+                ));
+            } else {
+                self.create_backup()?;
+            }
+            step.done();
+        }
+
+        // Move a subtree synced to an old prefix before merging into the new one
+        if let Some(old_prefix) = migrate_prefix {
+            let step = progress.start(
+                "migrate_prefix",
+                &format!(
+                    "Migrating devcontainer subtree from {} to {}",
+                    old_prefix, context.prefix
+                ),
+                "Migrating prefix",
+            );
+            if dry_run {
+                subtree::check_migration(&context.working_dir, old_prefix, &context.prefix)?;
+                planned.push(planned_git(&["mv", old_prefix, &context.prefix]));
+                planned.push(format!(
+                    "# Commit the move, re-recording the squashed subtree under {}",
+                    context.prefix
+                ));
+            } else {
+                subtree_manager.migrate_subtree(old_prefix, &context.prefix)?;
+            }
+            step.done();
+        } else {
+            self.warn_about_other_subtree_prefixes(&subtree_manager)?;
+        }
+
+        let branch_restore = self.restore_branch_on_error()?;
+
+        // Execute the Git command sequence for update
+
+        // 1. git fetch claude +refs/heads/<default branch>:refs/remotes/claude/<default branch>
+        let step = progress.start(
+            "fetch",
+            "Fetching from Claude Code repository",
+            "Fetching updates",
+        );
+        let upstream_branch = self.upstream_branch(&remote_manager)?;
+        let locked_fetch = lockfile
+            .as_ref()
+            .filter(|_| !context.full_history)
+            .map(|lockfile| {
+                // The locked revision is usually behind the tip a shallow fetch brings in
+                vec![
+                    "fetch",
+                    "--depth=1",
+                    &context.remote_name,
+                    &lockfile.upstream_commit,
+                ]
+            });
+        // What a dry run would merge, which the real run resets the tracking branch to
+        let mut incoming = None;
+        let upstream_url = self
+            .remote_url(&remote_manager)
+            .unwrap_or_else(|| context.repo_url.clone());
+        if dry_run {
+            planned.push(explain::command_line(&explain::fetch_args(
+                &context,
+                &upstream_branch,
+            )));
+            if let Some(args) = &locked_fetch {
+                planned.push(planned_git(args));
+            }
+            incoming = Some(match &lockfile {
+                Some(lockfile) => {
+                    self.fetch_ref_from(&upstream_url, &lockfile.upstream_commit, "locked")?
+                }
+                None => self.fetch_ref_from(&upstream_url, &upstream_branch, "latest")?,
+            });
+        } else {
+            self.fetch_tracked_upstream(&remote_manager, &upstream_branch)?;
+            if let Some(args) = &locked_fetch {
+                self.retry_on_network_error(|| {
+                    executor.execute_git_command(args, &context.working_dir)
+                })?;
+            }
+        }
+        step.done();
+
+        // 2. git checkout claude-main && git reset --hard refs/remotes/claude/<default branch>
+        let step = progress.start(
+            "reset_tracking",
+            "Updating tracking branch",
+            "Updating tracking branch",
+        );
+        let old_upstream = self.resolve_commit(&context.tracking_branch)?;
+
+        // Reset to latest remote state, or to the locked revision
+        let reset_target = match &lockfile {
+            Some(lockfile) => lockfile.upstream_commit.clone(),
+            None => remote_branch_ref(&self.context.remote_name, &upstream_branch),
+        };
+        let reset = ["reset", "--hard", &reset_target];
+        if dry_run {
+            planned.push(planned_git(&["checkout", &context.tracking_branch]));
+            planned.push(planned_git(&reset));
+        } else {
+            branch_manager.checkout_branch(&context.tracking_branch)?;
+            executor.execute_git_command(&reset, &context.working_dir)?;
+        }
+        step.done();
+
+        // 3. git subtree split --prefix=.devcontainer -b devcontainer-updated claude-main
+        let step = progress.start(
+            "split",
+            "Extracting updated devcontainer subtree",
+            "Extracting updates",
+        );
+        // split -b only fast-forwards an existing branch, and a split of shallow history
+        // shares no commits with the previous one, so the branch is recreated each time
+        let updated_branch_exists = validator.check_existing_branch(&context.updated_branch())?;
+        let split = if dry_run {
+            if updated_branch_exists {
+                planned.push(planned_git(&["branch", "-D", &context.updated_branch()]));
+            }
+            planned.push(explain::command_line(&subtree::split_args(
+                &context.upstream_prefix,
+                &context.updated_branch(),
+            )));
+            if let Some(lockfile) = &lockfile {
+                planned.push(format!(
+                    "# Check the split is {}, as recorded in {}",
+                    lockfile.subtree_split, LOCKFILE_NAME
+                ));
+            }
+            None
+        } else {
+            if updated_branch_exists {
+                branch_manager.delete_branch(&context.updated_branch())?;
+            }
+            let split = subtree_manager
+                .split_subtree(&context.upstream_prefix, &context.updated_branch())?;
+            self.report_split_warnings(&split);
+            if let Some(lockfile) = &lockfile {
+                if split.commit != lockfile.subtree_split {
+                    return Err(CliError::Repository {
+                        message: format!(
+                            "Subtree split {} does not match locked split {}",
+                            split.commit, lockfile.subtree_split
+                        ),
+                        suggestion: format!(
+                            "Upstream history may have been rewritten; run 'devcontainer-sync update' without --locked to refresh {}",
+                            LOCKFILE_NAME
+                        ),
+                    });
+                }
+            }
+            Some(split)
+        };
+        step.done();
+
+        // 4. git checkout <starting branch> && git subtree pull --prefix=.devcontainer devcontainer-updated --squash
+        let step = progress.start(
+            "checkout_master",
+            &format!("Returning to {}", branch_restore.describe()),
+            &format!("Returning to {}", branch_restore.short_name()),
+        );
+        if dry_run {
+            planned.push(planned_git(&["checkout", branch_restore.starting_branch()]));
+        } else {
+            branch_manager.checkout_branch(branch_restore.starting_branch())?;
+        }
+        step.done();
+
+        let step = progress.start(
+            "merge_subtree",
+            "Updating devcontainer files",
+            "Applying updates",
+        );
+        // Use git subtree merge to update the existing subtree
+        let head_before_merge = self.resolve_commit("HEAD")?;
+        let mut resolved_conflicts = Vec::new();
+        let merge_args = subtree::merge_args(
+            &context.prefix,
+            &context.updated_branch(),
+            context.subtree_message.as_deref(),
+        );
+        if dry_run {
+            planned.push(explain::command_line(&merge_args));
+            if reinit_subtree {
+                planned.push(format!(
+                    "# If the subtree's recorded base is missing, remove {}, commit '{}' and re-add it from {}",
+                    context.prefix,
+                    REINIT_REMOVE_COMMIT_MESSAGE,
+                    context.updated_branch()
+                ));
+            }
+        } else {
+            match executor.execute_git_command(&subtree::as_strs(&merge_args), &context.working_dir)
+            {
+                Ok(_) => self.drop_empty_merge(&head_before_merge)?,
+                Err(e) if subtree::is_lost_merge_base(&e) => {
+                    if !reinit_subtree {
+                        return Err(CliError::GitOperation {
+                            message: format!(
+                                "The {} subtree's recorded base is no longer in this repository's history ({})",
+                                context.prefix, e
+                            ),
+                            suggestion: "Re-run with --reinit-subtree to re-add the devcontainer from the latest upstream".to_string(),
+                        });
+                    }
+                    log::debug!("Subtree base is missing; re-adding {}", context.prefix);
+                    subtree_manager.reinit_subtree(
+                        &context.prefix,
+                        &context.updated_branch(),
+                        context.subtree_message.as_deref(),
+                    )?;
+                }
+                Err(e) => {
+                    resolved_conflicts =
+                        settle_merge_conflict(&subtree_manager, &context.prefix, force, e)?;
+                }
+            }
+        }
+        step.done();
+
+        let Some(split) = split else {
+            let incoming = incoming.unwrap_or_default();
+            let strip = if context.strip_firewall {
+                planned.push("# Commit the firewall strip shown below".to_string());
+                let changed_since = context.incremental_strip.then_some(old_upstream.as_str());
+                Some(self.preview_strip(
+                    &context,
+                    &incoming,
+                    changed_since,
+                    UPDATE_STRIP_COMMIT_MESSAGE,
+                )?)
+            } else {
+                None
+            };
+            planned.extend(explain::lockfile_steps().iter().map(planned_step));
+            if stash_planned {
+                planned.push(planned_git(&["stash", "pop"]));
+            }
+
+            let behind = executor.execute_git_command(
+                &[
+                    "rev-list",
+                    "--count",
+                    &format!("{}..{}", context.tracking_branch, incoming),
+                ],
+                &context.working_dir,
+            )?;
+            let short = &incoming[..incoming.len().min(12)];
+            let source = match &lockfile {
+                Some(_) => format!("locked revision {}", short),
+                None => format!("{} at {}", upstream_branch, short),
+            };
+            return Ok(dry_run_result(
+                &format!(
+                    "Dry run: update would merge {} upstream commit(s) from {} ({}) into {}; nothing is changed",
+                    behind.trim(),
+                    upstream_url,
+                    source,
+                    context.prefix
+                ),
+                planned,
+                strip,
+            ));
+        };
+
+        let subtree_split = split.commit.clone();
+        let mut summary = self.devcontainer_changes_since(&head_before_merge)?;
+        summary.old_upstream = old_upstream;
+        summary.new_upstream = self.resolve_commit(&context.tracking_branch)?;
+        summary.conflict = !resolved_conflicts.is_empty();
+
+        // Apply firewall stripping if requested
+        let mut firewall = None;
+        let mut strip_failure = None;
+        if context.strip_firewall {
+            let step = progress.start(
+                "strip_firewall",
+                "Stripping firewall configurations",
+                "Stripping firewall",
+            );
+
+            let customizer = self.customizer(&context);
+            let devcontainer_path = context.working_dir.join(&context.prefix);
+            let stripped = if context.incremental_strip {
+                let changed = self.files_changed_since(&head_before_merge)?;
+                log::debug!("Stripping {} file(s) changed by the merge", changed.len());
+                customizer.strip_changed_firewall_features(&devcontainer_path, &changed)
+            } else {
+                customizer.strip_firewall_features(&devcontainer_path)
+            };
+
+            match stripped {
+                Ok(result) => {
+                    if result.has_changes() {
+                        // Create a commit for the firewall customizations
+                        if let Err(e) =
+                            customizer.commit_customizations(&result, UPDATE_STRIP_COMMIT_MESSAGE)
+                        {
+                            log::debug!("Warning: Failed to commit firewall customizations: {}", e);
+                            strip_failure = Some(e);
+                        }
+
+                        log::debug!("Firewall stripping completed:");
+                        for change in &result.dockerfile_changes {
+                            log::debug!("  - Dockerfile: {}", change);
+                        }
+                        for change in &result.json_changes {
+                            log::debug!("  - devcontainer.json: {}", change);
+                        }
+                        for change in &result.compose_changes {
+                            log::debug!("  - {}", change);
+                        }
+                        if result.has_warnings() {
+                            log::debug!("Warnings:");
+                            for warning in &result.warnings {
+                                log::debug!("  ⚠️  {}", render_warning(warning, context.color));
+                            }
+                        }
+                    } else if !result.declined {
+                        log::debug!("No firewall configurations found to strip");
+                    }
+                    firewall = Some(result);
+                }
+                Err(e) => {
+                    log::debug!("Warning: Firewall stripping failed: {}", e);
+                    // Without --verbose the step line still needs closing off
+                    if !context.verbose && context.format == OutputFormat::Text {
+                        println!("⚠️");
+                    }
+                    strip_failure = Some(e);
+                }
+            }
+
+            step.done();
+        }
+
+        // Record the synced revision so teammates can reproduce it with 'update --locked'
+        let step = progress.start(
+            "record_lockfile",
+            &format!("Recording synced revision in {}", LOCKFILE_NAME),
+            "Recording lockfile",
+        );
+        self.record_lockfile(&subtree_split)?;
+        step.done();
+
+        progress.finish("update");
+        branch_restore.disarm();
+
+        let mut result = OperationResult::success(
+            "Successfully updated devcontainer configurations".to_string(),
+        );
+        if backup {
+            result.add_change(format!(
+                "Backed up {} to {}.backup",
+                context.prefix, context.prefix
+            ));
+        }
+        if let Some(old_prefix) = migrate_prefix {
+            result.add_change(format!(
+                "Moved devcontainer subtree from {} to {}",
+                old_prefix, context.prefix
+            ));
+        }
+        if !resolved_conflicts.is_empty() {
+            result.add_change(format!(
+                "Took upstream version of conflicting files: {}",
+                resolved_conflicts.join(", ")
+            ));
+        }
+        result.add_change(match &lockfile {
+            Some(lockfile) => format!(
+                "Merged locked upstream revision {}",
+                lockfile.upstream_commit
+            ),
+            None => format!("Merged upstream subtree split {}", subtree_split),
+        });
+        self.record_firewall_outcome(&mut result, firewall.as_ref(), strip_failure.as_ref());
+        for warning in &split.warnings {
+            result.add_warning(format!("git subtree split: {}", warning));
+        }
+        result.update = Some(summary);
+        result.tracking_branch = Some(context.tracking_branch.clone());
+        result.subtree_split = Some(subtree_split);
+        Ok(result)
+    }
+
+    /// Remove the sync; files in the devcontainer matching a `preserve` glob are moved to
+    /// `<prefix>.preserved/` first instead of being deleted
+    pub fn remove(
+        &self,
+        keep_files: bool,
+        no_commit: bool,
+        preserve: &[String],
+    ) -> Result<OperationResult, CliError> {
+        let progress = Progress::for_context(&self.context);
+        log::debug!("Removing devcontainer sync...");
+
+        // Validate that we're in a git repository
+        let validator = self.validator();
+        validator.validate_git_repository(&self.context.working_dir)?;
+        validator.validate_writable()?;
+        let preserve_patterns = preserve
+            .iter()
+            .map(|pattern| {
+                glob::Pattern::new(pattern).map_err(|e| CliError::Repository {
+                    message: format!("Invalid --preserve pattern '{}': {}", pattern, e),
+                    suggestion: "Use a glob such as 'my-*.sh' or 'scripts/**'".to_string(),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Create Git operation managers, sharing one executor
+        let executor = self.executor();
+        let remote_manager = GitRemoteManager::new(&executor, self.context.working_dir.clone());
+        let branch_manager = GitBranchManager::new(&executor, self.context.working_dir.clone());
+        let subtree_manager = GitSubtreeManager::new(&executor, self.context.working_dir.clone());
+        // A dry run notes each change here, in order, instead of making it
+        let dry_run = self.context.dry_run;
+        let mut planned = Vec::new();
+
+        // Execute the Git command sequence for remove
+
+        // With --ignore-missing, whatever an earlier partial cleanup already removed is
+        // skipped, so leftover branches can still be cleaned up; without it a missing
+        // remote or tracking branch fails before anything is removed
+        let ignore_missing = self.context.ignore_missing;
+        let remote_present = validator.check_existing_remote(&self.context.remote_name)?;
+        if !remote_present && !ignore_missing {
+            return Err(CliError::GitOperation {
+                message: format!("Remote '{}' does not exist", self.context.remote_name),
+                suggestion: "Pass --ignore-missing to clean up whatever is left".to_string(),
+            });
+        }
+        let branch_present = validator.check_existing_branch(&self.context.tracking_branch)?;
+        if !branch_present && !ignore_missing {
+            return Err(CliError::GitOperation {
+                message: format!("Branch '{}' does not exist", self.context.tracking_branch),
+                suggestion: "Pass --ignore-missing to clean up whatever is left".to_string(),
+            });
+        }
+        let remove_files = !keep_files && (!ignore_missing || self.prefix_tracked()?);
+
+        // Set the preserved files aside first: a clash with earlier ones fails the command
+        // before anything is removed
+        let moves = if remove_files {
+            self.files_to_preserve(&preserve_patterns)?
+        } else {
+            Vec::new()
+        };
+        let preserved = if dry_run {
+            for (file, destination) in &moves {
+                planned.push(format!(
+                    "mv {} {}",
+                    explain::shell_quote(&self.display_path(file)),
+                    explain::shell_quote(&self.display_path(destination))
+                ));
+            }
+            moves
+                .into_iter()
+                .map(|(_, destination)| destination)
+                .collect()
+        } else {
+            self.preserve_files(moves)?
+        };
+
+        // 1. git remote remove claude
+        let step = progress.start("remove_remote", "Removing Claude remote", "Removing remote");
+        let remote_owned = !remote_present || self.owns_remote(&remote_manager);
+        if remote_present && remote_owned {
+            if dry_run {
+                planned.push(planned_git(&[
+                    "remote",
+                    "remove",
+                    &self.context.remote_name,
+                ]));
+            } else {
+                remote_manager.remove_remote(&self.context.remote_name)?;
+            }
+        }
+        step.done();
+
+        // 2. git branch -D claude-main
+        let step = progress.start(
+            "delete_branches",
+            "Deleting tracking branch",
+            "Removing branches",
+        );
+        if branch_present {
+            if dry_run {
+                planned.push(planned_git(&[
+                    "branch",
+                    "-D",
+                    &self.context.tracking_branch,
+                ]));
+            } else {
+                branch_manager.delete_branch(&self.context.tracking_branch)?;
+            }
+        }
+
+        // 3. git branch -D devcontainer && git branch -D devcontainer-updated
+        log::debug!("Cleaning up subtree branches...");
+        for branch in [&self.context.work_branch, &self.context.updated_branch()] {
+            if dry_run {
+                if validator.check_existing_branch(branch)? {
+                    planned.push(planned_git(&["branch", "-D", branch]));
+                }
+            } else {
+                // These branches might not exist, so we ignore errors
+                let _ = branch_manager.delete_branch(branch);
+            }
+        }
+        step.done();
+
+        // 4. Remove .devcontainer directory if not keeping files
+        let mut nothing_to_commit = false;
+        if remove_files {
+            let step = progress.start(
+                "remove_files",
+                "Removing devcontainer directory",
+                "Removing files",
+            );
+            if dry_run {
+                if self.context.working_dir.join(&self.context.prefix).exists() {
+                    planned.push(format!(
+                        "rm -rf {}",
+                        explain::shell_quote(&self.context.prefix)
+                    ));
+                    planned.push(planned_git(&["add", &self.context.prefix]));
+                }
+                nothing_to_commit = !self.prefix_tracked()?;
+            } else {
+                subtree_manager.remove_subtree(&self.context.prefix)?;
+            }
+
+            // Commit the removal unless the user wants to review the staged deletion first.
+            // A devcontainer that was already deleted (or never tracked) leaves nothing
+            // staged, and git commit would fail on the empty index. Both are limited to the
+            // prefix, so other staged changes are neither counted nor committed.
+            if !no_commit && !dry_run {
+                let staged = executor.execute_git_command(
+                    &[
+                        "diff",
+                        "--cached",
+                        "--name-only",
+                        "--",
+                        &self.context.prefix,
+                    ],
+                    &self.context.working_dir,
+                )?;
+                nothing_to_commit = staged.trim().is_empty();
+            }
+            if !no_commit && !nothing_to_commit {
+                let commit = [
+                    "commit",
+                    "-m",
+                    REMOVE_COMMIT_MESSAGE,
+                    "--",
+                    &self.context.prefix,
+                ];
+                if dry_run {
+                    planned.push(planned_git(&commit));
+                } else {
+                    executor.execute_git_command(&commit, &self.context.working_dir)?;
+                }
+            }
+            step.done();
+        }
+
+        if dry_run {
+            if remote_present && !remote_owned {
+                planned.push(format!(
+                    "# Leave remote '{}' in place: it isn't the upstream remote init added",
+                    self.context.remote_name
+                ));
+            }
+            return Ok(dry_run_result(
+                "Dry run: remove would clean up devcontainer sync; nothing is changed",
+                planned,
+                None,
+            ));
+        }
+
+        progress.finish("remove");
+
+        let mut result =
+            OperationResult::success("Successfully removed devcontainer sync".to_string());
+        if !remote_present {
+            result.add_change(format!(
+                "No '{}' remote to remove",
+                self.context.remote_name
+            ));
+        } else if remote_owned {
+            result.add_change(format!("Removed '{}' remote", self.context.remote_name));
+        } else {
+            result.add_warning(format!(
+                "Left remote '{}' in place: it isn't the upstream remote init added",
+                self.context.remote_name
+            ));
+        }
+        result.add_change("Deleted tracking branches".to_string());
+        if remove_files {
+            result.add_change(if no_commit {
+                format!(
+                    "Removed {}; removal staged but not committed (--no-commit)",
+                    self.context.prefix
+                )
+            } else if nothing_to_commit {
+                format!(
+                    "Nothing to commit: {} was already removed",
+                    self.context.prefix
+                )
+            } else {
+                format!("Removed and committed removal of {}", self.context.prefix)
+            });
+        } else if keep_files {
+            result.add_change(format!("Kept {} files (--keep-files)", self.context.prefix));
+        } else {
+            result.add_change(format!("No {} directory to remove", self.context.prefix));
+        }
+        if !preserved.is_empty() {
+            result.add_change(format!(
+                "Preserved {} file(s) in {}: {}",
+                preserved.len(),
+                self.display_path(&self.preserved_dir()),
+                preserved
+                    .iter()
+                    .map(|file| self.display_path(file))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        Ok(result)
+    }
+
+    /// Put the `.devcontainer.backup` made by `update --backup` back in place and stage it
+    pub fn restore(&self, keep_backup: bool) -> Result<(), CliError> {
+        let progress = Progress::for_context(&self.context);
+        let working_dir = &self.context.working_dir;
+        let validator = self.validator();
+        validator.validate_git_repository(working_dir)?;
+        validator.validate_writable()?;
+
+        let devcontainer_path = working_dir.join(&self.context.prefix);
+        let backup_path = working_dir.join(format!("{}.backup", self.context.prefix));
+        if !backup_path.is_dir() {
+            return Err(CliError::FileSystem {
+                message: format!("No backup found at {}", self.display_path(&backup_path)),
+                suggestion: "Run 'devcontainer-sync update --backup' first to create one"
+                    .to_string(),
+            });
+        }
+
+        let step = progress.start(
+            "restore",
+            &format!("Restoring {} from backup", self.context.prefix),
+            "Restoring backup",
+        );
+        if devcontainer_path.exists() {
+            std::fs::remove_dir_all(&devcontainer_path).map_err(|e| CliError::FileSystem {
+                message: format!(
+                    "Failed to remove {} before restoring: {}",
+                    self.context.prefix, e
+                ),
+                suggestion: "Check file permissions and try again".to_string(),
+            })?;
+        }
+        if keep_backup {
+            self.copy_directory(&backup_path, &devcontainer_path)?;
+        } else {
+            std::fs::rename(&backup_path, &devcontainer_path).map_err(|e| {
+                CliError::FileSystem {
+                    message: format!("Failed to move backup into place: {}", e),
+                    suggestion:
+                        "Check file permissions, or retry with --keep-backup to copy instead"
+                            .to_string(),
+                }
+            })?;
+        }
+
+        // Stages deletions as well, so files added since the backup are removed from the index
+        self.executor()
+            .execute_git_command(&["add", &self.context.prefix], working_dir)?;
+        step.done();
+
+        progress.finish("restore");
+        match self.context.format {
+            OutputFormat::Text => {}
+            OutputFormat::Porcelain => {
+                println!("{}", porcelain_record("restore", "OK", &[]));
+                return Ok(());
+            }
+            OutputFormat::Ndjson | OutputFormat::Silent => return Ok(()),
+            OutputFormat::Json => {
+                let mut result = OperationResult::success(format!(
+                    "Restored {} from backup",
+                    self.context.prefix
+                ));
+                result.add_change(format!(
+                    "Staged {} restored from {}.backup",
+                    self.context.prefix, self.context.prefix
+                ));
+                println!("{}", json_result("restore", &result));
+                return Ok(());
+            }
+        }
+
+        println!(
+            "\n✅ Restored {} from {}.backup",
+            self.context.prefix, self.context.prefix
+        );
+        if keep_backup {
+            println!("📁 Kept the backup (--keep-backup specified)");
+        }
+        println!("📝 Changes staged; review them with 'git diff --cached' and commit");
+        Ok(())
+    }
+
+    /// Warn when a previously synced subtree lives at a prefix other than the requested one,
+    /// which would otherwise leave two parallel devcontainer copies
+    fn warn_about_other_subtree_prefixes(
+        &self,
+        subtree_manager: &impl SubtreeManager,
+    ) -> Result<(), CliError> {
+        let requested = self.context.prefix.replace('\\', "/");
+        let requested = requested.trim_end_matches('/');
+
+        for prefix in subtree_manager.subtree_prefixes()? {
+            if prefix != requested {
+                let warning = format!(
+                    "Warning: a devcontainer subtree already exists at '{}' (requested '{}').\nRun 'devcontainer-sync update --prefix {} --migrate-prefix {}' to move it.",
+                    prefix, requested, requested, prefix
+                );
+                // Keep machine-readable stdout clean
+                if self.context.format == OutputFormat::Text {
+                    println!("{}", warning);
+                } else {
+                    eprintln!("{}", warning);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The upstream's default branch, which may contain slashes
+    fn upstream_branch(&self, remote_manager: &impl RemoteManager) -> Result<String, CliError> {
+        let branch = self.branch_to_sync(remote_manager, &self.context.remote_name)?;
+        log::debug!("Upstream default branch is '{}'", branch);
+        Ok(branch)
+    }
+
+    /// The configured upstream branch, or else the default branch of `remote`
+    fn branch_to_sync(
+        &self,
+        remote_manager: &impl RemoteManager,
+        remote: &str,
+    ) -> Result<String, CliError> {
+        match &self.context.upstream_branch {
+            Some(branch) => Ok(branch.clone()),
+            None => remote_manager.default_branch(remote),
+        }
+    }
+
+    /// Fetch only `branch` from the upstream, or every ref with --fetch-all
+    fn fetch_upstream(
+        &self,
+        remote_manager: &impl RemoteManager,
+        branch: &str,
+    ) -> Result<(), CliError> {
+        let branch = (!self.context.fetch_all).then_some(branch);
+        // Only the tip is needed to extract the devcontainer, and skipping the rest of the
+        // upstream history is most of the work on a slow link
+        self.retry_on_network_error(|| {
+            if self.context.full_history {
+                remote_manager.fetch_remote(&self.context.remote_name, branch)
+            } else {
+                remote_manager.fetch_remote_shallow(&self.context.remote_name, branch)
+            }
+        })
+    }
+
+    /// Fetch `branch` for update, which resets the tracking branch to it, so a branch deleted or
+    /// renamed upstream is reported as such rather than as a failed fetch or reset
+    fn fetch_tracked_upstream(
+        &self,
+        remote_manager: &impl RemoteManager,
+        branch: &str,
+    ) -> Result<(), CliError> {
+        let remote = &self.context.remote_name;
+        match self.fetch_upstream(remote_manager, branch) {
+            Err(CliError::GitOperation { message, .. })
+                if message.contains("couldn't find remote ref") =>
+            {
+                return Err(CliError::upstream_branch_missing(remote, branch));
+            }
+            result => result?,
+        }
+
+        let remote_ref = format!("{}^{{commit}}", remote_branch_ref(remote, branch));
+        self.executor()
+            .execute_git_command(
+                &["rev-parse", "--verify", "--quiet", &remote_ref],
+                &self.context.working_dir,
+            )
+            .map_err(|_| CliError::upstream_branch_missing(remote, branch))?;
+        Ok(())
+    }
+
+    /// Retry a fetch that could not reach the remote, waiting twice as long each time, as a
+    /// dropped connection often comes back; other failures, refused credentials and
+    /// missing repositories included, would only fail again
+    fn retry_on_network_error<T>(
+        &self,
+        mut fetch: impl FnMut() -> Result<T, CliError>,
+    ) -> Result<T, CliError> {
+        let mut delay = self.context.retry_delay;
+        let mut attempt = 0;
+        loop {
+            match fetch() {
+                Err(error) if error.is_retryable() && attempt < self.context.retries => {
+                    attempt += 1;
+                    log::debug!(
+                        "{}; retrying in {}s (retry {} of {})",
+                        error,
+                        delay.as_secs_f64(),
+                        attempt,
+                        self.context.retries
+                    );
+                    std::thread::sleep(delay);
+                    delay *= 2;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// With --autostash, set uncommitted changes aside until the returned guard is dropped
+    fn autostash(&self) -> Result<Option<Autostash<SystemGitExecutor>>, CliError> {
+        if !self.context.autostash {
+            return Ok(None);
+        }
+        let autostash = Autostash::push(self.executor(), self.context.working_dir.clone())?;
+        if autostash.stash.is_some() {
+            log::debug!("Stashed uncommitted changes; they are restored when the command ends");
+        }
+        Ok(Some(autostash))
+    }
+
+    /// Remember the current branch, or the commit of a detached HEAD, so it is checked
+    /// back out if the command fails
+    fn restore_branch_on_error(&self) -> Result<BranchRestore<'_>, CliError> {
+        let executor = self.executor();
+        let working_dir = &self.context.working_dir;
+        let (start, detached) = match BranchRestore::current_branch(&executor, working_dir) {
+            Some(branch) => (branch, false),
+            None => (BranchRestore::current_commit(&executor, working_dir)?, true),
+        };
+        Ok(BranchRestore {
+            executor,
+            working_dir,
+            start,
+            detached,
+            armed: true,
+        })
+    }
+
+    fn executor(&self) -> SystemGitExecutor {
+        let executor = SystemGitExecutor::with_git_path(&self.context.git_path)
+            .with_timeout(self.context.timeout);
+        match &self.context.token {
+            Some(token) if self.context.repo_url.starts_with("https://") => {
+                executor.with_token(&self.context.repo_url, token)
+            }
+            _ => executor,
+        }
+    }
+
+    fn validator(&self) -> GitRepositoryValidator {
+        GitRepositoryValidator::new(self.context.working_dir.clone())
+            .with_git_path(self.context.git_path.clone())
+    }
+
+    /// The injected customizer, or the default one configured from `context`
+    fn customizer(&self, context: &CommandContext) -> ActiveCustomizer<'_> {
+        self.customizer_for(context, context.working_dir.join(&context.prefix))
+    }
+
+    /// Like `customizer`, reading the synced patterns file from the devcontainer at
+    /// `devcontainer_dir` rather than the one under the prefix
+    fn customizer_for(
+        &self,
+        context: &CommandContext,
+        devcontainer_dir: std::path::PathBuf,
+    ) -> ActiveCustomizer<'_> {
+        if let Some(customizer) = &self.customizer {
+            return ActiveCustomizer::Injected(customizer.as_ref());
+        }
+
+        // Kept packages win over extra packages so the two flags compose predictably
+        ActiveCustomizer::Default(
+            DefaultDevcontainerCustomizer::new(context.working_dir.clone(), context.verbose)
+                .with_git_path(context.git_path.clone())
+                .with_timeout(context.timeout)
+                .with_extra_packages(&context.extra_packages)
+                .with_kept_packages(&context.keep_packages)
+                .with_blame_ignore(context.blame_ignore)
+                .with_script_selection(context.select_scripts)
+                .with_diff_preview(context.preview_diff)
+                .with_color(context.color)
+                .with_synced_patterns(devcontainer_dir),
+        )
+    }
+
+    /// Fetch a single upstream ref into the diff namespace, falling back to a commit that
+    /// is already present locally (servers often refuse to serve a bare SHA)
+    fn fetch_upstream_ref(&self, rev: &str, name: &str) -> Result<String, CliError> {
+        self.fetch_ref_from(&self.context.repo_url, rev, name)
+    }
+
+    /// Like `fetch_upstream_ref`, but from `url` rather than the configured upstream
+    fn fetch_ref_from(&self, url: &str, rev: &str, name: &str) -> Result<String, CliError> {
+        let local_ref = format!("{}/{}", DIFF_REF_NAMESPACE, name);
+        let fetched = self.executor().execute_git_command(
+            &[
+                "fetch",
+                "--no-tags",
+                url,
+                &format!("+{}:{}", rev, local_ref),
+            ],
+            &self.context.working_dir,
+        );
+
+        let target = if fetched.is_ok() { &local_ref } else { rev };
+        self.resolve_commit(target)
+            .map_err(|_| CliError::Repository {
+                message: format!("Upstream ref '{}' not found at {}", rev, url),
+                suggestion: "Use a branch, tag or commit that exists in the upstream repository"
+                    .to_string(),
+            })
+    }
+
+    /// Undo a squash merge that left the prefix unchanged, as happens when upstream commits
+    /// touching it cancel out, so history doesn't collect empty merge commits
+    fn drop_empty_merge(&self, head_before_merge: &str) -> Result<(), CliError> {
+        let working_dir = &self.context.working_dir;
+        if self.resolve_commit("HEAD")? == head_before_merge {
+            return Ok(());
+        }
+
+        let executor = self.executor();
+        let changed = executor.execute_git_command(
+            &[
+                "diff",
+                "--name-only",
+                head_before_merge,
+                "HEAD",
+                "--",
+                &self.context.prefix,
+            ],
+            working_dir,
+        )?;
+        if changed.trim().is_empty() {
+            log::debug!(
+                "Upstream changes left {} unchanged; dropping the empty merge commit",
+                self.context.prefix
+            );
+            executor.execute_git_command(&["reset", "--soft", head_before_merge], working_dir)?;
+        }
+        Ok(())
+    }
+
+    /// Files under the prefix that were added or modified between `rev` and HEAD
+    fn files_changed_since(&self, rev: &str) -> Result<Vec<std::path::PathBuf>, CliError> {
+        let output = self.executor().execute_git_command(
+            &[
+                "diff",
+                "--name-only",
+                "--diff-filter=d",
+                rev,
+                "HEAD",
+                "--",
+                &self.context.prefix,
+            ],
+            &self.context.working_dir,
+        )?;
+        Ok(output
+            .lines()
+            .map(|path| self.context.working_dir.join(path))
+            .collect())
+    }
+
+    /// The devcontainer files added, modified and deleted between `rev` and HEAD
+    fn devcontainer_changes_since(&self, rev: &str) -> Result<UpdateSummary, CliError> {
+        let output = self.executor().execute_git_command(
+            &[
+                "diff",
+                "--name-status",
+                "--no-renames",
+                rev,
+                "HEAD",
+                "--",
+                &self.context.prefix,
+            ],
+            &self.context.working_dir,
+        )?;
+
+        let mut summary = UpdateSummary::default();
+        for line in output.lines() {
+            let Some((status, path)) = line.split_once('\t') else {
+                continue;
+            };
+            let files = match status {
+                "A" => &mut summary.added,
+                "D" => &mut summary.deleted,
+                _ => &mut summary.modified,
+            };
+            files.push(path.to_string());
+        }
+        Ok(summary)
+    }
+
+    fn resolve_commit(&self, rev: &str) -> Result<String, CliError> {
+        let executor = self.executor();
+        let output = executor.execute_git_command(
+            &["rev-parse", "--verify", &format!("{}^{{commit}}", rev)],
+            &self.context.working_dir,
+        )?;
+        Ok(output.trim().to_string())
+    }
+
+    /// Write the lockfile for the current tracking branch and subtree split, committing
+    /// it when the recorded revision changed
+    fn record_lockfile(&self, subtree_split: &str) -> Result<(), CliError> {
+        let lockfile = Lockfile::new(
+            self.resolve_commit(&self.context.tracking_branch)?,
+            subtree_split.to_string(),
+        );
+
+        let existing = Lockfile::read(&self.context.working_dir).ok().flatten();
+        if existing.as_ref() == Some(&lockfile) {
+            return Ok(());
+        }
+
+        lockfile.write(&self.context.working_dir)?;
+
+        let executor = self.executor();
+        executor.execute_git_command(&["add", "--", LOCKFILE_NAME], &self.context.working_dir)?;
+        executor.execute_git_command(
+            &["commit", "-m", LOCKFILE_COMMIT_MESSAGE, "--", LOCKFILE_NAME],
+            &self.context.working_dir,
+        )?;
+
+        log::debug!(
+            "Locked upstream revision {} (subtree split {})",
+            lockfile.upstream_commit,
+            lockfile.subtree_split
+        );
+
+        Ok(())
+    }
+
+    fn preserved_dir(&self) -> std::path::PathBuf {
+        self.context
+            .working_dir
+            .join(format!("{}.preserved", self.context.prefix))
+    }
+
+    /// The moves that preserve the devcontainer files matching `patterns`, by file name or
+    /// by path within the devcontainer, into `<prefix>.preserved/`. Every destination is
+    /// checked here, before anything moves, so a clash changes nothing.
+    fn files_to_preserve(
+        &self,
+        patterns: &[glob::Pattern],
+    ) -> Result<Vec<(std::path::PathBuf, std::path::PathBuf)>, CliError> {
+        let devcontainer_path = self.context.working_dir.join(&self.context.prefix);
+        if patterns.is_empty() || !devcontainer_path.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut files = Vec::new();
+        collect_files(&devcontainer_path, &mut files)?;
+        let preserved_dir = self.preserved_dir();
+        let mut moves = Vec::new();
+        for file in files {
+            let relative = file.strip_prefix(&devcontainer_path).unwrap_or(&file);
+            let file_name = relative.file_name().unwrap_or_default().to_string_lossy();
+            if !patterns
+                .iter()
+                .any(|pattern| pattern.matches(&file_name) || pattern.matches_path(relative))
+            {
+                continue;
+            }
+
+            let destination = preserved_dir.join(relative);
+            if destination.exists() {
+                return Err(CliError::FileSystem {
+                    message: format!(
+                        "Cannot preserve {}: {} already exists",
+                        self.display_path(&file),
+                        self.display_path(&destination)
+                    ),
+                    suggestion: format!(
+                        "Move {} out of the way, then retry the removal",
+                        self.display_path(&preserved_dir)
+                    ),
+                });
+            }
+            moves.push((file, destination));
+        }
+        Ok(moves)
+    }
+
+    /// Make the moves `files_to_preserve` planned, returning where the files ended up
+    fn preserve_files(
+        &self,
+        moves: Vec<(std::path::PathBuf, std::path::PathBuf)>,
+    ) -> Result<Vec<std::path::PathBuf>, CliError> {
+        let mut preserved = Vec::new();
+        for (file, destination) in moves {
+            let move_error = |e: std::io::Error| CliError::FileSystem {
+                message: format!("Failed to preserve {}: {}", self.display_path(&file), e),
+                suggestion: "Check file permissions and try again".to_string(),
+            };
+            if let Some(parent) = destination.parent() {
+                std::fs::create_dir_all(parent).map_err(move_error)?;
+            }
+            std::fs::rename(&file, &destination).map_err(move_error)?;
+            log::debug!(
+                "Preserved {} as {}",
+                self.display_path(&file),
+                self.display_path(&destination)
+            );
+            preserved.push(destination);
+        }
+        Ok(preserved)
+    }
+
+    fn create_backup(&self) -> Result<(), CliError> {
+        let (devcontainer_path, backup_path) = self.backup_paths()?;
+
+        // Remove existing backup if it exists
+        if backup_path.exists() {
+            std::fs::remove_dir_all(&backup_path).map_err(|e| CliError::FileSystem {
+                message: format!("Failed to remove existing backup directory: {}", e),
+                suggestion: "Check file permissions and try again".to_string(),
+            })?;
+        }
+
+        // Copy .devcontainer to .devcontainer.backup
+        self.copy_directory(&devcontainer_path, &backup_path)?;
+
+        log::debug!("Backup created at: {}", self.display_path(&backup_path));
+
+        Ok(())
+    }
+
+    /// The devcontainer and where `update --backup` copies it, once both are checked to be
+    /// usable
+    fn backup_paths(&self) -> Result<(std::path::PathBuf, std::path::PathBuf), CliError> {
+        let devcontainer_path = self.context.working_dir.join(&self.context.prefix);
+        let backup_path = self
+            .context
+            .working_dir
+            .join(format!("{}.backup", self.context.prefix));
+
+        // Check if .devcontainer exists
+        if !devcontainer_path.exists() {
+            return Err(CliError::FileSystem {
+                message: format!("No {} directory found to backup", self.context.prefix),
+                suggestion:
+                    "Run 'devcontainer-sync init' first to create devcontainer configuration"
+                        .to_string(),
+            });
+        }
+
+        // Only a previous backup directory is replaced; anything else is left alone
+        if backup_path.exists() && !backup_path.is_dir() {
+            return Err(CliError::FileSystem {
+                message: format!(
+                    "Cannot create backup: {} exists and is not a directory",
+                    self.display_path(&backup_path)
+                ),
+                suggestion: "Move or delete that file, then retry the update".to_string(),
+            });
+        }
+
+        Ok((devcontainer_path, backup_path))
+    }
+
+    /// With --autostash, note the stash a dry run would set uncommitted changes aside in,
+    /// returning whether there is one
+    fn plan_autostash(&self, planned: &mut Vec<String>) -> Result<bool, CliError> {
+        if !self.context.dry_run || !self.context.autostash {
+            return Ok(false);
+        }
+        let status = self
+            .executor()
+            .execute_git_command(&["status", "--porcelain"], &self.context.working_dir)?;
+        if status.trim().is_empty() {
+            return Ok(false);
+        }
+        planned.push(planned_git(&[
+            "stash",
+            "push",
+            "--include-untracked",
+            "-m",
+            AUTOSTASH_MESSAGE,
+        ]));
+        Ok(true)
+    }
+
+    /// Strip a copy of the upstream devcontainer at `commit`, to show the files a dry run's
+    /// firewall strip would change and the commit it would make as `message`. With
+    /// `changed_since`, only the files upstream changed after that commit are stripped, as
+    /// `--incremental-strip` does.
+    fn preview_strip(
+        &self,
+        context: &CommandContext,
+        commit: &str,
+        changed_since: Option<&str>,
+        message: &str,
+    ) -> Result<StripPreview, CliError> {
+        let scratch = self.extract_upstream_devcontainer(commit, "dry-run")?;
+        let devcontainer_path = scratch.path.join(&context.upstream_prefix);
+        // Prompts belong to the real run; the dry run reports everything it would strip
+        let strip_context = context
+            .clone()
+            .with_select_scripts(false)
+            .with_preview_diff(false);
+        let customizer = self.customizer_for(&strip_context, devcontainer_path.clone());
+        let result = match changed_since {
+            Some(rev) => {
+                let output = self.executor().execute_git_command(
+                    &[
+                        "diff",
+                        "--name-only",
+                        "--diff-filter=d",
+                        rev,
+                        commit,
+                        "--",
+                        &context.upstream_prefix,
+                    ],
+                    &context.working_dir,
+                )?;
+                let changed: Vec<_> = output.lines().map(|path| scratch.path.join(path)).collect();
+                customizer.strip_changed_firewall_features(&devcontainer_path, &changed)?
+            }
+            None => customizer.strip_firewall_features(&devcontainer_path)?,
+        };
+
+        let files = result
+            .touched_files()
+            .into_iter()
+            .map(|path| {
+                let relative = path.strip_prefix(&devcontainer_path).unwrap_or(path);
+                repo_relative(
+                    &std::path::Path::new(&context.prefix).join(relative),
+                    std::path::Path::new(""),
+                )
+            })
+            .collect();
+        Ok(StripPreview {
+            files,
+            message: result
+                .has_changes()
+                .then(|| customization_commit_message(message, &result)),
+        })
+    }
+
+    /// Print what git subtree split warned about while rewriting commits, with --verbose
+    fn report_split_warnings(&self, split: &Split) {
+        for warning in &split.warnings {
+            log::debug!("⚠️  git subtree split: {}", warning);
+        }
+    }
+
+    /// Add what a firewall strip changed, and the warnings worth acting on, to `result`
+    fn record_firewall_outcome(
+        &self,
+        result: &mut OperationResult,
+        firewall: Option<&FirewallRemovalResult>,
+        failure: Option<&CliError>,
+    ) {
+        if let Some(firewall) = firewall {
+            for removed in &firewall.files_removed {
+                result.add_change(format!("Removed {}", self.display_path(removed)));
+            }
+            for change in firewall.all_changes() {
+                result.add_change(change);
+            }
+            for warning in firewall.warnings_at_least(Severity::Warn) {
+                result.add_warning(format!("{}: {}", warning.severity, warning.message));
+            }
+        }
+        if let Some(failure) = failure {
+            result.add_warning(format!("Firewall stripping failed: {}", failure));
+        }
+    }
+
+    /// Refuse to sync into the home directory or the filesystem root, where a stray
+    /// `.devcontainer` and `claude` remote would be scattered somewhere unexpected
+    fn guard_against_unsafe_location(&self) -> Result<(), CliError> {
+        let canonical = |path: &std::path::Path| {
+            std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+        };
+        let working_dir = canonical(&self.context.working_dir);
+
+        if working_dir.parent().is_none() {
+            return Err(CliError::unsafe_sync_location(
+                &working_dir,
+                "the filesystem root",
+            ));
+        }
+        if let Some(home_dir) = &self.context.home_dir {
+            if canonical(home_dir) == working_dir {
+                return Err(CliError::unsafe_sync_location(
+                    &working_dir,
+                    "your home directory",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Init force-resets its branches, which is only safe for leftovers of an earlier
+    /// sync: those hold upstream history, unrelated to the user's own branches
+    fn guard_against_branch_in_use(&self, branch: &str, flag: &str) -> Result<(), CliError> {
+        if !self.validator().check_existing_branch(branch)? {
+            return Ok(());
+        }
+        let shares_history = self
+            .executor()
+            .execute_git_command(&["merge-base", "HEAD", branch], &self.context.working_dir)
+            .is_ok();
+        if shares_history {
+            return Err(CliError::branch_in_use(branch, flag));
+        }
+        Ok(())
+    }
+
+    /// Init adds the upstream remote itself, so an existing one means the repository is
+    /// already synced, unless it points somewhere else and the name is simply taken
+    fn existing_remote_error(&self, remote_manager: &impl RemoteManager) -> CliError {
+        match self.remote_url(remote_manager) {
+            Some(url) if url != self.context.repo_url => {
+                CliError::remote_name_taken(&self.context.remote_name, &url)
+            }
+            _ => CliError::remote_exists(&self.context.remote_name),
+        }
+    }
+
+    /// Whether the remote is the one init added: the tracking branch follows it, or it
+    /// points at the upstream repository
+    fn owns_remote(&self, remote_manager: &impl RemoteManager) -> bool {
+        let tracked_remote = self
+            .executor()
+            .execute_git_command(
+                &[
+                    "config",
+                    "--get",
+                    &format!("branch.{}.remote", self.context.tracking_branch),
+                ],
+                &self.context.working_dir,
+            )
+            .ok();
+        if tracked_remote.is_some_and(|remote| remote.trim() == self.context.remote_name) {
+            return true;
+        }
+        // A missing remote counts as ours, so removing it reports that it is missing
+        self.remote_url(remote_manager)
+            .is_none_or(|url| url == self.context.repo_url)
+    }
+
+    /// Whether git tracks any files under the devcontainer prefix
+    fn prefix_tracked(&self) -> Result<bool, CliError> {
+        Ok(!self
+            .executor()
+            .execute_git_command(
+                &["ls-files", "--", &self.context.prefix],
+                &self.context.working_dir,
+            )?
+            .trim()
+            .is_empty())
+    }
+
+    fn remote_url(&self, remote_manager: &impl RemoteManager) -> Option<String> {
+        remote_manager
+            .list_remotes()
+            .ok()?
+            .into_iter()
+            .find(|remote| remote.name == self.context.remote_name)
+            .map(|remote| remote.url)
+    }
+
+    /// A tracking branch left by an earlier init follows a branch of the upstream
+    /// remote; one following anything else belongs to someone else
+    fn guard_against_foreign_tracking_branch(&self, branch: &str) -> Result<(), CliError> {
+        if !self.validator().check_existing_branch(branch)? {
+            return Ok(());
+        }
+        let upstream_spec = format!("{}@{{upstream}}", branch);
+        let upstream = self
+            .executor()
+            .execute_git_command(
+                &["rev-parse", "--abbrev-ref", &upstream_spec],
+                &self.context.working_dir,
+            )
+            .map(|output| output.trim().to_string())
+            .ok();
+        let remote_prefix = format!("{}/", self.context.remote_name);
+        match upstream {
+            Some(upstream) if upstream.starts_with(&remote_prefix) => Ok(()),
+            upstream => Err(CliError::foreign_tracking_branch(
+                branch,
+                &self.context.remote_name,
+                upstream.as_deref(),
+            )),
+        }
+    }
+
+    /// A path as shown to the user: relative to the repository root
+    fn display_path(&self, path: &std::path::Path) -> String {
+        repo_relative(path, &self.context.working_dir)
+    }
+
+    fn copy_directory(&self, src: &std::path::Path, dst: &std::path::Path) -> Result<(), CliError> {
+        std::fs::create_dir_all(dst).map_err(|e| CliError::FileSystem {
+            message: format!("Failed to create backup directory: {}", e),
+            suggestion: "Check file permissions and available disk space".to_string(),
+        })?;
+
+        for entry in std::fs::read_dir(src).map_err(|e| CliError::FileSystem {
+            message: format!("Failed to read .devcontainer directory: {}", e),
+            suggestion: "Check file permissions".to_string(),
+        })? {
+            let entry = entry.map_err(|e| CliError::FileSystem {
+                message: format!("Failed to read directory entry: {}", e),
+                suggestion: "Check file permissions".to_string(),
+            })?;
+
+            let src_path = entry.path();
+            let dst_path = dst.join(entry.file_name());
+
+            if src_path.is_dir() {
+                self.copy_directory(&src_path, &dst_path)?;
+            } else {
+                // fs::copy carries the permission bits over, keeping scripts executable
+                std::fs::copy(&src_path, &dst_path).map_err(|e| CliError::FileSystem {
+                    message: format!(
+                        "Failed to copy file {}: {}",
+                        self.display_path(&src_path),
+                        e
+                    ),
+                    suggestion: "Check file permissions and available disk space".to_string(),
+                })?;
+            }
+        }
+
+        // Applied last so a read-only source directory doesn't block copying into it
+        let permissions = std::fs::metadata(src)
+            .map(|metadata| metadata.permissions())
+            .map_err(|e| CliError::FileSystem {
+                message: format!(
+                    "Failed to read permissions of {}: {}",
+                    self.display_path(src),
+                    e
+                ),
+                suggestion: "Check file permissions".to_string(),
+            })?;
+        std::fs::set_permissions(dst, permissions).map_err(|e| CliError::FileSystem {
+            message: format!(
+                "Failed to set backup permissions on {}: {}",
+                self.display_path(dst),
+                e
+            ),
+            suggestion: "Check file permissions".to_string(),
+        })?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::customizer::FirewallRemovalResult;
+    use std::cell::RefCell;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::rc::Rc;
+    use tempfile::TempDir;
+
+    fn git(args: &[&str], dir: &Path) -> String {
+        let output = std::process::Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .expect("Failed to run git");
+        assert!(
+            output.status.success(),
+            "git {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).to_string()
+    }
+
+    fn init_repo(path: &Path, branch: &str) {
+        git(&["init", &format!("--initial-branch={}", branch)], path);
+        git(&["config", "user.name", "Test User"], path);
+        git(&["config", "user.email", "test@example.com"], path);
+    }
+
+    /// A local upstream with a devcontainer, and a consumer repository with one commit
+    fn fixture_repos() -> (TempDir, TempDir) {
+        let upstream_dir = TempDir::new().unwrap();
+        let upstream = upstream_dir.path();
+        init_repo(upstream, "main");
+        fs::create_dir(upstream.join(".devcontainer")).unwrap();
+        fs::write(
+            upstream.join(".devcontainer/devcontainer.json"),
+            "{\"name\": \"Test\", \"runArgs\": [\"--cap-add=NET_ADMIN\"]}\n",
+        )
+        .unwrap();
+        git(&["add", "."], upstream);
+        git(&["commit", "-m", "Add devcontainer"], upstream);
+
+        let repo_dir = TempDir::new().unwrap();
+        let repo = repo_dir.path();
+        init_repo(repo, MASTER_BRANCH);
+        fs::write(repo.join("README.md"), "# Test\n").unwrap();
+        git(&["add", "."], repo);
+        git(&["commit", "-m", "Initial commit"], repo);
+
+        (upstream_dir, repo_dir)
+    }
+
+    fn app_for(upstream: &Path, repo: &Path) -> CliApp {
+        CliApp::new(false)
+            .with_working_dir(repo.to_path_buf())
+            .with_repo_url(upstream.display().to_string())
+            .with_format(OutputFormat::Ndjson)
+    }
+
+    /// Records the git commands it is asked to run, failing any that start with `fail_on`
+    struct RecordingExecutor {
+        commands: Rc<RefCell<Vec<String>>>,
+        fail_on: Option<&'static str>,
+        /// Output returned for commands starting with the first element
+        output: Option<(&'static str, &'static str)>,
+    }
+
+    impl GitExecutor for RecordingExecutor {
+        fn execute_git_command(
+            &self,
+            args: &[&str],
+            working_dir: &Path,
+        ) -> Result<String, CliError> {
+            self.execute_git_command_with_timeout(args, working_dir, default_timeout())
+        }
+
+        fn execute_git_command_with_timeout(
+            &self,
+            args: &[&str],
+            _: &Path,
+            _: std::time::Duration,
+        ) -> Result<String, CliError> {
+            let command = args.join(" ");
+            self.commands.borrow_mut().push(command.clone());
+            match self.fail_on {
+                Some(prefix) if command.starts_with(prefix) => Err(CliError::GitOperation {
+                    message: format!("git {} failed", command),
+                    suggestion: String::new(),
+                }),
+                _ => match self.output {
+                    Some((prefix, output)) if command.starts_with(prefix) => Ok(output.to_string()),
+                    _ => Ok(String::new()),
+                },
+            }
+        }
+    }
+
+    /// Fails its first `failures` fetches with `stderr`, a dropped connection by default
+    struct FlakyExecutor {
+        fetches: RefCell<u32>,
+        failures: u32,
+        stderr: &'static str,
+    }
+
+    impl GitExecutor for FlakyExecutor {
+        fn execute_git_command(
+            &self,
+            args: &[&str],
+            working_dir: &Path,
+        ) -> Result<String, CliError> {
+            self.execute_git_command_with_timeout(args, working_dir, default_timeout())
+        }
+
+        fn execute_git_command_with_timeout(
+            &self,
+            args: &[&str],
+            _: &Path,
+            _: std::time::Duration,
+        ) -> Result<String, CliError> {
+            if args.first() != Some(&"fetch") {
+                return Ok(String::new());
+            }
+            *self.fetches.borrow_mut() += 1;
+            if *self.fetches.borrow() <= self.failures {
+                return Err(CliError::git_command_failed(args, self.stderr));
+            }
+            Ok(String::new())
+        }
+    }
+
+    /// Records the paths it is asked to strip, without changing anything
+    struct RecordingCustomizer {
+        stripped: Rc<RefCell<Vec<PathBuf>>>,
+    }
+
+    impl DevcontainerCustomizer for RecordingCustomizer {
+        fn strip_firewall_features(
+            &self,
+            devcontainer_path: &Path,
+        ) -> Result<FirewallRemovalResult, CliError> {
+            self.stripped
+                .borrow_mut()
+                .push(devcontainer_path.to_path_buf());
+            Ok(FirewallRemovalResult::new())
+        }
+
+        fn detect_firewall_scripts(&self, _: &Path) -> Result<Vec<PathBuf>, CliError> {
+            Ok(Vec::new())
+        }
+
+        fn strip_devcontainer_json_firewall(&self, _: &Path) -> Result<Vec<String>, CliError> {
+            Ok(Vec::new())
+        }
+
+        fn strip_dockerfile_firewall(&self, _: &Path) -> Result<Vec<String>, CliError> {
+            Ok(Vec::new())
+        }
+
+        fn validate_firewall_removal(&self, _: &FirewallRemovalResult) -> Vec<Warning> {
+            Vec::new()
+        }
+
+        fn commit_customizations(
+            &self,
+            _: &FirewallRemovalResult,
+            _: &str,
+        ) -> Result<(), CliError> {
+            Ok(())
+        }
+    }
+
+    /// Reports a change to devcontainer.json, then fails to commit it
+    struct UncommittableCustomizer;
+
+    impl DevcontainerCustomizer for UncommittableCustomizer {
+        fn strip_firewall_features(
+            &self,
+            devcontainer_path: &Path,
+        ) -> Result<FirewallRemovalResult, CliError> {
+            let mut result = FirewallRemovalResult::new();
+            result.add_modified_file(devcontainer_path.join("devcontainer.json"));
+            result.add_json_change("Removed NET_ADMIN capability".to_string());
+            Ok(result)
+        }
+
+        fn detect_firewall_scripts(&self, _: &Path) -> Result<Vec<PathBuf>, CliError> {
+            Ok(Vec::new())
+        }
+
+        fn strip_devcontainer_json_firewall(&self, _: &Path) -> Result<Vec<String>, CliError> {
+            Ok(Vec::new())
+        }
+
+        fn strip_dockerfile_firewall(&self, _: &Path) -> Result<Vec<String>, CliError> {
+            Ok(Vec::new())
+        }
+
+        fn validate_firewall_removal(&self, _: &FirewallRemovalResult) -> Vec<Warning> {
+            Vec::new()
+        }
+
+        fn commit_customizations(
+            &self,
+            _: &FirewallRemovalResult,
+            _: &str,
+        ) -> Result<(), CliError> {
+            Err(CliError::git_command_failed(
+                &["commit"],
+                "nothing added to commit",
+            ))
+        }
+    }
+
+    #[test]
+    fn test_init_dry_run_returns_its_plan_and_changes_nothing() {
+        let (upstream_dir, repo_dir) = fixture_repos();
+        let repo = repo_dir.path();
+        let refs_before = git(&["for-each-ref"], repo);
+
+        let (report, result) = app_for(upstream_dir.path(), repo)
+            .with_dry_run(true)
+            .init_with_result(false, false, false)
+            .unwrap();
+
+        assert!(report.is_none());
+        assert!(result
+            .message
+            .starts_with("Dry run: init would add .devcontainer"));
+        assert!(result.changes.contains(&format!(
+            "git remote add claude {}",
+            upstream_dir.path().display()
+        )));
+        assert_eq!(git(&["for-each-ref"], repo), refs_before);
+        assert!(git(&["remote"], repo).trim().is_empty());
+        assert!(!repo.join(DEVCONTAINER_PREFIX).exists());
+    }
+
+    #[test]
+    fn test_init_returns_report_matching_repository() {
+        let (upstream_dir, repo_dir) = fixture_repos();
+        let repo = repo_dir.path();
+
+        let report = app_for(upstream_dir.path(), repo)
+            .init(true, false, false)
+            .unwrap()
+            .unwrap();
+
+        assert!(report.remote_added);
+        assert_eq!(
+            git(&["remote"], repo).trim(),
+            CLAUDE_REMOTE_NAME,
+            "remote should exist"
+        );
+        assert_eq!(report.tracking_branch, CLAUDE_BRANCH_NAME);
+        assert_eq!(
+            report.subtree_commit,
+            git(&["rev-parse", DEVCONTAINER_BRANCH], repo).trim()
+        );
+        assert_eq!(report.devcontainer_path, repo.join(DEVCONTAINER_PREFIX));
+        assert!(report.devcontainer_path.join("devcontainer.json").exists());
+
+        let firewall = report.firewall.expect("firewall result");
+        assert!(firewall
+            .json_changes
+            .iter()
+            .any(|change| change.contains("NET_ADMIN")));
+    }
+
+    #[test]
+    fn test_init_from_a_detached_head_returns_to_that_commit() {
+        let (upstream_dir, repo_dir) = fixture_repos();
+        let repo = repo_dir.path();
+        let start = git(&["rev-parse", "HEAD"], repo);
+        git(&["checkout", "--detach"], repo);
+        git(&["branch", "-D", MASTER_BRANCH], repo);
+
+        app_for(upstream_dir.path(), repo)
+            .init(false, false, false)
+            .unwrap();
+
+        // The subtree is added on top of the detached commit, not on a master branch
+        let current = std::process::Command::new("git")
+            .args(["symbolic-ref", "-q", "HEAD"])
+            .current_dir(repo)
+            .output()
+            .unwrap();
+        assert!(!current.status.success(), "HEAD should still be detached");
+        assert!(git(&["log", "--format=%H", "HEAD"], repo).contains(start.trim()));
+        assert!(!git(&["branch", "--format=%(refname:short)"], repo)
+            .lines()
+            .any(|branch| branch == MASTER_BRANCH));
+        assert!(repo
+            .join(DEVCONTAINER_PREFIX)
+            .join("devcontainer.json")
+            .exists());
+    }
+
+    #[test]
+    fn test_custom_branch_names_are_used_by_init_update_and_remove() {
+        let (upstream_dir, repo_dir) = fixture_repos();
+        let repo = repo_dir.path();
+        let app = || {
+            app_for(upstream_dir.path(), repo)
+                .with_tracking_branch("upstream-claude".to_string())
+                .with_work_branch("dc-split".to_string())
+        };
+
+        let report = app().init(false, false, false).unwrap().unwrap();
+        assert_eq!(report.tracking_branch, "upstream-claude");
+        assert_eq!(
+            report.subtree_commit,
+            git(&["rev-parse", "dc-split"], repo).trim()
+        );
+
+        app()
+            .update(false, false, false, false, None, false)
+            .unwrap();
+        let branches = git(&["branch", "--format=%(refname:short)"], repo);
+        assert!(branches.lines().any(|b| b == "dc-split-updated"));
+        assert!(!branches.lines().any(|b| b == CLAUDE_BRANCH_NAME));
+        assert!(!branches.lines().any(|b| b == DEVCONTAINER_BRANCH));
+
+        app().remove(false, false, &[]).unwrap();
+        assert_eq!(
+            git(&["branch", "--format=%(refname:short)"], repo).trim(),
+            MASTER_BRANCH
+        );
+    }
+
+    #[test]
+    fn test_init_refuses_to_reset_a_user_branch_without_force() {
+        let (upstream_dir, repo_dir) = fixture_repos();
+        let repo = repo_dir.path();
+        git(&["branch", DEVCONTAINER_BRANCH], repo);
+        let user_commit = git(&["rev-parse", DEVCONTAINER_BRANCH], repo);
+
+        match app_for(upstream_dir.path(), repo).init(false, false, false) {
+            Err(CliError::Repository {
+                message,
+                suggestion,
+            }) => {
+                assert!(message.contains("'devcontainer'"));
+                assert!(suggestion.contains("--work-branch"));
+            }
+            other => panic!("Expected Repository error, got {:?}", other),
+        }
+        assert_eq!(git(&["rev-parse", DEVCONTAINER_BRANCH], repo), user_commit);
+        assert_eq!(git(&["remote"], repo).trim(), "");
+
+        app_for(upstream_dir.path(), repo)
+            .init(false, false, true)
+            .unwrap();
+        assert_ne!(git(&["rev-parse", DEVCONTAINER_BRANCH], repo), user_commit);
+    }
+
+    #[test]
+    fn test_init_refuses_to_reset_a_tracking_branch_of_another_remote() {
+        let (upstream_dir, repo_dir) = fixture_repos();
+        let repo = repo_dir.path();
+        // Unrelated history, so only the missing upstream gives it away
+        let empty_tree = git(&["hash-object", "-t", "tree", "/dev/null"], repo);
+        let orphan = git(
+            &[
+                "commit-tree",
+                empty_tree.trim(),
+                "-m",
+                "Someone else's branch",
+            ],
+            repo,
+        );
+        git(&["branch", CLAUDE_BRANCH_NAME, orphan.trim()], repo);
+        let foreign_commit = git(&["rev-parse", CLAUDE_BRANCH_NAME], repo);
+
+        match app_for(upstream_dir.path(), repo).init(false, false, false) {
+            Err(CliError::Repository {
+                message,
+                suggestion,
+            }) => {
+                assert!(message.contains("'claude-main'"));
+                assert!(message.contains("tracks no remote branch"));
+                assert!(suggestion.contains("--tracking-branch"));
+            }
+            other => panic!("Expected Repository error, got {:?}", other),
+        }
+        assert_eq!(
+            git(&["rev-parse", CLAUDE_BRANCH_NAME], repo),
+            foreign_commit
+        );
+
+        app_for(upstream_dir.path(), repo)
+            .init(false, false, true)
+            .unwrap();
+        assert_ne!(
+            git(&["rev-parse", CLAUDE_BRANCH_NAME], repo),
+            foreign_commit
+        );
+    }
+
+    #[test]
+    fn test_init_refuses_a_remote_name_taken_by_another_repository() {
+        let (upstream_dir, repo_dir) = fixture_repos();
+        let repo = repo_dir.path();
+        git(
+            &[
+                "remote",
+                "add",
+                CLAUDE_REMOTE_NAME,
+                "https://example.com/other.git",
+            ],
+            repo,
+        );
+
+        match app_for(upstream_dir.path(), repo).init(false, false, false) {
+            Err(CliError::Repository {
+                message,
+                suggestion,
+            }) => {
+                assert!(message.contains("https://example.com/other.git"));
+                assert!(suggestion.contains("--remote-name"));
+            }
+            other => panic!("Expected Repository error, got {:?}", other),
+        }
+        assert_eq!(
+            git(&["remote", "get-url", CLAUDE_REMOTE_NAME], repo).trim(),
+            "https://example.com/other.git"
+        );
+        assert!(!repo.join(DEVCONTAINER_PREFIX).exists());
+
+        app_for(upstream_dir.path(), repo)
+            .with_remote_name("upstream".to_string())
+            .init(false, false, false)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_remove_leaves_a_remote_init_did_not_add() {
+        let (upstream_dir, repo_dir) = fixture_repos();
+        let repo = repo_dir.path();
+        app_for(upstream_dir.path(), repo)
+            .with_remote_name("upstream".to_string())
+            .init(false, false, false)
+            .unwrap();
+        git(
+            &[
+                "remote",
+                "add",
+                CLAUDE_REMOTE_NAME,
+                "https://example.com/other.git",
+            ],
+            repo,
+        );
+
+        // Forgetting --remote-name must not take the unrelated 'claude' remote with it
+        let result = app_for(upstream_dir.path(), repo)
+            .remove(true, false, &[])
+            .unwrap();
+
+        assert!(result.warnings[0].contains("Left remote 'claude' in place"));
+        assert_eq!(
+            git(&["remote", "get-url", CLAUDE_REMOTE_NAME], repo).trim(),
+            "https://example.com/other.git"
+        );
+    }
+
+    #[test]
+    fn test_init_strips_with_injected_customizer() {
+        let (upstream_dir, repo_dir) = fixture_repos();
+        let repo = repo_dir.path();
+        let stripped = Rc::new(RefCell::new(Vec::new()));
+
+        app_for(upstream_dir.path(), repo)
+            .with_customizer(Box::new(RecordingCustomizer {
+                stripped: Rc::clone(&stripped),
+            }))
+            .init(true, false, false)
+            .unwrap();
+
+        assert_eq!(*stripped.borrow(), vec![repo.join(DEVCONTAINER_PREFIX)]);
+        // The mock changed nothing, so the firewall setup is still there
+        let json = fs::read_to_string(repo.join(".devcontainer/devcontainer.json")).unwrap();
+        assert!(json.contains("NET_ADMIN"));
+    }
+
+    #[test]
+    fn test_init_warns_when_the_strip_commit_fails() {
+        let (upstream_dir, repo_dir) = fixture_repos();
+
+        let (_, result) = app_for(upstream_dir.path(), repo_dir.path())
+            .with_customizer(Box::new(UncommittableCustomizer))
+            .init_with_result(true, false, false)
+            .unwrap();
+
+        assert!(result.success);
+        assert!(result
+            .warnings
+            .iter()
+            .any(|warning| warning.starts_with("Firewall stripping failed:")
+                && warning.contains("nothing added to commit")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_create_backup_preserves_contents_and_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let repo_dir = TempDir::new().unwrap();
+        let repo = repo_dir.path();
+        let scripts = repo.join(".devcontainer/scripts");
+        fs::create_dir_all(&scripts).unwrap();
+        fs::write(repo.join(".devcontainer/devcontainer.json"), "{}\n").unwrap();
+        fs::write(scripts.join("setup.sh"), "#!/bin/sh\n").unwrap();
+        fs::set_permissions(scripts.join("setup.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+
+        let app = CliApp::new(false).with_working_dir(repo.to_path_buf());
+        app.create_backup().unwrap();
+
+        let backup = repo.join(".devcontainer.backup");
+        assert_eq!(
+            fs::read_to_string(backup.join("devcontainer.json")).unwrap(),
+            "{}\n"
+        );
+        let mode = fs::metadata(backup.join("scripts/setup.sh"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o755);
+    }
+
+    #[test]
+    fn test_create_backup_refuses_to_replace_a_file() {
+        let repo_dir = TempDir::new().unwrap();
+        let repo = repo_dir.path();
+        fs::create_dir(repo.join(".devcontainer")).unwrap();
+        fs::write(repo.join(".devcontainer.backup"), "blocking file").unwrap();
+
+        let app = CliApp::new(false).with_working_dir(repo.to_path_buf());
+        match app.create_backup() {
+            Err(CliError::FileSystem { message, .. }) => assert!(message.contains("backup")),
+            other => panic!("Expected FileSystem error, got {:?}", other),
+        }
+        assert_eq!(
+            fs::read_to_string(repo.join(".devcontainer.backup")).unwrap(),
+            "blocking file"
+        );
+    }
+
+    #[test]
+    fn test_init_rollback_undoes_recorded_steps_in_reverse() {
+        let commands = Rc::new(RefCell::new(Vec::new()));
+        let executor = RecordingExecutor {
+            commands: Rc::clone(&commands),
+            // A failing branch deletion must not stop the remote from being removed
+            fail_on: Some("branch -D devcontainer"),
+            output: None,
+        };
+
+        let mut rollback = InitRollback::new(executor, PathBuf::from("."));
+        rollback.remote_added(CLAUDE_REMOTE_NAME);
+        rollback.branch_created(CLAUDE_BRANCH_NAME);
+        rollback.branch_created(DEVCONTAINER_BRANCH);
+        drop(rollback);
+
+        assert_eq!(
+            *commands.borrow(),
+            vec![
+                "branch -D devcontainer",
+                "branch -D claude-main",
+                "remote remove claude",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_disarmed_init_rollback_leaves_repository_alone() {
+        let commands = Rc::new(RefCell::new(Vec::new()));
+        let executor = RecordingExecutor {
+            commands: Rc::clone(&commands),
+            fail_on: None,
+            output: None,
+        };
+
+        let mut rollback = InitRollback::new(executor, PathBuf::from("."));
+        rollback.remote_added(CLAUDE_REMOTE_NAME);
+        rollback.branch_created(CLAUDE_BRANCH_NAME);
+        rollback.disarm();
+
+        assert!(commands.borrow().is_empty());
+    }
+
+    fn flaky_fetch(failures: u32, retries: u32) -> (Result<(), CliError>, u32) {
+        failing_fetch(
+            "fatal: unable to access 'https://github.com/anthropics/claude-code.git/': Could not resolve host: github.com",
+            failures,
+            retries,
+        )
+    }
+
+    fn failing_fetch(
+        stderr: &'static str,
+        failures: u32,
+        retries: u32,
+    ) -> (Result<(), CliError>, u32) {
+        let executor = FlakyExecutor {
+            fetches: RefCell::new(0),
+            failures,
+            stderr,
+        };
+        let app = CliApp::new(false)
+            .with_retries(retries)
+            .with_retry_delay(std::time::Duration::ZERO);
+        let remote_manager = GitRemoteManager::new(&executor, PathBuf::from("."));
+
+        let result = app.fetch_upstream(&remote_manager, CLAUDE_UPSTREAM_BRANCH);
+        (result, executor.fetches.into_inner())
+    }
+
+    #[test]
+    fn test_fetch_is_retried_after_network_failures() {
+        let (result, fetches) = flaky_fetch(2, 3);
+
+        result.unwrap();
+        assert_eq!(fetches, 3);
+    }
+
+    #[test]
+    fn test_fetch_gives_up_once_retries_run_out() {
+        let (result, fetches) = flaky_fetch(5, 2);
+
+        assert!(matches!(
+            result,
+            Err(CliError::Network {
+                retryable: true,
+                ..
+            })
+        ));
+        assert_eq!(fetches, 3);
+    }
+
+    #[test]
+    fn test_fetch_is_not_retried_when_the_remote_refuses_or_lacks_the_repository() {
+        for stderr in [
+            "git@github.com: Permission denied (publickey).\nfatal: Could not read from remote repository.\n",
+            "fatal: Authentication failed for 'https://github.com/myorg/claude-code.git/'\n",
+            "fatal: '/nonexistent/repo.git' does not appear to be a git repository\nfatal: Could not read from remote repository.\n",
+        ] {
+            let (result, fetches) = failing_fetch(stderr, 5, 3);
+
+            assert!(result.is_err());
+            assert_eq!(fetches, 1, "{}", stderr);
+        }
+    }
+
+    #[test]
+    fn test_fetch_from_a_missing_remote_is_not_retried() {
+        let commands = Rc::new(RefCell::new(Vec::new()));
+        let executor = RecordingExecutor {
+            commands: Rc::clone(&commands),
+            fail_on: Some("remote get-url"),
+            output: None,
+        };
+        let app = CliApp::new(false).with_retry_delay(std::time::Duration::ZERO);
+        let remote_manager = GitRemoteManager::new(&executor, PathBuf::from("."));
+
+        let result = app.fetch_upstream(&remote_manager, CLAUDE_UPSTREAM_BRANCH);
+
+        assert!(matches!(result, Err(CliError::GitOperation { .. })));
+        assert_eq!(*commands.borrow(), vec!["remote get-url claude"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_configured_timeout_reaches_the_executor() {
+        let dir = TempDir::new().unwrap();
+        // Stand-in for a git command stalled on the network
+        let app = CliApp::new(false)
+            .with_git_path(PathBuf::from("sleep"))
+            .with_timeout(std::time::Duration::from_millis(200));
+
+        let error = app
+            .executor()
+            .execute_git_command(&["5"], dir.path())
+            .unwrap_err();
+
+        assert!(error.to_string().ends_with("git 5 timed out after 0.2s"));
+    }
+
+    #[test]
+    fn test_cli_app_carries_dry_run_into_its_context() {
+        let app = CliApp::new(true).with_dry_run(true);
+
+        assert!(app.context.dry_run);
+        assert!(app.context.verbose);
+        assert!(!CliApp::new(false).context.dry_run);
+    }
+
+    #[test]
+    fn test_update_summary_lists_merged_files_and_upstream_revisions() {
+        let (upstream_dir, repo_dir) = fixture_repos();
+        let (upstream, repo) = (upstream_dir.path(), repo_dir.path());
+        let app = app_for(upstream, repo);
+        app.init(false, true, false).unwrap();
+
+        let old_upstream = git(&["rev-parse", "HEAD"], upstream);
+        fs::write(
+            upstream.join(".devcontainer/devcontainer.json"),
+            "{\"name\": \"Test v2\"}\n",
+        )
+        .unwrap();
+        git(&["commit", "-am", "Rename devcontainer"], upstream);
+        let new_upstream = git(&["rev-parse", "HEAD"], upstream);
+
+        let result = app.update(false, false, false, false, None, false).unwrap();
+
+        let summary = result.update.unwrap();
+        assert_eq!(summary.old_upstream, old_upstream.trim());
+        assert_eq!(summary.new_upstream, new_upstream.trim());
+        assert_eq!(summary.modified, vec![".devcontainer/devcontainer.json"]);
+        assert!(summary.added.is_empty() && summary.deleted.is_empty());
+        assert!(!summary.conflict);
+    }
+
+    #[test]
+    fn test_update_warns_when_the_strip_commit_fails() {
+        let (upstream_dir, repo_dir) = fixture_repos();
+        let (upstream, repo) = (upstream_dir.path(), repo_dir.path());
+        app_for(upstream, repo).init(false, true, false).unwrap();
+
+        fs::write(
+            upstream.join(".devcontainer/devcontainer.json"),
+            "{\"name\": \"Test v2\"}\n",
+        )
+        .unwrap();
+        git(&["commit", "-am", "Rename devcontainer"], upstream);
+
+        let result = app_for(upstream, repo)
+            .with_customizer(Box::new(UncommittableCustomizer))
+            .update(false, false, true, false, None, false)
+            .unwrap();
+
+        assert!(result.success);
+        assert!(result
+            .warnings
+            .iter()
+            .any(|warning| warning.starts_with("Firewall stripping failed:")
+                && warning.contains("nothing added to commit")));
+    }
+
+    #[test]
+    fn test_update_reports_an_upstream_branch_that_was_renamed() {
+        let (upstream_dir, repo_dir) = fixture_repos();
+        let (upstream, repo) = (upstream_dir.path(), repo_dir.path());
+        let app = app_for(upstream, repo).with_upstream_branch("main".to_string());
+        app.init(false, true, false).unwrap();
+
+        git(&["branch", "-m", "main", "trunk"], upstream);
+        let error = app
+            .update(false, false, false, false, None, false)
+            .unwrap_err();
+
+        assert!(matches!(error, CliError::Repository { .. }));
+        assert!(error
+            .to_string()
+            .contains("Branch 'main' no longer exists on remote 'claude'"));
+        assert!(error.suggestion().contains("default_branch"));
+    }
+
+    fn merge_failure() -> CliError {
+        CliError::GitOperation {
+            message: "git subtree merge failed".to_string(),
+            suggestion: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_conflicting_merge_is_aborted_without_force() {
+        let commands = Rc::new(RefCell::new(Vec::new()));
+        let executor = RecordingExecutor {
+            commands: Rc::clone(&commands),
+            fail_on: None,
+            output: Some(("diff --name-only", ".devcontainer/Dockerfile\n")),
+        };
+        let subtree_manager = GitSubtreeManager::new(executor, PathBuf::from("."));
+
+        let error =
+            settle_merge_conflict(&subtree_manager, ".devcontainer", false, merge_failure())
+                .unwrap_err();
+
+        assert_eq!(error.exit_code(), 5);
+        match error {
+            CliError::Conflict {
+                message,
+                suggestion,
+                conflicting_paths,
+            } => {
+                assert!(message.contains(".devcontainer/Dockerfile"));
+                assert!(suggestion.contains("--force"));
+                assert_eq!(
+                    conflicting_paths,
+                    vec![PathBuf::from(".devcontainer/Dockerfile")]
+                );
+            }
+            other => panic!("Expected Conflict error, got {:?}", other),
+        }
+        assert_eq!(
+            *commands.borrow(),
+            vec!["diff --name-only --diff-filter=U", "merge --abort"]
+        );
+    }
+
+    #[test]
+    fn test_conflicting_merge_takes_upstream_with_force() {
+        let commands = Rc::new(RefCell::new(Vec::new()));
+        let executor = RecordingExecutor {
+            commands: Rc::clone(&commands),
+            // Upstream deleted init-firewall.sh, so there is no version of theirs
+            fail_on: Some("checkout --theirs -- .devcontainer/init-firewall.sh"),
+            output: Some((
+                "diff --name-only",
+                ".devcontainer/Dockerfile\n.devcontainer/init-firewall.sh\n",
+            )),
+        };
+        let subtree_manager = GitSubtreeManager::new(executor, PathBuf::from("."));
+
+        let resolved =
+            settle_merge_conflict(&subtree_manager, ".devcontainer", true, merge_failure())
+                .unwrap();
+
+        assert_eq!(
+            resolved,
+            vec![".devcontainer/Dockerfile", ".devcontainer/init-firewall.sh"]
+        );
+        assert_eq!(
+            *commands.borrow(),
+            vec![
+                "diff --name-only --diff-filter=U",
+                "checkout --theirs -- .devcontainer/Dockerfile",
+                "add -- .devcontainer/Dockerfile",
+                "checkout --theirs -- .devcontainer/init-firewall.sh",
+                "rm --quiet -- .devcontainer/init-firewall.sh",
+                "commit --no-edit",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_failure_without_conflicts_is_passed_through() {
+        let executor = RecordingExecutor {
+            commands: Rc::new(RefCell::new(Vec::new())),
+            fail_on: None,
+            output: None,
+        };
+        let subtree_manager = GitSubtreeManager::new(executor, PathBuf::from("."));
+
+        let error = settle_merge_conflict(&subtree_manager, ".devcontainer", true, merge_failure())
+            .unwrap_err();
+
+        assert!(error.to_string().contains("git subtree merge failed"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_failed_init_removes_remote_and_branches() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (upstream_dir, repo_dir) = fixture_repos();
+        let repo = repo_dir.path();
+        let scripts = TempDir::new().unwrap();
+        let fake_git = scripts.path().join("git");
+        fs::write(
+            &fake_git,
+            "#!/bin/sh\nif [ \"$1\" = subtree ] && [ \"$2\" = add ]; then\n  echo 'subtree add failed' >&2\n  exit 1\nfi\nexec git \"$@\"\n",
+        )
+        .unwrap();
+        fs::set_permissions(&fake_git, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let error = app_for(upstream_dir.path(), repo)
+            .with_git_path(fake_git)
+            .init(false, false, false)
+            .unwrap_err();
+
+        assert!(error.to_string().contains("subtree add failed"));
+        assert_eq!(git(&["remote"], repo).trim(), "");
+        assert_eq!(
+            git(&["branch", "--format=%(refname:short)"], repo).trim(),
+            MASTER_BRANCH
+        );
+    }
+
+    #[test]
+    fn test_init_refuses_home_directory_without_force() {
+        let (upstream_dir, repo_dir) = fixture_repos();
+        let repo = repo_dir.path();
+
+        let error = app_for(upstream_dir.path(), repo)
+            .with_home_dir(repo.to_path_buf())
+            .init(false, false, false)
+            .unwrap_err();
+
+        assert!(matches!(error, CliError::Repository { .. }));
+        assert!(error.to_string().contains("home directory"));
+        assert_eq!(git(&["remote"], repo).trim(), "");
+        assert!(!repo.join(DEVCONTAINER_PREFIX).exists());
+
+        app_for(upstream_dir.path(), repo)
+            .with_home_dir(repo.to_path_buf())
+            .init(false, false, true)
+            .unwrap();
+        assert!(repo.join(DEVCONTAINER_PREFIX).exists());
+    }
+
+    #[test]
+    fn test_init_refuses_dirty_working_tree_without_allow_dirty() {
+        let (upstream_dir, repo_dir) = fixture_repos();
+        let repo = repo_dir.path();
+        fs::write(repo.join("notes.txt"), "work in progress\n").unwrap();
+
+        let error = app_for(upstream_dir.path(), repo)
+            .init(false, false, false)
+            .unwrap_err();
+
+        assert!(matches!(error, CliError::Repository { .. }));
+        assert!(error.to_string().contains("notes.txt"));
+        assert_eq!(git(&["remote"], repo).trim(), "");
+
+        app_for(upstream_dir.path(), repo)
+            .with_allow_dirty(true)
+            .init(false, false, false)
+            .unwrap();
+        assert!(repo.join(DEVCONTAINER_PREFIX).exists());
+        assert!(repo.join("notes.txt").exists());
+    }
+
+    #[test]
+    fn test_update_with_autostash_keeps_uncommitted_changes() {
+        let (upstream_dir, repo_dir) = fixture_repos();
+        let repo = repo_dir.path();
+        app_for(upstream_dir.path(), repo)
+            .init(false, false, false)
+            .unwrap();
+        fs::write(
+            upstream_dir.path().join(".devcontainer/post-create.sh"),
+            "echo hi\n",
+        )
+        .unwrap();
+        git(&["add", "."], upstream_dir.path());
+        git(
+            &["commit", "-m", "Add post-create script"],
+            upstream_dir.path(),
+        );
+        fs::write(repo.join("README.md"), "# Test\n\nDraft\n").unwrap();
+        fs::write(repo.join("notes.txt"), "work in progress\n").unwrap();
+
+        app_for(upstream_dir.path(), repo)
+            .with_autostash(true)
+            .update(false, false, false, false, None, false)
+            .unwrap();
+
+        assert!(repo.join(".devcontainer/post-create.sh").exists());
+        assert_eq!(
+            fs::read_to_string(repo.join("README.md")).unwrap(),
+            "# Test\n\nDraft\n"
+        );
+        assert!(repo.join("notes.txt").exists());
+        assert_eq!(git(&["stash", "list"], repo).trim(), "");
+    }
+
+    #[test]
+    fn test_update_check_counts_upstream_commits_without_merging() {
+        let (upstream_dir, repo_dir) = fixture_repos();
+        let repo = repo_dir.path();
+        let app = || app_for(upstream_dir.path(), repo).with_check_updates(true);
+        app_for(upstream_dir.path(), repo)
+            .init(false, false, false)
+            .unwrap();
+
+        assert!(
+            app()
+                .update(false, false, false, false, None, false)
+                .unwrap()
+                .success
+        );
+
+        for script in ["post-create.sh", "post-start.sh"] {
+            fs::write(
+                upstream_dir.path().join(".devcontainer").join(script),
+                "echo hi\n",
+            )
+            .unwrap();
+            git(&["add", "."], upstream_dir.path());
+            git(
+                &["commit", "-m", &format!("Add {}", script)],
+                upstream_dir.path(),
+            );
+        }
+        let head = git(&["rev-parse", "HEAD"], repo);
+
+        let result = app()
+            .update(false, false, false, false, None, false)
+            .unwrap();
+
+        assert!(result.success);
+        assert_eq!(result.upstream_commits, Some(2));
+        assert!(result.message.contains("2 upstream commits"));
+        assert_eq!(git(&["rev-parse", "HEAD"], repo), head);
+        assert!(!repo.join(".devcontainer/post-create.sh").exists());
+    }
+
+    #[test]
+    fn test_autostash_keeps_the_stash_when_pop_conflicts() {
+        let (_upstream_dir, repo_dir) = fixture_repos();
+        let repo = repo_dir.path();
+        fs::write(repo.join("README.md"), "# Mine\n").unwrap();
+
+        let autostash = Autostash::push(SystemGitExecutor::new(), repo.to_path_buf()).unwrap();
+        assert!(autostash.stash.is_some());
+        fs::write(repo.join("README.md"), "# Theirs\n").unwrap();
+        git(&["commit", "-am", "Conflicting change"], repo);
+        drop(autostash);
+
+        assert!(git(&["stash", "list"], repo).contains(AUTOSTASH_MESSAGE));
+    }
+
+    #[test]
+    fn test_autostash_with_nothing_to_stash_pops_nothing() {
+        let (_upstream_dir, repo_dir) = fixture_repos();
+        let repo = repo_dir.path();
+        fs::write(repo.join("notes.txt"), "older stash\n").unwrap();
+        git(&["stash", "push", "--include-untracked"], repo);
+
+        let autostash = Autostash::push(SystemGitExecutor::new(), repo.to_path_buf()).unwrap();
+        assert!(autostash.stash.is_none());
+        drop(autostash);
+
+        // The user's own stash is left alone
+        assert_eq!(git(&["stash", "list"], repo).lines().count(), 1);
+        assert!(!repo.join("notes.txt").exists());
+    }
+
+    #[test]
+    fn test_run_methods_return_the_operation_result() {
+        let (upstream_dir, repo_dir) = fixture_repos();
+        let repo = repo_dir.path();
+        let app = CliApp::new(true)
+            .with_working_dir(repo.to_path_buf())
+            .with_repo_url(upstream_dir.path().display().to_string());
+
+        let result = app.run_init(true, true, false).unwrap();
+        assert!(result.success);
+        assert!(result
+            .changes
+            .contains(&"Created tracking branch 'claude-main'".to_string()));
+        assert!(result
+            .changes
+            .iter()
+            .any(|change| change.contains("NET_ADMIN")));
+
+        let result = app
+            .run_update(false, false, false, false, None, false)
+            .unwrap();
+        assert!(result.success);
+
+        let result = app.run_remove(false, false, &[]).unwrap();
+        assert!(result.success);
+        assert!(!repo.join(DEVCONTAINER_PREFIX).exists());
+    }
+
+    #[test]
+    fn test_split_warnings_reach_the_operation_result() {
+        let (upstream_dir, repo_dir) = fixture_repos();
+        let upstream = upstream_dir.path();
+        // A Latin-1 commit message, which git commit would refuse to write as-is; git
+        // subtree split warns when it copies the commit with git commit-tree
+        fs::write(upstream.join(".devcontainer/post-create.sh"), "echo hi\n").unwrap();
+        git(&["add", "."], upstream);
+        let tree = git(&["write-tree"], upstream);
+        let parent = git(&["rev-parse", "HEAD"], upstream);
+        let mut commit = format!(
+            "tree {}\nparent {}\nauthor Test User <test@example.com> 1700000000 +0000\ncommitter Test User <test@example.com> 1700000000 +0000\n\n",
+            tree.trim(),
+            parent.trim()
+        )
+        .into_bytes();
+        commit.extend_from_slice(b"Caf\xe9 setup script\n");
+        let commit_file = upstream_dir.path().join("commit.raw");
+        fs::write(&commit_file, commit).unwrap();
+        let sha = git(
+            &[
+                "hash-object",
+                "-t",
+                "commit",
+                "-w",
+                &commit_file.display().to_string(),
+            ],
+            upstream,
+        );
+        git(&["update-ref", "refs/heads/main", sha.trim()], upstream);
+
+        let result = app_for(upstream, repo_dir.path())
+            .run_init(false, true, false)
+            .unwrap();
+
+        assert!(
+            result.warnings.iter().any(|warning| warning.starts_with(
+                "git subtree split: Warning: commit message did not conform to UTF-8"
+            )),
+            "{:?}",
+            result.warnings
+        );
     }
 }
@@ -0,0 +1,399 @@
+pub use crate::types::OutputFormat;
+use crate::types::{CommandContext, DryRunPlan, OperationResult};
+use serde_json::json;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+/// Progress notifications delivered to a [`Progress`] listener
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProgressEvent<'a> {
+    StepStarted {
+        id: &'a str,
+        description: &'a str,
+        summary: &'a str,
+    },
+    StepDone {
+        id: &'a str,
+        duration: Duration,
+    },
+    Finished {
+        command: &'a str,
+        duration: Duration,
+    },
+}
+
+type Listener = Box<dyn Fn(&ProgressEvent)>;
+
+/// Step-by-step progress reporting for long-running commands
+///
+/// Each step is announced with [`Progress::start`] and completed with [`Step::done`];
+/// the resulting [`ProgressEvent`]s go to a listener, which renders them as text or
+/// NDJSON, or can be supplied by library users.
+pub struct Progress {
+    started: Instant,
+    listener: Listener,
+}
+
+/// A step that has been announced but not yet completed
+#[must_use = "call done() once the step succeeds"]
+pub struct Step<'a> {
+    progress: &'a Progress,
+    id: &'static str,
+    started: Instant,
+}
+
+impl Progress {
+    pub fn new(verbose: bool, format: OutputFormat) -> Self {
+        match format {
+            OutputFormat::Text => Self::with_listener(move |event| render_text(event, verbose)),
+            OutputFormat::Ndjson => Self::with_listener(render_ndjson),
+            // Porcelain only reports the final result record
+            OutputFormat::Porcelain | OutputFormat::Json | OutputFormat::Silent => {
+                Self::with_listener(|_| {})
+            }
+        }
+    }
+
+    /// Progress for a command run with `context`; `--no-progress` drops the step lines
+    /// of text output, while machine-readable formats keep their events. A dry run
+    /// reports its plan instead.
+    pub fn for_context(context: &CommandContext) -> Self {
+        if context.dry_run || (context.no_progress && context.format == OutputFormat::Text) {
+            Self::with_listener(|_| {})
+        } else {
+            Self::new(context.verbose, context.format)
+        }
+    }
+
+    pub fn with_listener(listener: impl Fn(&ProgressEvent) + 'static) -> Self {
+        Self {
+            started: Instant::now(),
+            listener: Box::new(listener),
+        }
+    }
+
+    /// Announce a step; `id` names it in events, `description` is shown in verbose
+    /// mode and `summary` in compact output
+    pub fn start(&self, id: &'static str, description: &str, summary: &str) -> Step<'_> {
+        (self.listener)(&ProgressEvent::StepStarted {
+            id,
+            description,
+            summary,
+        });
+
+        Step {
+            progress: self,
+            id,
+            started: Instant::now(),
+        }
+    }
+
+    /// Report that `command` completed successfully
+    pub fn finish(&self, command: &str) {
+        (self.listener)(&ProgressEvent::Finished {
+            command,
+            duration: self.started.elapsed(),
+        });
+    }
+}
+
+impl Step<'_> {
+    pub fn done(self) {
+        (self.progress.listener)(&ProgressEvent::StepDone {
+            id: self.id,
+            duration: self.started.elapsed(),
+        });
+    }
+}
+
+/// Verbose mode prints full descriptions with timings and a total; otherwise each
+/// step is a compact `Step... ✓` line
+fn render_text(event: &ProgressEvent, verbose: bool) {
+    match event {
+        ProgressEvent::StepStarted {
+            description,
+            summary,
+            ..
+        } => {
+            if verbose {
+                println!("{}...", description);
+            } else {
+                print!("{}... ", summary);
+                io::stdout().flush().unwrap();
+            }
+        }
+        ProgressEvent::StepDone { duration, .. } => {
+            if verbose {
+                println!("  done in {}", format_duration(*duration));
+            } else {
+                println!("✓");
+            }
+        }
+        ProgressEvent::Finished { duration, .. } => {
+            if verbose {
+                println!("Total time: {}", format_duration(*duration));
+            }
+        }
+    }
+}
+
+fn render_ndjson(event: &ProgressEvent) {
+    println!("{}", ndjson_event(event));
+}
+
+/// The JSON object emitted for `event` in NDJSON mode
+pub fn ndjson_event(event: &ProgressEvent) -> serde_json::Value {
+    match event {
+        ProgressEvent::StepStarted { id, .. } => json!({ "event": format!("{}_started", id) }),
+        ProgressEvent::StepDone { id, duration } => json!({
+            "event": format!("{}_done", id),
+            "duration_ms": duration.as_millis() as u64,
+        }),
+        ProgressEvent::Finished { command, duration } => json!({
+            "event": "result",
+            "command": command,
+            "success": true,
+            "duration_ms": duration.as_millis() as u64,
+        }),
+    }
+}
+
+/// The `--json` result object: an [`OperationResult`] tagged with the command that produced it
+pub fn json_result(command: &str, result: &OperationResult) -> String {
+    let mut value = serde_json::to_value(result).unwrap_or_default();
+    if let Some(object) = value.as_object_mut() {
+        object.insert("command".to_string(), command.into());
+    }
+    value.to_string()
+}
+
+/// Print what `command` did in `format`: a summary for people, one JSON document or one
+/// porcelain record. Progress was already reported while it ran.
+pub fn print_result(command: &str, result: &OperationResult, format: OutputFormat) {
+    match format {
+        OutputFormat::Text => print_text_result(command, result),
+        OutputFormat::Json => println!("{}", json_result(command, result)),
+        OutputFormat::Porcelain => println!("{}", porcelain_result(command, result)),
+        OutputFormat::Ndjson | OutputFormat::Silent => {}
+    }
+}
+
+fn print_text_result(command: &str, result: &OperationResult) {
+    if let Some(plan) = &result.plan {
+        print_dry_run(&result.message, plan);
+        return;
+    }
+    if let Some(behind) = result.upstream_commits {
+        println!("{}", result.message);
+        if behind > 0 {
+            println!("Run 'devcontainer-sync update' to sync them");
+        }
+        return;
+    }
+
+    println!("\n✅ {}!", result.message);
+    for change in &result.changes {
+        println!("  • {}", change);
+    }
+    for warning in &result.warnings {
+        println!("⚠️  {}", warning);
+    }
+    match command {
+        "init" => {
+            println!("\nNext steps:");
+            println!("  • Run 'devcontainer-sync update' to get the latest configurations");
+            println!("  • Run 'devcontainer-sync remove' to clean up if no longer needed");
+        }
+        "update" => {
+            println!("\nYour devcontainer is now up to date with the latest configurations.")
+        }
+        "remove" => {
+            println!("\nDevcontainer sync has been completely removed from this repository.")
+        }
+        _ => {}
+    }
+}
+
+/// A dry run's plan as text: the header, the commands in order, then the files the
+/// firewall strip would change and the commit it would make
+fn print_dry_run(header: &str, plan: &DryRunPlan) {
+    println!("{}", header);
+    if plan.planned.is_empty() {
+        println!("\nNothing would change");
+    } else {
+        println!("\nWould run:");
+        for line in &plan.planned {
+            println!("  {}", line);
+        }
+    }
+
+    let Some(strip) = &plan.strip else {
+        return;
+    };
+    let Some(message) = &strip.message else {
+        println!("\nNo firewall configurations found to strip; nothing would be committed");
+        return;
+    };
+    println!("\nWould change:");
+    for file in &strip.files {
+        println!("  {}", file);
+    }
+    println!("\nWould commit:");
+    for line in message.lines() {
+        if line.is_empty() {
+            println!();
+        } else {
+            println!("  {}", line);
+        }
+    }
+}
+
+/// The porcelain record for `result`: a dry run, an update check with its count, or the
+/// tracking branch and subtree split a sync left behind
+fn porcelain_result(command: &str, result: &OperationResult) -> String {
+    if result.plan.is_some() {
+        return porcelain_record(command, "DRY-RUN", &[]);
+    }
+    if let Some(behind) = result.upstream_commits {
+        return porcelain_record(command, "CHECK", &[&behind.to_string()]);
+    }
+    let fields: Vec<&str> = [&result.tracking_branch, &result.subtree_split]
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .collect();
+    porcelain_record(command, "OK", &fields)
+}
+
+/// A `--porcelain` result record: `<COMMAND> <STATUS> [<field>...]`, space-separated
+///
+/// This is format v1. Fields keep their position and meaning across releases; new
+/// fields are only ever appended at the end.
+pub fn porcelain_record(command: &str, status: &str, fields: &[&str]) -> String {
+    let mut record = vec![command.to_uppercase(), status.to_string()];
+    record.extend(fields.iter().map(|field| field.to_string()));
+    record.join(" ")
+}
+
+/// Format a duration as seconds with one decimal place, e.g. `4.2s`
+pub fn format_duration(duration: Duration) -> String {
+    format!("{:.1}s", duration.as_secs_f64())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CliError;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_millis(4200)), "4.2s");
+        assert_eq!(format_duration(Duration::from_millis(40)), "0.0s");
+        assert_eq!(format_duration(Duration::from_secs(61)), "61.0s");
+    }
+
+    #[test]
+    fn test_listener_receives_step_events_in_order() {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let recorded = Rc::clone(&events);
+        let progress = Progress::with_listener(move |event| {
+            recorded
+                .borrow_mut()
+                .push(ndjson_event(event)["event"].clone());
+        });
+
+        progress.start("fetch", "Fetching", "Fetching").done();
+        progress.finish("init");
+
+        assert_eq!(
+            *events.borrow(),
+            vec![json!("fetch_started"), json!("fetch_done"), json!("result")]
+        );
+    }
+
+    #[test]
+    fn test_json_result() {
+        let mut result = OperationResult::success("Removed devcontainer sync".to_string());
+        result.add_change("Removed 'claude' remote".to_string());
+        result.add_warning("warn: Dockerfile not found".to_string());
+
+        let value: serde_json::Value =
+            serde_json::from_str(&json_result("remove", &result)).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "command": "remove",
+                "success": true,
+                "message": "Removed devcontainer sync",
+                "changes": ["Removed 'claude' remote"],
+                "warnings": ["warn: Dockerfile not found"],
+                "errors": [],
+            })
+        );
+    }
+
+    #[test]
+    fn test_porcelain_result_for_each_kind_of_outcome() {
+        let mut synced = OperationResult::success("Successfully initialized".to_string());
+        synced.tracking_branch = Some("claude-main".to_string());
+        synced.subtree_split = Some("abc123".to_string());
+        let mut check = OperationResult::success("Up to date with upstream".to_string());
+        check.upstream_commits = Some(0);
+        let mut dry_run = OperationResult::success("Dry run".to_string());
+        dry_run.plan = Some(DryRunPlan::default());
+
+        assert_eq!(
+            porcelain_result("init", &synced),
+            "INIT OK claude-main abc123"
+        );
+        assert_eq!(porcelain_result("update", &check), "UPDATE CHECK 0");
+        assert_eq!(porcelain_result("remove", &dry_run), "REMOVE DRY-RUN");
+        assert_eq!(
+            porcelain_result("remove", &OperationResult::success(String::new())),
+            "REMOVE OK"
+        );
+    }
+
+    #[test]
+    fn test_json_result_serializes_errors() {
+        let result =
+            OperationResult::failure("update failed".to_string(), CliError::not_git_repository());
+
+        let value: serde_json::Value =
+            serde_json::from_str(&json_result("update", &result)).unwrap();
+        assert_eq!(value["success"], json!(false));
+        assert_eq!(value["errors"][0]["exit_code"], json!(1));
+        assert_eq!(
+            value["errors"][0]["message"],
+            json!("Repository error: Current directory is not a git repository")
+        );
+    }
+
+    #[test]
+    fn test_porcelain_record() {
+        assert_eq!(
+            porcelain_record("init", "OK", &["claude-main", "abc123"]),
+            "INIT OK claude-main abc123"
+        );
+        assert_eq!(
+            porcelain_record("strip-firewall", "ERR", &["4"]),
+            "STRIP-FIREWALL ERR 4"
+        );
+        assert_eq!(porcelain_record("remove", "OK", &[]), "REMOVE OK");
+    }
+
+    #[test]
+    fn test_ndjson_event_duration() {
+        let event = ProgressEvent::StepDone {
+            id: "fetch",
+            duration: Duration::from_millis(4200),
+        };
+
+        assert_eq!(
+            ndjson_event(&event),
+            json!({ "event": "fetch_done", "duration_ms": 4200 })
+        );
+    }
+}
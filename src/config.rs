@@ -1,15 +1,308 @@
+use crate::error::CliError;
+use serde::Deserialize;
+use std::path::Path;
 use std::time::Duration;
 
 pub const CLAUDE_REMOTE_NAME: &str = "claude";
 pub const CLAUDE_REPO_URL: &str = "https://github.com/anthropics/claude-code.git";
+pub const CLAUDE_REPO_NAME: &str = "claude-code";
+/// Default name of the branch that tracks upstream, overridable with --tracking-branch
 pub const CLAUDE_BRANCH_NAME: &str = "claude-main";
 pub const CLAUDE_REMOTE_BRANCH: &str = "claude/main";
+pub const CLAUDE_UPSTREAM_BRANCH: &str = "main";
+/// Default name of the subtree split branch, overridable with --work-branch
 pub const DEVCONTAINER_BRANCH: &str = "devcontainer";
+/// Default name of the split branch `update` merges from: `<work branch>-updated`
 pub const DEVCONTAINER_UPDATED_BRANCH: &str = "devcontainer-updated";
 pub const DEVCONTAINER_PREFIX: &str = ".devcontainer";
 pub const MASTER_BRANCH: &str = "master";
+pub const REMOVE_COMMIT_MESSAGE: &str = "Remove devcontainer configuration";
+pub const STRIP_FIREWALL_COMMIT_MESSAGE: &str = "Strip firewall configurations from devcontainer";
+pub const UPDATE_STRIP_COMMIT_MESSAGE: &str =
+    "Strip firewall configurations from updated devcontainer";
+/// Message of the stash `--autostash` keeps uncommitted changes in while a command runs
+pub const AUTOSTASH_MESSAGE: &str = "devcontainer-sync autostash";
+pub const REINIT_REMOVE_COMMIT_MESSAGE: &str = "Remove devcontainer subtree before re-adding it";
+pub const DEFAULT_GIT_PROGRAM: &str = "git";
+/// Oldest git that supports everything the tool runs (`ls-remote --symref` arrived in 2.8)
+pub const MIN_GIT_VERSION: (u32, u32) = (2, 8);
+pub const BLAME_IGNORE_REVS_FILE: &str = ".git-blame-ignore-revs";
+pub const BLAME_IGNORE_COMMIT_MESSAGE: &str = "Ignore firewall strip commit in git blame";
+/// Extra firewall detection regexes, one per line, read from the repository root
+pub const PATTERNS_FILE_NAME: &str = ".devcontainer-sync-patterns";
+/// Team-wide settings, read from the repository root
+pub const CONFIG_FILE_NAME: &str = ".devcontainer-sync.toml";
+/// Namespace for the temporary refs `diff` and `changelog` fetch upstream refs into
+pub const DIFF_REF_NAMESPACE: &str = "refs/devcontainer-sync/diff";
 pub const DEFAULT_TIMEOUT_SECS: u64 = 30;
+/// Read for `--token` when the flag isn't given, so the token can stay out of shell history
+pub const TOKEN_ENV_VAR: &str = "DEVCONTAINER_SYNC_TOKEN";
+
+/// Times a fetch that failed on the network is retried, overridable with --retries
+pub const DEFAULT_FETCH_RETRIES: u32 = 3;
+/// Wait before the first fetch retry, doubled before each later one
+pub const FETCH_RETRY_DELAY: Duration = Duration::from_secs(1);
 
 pub fn default_timeout() -> Duration {
     Duration::from_secs(DEFAULT_TIMEOUT_SECS)
 }
+
+/// Fully qualified remote-tracking ref for `branch` on `remote`
+///
+/// Spelling out `refs/remotes/` keeps branch names containing slashes or dots
+/// (e.g. `release/v2`) from being mistaken for other refs.
+pub fn remote_branch_ref(remote: &str, branch: &str) -> String {
+    format!("refs/remotes/{}/{}", remote, branch)
+}
+
+/// Refspec fetching only `branch` from `remote` into its remote-tracking ref
+pub fn branch_fetch_refspec(remote: &str, branch: &str) -> String {
+    format!(
+        "+refs/heads/{}:{}",
+        branch,
+        remote_branch_ref(remote, branch)
+    )
+}
+
+/// Expand a GitHub organization name to the URL of its same-named claude-code fork
+pub fn fork_repo_url(org: &str) -> Result<String, CliError> {
+    let valid = !org.is_empty()
+        && !org.starts_with('-')
+        && org
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+
+    if !valid {
+        return Err(CliError::Repository {
+            message: format!("Invalid fork organization name '{}'", org),
+            suggestion:
+                "Pass only the GitHub organization (e.g. '--fork myorg'), not a path or URL"
+                    .to_string(),
+        });
+    }
+
+    Ok(format!(
+        "https://github.com/{}/{}.git",
+        org, CLAUDE_REPO_NAME
+    ))
+}
+
+/// Check that `url` looks like something git can fetch from: `<scheme>://<rest>` or the
+/// scp-like `user@host:path` form
+pub fn validate_repo_url(url: &str) -> Result<String, CliError> {
+    let has_scheme = match url.split_once("://") {
+        Some((scheme, rest)) => {
+            !scheme.is_empty()
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+                && !rest.is_empty()
+        }
+        None => false,
+    };
+    let is_scp_like = match url.split_once(':') {
+        Some((host, path)) => host.contains('@') && !host.contains('/') && !path.is_empty(),
+        None => false,
+    };
+
+    if !has_scheme && !is_scp_like {
+        return Err(CliError::Repository {
+            message: format!("Invalid repository URL '{}'", url),
+            suggestion: format!(
+                "Pass a full URL including the scheme, e.g. '--repo-url {}'",
+                CLAUDE_REPO_URL
+            ),
+        });
+    }
+
+    Ok(url.to_string())
+}
+
+/// Check that `prefix` names a directory inside the repository: a relative path with no
+/// `..` components. Trailing slashes are dropped, as git subtree rejects them.
+pub fn validate_prefix(prefix: &str, flag: &str) -> Result<String, CliError> {
+    let trimmed = prefix.trim_end_matches('/');
+    let inside_repo = !trimmed.is_empty()
+        && Path::new(trimmed)
+            .components()
+            .all(|component| matches!(component, std::path::Component::Normal(_)));
+
+    if !inside_repo {
+        return Err(CliError::FileSystem {
+            message: format!(
+                "Invalid {} '{}': it must be a relative path inside the repository",
+                flag, prefix
+            ),
+            suggestion: format!(
+                "Use a path relative to the repository root without '..', such as {}",
+                DEVCONTAINER_PREFIX
+            ),
+        });
+    }
+    Ok(trimmed.to_string())
+}
+
+/// Settings from `.devcontainer-sync.toml`; command-line flags override them, and unset
+/// ones fall back to the defaults above
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SyncConfig {
+    pub repo_url: Option<String>,
+    pub remote_name: Option<String>,
+    pub tracking_branch: Option<String>,
+    pub prefix: Option<String>,
+    /// Upstream branch to sync, instead of asking the upstream for its default branch
+    pub default_branch: Option<String>,
+}
+
+/// Read `.devcontainer-sync.toml` from `working_dir`; a missing file means no settings
+pub fn load_config(working_dir: &Path) -> Result<SyncConfig, CliError> {
+    let path = working_dir.join(CONFIG_FILE_NAME);
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(SyncConfig::default()),
+        Err(e) => {
+            return Err(CliError::FileSystem {
+                message: format!("Failed to read {}: {}", CONFIG_FILE_NAME, e),
+                suggestion: "Check file permissions".to_string(),
+            })
+        }
+    };
+
+    // toml's message quotes the offending line and marks the key or value at fault
+    let mut config: SyncConfig = toml::from_str(&content).map_err(|e| CliError::FileSystem {
+        message: format!("Invalid {}: {}", CONFIG_FILE_NAME, e),
+        suggestion: format!(
+            "Fix the marked key in {}; valid keys are repo_url, remote_name, tracking_branch, prefix and default_branch",
+            CONFIG_FILE_NAME
+        ),
+    })?;
+    if let Some(prefix) = &config.prefix {
+        let prefix = validate_prefix(prefix, "prefix").map_err(|e| CliError::FileSystem {
+            message: format!("Invalid prefix in {}: {}", CONFIG_FILE_NAME, e),
+            suggestion: e.suggestion().to_string(),
+        })?;
+        config.prefix = Some(prefix);
+    }
+    if let Some(repo_url) = &config.repo_url {
+        validate_repo_url(repo_url).map_err(|e| CliError::FileSystem {
+            message: format!("Invalid repo_url in {}: {}", CONFIG_FILE_NAME, e),
+            suggestion: e.suggestion().to_string(),
+        })?;
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fork_repo_url() {
+        assert_eq!(
+            fork_repo_url("myorg").unwrap(),
+            "https://github.com/myorg/claude-code.git"
+        );
+    }
+
+    #[test]
+    fn test_validate_repo_url() {
+        for url in [
+            CLAUDE_REPO_URL,
+            "ssh://git@example.com/team/claude-code.git",
+            "git@github.com:myorg/claude-code.git",
+            "git@gitlab.example.com:team/sub/claude-code.git",
+            "file:///srv/git/claude-code.git",
+        ] {
+            assert_eq!(validate_repo_url(url).unwrap(), url);
+        }
+        for url in [
+            "",
+            "github.com/myorg/claude-code",
+            "https://",
+            "://host/repo",
+        ] {
+            assert!(
+                validate_repo_url(url).is_err(),
+                "'{}' should be rejected",
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn test_fork_repo_url_rejects_paths_and_urls() {
+        for org in ["", "my/org", "https://github.com/myorg", "org:repo", "-org"] {
+            assert!(fork_repo_url(org).is_err(), "'{}' should be rejected", org);
+        }
+    }
+
+    #[test]
+    fn test_load_config() {
+        let dir = tempfile::TempDir::new().unwrap();
+        assert_eq!(load_config(dir.path()).unwrap(), SyncConfig::default());
+
+        std::fs::write(
+            dir.path().join(CONFIG_FILE_NAME),
+            "repo_url = \"git@github.com:myorg/claude-code.git\"\nremote_name = \"upstream\"\n",
+        )
+        .unwrap();
+        let config = load_config(dir.path()).unwrap();
+        assert_eq!(
+            config.repo_url.as_deref(),
+            Some("git@github.com:myorg/claude-code.git")
+        );
+        assert_eq!(config.remote_name.as_deref(), Some("upstream"));
+        assert_eq!(config.prefix, None);
+    }
+
+    #[test]
+    fn test_load_config_points_at_the_offending_key() {
+        let dir = tempfile::TempDir::new().unwrap();
+        for (content, expected) in [
+            ("prefix = 5\n", "prefix = 5"),
+            (
+                "remote-name = \"upstream\"\n",
+                "unknown field `remote-name`",
+            ),
+            ("repo_url = \"github.com/x\"\n", "Invalid repo_url"),
+        ] {
+            std::fs::write(dir.path().join(CONFIG_FILE_NAME), content).unwrap();
+
+            match load_config(dir.path()) {
+                Err(CliError::FileSystem { message, .. }) => {
+                    assert!(message.contains(expected), "{}", message)
+                }
+                other => panic!("Expected FileSystem error, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_prefix() {
+        for (prefix, expected) in [
+            (".devcontainer", ".devcontainer"),
+            (".devcontainer/base", ".devcontainer/base"),
+            ("tools/devcontainer/", "tools/devcontainer"),
+        ] {
+            assert_eq!(validate_prefix(prefix, "--prefix").unwrap(), expected);
+        }
+        for prefix in [
+            "",
+            "/",
+            "/etc/devcontainer",
+            "../outside",
+            "a/../../b",
+            "./x",
+        ] {
+            assert!(
+                matches!(
+                    validate_prefix(prefix, "--prefix"),
+                    Err(CliError::FileSystem { .. })
+                ),
+                "{} should be rejected",
+                prefix
+            );
+        }
+    }
+}
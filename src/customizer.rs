@@ -1,7 +1,192 @@
+use crate::config::PATTERNS_FILE_NAME;
 use crate::error::CliError;
+use crate::types::{ColorChoice, Severity, Warning};
 use regex::Regex;
+use similar::{ChangeTag, TextDiff};
+use std::io::{BufRead, IsTerminal, Write};
 use std::path::{Path, PathBuf};
 
+/// Packages removed from Dockerfile apt install commands when stripping firewall support
+pub const DEFAULT_FIREWALL_PACKAGES: [&str; 5] =
+    ["iptables", "ipset", "iproute2", "dnsutils", "aggregate"];
+
+/// Packages removed from Dockerfile apt install commands when stripping GPU support
+pub const DEFAULT_GPU_PACKAGES: [&str; 2] = ["nvidia-container-toolkit", "nvidia-utils"];
+
+/// A named group of devcontainer features that are stripped together
+///
+/// Each profile lists the scripts, `devcontainer.json` settings and Dockerfile packages
+/// that make up the feature; the stripping logic itself is shared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeatureProfile {
+    /// The egress firewall: init-firewall.sh, NET_ADMIN/NET_RAW and the iptables tooling
+    Firewall,
+    /// GPU passthrough: `--gpus` run arguments, GPU host requirements and NVIDIA tooling
+    Gpu,
+}
+
+impl FeatureProfile {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FeatureProfile::Firewall => "firewall",
+            FeatureProfile::Gpu => "gpu",
+        }
+    }
+
+    /// Packages removed from Dockerfile apt install commands
+    pub fn packages(&self) -> &'static [&'static str] {
+        match self {
+            FeatureProfile::Firewall => &DEFAULT_FIREWALL_PACKAGES,
+            FeatureProfile::Gpu => &DEFAULT_GPU_PACKAGES,
+        }
+    }
+
+    /// Script names that are removed whatever their content
+    pub fn script_names(&self) -> &'static [&'static str] {
+        match self {
+            FeatureProfile::Firewall => &["init-firewall.sh", "firewall.sh", "iptables.sh"],
+            FeatureProfile::Gpu => &["install-cuda.sh", "gpu-setup.sh"],
+        }
+    }
+
+    /// Regex patterns whose match marks a script, or a changed line, as part of the feature
+    ///
+    /// These patterns are designed to be flexible and resilient to upstream changes.
+    /// If upstream changes break detection, they can be updated without changing the
+    /// core logic, making maintenance easier.
+    pub fn content_patterns(&self) -> &'static [&'static str] {
+        match self {
+            FeatureProfile::Firewall => &[
+                r"iptables\s*\\?",
+                r"ipset\s*\\?",
+                r"iproute2\s*\\?",
+                r"dnsutils\s*\\?",
+                r"aggregate\s*\\?",
+                r"--cap-add=NET_ADMIN",
+                r"--cap-add=NET_RAW",
+                r"init-firewall\.sh",
+                r"firewall.*\.sh",
+                r"postStartCommand.*firewall",
+                r"waitFor.*postStartCommand",
+            ],
+            FeatureProfile::Gpu => &[
+                r"nvidia-smi",
+                r"nvidia-container-toolkit",
+                r"--gpus",
+                r"CUDA_VISIBLE_DEVICES",
+                r"install-cuda\.sh",
+            ],
+        }
+    }
+
+    /// `runArgs` entries that are dropped; a flag without `=` also takes its separate
+    /// value argument with it
+    pub fn run_args(&self) -> &'static [&'static str] {
+        match self {
+            FeatureProfile::Firewall => &["--cap-add=NET_ADMIN", "--cap-add=NET_RAW"],
+            FeatureProfile::Gpu => &["--gpus"],
+        }
+    }
+
+    /// Linux capabilities the `runArgs` grant, which compose services request with `cap_add`
+    fn capabilities(&self) -> Vec<&'static str> {
+        self.run_args()
+            .iter()
+            .filter_map(|arg| arg.strip_prefix("--cap-add="))
+            .collect()
+    }
+
+    fn run_args_description(&self) -> &'static str {
+        match self {
+            FeatureProfile::Firewall => "NET_ADMIN and NET_RAW capabilities",
+            FeatureProfile::Gpu => "GPU access",
+        }
+    }
+
+    /// A postStartCommand mentioning this is removed, along with a waitFor on it
+    fn command_keyword(&self) -> &'static str {
+        match self {
+            FeatureProfile::Firewall => "firewall",
+            FeatureProfile::Gpu => "nvidia",
+        }
+    }
+
+    /// Keys removed from the `hostRequirements` object
+    fn host_requirements(&self) -> &'static [&'static str] {
+        match self {
+            FeatureProfile::Firewall => &[],
+            FeatureProfile::Gpu => &["gpu"],
+        }
+    }
+
+    /// Dockerfile comment that opens a setup section running up to the next `USER node`
+    fn dockerfile_section(&self) -> Option<&'static str> {
+        match self {
+            FeatureProfile::Firewall => Some("# Copy and set up firewall script"),
+            FeatureProfile::Gpu => None,
+        }
+    }
+}
+
+/// Whether `path`, with symlinks resolved, lies inside `dir`; paths that can't be
+/// resolved count as outside
+fn is_within(path: &Path, dir: &Path) -> bool {
+    match (path.canonicalize(), dir.canonicalize()) {
+        (Ok(path), Ok(dir)) => path.starts_with(dir),
+        _ => false,
+    }
+}
+
+/// Where a detection pattern comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatternSource {
+    BuiltIn,
+    /// The repository's patterns file
+    Config,
+    /// The patterns file that came with the synced upstream devcontainer
+    Synced,
+}
+
+impl std::fmt::Display for PatternSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PatternSource::BuiltIn => write!(f, "built-in"),
+            PatternSource::Config => write!(f, "config"),
+            PatternSource::Synced => write!(f, "synced"),
+        }
+    }
+}
+
+/// A regex used to recognize feature content, with its source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectionPattern {
+    pub pattern: String,
+    pub source: PatternSource,
+}
+
+/// Read the patterns listed in the patterns file under `working_dir`, skipping blank
+/// lines and `#` comments; a missing file means no extra patterns
+pub fn read_patterns_file(working_dir: &Path) -> Result<Vec<String>, CliError> {
+    let path = working_dir.join(PATTERNS_FILE_NAME);
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(CliError::FileSystem {
+                message: format!("Failed to read {}: {}", PATTERNS_FILE_NAME, e),
+                suggestion: "Check file permissions".to_string(),
+            })
+        }
+    };
+
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
 /// Trait for customizing devcontainer configurations
 pub trait DevcontainerCustomizer {
     /// Strip firewall features from devcontainer directory
@@ -10,6 +195,34 @@ pub trait DevcontainerCustomizer {
         devcontainer_path: &Path,
     ) -> Result<FirewallRemovalResult, CliError>;
 
+    /// Strip the features in `profile` from the devcontainer directory. Customizers that
+    /// don't override this only support the firewall profile.
+    fn strip_features(
+        &self,
+        devcontainer_path: &Path,
+        profile: FeatureProfile,
+    ) -> Result<CustomizationResult, CliError> {
+        match profile {
+            FeatureProfile::Firewall => self.strip_firewall_features(devcontainer_path),
+            other => Err(CliError::Repository {
+                message: format!("This customizer cannot strip the {} profile", other.name()),
+                suggestion: "Use DefaultDevcontainerCustomizer, or implement strip_features"
+                    .to_string(),
+            }),
+        }
+    }
+
+    /// Strip firewall features from only the `changed` files under the devcontainer
+    /// directory, leaving the rest untouched. Strips the whole directory by default.
+    fn strip_changed_firewall_features(
+        &self,
+        devcontainer_path: &Path,
+        changed: &[PathBuf],
+    ) -> Result<FirewallRemovalResult, CliError> {
+        let _ = changed;
+        self.strip_firewall_features(devcontainer_path)
+    }
+
     /// Detect firewall scripts using flexible pattern matching
     fn detect_firewall_scripts(&self, devcontainer_path: &Path) -> Result<Vec<PathBuf>, CliError>;
 
@@ -19,33 +232,68 @@ pub trait DevcontainerCustomizer {
     /// Strip firewall configurations from Dockerfile
     fn strip_dockerfile_firewall(&self, dockerfile_path: &Path) -> Result<Vec<String>, CliError>;
 
+    /// Strip firewall capabilities from a docker-compose file's services. Customizers that
+    /// don't override this leave compose files alone.
+    fn strip_compose_firewall(&self, compose_path: &Path) -> Result<Vec<String>, CliError> {
+        let _ = compose_path;
+        Ok(Vec::new())
+    }
+
     /// Validate firewall removal results and generate warnings
-    fn validate_firewall_removal(&self, removal_result: &FirewallRemovalResult) -> Vec<String>;
+    fn validate_firewall_removal(&self, removal_result: &FirewallRemovalResult) -> Vec<Warning>;
 
     /// Commit customizations to git with descriptive message
-    fn commit_customizations(&self, changes: &[String], message: &str) -> Result<(), CliError>;
+    ///
+    /// Only the files recorded in `removal_result` are staged and committed, so
+    /// unrelated local edits under the devcontainer directory stay out of the commit.
+    fn commit_customizations(
+        &self,
+        removal_result: &FirewallRemovalResult,
+        message: &str,
+    ) -> Result<(), CliError>;
 }
 
-/// Result of firewall removal operation
+/// A line stripping removed or rewrote, so a review can see where each change happened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub path: PathBuf,
+    /// 1-based line number in the file before stripping
+    pub line: usize,
+    /// The original line, without its newline
+    pub removed: String,
+}
+
+/// Result of stripping a feature profile
 #[derive(Debug, Clone, Default)]
-pub struct FirewallRemovalResult {
+pub struct CustomizationResult {
     pub files_modified: Vec<PathBuf>,
     pub files_removed: Vec<PathBuf>,
     pub dockerfile_changes: Vec<String>,
     pub json_changes: Vec<String>,
-    pub warnings: Vec<String>,
-    pub patterns_not_found: Vec<String>,
+    pub compose_changes: Vec<String>,
+    /// Every line removed or rewritten in the modified files
+    pub changes: Vec<Change>,
+    pub warnings: Vec<Warning>,
+    pub patterns_not_found: Vec<Warning>,
+    /// The previewed changes were declined, so none were applied
+    pub declined: bool,
 }
 
-impl FirewallRemovalResult {
+/// The name `CustomizationResult` had when only the firewall could be stripped
+pub type FirewallRemovalResult = CustomizationResult;
+
+impl CustomizationResult {
     pub fn new() -> Self {
         Self {
             files_modified: Vec::new(),
             files_removed: Vec::new(),
             dockerfile_changes: Vec::new(),
             json_changes: Vec::new(),
+            compose_changes: Vec::new(),
+            changes: Vec::new(),
             warnings: Vec::new(),
             patterns_not_found: Vec::new(),
+            declined: false,
         }
     }
 
@@ -65,12 +313,17 @@ impl FirewallRemovalResult {
         self.json_changes.push(change);
     }
 
-    pub fn add_warning(&mut self, warning: String) {
+    pub fn add_compose_change(&mut self, change: String) {
+        self.compose_changes.push(change);
+    }
+
+    pub fn add_warning(&mut self, warning: Warning) {
         self.warnings.push(warning);
     }
 
     pub fn add_pattern_not_found(&mut self, pattern: String) {
-        self.patterns_not_found.push(pattern);
+        self.patterns_not_found
+            .push(Warning::info("pattern-not-found", pattern));
     }
 
     pub fn has_changes(&self) -> bool {
@@ -80,6 +333,516 @@ impl FirewallRemovalResult {
     pub fn has_warnings(&self) -> bool {
         !self.warnings.is_empty() || !self.patterns_not_found.is_empty()
     }
+
+    /// Warnings and missing patterns at or above `severity`
+    pub fn warnings_at_least(&self, severity: Severity) -> Vec<&Warning> {
+        self.warnings
+            .iter()
+            .chain(self.patterns_not_found.iter())
+            .filter(|warning| warning.severity >= severity)
+            .collect()
+    }
+
+    /// All change descriptions, Dockerfile changes first
+    pub fn all_changes(&self) -> Vec<String> {
+        self.dockerfile_changes
+            .iter()
+            .chain(self.json_changes.iter())
+            .chain(self.compose_changes.iter())
+            .cloned()
+            .collect()
+    }
+
+    /// Paths touched by the removal (modified and removed files)
+    pub fn touched_files(&self) -> Vec<&PathBuf> {
+        self.files_modified
+            .iter()
+            .chain(self.files_removed.iter())
+            .collect()
+    }
+}
+
+/// The message `commit_customizations` commits `removal_result` with: `message`,
+/// followed by the list of changes made
+pub fn customization_commit_message(message: &str, removal_result: &CustomizationResult) -> String {
+    let changes = removal_result.all_changes();
+    if changes.is_empty() {
+        message.to_string()
+    } else {
+        format!("{}\n\nChanges made:\n- {}", message, changes.join("\n- "))
+    }
+}
+
+/// Render `path` relative to the repository root, with forward slashes, so reports show
+/// `.devcontainer/init-firewall.sh` rather than the user's home directory
+pub fn repo_relative(path: &Path, root: &Path) -> String {
+    match path.strip_prefix(root) {
+        Ok(relative) => relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+        Err(_) => path.display().to_string(),
+    }
+}
+
+/// Ask which of the detected firewall `scripts` to remove, as a numbered checklist
+///
+/// Answers are script numbers separated by commas or spaces, `all` or `none`; an empty
+/// answer or closed input removes every script, and anything else asks again.
+pub fn select_scripts(
+    scripts: &[PathBuf],
+    root: &Path,
+    mut input: impl BufRead,
+    mut output: impl Write,
+) -> Result<Vec<PathBuf>, CliError> {
+    let write_error = |e: std::io::Error| CliError::FileSystem {
+        message: format!("Failed to write script selection prompt: {}", e),
+        suggestion: "Run without --select-scripts to remove every detected script".to_string(),
+    };
+
+    writeln!(output, "Detected firewall scripts:").map_err(write_error)?;
+    for (number, script) in scripts.iter().enumerate() {
+        writeln!(output, "  {}) {}", number + 1, repo_relative(script, root))
+            .map_err(write_error)?;
+    }
+
+    loop {
+        write!(
+            output,
+            "Remove which scripts? (numbers, 'all' or 'none') [all]: "
+        )
+        .map_err(write_error)?;
+        output.flush().map_err(write_error)?;
+
+        let mut answer = String::new();
+        let bytes_read = input
+            .read_line(&mut answer)
+            .map_err(|e| CliError::FileSystem {
+                message: format!("Failed to read script selection: {}", e),
+                suggestion: "Run without --select-scripts to remove every detected script"
+                    .to_string(),
+            })?;
+        let answer = answer.trim().to_lowercase();
+        if bytes_read == 0 || answer.is_empty() || answer == "all" {
+            return Ok(scripts.to_vec());
+        }
+        if answer == "none" {
+            return Ok(Vec::new());
+        }
+
+        let numbers: Option<Vec<usize>> = answer
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|part| !part.is_empty())
+            .map(|part| {
+                part.parse::<usize>()
+                    .ok()
+                    .filter(|n| (1..=scripts.len()).contains(n))
+            })
+            .collect();
+        match numbers {
+            Some(numbers) => {
+                return Ok(scripts
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, _)| numbers.contains(&(index + 1)))
+                    .map(|(_, script)| script.clone())
+                    .collect())
+            }
+            None => writeln!(
+                output,
+                "Please enter numbers between 1 and {}, 'all' or 'none'",
+                scripts.len()
+            )
+            .map_err(write_error)?,
+        }
+    }
+}
+
+/// A stripped file's new content, worked out before anything is written
+struct Rewrite {
+    path: PathBuf,
+    /// How the file is named in messages
+    file_name: String,
+    original: String,
+    modified: String,
+    changes: Vec<String>,
+    warnings: Vec<Warning>,
+}
+
+impl Rewrite {
+    /// The changes, naming the file unless it has the `canonical` name, so changes to
+    /// variants such as `Dockerfile.dev` can be told apart
+    fn changes_in_file(&self, canonical: &str) -> Vec<String> {
+        if self.file_name == canonical {
+            return self.changes.clone();
+        }
+        self.changes
+            .iter()
+            .map(|change| format!("{}: {}", self.file_name, change))
+            .collect()
+    }
+
+    /// The original lines the rewrite removes or replaces
+    fn removed_lines(&self) -> Vec<Change> {
+        TextDiff::from_lines(&self.original, &self.modified)
+            .iter_all_changes()
+            .filter(|change| change.tag() == ChangeTag::Delete)
+            .filter_map(|change| {
+                Some(Change {
+                    path: self.path.clone(),
+                    line: change.old_index()? + 1,
+                    removed: change.value().trim_end_matches(['\r', '\n']).to_string(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Unified diff turning `original` into `modified`, or deleting the file when there is
+/// no `modified`, with removed lines in red and added lines in green when `color` is set
+pub fn render_diff(path: &str, original: &str, modified: Option<&str>, color: bool) -> String {
+    let paint = |line: String, code: &str| {
+        if color {
+            format!("\x1b[{}m{}\x1b[0m", code, line)
+        } else {
+            line
+        }
+    };
+    let new_path = match modified {
+        Some(_) => format!("b/{}", path),
+        None => "/dev/null".to_string(),
+    };
+
+    let mut rendered = String::new();
+    rendered.push_str(&paint(format!("--- a/{}", path), "1"));
+    rendered.push('\n');
+    rendered.push_str(&paint(format!("+++ {}", new_path), "1"));
+    rendered.push('\n');
+
+    let diff = TextDiff::from_lines(original, modified.unwrap_or_default());
+    for hunk in diff.unified_diff().context_radius(3).iter_hunks() {
+        rendered.push_str(&paint(hunk.header().to_string(), "36"));
+        rendered.push('\n');
+        for change in hunk.iter_changes() {
+            let line = change.value().trim_end_matches('\n');
+            let line = match change.tag() {
+                ChangeTag::Delete => paint(format!("-{}", line), "31"),
+                ChangeTag::Insert => paint(format!("+{}", line), "32"),
+                ChangeTag::Equal => format!(" {}", line),
+            };
+            rendered.push_str(&line);
+            rendered.push('\n');
+        }
+    }
+    rendered
+}
+
+/// Ask whether to apply the previewed changes; only `y` or `yes` applies them
+pub fn confirm_changes(mut input: impl BufRead, mut output: impl Write) -> Result<bool, CliError> {
+    let _ = write!(output, "Apply these changes? (y/N): ");
+    let _ = output.flush();
+
+    let mut answer = String::new();
+    input
+        .read_line(&mut answer)
+        .map_err(|e| CliError::FileSystem {
+            message: format!("Failed to read user input: {}", e),
+            suggestion: "Run without --preview-diff to apply the changes without asking"
+                .to_string(),
+        })?;
+    let answer = answer.trim().to_lowercase();
+    Ok(answer == "y" || answer == "yes")
+}
+
+/// Plain JSON recovered from a devcontainer.json, with what had to change to get it
+struct PlainJson {
+    json: String,
+    had_comments: bool,
+    /// Lenient syntax that was normalized, such as "trailing commas"
+    repairs: Vec<&'static str>,
+}
+
+/// Turn JSONC (JSON with `//` and `/* */` comments and trailing commas, as devcontainer.json
+/// files usually are) into plain JSON, also turning hand-written single-quoted strings
+/// into double-quoted ones. Newlines inside comments are kept so parse errors still point
+/// at the right line.
+fn strip_jsonc(content: &str) -> PlainJson {
+    let mut without_comments = String::with_capacity(content.len());
+    let mut had_comments = false;
+    let mut repairs = Vec::new();
+    let mut chars = content.chars().peekable();
+    let mut quote = None;
+    while let Some(c) = chars.next() {
+        if let Some(open) = quote {
+            match c {
+                '\\' => match chars.next() {
+                    Some('\'') if open == '\'' => without_comments.push('\''),
+                    Some(escaped) => {
+                        without_comments.push('\\');
+                        without_comments.push(escaped);
+                    }
+                    None => without_comments.push('\\'),
+                },
+                _ if c == open => {
+                    quote = None;
+                    without_comments.push('"');
+                }
+                // Only reachable inside a single-quoted string
+                '"' => without_comments.push_str("\\\""),
+                _ => without_comments.push(c),
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                quote = Some('"');
+                without_comments.push(c);
+            }
+            ('\'', _) => {
+                if !repairs.contains(&"single-quoted strings") {
+                    repairs.push("single-quoted strings");
+                }
+                quote = Some('\'');
+                without_comments.push('"');
+            }
+            ('/', Some('/')) => {
+                had_comments = true;
+                while chars.peek().is_some_and(|&next| next != '\n') {
+                    chars.next();
+                }
+            }
+            ('/', Some('*')) => {
+                had_comments = true;
+                chars.next();
+                let mut previous = '\0';
+                for next in chars.by_ref() {
+                    if previous == '*' && next == '/' {
+                        break;
+                    }
+                    if next == '\n' {
+                        without_comments.push('\n');
+                    }
+                    previous = next;
+                }
+            }
+            _ => without_comments.push(c),
+        }
+    }
+
+    let mut json = String::with_capacity(without_comments.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for (index, c) in without_comments.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c == ',' {
+            let next = without_comments[index + 1..].trim_start().chars().next();
+            if matches!(next, Some('}') | Some(']')) {
+                if !repairs.contains(&"trailing commas") {
+                    repairs.push("trailing commas");
+                }
+                continue;
+            }
+        }
+        json.push(c);
+    }
+    PlainJson {
+        json,
+        had_comments,
+        repairs,
+    }
+}
+
+/// Lifecycle commands a devcontainer.json can run, in the order they run
+const LIFECYCLE_COMMANDS: [&str; 6] = [
+    "initializeCommand",
+    "onCreateCommand",
+    "updateContentCommand",
+    "postCreateCommand",
+    "postStartCommand",
+    "postAttachCommand",
+];
+
+/// The settings in a devcontainer.json people most want to see survive stripping: its
+/// name, image or Dockerfile, run arguments, extensions and lifecycle commands
+fn preserved_settings(content: &str) -> Vec<String> {
+    let Ok(json) = serde_json::from_str::<serde_json::Value>(&strip_jsonc(content).json) else {
+        return Vec::new();
+    };
+    let mut settings = Vec::new();
+
+    if let Some(name) = json.get("name").and_then(|v| v.as_str()) {
+        settings.push(format!("name: {}", name));
+    }
+    if let Some(image) = json.get("image").and_then(|v| v.as_str()) {
+        settings.push(format!("image: {}", image));
+    }
+    if let Some(dockerfile) = json.pointer("/build/dockerfile").and_then(|v| v.as_str()) {
+        settings.push(format!("Dockerfile: {}", dockerfile));
+    }
+    if let Some(run_args) = json.get("runArgs").and_then(|v| v.as_array()) {
+        let run_args: Vec<&str> = run_args.iter().filter_map(|arg| arg.as_str()).collect();
+        if !run_args.is_empty() {
+            settings.push(format!("runArgs: {}", run_args.join(" ")));
+        }
+    }
+    if let Some(extensions) = json
+        .pointer("/customizations/vscode/extensions")
+        .and_then(|v| v.as_array())
+    {
+        settings.push(format!("{} VS Code extension(s)", extensions.len()));
+    }
+    for command in LIFECYCLE_COMMANDS {
+        if json.get(command).is_some() {
+            settings.push(command.to_string());
+        }
+    }
+    settings
+}
+
+/// The file name of `path`, for messages
+fn config_file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Whether `file_name` names a Dockerfile: `Dockerfile*`, `*.dockerfile` or
+/// `Containerfile`, in any case
+fn is_dockerfile_name(file_name: &str) -> bool {
+    let lower = file_name.to_lowercase();
+    lower.starts_with("dockerfile") || lower.ends_with(".dockerfile") || lower == "containerfile"
+}
+
+/// Whether `file_name` names a devcontainer configuration such as `devcontainer.json`
+/// or `devcontainer.dev.json`, in any case. The Dev Container CLI's
+/// `devcontainer-lock.json` only pins feature versions, so it is left out.
+fn is_devcontainer_json_name(file_name: &str) -> bool {
+    let lower = file_name.to_lowercase();
+    lower.contains("devcontainer") && lower.ends_with(".json") && !lower.ends_with("-lock.json")
+}
+
+/// Compose files a `dockerComposeFile` devcontainer usually keeps next to devcontainer.json
+const COMPOSE_FILE_NAMES: [&str; 4] = [
+    "docker-compose.yml",
+    "docker-compose.yaml",
+    "compose.yaml",
+    "compose.yml",
+];
+
+/// The compose files present in the devcontainer directory
+fn discover_compose_files(devcontainer_path: &Path) -> Vec<PathBuf> {
+    COMPOSE_FILE_NAMES
+        .iter()
+        .map(|name| devcontainer_path.join(name))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// How many directory levels below the devcontainer are scanned for scripts, so a
+/// pathologically deep tree can't stall stripping
+const MAX_SCRIPT_SCAN_DEPTH: usize = 8;
+
+/// Every file under `dir`, sorted by path, descending at most `max_depth` levels.
+/// Symlinked directories are skipped, as a link back up the tree would loop forever.
+fn files_under(dir: &Path, max_depth: usize) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return files;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => {
+                if max_depth > 0 {
+                    files.extend(files_under(&path, max_depth - 1));
+                }
+            }
+            Ok(file_type) if file_type.is_symlink() && path.is_dir() => {}
+            Ok(_) => files.push(path),
+            Err(_) => {}
+        }
+    }
+    files.sort();
+    files
+}
+
+/// The devcontainer.json and Dockerfile variants directly inside `devcontainer_path`,
+/// each list sorted by path
+fn discover_config_files(devcontainer_path: &Path) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let mut json_paths = Vec::new();
+    let mut dockerfile_paths = Vec::new();
+    if let Ok(entries) = std::fs::read_dir(devcontainer_path) {
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if !path.is_file() {
+                continue;
+            }
+            let file_name = config_file_name(&path);
+            if is_devcontainer_json_name(&file_name) {
+                json_paths.push(path);
+            } else if is_dockerfile_name(&file_name) {
+                dockerfile_paths.push(path);
+            }
+        }
+    }
+    json_paths.sort();
+    dockerfile_paths.sort();
+    (json_paths, dockerfile_paths)
+}
+
+/// The name of the build stage a Dockerfile `FROM` line starts: its `AS` alias, or
+/// `number` for an unnamed stage. `None` for any other line.
+fn stage_name(line: &str, number: usize) -> Option<String> {
+    let mut words = line.split_whitespace();
+    if !words.next()?.eq_ignore_ascii_case("FROM") {
+        return None;
+    }
+    let words: Vec<&str> = words.collect();
+    match words
+        .iter()
+        .position(|word| word.eq_ignore_ascii_case("AS"))
+    {
+        Some(index) if index + 1 < words.len() => Some(format!("'{}'", words[index + 1])),
+        _ => Some(number.to_string()),
+    }
+}
+
+/// End `modified` with a newline exactly when `original` did, so rewrites don't
+/// introduce "No newline at end of file" diffs
+fn match_trailing_newline(original: &str, mut modified: String) -> String {
+    let had_newline = original.ends_with('\n');
+    while modified.ends_with('\n') {
+        modified.pop();
+    }
+    if had_newline {
+        modified.push('\n');
+    }
+    modified
+}
+
+/// Flag sudoers entries for the firewall script that survived stripping, since the
+/// container would still grant passwordless sudo to a script that no longer exists
+fn leftover_sudoers_warning(dockerfile: &str) -> Option<Warning> {
+    dockerfile
+        .lines()
+        .find(|line| line.contains("sudoers") && line.contains("firewall"))
+        .map(|line| {
+            Warning::warn(
+                "leftover-sudoers",
+                format!(
+                    "Dockerfile still grants sudo for the firewall script: {}",
+                    line.trim()
+                ),
+            )
+        })
 }
 
 /// Default implementation of DevcontainerCustomizer
@@ -108,6 +871,15 @@ impl FirewallRemovalResult {
 pub struct DefaultDevcontainerCustomizer {
     working_dir: PathBuf,
     verbose: bool,
+    firewall_packages: Vec<String>,
+    kept_packages: Vec<String>,
+    git_path: PathBuf,
+    git_timeout: std::time::Duration,
+    blame_ignore: bool,
+    select_scripts: bool,
+    preview_diff: bool,
+    color: ColorChoice,
+    synced_patterns_dir: Option<PathBuf>,
 }
 
 impl DefaultDevcontainerCustomizer {
@@ -115,45 +887,179 @@ impl DefaultDevcontainerCustomizer {
         Self {
             working_dir,
             verbose,
+            firewall_packages: DEFAULT_FIREWALL_PACKAGES
+                .iter()
+                .map(|package| package.to_string())
+                .collect(),
+            kept_packages: Vec::new(),
+            git_path: PathBuf::from(crate::config::DEFAULT_GIT_PROGRAM),
+            git_timeout: crate::config::default_timeout(),
+            blame_ignore: false,
+            select_scripts: false,
+            preview_diff: false,
+            color: ColorChoice::Auto,
+            synced_patterns_dir: None,
         }
     }
 
-    /// Create regex patterns for firewall detection
-    ///
-    /// These patterns are designed to be flexible and resilient to upstream changes.
-    /// They use regex syntax to match variations in formatting and structure.
-    ///
-    /// If upstream changes break detection, these patterns can be updated without
-    /// changing the core logic, making maintenance easier.
-    fn create_firewall_patterns() -> Result<Vec<Regex>, CliError> {
-        let patterns = [
-            r"iptables\s*\\?",
-            r"ipset\s*\\?",
-            r"iproute2\s*\\?",
-            r"dnsutils\s*\\?",
-            r"aggregate\s*\\?",
-            r"--cap-add=NET_ADMIN",
-            r"--cap-add=NET_RAW",
-            r"init-firewall\.sh",
-            r"firewall.*\.sh",
-            r"postStartCommand.*firewall",
-            r"waitFor.*postStartCommand",
-        ];
-
-        patterns
+    /// Use this git binary when committing customizations
+    pub fn with_git_path(mut self, git_path: PathBuf) -> Self {
+        self.git_path = git_path;
+        self
+    }
+
+    /// Kill git commands run while committing customizations (hooks included) after
+    /// `timeout`; zero lets them run indefinitely
+    pub fn with_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.git_timeout = timeout;
+        self
+    }
+
+    /// Record each customization commit in `.git-blame-ignore-revs`
+    pub fn with_blame_ignore(mut self, blame_ignore: bool) -> Self {
+        self.blame_ignore = blame_ignore;
+        self
+    }
+
+    /// Ask which firewall scripts to remove when several are detected and stdin is a
+    /// terminal; otherwise every detected script is removed
+    pub fn with_script_selection(mut self, select_scripts: bool) -> Self {
+        self.select_scripts = select_scripts;
+        self
+    }
+
+    /// Show the changes as a diff before making them, asking for confirmation when stdin
+    /// is a terminal
+    pub fn with_diff_preview(mut self, preview_diff: bool) -> Self {
+        self.preview_diff = preview_diff;
+        self
+    }
+
+    /// When to color the preview diff
+    pub fn with_color(mut self, color: ColorChoice) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Also use the patterns file in the synced devcontainer at `devcontainer_dir`, so
+    /// patterns the upstream ships with its devcontainer are detected too
+    pub fn with_synced_patterns(mut self, devcontainer_dir: PathBuf) -> Self {
+        self.synced_patterns_dir = Some(devcontainer_dir);
+        self
+    }
+
+    /// Leave the given packages in apt install commands instead of stripping them
+    pub fn with_kept_packages(mut self, packages: &[String]) -> Self {
+        self.firewall_packages
+            .retain(|package| !packages.contains(package));
+        self.kept_packages.extend_from_slice(packages);
+        self
+    }
+
+    /// Strip these packages in addition to the built-in firewall packages
+    pub fn with_extra_packages(mut self, packages: &[String]) -> Self {
+        for package in packages {
+            if !self.firewall_packages.contains(package) {
+                self.firewall_packages.push(package.clone());
+            }
+        }
+        self
+    }
+
+    /// Packages that will be removed from apt install commands
+    pub fn firewall_packages(&self) -> &[String] {
+        &self.firewall_packages
+    }
+
+    /// Packages removed from apt install commands for `profile`; extra packages only
+    /// apply to the firewall
+    fn packages_for(&self, profile: FeatureProfile) -> Vec<String> {
+        if profile == FeatureProfile::Firewall {
+            return self.firewall_packages.clone();
+        }
+        profile
+            .packages()
+            .iter()
+            .map(|package| package.to_string())
+            .filter(|package| !self.kept_packages.contains(package))
+            .collect()
+    }
+
+    /// Every detection pattern in effect for `profile`: the built-in set, plus for the
+    /// firewall any patterns listed in the repository's patterns file and in the one that
+    /// came with the synced devcontainer
+    pub fn detection_patterns(
+        &self,
+        profile: FeatureProfile,
+    ) -> Result<Vec<DetectionPattern>, CliError> {
+        let mut patterns: Vec<DetectionPattern> = profile
+            .content_patterns()
+            .iter()
+            .map(|pattern| DetectionPattern {
+                pattern: pattern.to_string(),
+                source: PatternSource::BuiltIn,
+            })
+            .collect();
+
+        if profile == FeatureProfile::Firewall {
+            patterns.extend(
+                read_patterns_file(&self.working_dir)?
+                    .into_iter()
+                    .map(|pattern| DetectionPattern {
+                        pattern,
+                        source: PatternSource::Config,
+                    }),
+            );
+            if let Some(dir) = &self.synced_patterns_dir {
+                patterns.extend(read_patterns_file(dir)?.into_iter().map(|pattern| {
+                    DetectionPattern {
+                        pattern,
+                        source: PatternSource::Synced,
+                    }
+                }));
+            }
+        }
+
+        Ok(patterns)
+    }
+
+    /// Compile the detection patterns of `profile`
+    fn create_patterns(&self, profile: FeatureProfile) -> Result<Vec<Regex>, CliError> {
+        self.detection_patterns(profile)?
             .iter()
-            .map(|pattern| {
-                Regex::new(pattern).map_err(|e| CliError::Repository {
-                    message: format!("Invalid regex pattern '{}': {}", pattern, e),
-                    suggestion: "This is a bug in the firewall pattern configuration".to_string(),
+            .map(|detection| {
+                Regex::new(&detection.pattern).map_err(|e| CliError::Repository {
+                    message: format!("Invalid regex pattern '{}': {}", detection.pattern, e),
+                    suggestion: match detection.source {
+                        PatternSource::BuiltIn => format!(
+                            "This is a bug in the {} pattern configuration",
+                            profile.name()
+                        ),
+                        PatternSource::Config => {
+                            format!("Fix or remove the pattern in {}", PATTERNS_FILE_NAME)
+                        }
+                        PatternSource::Synced => format!(
+                            "The pattern came from upstream in the devcontainer's {}; report it upstream",
+                            PATTERNS_FILE_NAME
+                        ),
+                    },
                 })
             })
             .collect()
     }
 
     /// Check if content matches any firewall patterns
-    fn matches_firewall_patterns(&self, content: &str) -> Result<Vec<String>, CliError> {
-        let patterns = Self::create_firewall_patterns()?;
+    pub fn matches_firewall_patterns(&self, content: &str) -> Result<Vec<String>, CliError> {
+        self.matches_profile_patterns(content, FeatureProfile::Firewall)
+    }
+
+    /// Check if content matches any of the patterns of `profile`
+    pub fn matches_profile_patterns(
+        &self,
+        content: &str,
+        profile: FeatureProfile,
+    ) -> Result<Vec<String>, CliError> {
+        let patterns = self.create_patterns(profile)?;
         let mut matches = Vec::new();
 
         for pattern in patterns {
@@ -165,76 +1071,254 @@ impl DefaultDevcontainerCustomizer {
         Ok(matches)
     }
 
-    /// Log operation if verbose mode is enabled
-    fn log_verbose(&self, message: &str) {
-        if self.verbose {
-            println!("🔧 {}", message);
+    /// Append HEAD to `.git-blame-ignore-revs` and commit that on its own, so blame
+    /// skips the strip commit
+    fn record_blame_ignore(
+        &self,
+        executor: &impl crate::git::GitExecutor,
+        message: &str,
+    ) -> Result<(), CliError> {
+        use crate::config::{BLAME_IGNORE_COMMIT_MESSAGE, BLAME_IGNORE_REVS_FILE};
+
+        let sha = executor.execute_git_command(&["rev-parse", "HEAD"], &self.working_dir)?;
+        let sha = sha.trim();
+
+        let path = self.working_dir.join(BLAME_IGNORE_REVS_FILE);
+        let mut contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                return Err(CliError::FileSystem {
+                    message: format!("Failed to read {}: {}", BLAME_IGNORE_REVS_FILE, e),
+                    suggestion: "Check file permissions".to_string(),
+                })
+            }
+        };
+        if !contents.is_empty() && !contents.ends_with('\n') {
+            contents.push('\n');
         }
+        contents.push_str(&format!("# {}\n{}\n", message, sha));
+
+        std::fs::write(&path, contents).map_err(|e| CliError::FileSystem {
+            message: format!("Failed to write {}: {}", BLAME_IGNORE_REVS_FILE, e),
+            suggestion: "Check file permissions".to_string(),
+        })?;
+
+        executor.execute_git_command(&["add", "--", BLAME_IGNORE_REVS_FILE], &self.working_dir)?;
+        executor.execute_git_command(
+            &[
+                "commit",
+                "-m",
+                BLAME_IGNORE_COMMIT_MESSAGE,
+                "--",
+                BLAME_IGNORE_REVS_FILE,
+            ],
+            &self.working_dir,
+        )?;
+
+        self.log_verbose(&format!("Recorded {} in {}", sha, BLAME_IGNORE_REVS_FILE));
+        Ok(())
     }
-}
 
-impl DevcontainerCustomizer for DefaultDevcontainerCustomizer {
-    fn strip_firewall_features(
+    /// Strip the `profile` features from `devcontainer_path`, or only from the files in `scope`
+    fn strip_scoped(
         &self,
         devcontainer_path: &Path,
-    ) -> Result<FirewallRemovalResult, CliError> {
-        let mut result = FirewallRemovalResult::new();
-
-        self.log_verbose("Starting firewall feature stripping...");
-
-        // Detect and remove firewall scripts
-        let scripts = self.detect_firewall_scripts(devcontainer_path)?;
+        scope: Option<&[PathBuf]>,
+        profile: FeatureProfile,
+    ) -> Result<CustomizationResult, CliError> {
+        let name = profile.name();
+        let mut result = CustomizationResult::new();
+        let in_scope = |path: &Path| scope.is_none_or(|files| files.iter().any(|f| f == path));
+
+        self.log_verbose(&format!("Starting {} feature stripping...", name));
+
+        // Detect and remove the profile's scripts
+        let mut scripts = self.detect_scripts(devcontainer_path, profile)?;
+        scripts.retain(|script| in_scope(script));
+        if self.select_scripts && scripts.len() > 1 && std::io::stdin().is_terminal() {
+            let selected = select_scripts(
+                &scripts,
+                &self.working_dir,
+                std::io::stdin().lock(),
+                std::io::stderr(),
+            )?;
+            for script in scripts.iter().filter(|script| !selected.contains(script)) {
+                result.add_warning(Warning::info(
+                    &format!("kept-{}-script", name),
+                    format!(
+                        "Kept {} script {} as selected",
+                        name,
+                        self.display_path(script)
+                    ),
+                ));
+            }
+            scripts = selected;
+        }
+        let mut removals = Vec::new();
         for script in scripts {
-            if script.exists() {
-                std::fs::remove_file(&script).map_err(|e| CliError::FileSystem {
-                    message: format!(
-                        "Failed to remove firewall script {}: {}",
-                        script.display(),
-                        e
+            if script.exists() && !is_within(&script, devcontainer_path) {
+                // A symlink, or a symlinked directory, leading out of the devcontainer
+                result.add_warning(Warning::warn(
+                    "outside-devcontainer",
+                    format!(
+                        "Not removing {} script {}: it resolves outside {}",
+                        name,
+                        self.display_path(&script),
+                        self.display_path(devcontainer_path)
                     ),
-                    suggestion: "Check file permissions and try again".to_string(),
-                })?;
-                result.add_removed_file(script.clone());
-                self.log_verbose(&format!("Removed firewall script: {}", script.display()));
+                ));
+            } else if script.exists() {
+                removals.push(script);
             }
         }
 
-        // Strip devcontainer.json firewall configurations
-        let json_path = devcontainer_path.join("devcontainer.json");
-        if json_path.exists() {
-            let changes = self.strip_devcontainer_json_firewall(&json_path)?;
-            if !changes.is_empty() {
-                result.add_modified_file(json_path);
-                for change in changes {
-                    result.add_json_change(change);
+        // Work out the profile's devcontainer.json and Dockerfile changes, writing nothing yet
+        let (json_paths, dockerfile_paths) = discover_config_files(devcontainer_path);
+        if json_paths.is_empty() {
+            result.add_warning(Warning::warn(
+                "missing-devcontainer-json",
+                "devcontainer.json not found",
+            ));
+        }
+        if dockerfile_paths.is_empty() {
+            result.add_warning(Warning::warn("missing-dockerfile", "Dockerfile not found"));
+        }
+        // Files unchanged since they were last stripped are left alone
+        let json_paths: Vec<PathBuf> = json_paths.into_iter().filter(|p| in_scope(p)).collect();
+        let dockerfile_paths: Vec<PathBuf> = dockerfile_paths
+            .into_iter()
+            .filter(|p| in_scope(p))
+            .collect();
+
+        let mut json_rewrites = Vec::new();
+        for json_path in &json_paths {
+            json_rewrites.extend(self.propose_json_rewrite(json_path, profile)?);
+        }
+        let mut dockerfile_rewrites = Vec::new();
+        for dockerfile_path in &dockerfile_paths {
+            dockerfile_rewrites.extend(self.propose_dockerfile_rewrite(dockerfile_path, profile)?);
+        }
+        let mut compose_rewrites = Vec::new();
+        for compose_path in discover_compose_files(devcontainer_path) {
+            if in_scope(&compose_path) {
+                compose_rewrites.extend(self.propose_compose_rewrite(&compose_path, profile)?);
+            }
+        }
+
+        let has_changes = !removals.is_empty()
+            || !json_rewrites.is_empty()
+            || !dockerfile_rewrites.is_empty()
+            || !compose_rewrites.is_empty();
+        if self.preview_diff && has_changes {
+            let rewrites: Vec<&Rewrite> = json_rewrites
+                .iter()
+                .chain(dockerfile_rewrites.iter())
+                .chain(compose_rewrites.iter())
+                .collect();
+            if !self.confirm_preview(&removals, &rewrites)? {
+                self.log_verbose(&format!("Left {} features in place as declined", name));
+                result.declined = true;
+                return Ok(result);
+            }
+        }
+
+        // Remove the profile's scripts
+        for script in removals {
+            std::fs::remove_file(&script).map_err(|e| CliError::FileSystem {
+                message: format!(
+                    "Failed to remove {} script {}: {}",
+                    name,
+                    self.display_path(&script),
+                    e
+                ),
+                suggestion: "Check file permissions and try again".to_string(),
+            })?;
+            self.log_verbose(&format!(
+                "Removed {} script: {}",
+                name,
+                self.display_path(&script)
+            ));
+            result.add_removed_file(script);
+        }
+
+        // Strip the profile's devcontainer.json configurations
+        for rewrite in json_rewrites {
+            self.apply_rewrite(&rewrite)?;
+            self.record_removed_lines(&rewrite, &mut result);
+            for change in rewrite.changes_in_file("devcontainer.json") {
+                result.add_json_change(change);
+            }
+            for warning in rewrite.warnings {
+                result.add_warning(warning);
+            }
+            result.add_modified_file(rewrite.path);
+        }
+
+        // Strip the profile's Dockerfile configurations
+        for rewrite in dockerfile_rewrites {
+            self.apply_rewrite(&rewrite)?;
+            self.record_removed_lines(&rewrite, &mut result);
+            for change in rewrite.changes_in_file("Dockerfile") {
+                result.add_dockerfile_change(change);
+            }
+            result.add_modified_file(rewrite.path);
+        }
+
+        // Strip the profile's capabilities from compose services
+        for rewrite in compose_rewrites {
+            self.apply_rewrite(&rewrite)?;
+            self.record_removed_lines(&rewrite, &mut result);
+            for change in rewrite.changes_in_file("") {
+                result.add_compose_change(change);
+            }
+            for warning in rewrite.warnings {
+                result.add_warning(warning);
+            }
+            result.add_modified_file(rewrite.path);
+        }
+        if profile == FeatureProfile::Firewall {
+            for dockerfile_path in &dockerfile_paths {
+                if let Some(warning) = std::fs::read_to_string(dockerfile_path)
+                    .ok()
+                    .and_then(|content| leftover_sudoers_warning(&content))
+                {
+                    result.add_warning(warning);
                 }
             }
-        } else {
-            result.add_warning("devcontainer.json not found".to_string());
         }
 
-        // Strip Dockerfile firewall configurations
-        let dockerfile_path = devcontainer_path.join("Dockerfile");
-        if dockerfile_path.exists() {
-            let changes = self.strip_dockerfile_firewall(&dockerfile_path)?;
-            if !changes.is_empty() {
-                result.add_modified_file(dockerfile_path);
-                for change in changes {
-                    result.add_dockerfile_change(change);
+        if self.verbose || log::log_enabled!(log::Level::Debug) {
+            for json_path in &json_paths {
+                let settings = std::fs::read_to_string(json_path)
+                    .map(|content| preserved_settings(&content))
+                    .unwrap_or_default();
+                if settings.is_empty() {
+                    continue;
                 }
+                let file_name = config_file_name(json_path);
+                let source = if file_name == "devcontainer.json" {
+                    String::new()
+                } else {
+                    format!(" in {}", file_name)
+                };
+                self.log_verbose(&format!(
+                    "Preserved configuration{}:\n  - {}",
+                    source,
+                    settings.join("\n  - ")
+                ));
             }
-        } else {
-            result.add_warning("Dockerfile not found".to_string());
         }
 
         // Validate results
-        let validation_warnings = self.validate_firewall_removal(&result);
+        let validation_warnings = self.validate_removal(&result, profile);
         for warning in validation_warnings {
             result.add_warning(warning);
         }
 
         self.log_verbose(&format!(
-            "Firewall stripping complete: {} files modified, {} files removed, {} warnings",
+            "Stripping {} features complete: {} files modified, {} files removed, {} warnings",
+            name,
             result.files_modified.len(),
             result.files_removed.len(),
             result.warnings.len()
@@ -243,32 +1327,90 @@ impl DevcontainerCustomizer for DefaultDevcontainerCustomizer {
         Ok(result)
     }
 
-    fn detect_firewall_scripts(&self, devcontainer_path: &Path) -> Result<Vec<PathBuf>, CliError> {
-        let mut scripts = Vec::new();
+    fn display_path(&self, path: &Path) -> String {
+        repo_relative(path, &self.working_dir)
+    }
 
-        // Check for common firewall script names
-        let script_patterns = ["init-firewall.sh", "firewall.sh", "iptables.sh"];
+    /// Show the pending changes as a diff on stderr and, when someone is at the terminal,
+    /// ask whether to apply them; without a terminal they are shown and applied
+    fn confirm_preview(
+        &self,
+        removals: &[PathBuf],
+        rewrites: &[&Rewrite],
+    ) -> Result<bool, CliError> {
+        let color = self.color.enabled(std::io::stderr().is_terminal());
+        let mut diff = String::new();
+        for script in removals {
+            let bytes = std::fs::read(script).unwrap_or_default();
+            let content = String::from_utf8_lossy(&bytes);
+            diff.push_str(&render_diff(
+                &self.display_path(script),
+                &content,
+                None,
+                color,
+            ));
+        }
+        for rewrite in rewrites {
+            diff.push_str(&render_diff(
+                &self.display_path(&rewrite.path),
+                &rewrite.original,
+                Some(&rewrite.modified),
+                color,
+            ));
+        }
+        eprint!("\n{}", diff);
 
-        for pattern in script_patterns {
-            let script_path = devcontainer_path.join(pattern);
-            if script_path.exists() {
-                scripts.push(script_path);
-            }
+        if !std::io::stdin().is_terminal() {
+            return Ok(true);
         }
+        confirm_changes(std::io::stdin().lock(), std::io::stderr())
+    }
 
-        // Also check for any .sh files that contain firewall-related content
+    /// Verbose customizers log at info so embedders see the detail without
+    /// enabling debug output for the whole crate
+    fn log_verbose(&self, message: &str) {
+        let level = if self.verbose {
+            log::Level::Info
+        } else {
+            log::Level::Debug
+        };
+        log::log!(level, "🔧 {}", message);
+    }
+
+    fn detect_scripts(
+        &self,
+        devcontainer_path: &Path,
+        profile: FeatureProfile,
+    ) -> Result<Vec<PathBuf>, CliError> {
+        let files = files_under(devcontainer_path, MAX_SCRIPT_SCAN_DEPTH);
+
+        // Check for the profile's common script names, in any subdirectory
+        let mut scripts: Vec<PathBuf> = files
+            .iter()
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| profile.script_names().contains(&name))
+            })
+            .cloned()
+            .collect();
+
+        // Also check for any .sh files that contain feature-related content
         // but avoid duplicates from the name-based detection above
-        if let Ok(entries) = std::fs::read_dir(devcontainer_path) {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if path.extension().and_then(|s| s.to_str()) == Some("sh")
-                    && !scripts.contains(&path)
-                {
-                    if let Ok(content) = std::fs::read_to_string(&path) {
-                        let matches = self.matches_firewall_patterns(&content)?;
-                        if !matches.is_empty() {
-                            scripts.push(path);
-                        }
+        for path in files {
+            if path.extension().and_then(|s| s.to_str()) == Some("sh") && !scripts.contains(&path) {
+                // Decode lossily so a script with stray non-UTF-8 bytes is still scanned
+                if let Ok(bytes) = std::fs::read(&path) {
+                    let content = String::from_utf8_lossy(&bytes);
+                    if let std::borrow::Cow::Owned(_) = content {
+                        self.log_verbose(&format!(
+                            "{} is not valid UTF-8; scanning it lossily",
+                            self.display_path(&path)
+                        ));
+                    }
+                    let matches = self.matches_profile_patterns(&content, profile)?;
+                    if !matches.is_empty() {
+                        scripts.push(path);
                     }
                 }
             }
@@ -277,75 +1419,302 @@ impl DevcontainerCustomizer for DefaultDevcontainerCustomizer {
         Ok(scripts)
     }
 
-    fn strip_devcontainer_json_firewall(&self, json_path: &Path) -> Result<Vec<String>, CliError> {
+    fn strip_json_features(
+        &self,
+        json_path: &Path,
+        profile: FeatureProfile,
+    ) -> Result<Vec<String>, CliError> {
+        let rewrite = self.propose_json_rewrite(json_path, profile)?;
+        if let Some(rewrite) = &rewrite {
+            self.apply_rewrite(rewrite)?;
+        }
+        Ok(rewrite.map(|rewrite| rewrite.changes).unwrap_or_default())
+    }
+
+    /// Work out the stripped devcontainer.json without writing it
+    fn propose_json_rewrite(
+        &self,
+        json_path: &Path,
+        profile: FeatureProfile,
+    ) -> Result<Option<Rewrite>, CliError> {
         let content = std::fs::read_to_string(json_path).map_err(|e| CliError::FileSystem {
             message: format!("Failed to read devcontainer.json: {}", e),
             suggestion: "Check file permissions and ensure the file exists".to_string(),
         })?;
 
+        let PlainJson {
+            json: plain_json,
+            had_comments,
+            repairs,
+        } = strip_jsonc(&content);
         let mut json: serde_json::Value =
-            serde_json::from_str(&content).map_err(|e| CliError::Repository {
+            serde_json::from_str(&plain_json).map_err(|e| CliError::Repository {
                 message: format!("Invalid JSON in devcontainer.json: {}", e),
                 suggestion: "Fix JSON syntax errors in devcontainer.json".to_string(),
             })?;
 
         let mut changes = Vec::new();
 
-        // Remove firewall capabilities from runArgs
+        // Remove the profile's runArgs
         if let Some(run_args) = json.get_mut("runArgs").and_then(|v| v.as_array_mut()) {
             let original_len = run_args.len();
-            run_args.retain(|arg| {
-                if let Some(arg_str) = arg.as_str() {
-                    !arg_str.contains("--cap-add=NET_ADMIN")
-                        && !arg_str.contains("--cap-add=NET_RAW")
-                } else {
-                    true
+            let mut kept = Vec::with_capacity(original_len);
+            let mut args = std::mem::take(run_args).into_iter();
+            while let Some(arg) = args.next() {
+                let arg_str = arg.as_str().unwrap_or_default();
+                if !profile.run_args().iter().any(|flag| arg_str.contains(flag)) {
+                    kept.push(arg);
+                } else if !arg_str.contains('=') && profile.run_args().contains(&arg_str) {
+                    // A bare flag such as "--gpus" takes its value as the next argument
+                    args.next();
                 }
-            });
+            }
+            *run_args = kept;
             if run_args.len() < original_len {
-                changes.push("Removed NET_ADMIN and NET_RAW capabilities from runArgs".to_string());
+                changes.push(format!(
+                    "Removed {} from runArgs",
+                    profile.run_args_description()
+                ));
             }
         }
 
-        // Remove postStartCommand if it references firewall
+        // Remove postStartCommand if it references the feature. Keys are removed with
+        // shift_remove so the rewrite keeps the upstream key order, including a leading
+        // "$schema" reference, and the diff stays minimal
         if let Some(post_start) = json.get("postStartCommand").and_then(|v| v.as_str()) {
-            if post_start.contains("firewall") {
-                json.as_object_mut().unwrap().remove("postStartCommand");
-                changes.push("Removed postStartCommand referencing firewall".to_string());
+            if post_start.contains(profile.command_keyword()) {
+                json.as_object_mut()
+                    .unwrap()
+                    .shift_remove("postStartCommand");
+                changes.push(format!(
+                    "Removed postStartCommand referencing {}",
+                    profile.command_keyword()
+                ));
+            }
+        }
+
+        // Remove the feature's host requirements, and the object if nothing is left
+        if let Some(requirements) = json
+            .get_mut("hostRequirements")
+            .and_then(|v| v.as_object_mut())
+        {
+            let removed: Vec<&str> = profile
+                .host_requirements()
+                .iter()
+                .copied()
+                .filter(|key| requirements.shift_remove(*key).is_some())
+                .collect();
+            let now_empty = requirements.is_empty();
+            if !removed.is_empty() {
+                changes.push(format!(
+                    "Removed {} from hostRequirements",
+                    removed.join(", ")
+                ));
+                if now_empty {
+                    json.as_object_mut()
+                        .unwrap()
+                        .shift_remove("hostRequirements");
+                }
+            }
+        }
+
+        // Remove features whose identifier matches a detection pattern, such as
+        // ghcr.io/.../iptables, and the object if nothing is left
+        let patterns = self.create_patterns(profile)?;
+        if let Some(features) = json.get_mut("features").and_then(|v| v.as_object_mut()) {
+            let matching: Vec<String> = features
+                .keys()
+                .filter(|key| patterns.iter().any(|pattern| pattern.is_match(key)))
+                .cloned()
+                .collect();
+            for key in &matching {
+                features.shift_remove(key);
+                changes.push(format!("Removed {} feature {}", profile.name(), key));
+            }
+            if !matching.is_empty() && features.is_empty() {
+                json.as_object_mut().unwrap().shift_remove("features");
             }
         }
 
         // Remove waitFor if it references postStartCommand
         if let Some(wait_for) = json.get("waitFor").and_then(|v| v.as_str()) {
             if wait_for == "postStartCommand" && json.get("postStartCommand").is_none() {
-                json.as_object_mut().unwrap().remove("waitFor");
+                json.as_object_mut().unwrap().shift_remove("waitFor");
                 changes.push("Removed waitFor since postStartCommand was removed".to_string());
             }
         }
 
-        // Write back the modified JSON if there were changes
-        if !changes.is_empty() {
-            let modified_content =
-                serde_json::to_string_pretty(&json).map_err(|e| CliError::Repository {
-                    message: format!("Failed to serialize modified JSON: {}", e),
-                    suggestion: "This is likely a bug in the JSON modification logic".to_string(),
-                })?;
+        if changes.is_empty() {
+            return Ok(None);
+        }
 
-            std::fs::write(json_path, modified_content).map_err(|e| CliError::FileSystem {
-                message: format!("Failed to write modified devcontainer.json: {}", e),
-                suggestion: "Check file permissions and available disk space".to_string(),
+        let modified_content =
+            serde_json::to_string_pretty(&json).map_err(|e| CliError::Repository {
+                message: format!("Failed to serialize modified JSON: {}", e),
+                suggestion: "This is likely a bug in the JSON modification logic".to_string(),
             })?;
+        let modified = match_trailing_newline(&content, modified_content);
 
-            self.log_verbose(&format!(
-                "Modified devcontainer.json: {}",
-                changes.join(", ")
+        // The rewrite is strict JSON, so lenient syntax in the hand-edited original is gone
+        let mut warnings = Vec::new();
+        if !repairs.is_empty() {
+            warnings.push(Warning::warn(
+                "normalized-json",
+                format!(
+                    "Normalized minor JSON issues in {}: {}",
+                    self.display_path(json_path),
+                    repairs.join(", ")
+                ),
+            ));
+        }
+        // Rewriting it as plain JSON loses any comments, which the user has to restore by
+        // hand, so this is a warning rather than one of the changes made
+        if had_comments {
+            warnings.push(Warning::warn(
+                "dropped-comments",
+                format!(
+                    "Dropped comments while rewriting {}",
+                    self.display_path(json_path)
+                ),
             ));
         }
 
-        Ok(changes)
+        Ok(Some(Rewrite {
+            path: json_path.to_path_buf(),
+            file_name: config_file_name(json_path),
+            original: content,
+            modified,
+            changes,
+            warnings,
+        }))
     }
 
-    fn strip_dockerfile_firewall(&self, dockerfile_path: &Path) -> Result<Vec<String>, CliError> {
+    fn strip_compose_features(
+        &self,
+        compose_path: &Path,
+        profile: FeatureProfile,
+    ) -> Result<Vec<String>, CliError> {
+        let rewrite = self.propose_compose_rewrite(compose_path, profile)?;
+        if let Some(rewrite) = &rewrite {
+            self.apply_rewrite(rewrite)?;
+        }
+        Ok(rewrite.map(|rewrite| rewrite.changes).unwrap_or_default())
+    }
+
+    /// Work out the compose file with the profile's capabilities dropped from every
+    /// service's `cap_add`, without writing it
+    fn propose_compose_rewrite(
+        &self,
+        compose_path: &Path,
+        profile: FeatureProfile,
+    ) -> Result<Option<Rewrite>, CliError> {
+        let capabilities = profile.capabilities();
+        if capabilities.is_empty() {
+            return Ok(None);
+        }
+        let file_name = config_file_name(compose_path);
+        let content = std::fs::read_to_string(compose_path).map_err(|e| CliError::FileSystem {
+            message: format!("Failed to read {}: {}", file_name, e),
+            suggestion: "Check file permissions and ensure the file exists".to_string(),
+        })?;
+        let mut compose: serde_yaml::Value =
+            serde_yaml::from_str(&content).map_err(|e| CliError::Repository {
+                message: format!("Invalid YAML in {}: {}", file_name, e),
+                suggestion: format!("Fix YAML syntax errors in {}", file_name),
+            })?;
+
+        let mut changes = Vec::new();
+        if let Some(services) = compose
+            .get_mut("services")
+            .and_then(|services| services.as_mapping_mut())
+        {
+            for (service, config) in services.iter_mut() {
+                let Some(cap_add) = config
+                    .get_mut("cap_add")
+                    .and_then(|cap_add| cap_add.as_sequence_mut())
+                else {
+                    continue;
+                };
+                // Compose accepts capabilities with or without the CAP_ prefix
+                let mut removed = Vec::new();
+                cap_add.retain(|cap| {
+                    let name = cap.as_str().unwrap_or_default();
+                    let bare = name.strip_prefix("CAP_").unwrap_or(name);
+                    let granted = capabilities
+                        .iter()
+                        .any(|capability| bare.eq_ignore_ascii_case(capability));
+                    if granted {
+                        removed.push(name.to_string());
+                    }
+                    !granted
+                });
+                if removed.is_empty() {
+                    continue;
+                }
+                if cap_add.is_empty() {
+                    if let Some(config) = config.as_mapping_mut() {
+                        config.shift_remove("cap_add");
+                    }
+                }
+                changes.push(format!(
+                    "Removed {} from cap_add of service '{}'",
+                    removed.join(", "),
+                    service.as_str().unwrap_or_default()
+                ));
+            }
+        }
+
+        if changes.is_empty() {
+            return Ok(None);
+        }
+        // As for devcontainer.json, lost comments are for the user to restore, not a change
+        let mut warnings = Vec::new();
+        if content
+            .lines()
+            .any(|line| line.trim_start().starts_with('#'))
+        {
+            warnings.push(Warning::warn(
+                "dropped-comments",
+                format!(
+                    "Dropped comments while rewriting {}",
+                    self.display_path(compose_path)
+                ),
+            ));
+        }
+        let modified_content =
+            serde_yaml::to_string(&compose).map_err(|e| CliError::Repository {
+                message: format!("Failed to serialize modified {}: {}", file_name, e),
+                suggestion: "This is likely a bug in the compose modification logic".to_string(),
+            })?;
+
+        Ok(Some(Rewrite {
+            path: compose_path.to_path_buf(),
+            file_name,
+            modified: match_trailing_newline(&content, modified_content),
+            original: content,
+            changes,
+            warnings,
+        }))
+    }
+
+    fn strip_dockerfile_features(
+        &self,
+        dockerfile_path: &Path,
+        profile: FeatureProfile,
+    ) -> Result<Vec<String>, CliError> {
+        let rewrite = self.propose_dockerfile_rewrite(dockerfile_path, profile)?;
+        if let Some(rewrite) = &rewrite {
+            self.apply_rewrite(rewrite)?;
+        }
+        Ok(rewrite.map(|rewrite| rewrite.changes).unwrap_or_default())
+    }
+
+    /// Work out the stripped Dockerfile without writing it
+    fn propose_dockerfile_rewrite(
+        &self,
+        dockerfile_path: &Path,
+        profile: FeatureProfile,
+    ) -> Result<Option<Rewrite>, CliError> {
         let content =
             std::fs::read_to_string(dockerfile_path).map_err(|e| CliError::FileSystem {
                 message: format!("Failed to read Dockerfile: {}", e),
@@ -353,29 +1722,52 @@ impl DevcontainerCustomizer for DefaultDevcontainerCustomizer {
             })?;
 
         let lines: Vec<&str> = content.lines().collect();
-        let mut modified_lines = Vec::new();
+        let mut modified_lines: Vec<String> = Vec::new();
         let mut changes = Vec::new();
-        let mut in_firewall_section = false;
+        let name = profile.name();
+        let packages = self.packages_for(profile);
+        let mut in_feature_section = false;
         let mut in_apt_install = false;
+        // Multi-stage builds are stripped stage by stage, and each change names its stage
+        let multi_stage = lines
+            .iter()
+            .filter(|line| stage_name(line, 0).is_some())
+            .count()
+            > 1;
+        let mut stage_count = 0;
+        let mut stage_suffix = String::new();
 
         for line in lines {
             let mut skip_line = false;
 
-            // Check if we're entering a firewall section
-            if line.contains("# Copy and set up firewall script") {
-                in_firewall_section = true;
+            // Each FROM starts a fresh stage, so nothing carries over from the last one
+            if let Some(stage) = stage_name(line, stage_count + 1) {
+                stage_count += 1;
+                in_feature_section = false;
+                in_apt_install = false;
+                if multi_stage {
+                    stage_suffix = format!(" in stage {}", stage);
+                }
+            }
+
+            // Check if we're entering the feature's setup section
+            if profile
+                .dockerfile_section()
+                .is_some_and(|marker| line.contains(marker))
+            {
+                in_feature_section = true;
                 skip_line = true;
-                changes.push("Removed firewall setup section".to_string());
+                changes.push(format!("Removed {} setup section{}", name, stage_suffix));
             }
 
-            // Check if we're exiting a firewall section (when we see USER node after firewall setup)
-            if in_firewall_section && line.trim() == "USER node" {
-                in_firewall_section = false;
+            // Check if we're exiting the section (when we see USER node after the setup)
+            if in_feature_section && line.trim() == "USER node" {
+                in_feature_section = false;
                 skip_line = true;
             }
 
-            // Skip lines in firewall section
-            if in_firewall_section {
+            // Skip lines in the feature section
+            if in_feature_section {
                 skip_line = true;
             }
 
@@ -385,11 +1777,10 @@ impl DevcontainerCustomizer for DefaultDevcontainerCustomizer {
             }
 
             if in_apt_install {
-                let firewall_packages = ["iptables", "ipset", "iproute2", "dnsutils", "aggregate"];
                 let mut modified_line = line.to_string();
                 let mut package_removed = false;
 
-                for package in firewall_packages {
+                for package in &packages {
                     if modified_line.contains(package) {
                         // Remove the package and any trailing backslash/whitespace
                         modified_line = modified_line.replace(&format!("  {} \\", package), "");
@@ -400,15 +1791,34 @@ impl DevcontainerCustomizer for DefaultDevcontainerCustomizer {
                     }
                 }
 
-                if package_removed && !changes.iter().any(|c| c.contains("firewall packages")) {
-                    changes.push("Removed firewall packages from apt install".to_string());
+                let message = format!("Removed {} packages from apt install{}", name, stage_suffix);
+                if package_removed && !changes.contains(&message) {
+                    changes.push(message);
                 }
 
-                // Check if this line ends the apt install command
-                if !line.ends_with('\\') {
+                // Check if this line ends the apt install command. Comment and blank lines
+                // inside a continuation don't end the instruction, so the next block is only
+                // picked up once the real last line of this one has been seen
+                let trimmed = line.trim();
+                let continues = line.trim_end().ends_with('\\');
+                if !continues && !trimmed.is_empty() && !trimmed.starts_with('#') {
                     in_apt_install = false;
                 }
 
+                if package_removed && modified_line.trim().trim_end_matches('\\').is_empty() {
+                    // The whole line was feature packages. When it was the last line of the
+                    // instruction, the previous line's continuation has to go too, or the
+                    // next instruction would be folded into this RUN
+                    if !continues {
+                        if let Some(previous) = modified_lines.last_mut() {
+                            if let Some(stripped) = previous.trim_end().strip_suffix('\\') {
+                                *previous = stripped.trim_end().to_string();
+                            }
+                        }
+                    }
+                    skip_line = true;
+                }
+
                 if !skip_line {
                     modified_lines.push(modified_line);
                 }
@@ -417,61 +1827,167 @@ impl DevcontainerCustomizer for DefaultDevcontainerCustomizer {
             }
         }
 
-        // Write back the modified Dockerfile if there were changes
-        if !changes.is_empty() {
-            let modified_content = modified_lines.join("\n");
-            std::fs::write(dockerfile_path, modified_content).map_err(|e| {
-                CliError::FileSystem {
-                    message: format!("Failed to write modified Dockerfile: {}", e),
-                    suggestion: "Check file permissions and available disk space".to_string(),
-                }
-            })?;
+        if changes.is_empty() {
+            return Ok(None);
+        }
+
+        let modified = match_trailing_newline(&content, modified_lines.join("\n"));
+        Ok(Some(Rewrite {
+            path: dockerfile_path.to_path_buf(),
+            file_name: config_file_name(dockerfile_path),
+            original: content,
+            modified,
+            changes,
+            warnings: Vec::new(),
+        }))
+    }
 
-            self.log_verbose(&format!("Modified Dockerfile: {}", changes.join(", ")));
+    /// Record the lines `rewrite` removed, printing each in verbose mode
+    fn record_removed_lines(&self, rewrite: &Rewrite, result: &mut CustomizationResult) {
+        for change in rewrite.removed_lines() {
+            self.log_verbose(&format!(
+                "{}:{} removed '{}'",
+                self.display_path(&change.path),
+                change.line,
+                change.removed
+            ));
+            result.changes.push(change);
         }
+    }
 
-        Ok(changes)
+    fn apply_rewrite(&self, rewrite: &Rewrite) -> Result<(), CliError> {
+        std::fs::write(&rewrite.path, &rewrite.modified).map_err(|e| CliError::FileSystem {
+            message: format!("Failed to write modified {}: {}", rewrite.file_name, e),
+            suggestion: "Check file permissions and available disk space".to_string(),
+        })?;
+
+        self.log_verbose(&format!(
+            "Modified {}: {}",
+            rewrite.file_name,
+            rewrite.changes.join(", ")
+        ));
+        Ok(())
     }
 
-    fn validate_firewall_removal(&self, removal_result: &FirewallRemovalResult) -> Vec<String> {
+    fn validate_removal(
+        &self,
+        removal_result: &CustomizationResult,
+        profile: FeatureProfile,
+    ) -> Vec<Warning> {
+        let name = profile.name();
         let mut warnings = Vec::new();
 
         // Check if we expected to find certain files but didn't
         if removal_result.files_removed.is_empty() {
-            warnings.push("No firewall scripts were found to remove".to_string());
+            warnings.push(Warning::info(
+                &format!("no-{}-scripts", name),
+                format!("No {} scripts were found to remove", name),
+            ));
         }
 
         if removal_result.dockerfile_changes.is_empty() {
-            warnings.push("No firewall configurations found in Dockerfile".to_string());
+            warnings.push(Warning::info(
+                &format!("no-dockerfile-{}", name),
+                format!("No {} configurations found in Dockerfile", name),
+            ));
         }
 
         if removal_result.json_changes.is_empty() {
-            warnings.push("No firewall configurations found in devcontainer.json".to_string());
+            warnings.push(Warning::info(
+                &format!("no-json-{}", name),
+                format!("No {} configurations found in devcontainer.json", name),
+            ));
         }
 
         // This is expected behavior - we want to warn when patterns aren't found
         // so users know what wasn't stripped
         warnings
     }
+}
 
-    fn commit_customizations(&self, changes: &[String], message: &str) -> Result<(), CliError> {
+impl DevcontainerCustomizer for DefaultDevcontainerCustomizer {
+    fn strip_firewall_features(
+        &self,
+        devcontainer_path: &Path,
+    ) -> Result<FirewallRemovalResult, CliError> {
+        self.strip_features(devcontainer_path, FeatureProfile::Firewall)
+    }
+
+    fn strip_features(
+        &self,
+        devcontainer_path: &Path,
+        profile: FeatureProfile,
+    ) -> Result<CustomizationResult, CliError> {
+        self.strip_scoped(devcontainer_path, None, profile)
+    }
+
+    fn strip_changed_firewall_features(
+        &self,
+        devcontainer_path: &Path,
+        changed: &[PathBuf],
+    ) -> Result<FirewallRemovalResult, CliError> {
+        self.strip_scoped(devcontainer_path, Some(changed), FeatureProfile::Firewall)
+    }
+
+    fn detect_firewall_scripts(&self, devcontainer_path: &Path) -> Result<Vec<PathBuf>, CliError> {
+        self.detect_scripts(devcontainer_path, FeatureProfile::Firewall)
+    }
+
+    fn strip_devcontainer_json_firewall(&self, json_path: &Path) -> Result<Vec<String>, CliError> {
+        self.strip_json_features(json_path, FeatureProfile::Firewall)
+    }
+
+    fn strip_dockerfile_firewall(&self, dockerfile_path: &Path) -> Result<Vec<String>, CliError> {
+        self.strip_dockerfile_features(dockerfile_path, FeatureProfile::Firewall)
+    }
+
+    fn strip_compose_firewall(&self, compose_path: &Path) -> Result<Vec<String>, CliError> {
+        self.strip_compose_features(compose_path, FeatureProfile::Firewall)
+    }
+
+    fn validate_firewall_removal(&self, removal_result: &FirewallRemovalResult) -> Vec<Warning> {
+        self.validate_removal(removal_result, FeatureProfile::Firewall)
+    }
+
+    fn commit_customizations(
+        &self,
+        removal_result: &FirewallRemovalResult,
+        message: &str,
+    ) -> Result<(), CliError> {
         use crate::git::{GitExecutor, SystemGitExecutor};
 
-        let executor = SystemGitExecutor::new();
+        let executor =
+            SystemGitExecutor::with_git_path(&self.git_path).with_timeout(self.git_timeout);
+
+        let paths: Vec<String> = removal_result
+            .touched_files()
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect();
+
+        if paths.is_empty() {
+            self.log_verbose("No customized files to commit");
+            return Ok(());
+        }
 
-        // Add all modified files to git
-        executor.execute_git_command(&["add", ".devcontainer"], &self.working_dir)?;
+        // Stage only the files the strip touched (this also stages deletions)
+        let mut add_args = vec!["add", "--"];
+        add_args.extend(paths.iter().map(|p| p.as_str()));
+        executor.execute_git_command(&add_args, &self.working_dir)?;
 
         // Create commit with detailed message
-        let full_message = if changes.is_empty() {
-            message.to_string()
-        } else {
-            format!("{}\n\nChanges made:\n{}", message, changes.join("\n- "))
-        };
+        let full_message = customization_commit_message(message, removal_result);
 
-        executor.execute_git_command(&["commit", "-m", &full_message], &self.working_dir)?;
+        // Limit the commit to the same paths so anything else already staged is left alone
+        let mut commit_args = vec!["commit", "-m", &full_message, "--"];
+        commit_args.extend(paths.iter().map(|p| p.as_str()));
+        executor.execute_git_command(&commit_args, &self.working_dir)?;
 
         self.log_verbose("Committed firewall customizations to git");
+
+        if self.blame_ignore {
+            self.record_blame_ignore(&executor, message)?;
+        }
         Ok(())
     }
 }
@@ -492,51 +2008,667 @@ mod tests {
         fs::write(&script_path, "#!/bin/bash\niptables -F\n").unwrap();
 
         let customizer = DefaultDevcontainerCustomizer::new(temp_dir.path().to_path_buf(), false);
-        let scripts = customizer
-            .detect_firewall_scripts(devcontainer_path)
+        let scripts = customizer
+            .detect_firewall_scripts(devcontainer_path)
+            .unwrap();
+
+        assert_eq!(scripts.len(), 1);
+        assert_eq!(scripts[0], script_path);
+    }
+
+    #[test]
+    fn test_detect_firewall_scripts_by_content() {
+        let temp_dir = TempDir::new().unwrap();
+        let devcontainer_path = temp_dir.path();
+
+        // Create a script with firewall content but different name
+        let script_path = devcontainer_path.join("setup.sh");
+        fs::write(
+            &script_path,
+            "#!/bin/bash\necho 'Setting up iptables'\niptables -A INPUT -j DROP\n",
+        )
+        .unwrap();
+
+        let customizer = DefaultDevcontainerCustomizer::new(temp_dir.path().to_path_buf(), false);
+        let scripts = customizer
+            .detect_firewall_scripts(devcontainer_path)
+            .unwrap();
+
+        assert_eq!(scripts.len(), 1);
+        assert_eq!(scripts[0], script_path);
+    }
+
+    #[test]
+    fn test_detect_firewall_scripts_by_custom_pattern() {
+        let temp_dir = TempDir::new().unwrap();
+        let devcontainer_path = temp_dir.path().join(".devcontainer");
+        fs::create_dir(&devcontainer_path).unwrap();
+        fs::write(
+            temp_dir.path().join(PATTERNS_FILE_NAME),
+            "# nftables rules\nnft add rule\n",
+        )
+        .unwrap();
+        let script_path = devcontainer_path.join("egress.sh");
+        fs::write(
+            &script_path,
+            "#!/bin/bash\nnft add rule inet filter output drop\n",
+        )
+        .unwrap();
+
+        let customizer = DefaultDevcontainerCustomizer::new(temp_dir.path().to_path_buf(), false);
+        let scripts = customizer
+            .detect_firewall_scripts(&devcontainer_path)
+            .unwrap();
+
+        assert_eq!(scripts, vec![script_path]);
+    }
+
+    #[test]
+    fn test_detection_patterns_include_the_synced_patterns_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let devcontainer_path = temp_dir.path().join(".devcontainer");
+        fs::create_dir(&devcontainer_path).unwrap();
+        fs::write(temp_dir.path().join(PATTERNS_FILE_NAME), "nft add rule\n").unwrap();
+        fs::write(devcontainer_path.join(PATTERNS_FILE_NAME), "ufw allow\n").unwrap();
+        let script_path = devcontainer_path.join("egress.sh");
+        fs::write(&script_path, "#!/bin/bash\nufw allow out 443\n").unwrap();
+
+        let customizer = DefaultDevcontainerCustomizer::new(temp_dir.path().to_path_buf(), false)
+            .with_synced_patterns(devcontainer_path.clone());
+        let patterns = customizer
+            .detection_patterns(FeatureProfile::Firewall)
+            .unwrap();
+
+        assert!(patterns.contains(&DetectionPattern {
+            pattern: "nft add rule".to_string(),
+            source: PatternSource::Config,
+        }));
+        assert_eq!(
+            patterns.last(),
+            Some(&DetectionPattern {
+                pattern: "ufw allow".to_string(),
+                source: PatternSource::Synced,
+            })
+        );
+        assert_eq!(
+            customizer
+                .detect_firewall_scripts(&devcontainer_path)
+                .unwrap(),
+            vec![script_path]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_strip_refuses_to_delete_scripts_resolving_outside_devcontainer() {
+        let temp_dir = TempDir::new().unwrap();
+        let devcontainer_path = temp_dir.path().join(".devcontainer");
+        fs::create_dir(&devcontainer_path).unwrap();
+        let outside = temp_dir.path().join("init-firewall.sh");
+        fs::write(&outside, "#!/bin/bash\niptables -F\n").unwrap();
+        std::os::unix::fs::symlink(&outside, devcontainer_path.join("init-firewall.sh")).unwrap();
+
+        let customizer = DefaultDevcontainerCustomizer::new(temp_dir.path().to_path_buf(), false);
+        let result = customizer
+            .strip_firewall_features(&devcontainer_path)
+            .unwrap();
+
+        assert!(outside.exists());
+        assert!(result.files_removed.is_empty());
+        assert!(result
+            .warnings
+            .iter()
+            .any(|warning| warning.code == "outside-devcontainer"));
+    }
+
+    #[test]
+    fn test_strip_removes_firewall_scripts_in_subdirectories() {
+        let temp_dir = TempDir::new().unwrap();
+        let devcontainer_path = temp_dir.path().join(".devcontainer");
+        let scripts_dir = devcontainer_path.join("scripts");
+        fs::create_dir_all(&scripts_dir).unwrap();
+        let by_name = scripts_dir.join("init-firewall.sh");
+        fs::write(&by_name, "#!/bin/bash\necho setting up\n").unwrap();
+        let by_content = scripts_dir.join("network.sh");
+        fs::write(&by_content, "#!/bin/bash\niptables -F\n").unwrap();
+        let unrelated = scripts_dir.join("post-create.sh");
+        fs::write(&unrelated, "#!/bin/bash\nnpm install\n").unwrap();
+
+        let customizer = DefaultDevcontainerCustomizer::new(temp_dir.path().to_path_buf(), false);
+        let result = customizer
+            .strip_firewall_features(&devcontainer_path)
+            .unwrap();
+
+        assert_eq!(
+            result.files_removed,
+            vec![by_name.clone(), by_content.clone()]
+        );
+        assert!(!by_name.exists());
+        assert!(!by_content.exists());
+        assert!(unrelated.exists());
+    }
+
+    #[test]
+    fn test_strip_removes_firewall_capabilities_from_compose_services() {
+        let temp_dir = TempDir::new().unwrap();
+        let devcontainer_path = temp_dir.path().join(".devcontainer");
+        fs::create_dir_all(&devcontainer_path).unwrap();
+        let compose_path = devcontainer_path.join("docker-compose.yml");
+        fs::write(
+            &compose_path,
+            "services:\n  app:\n    image: node:20\n    cap_add:\n      - NET_ADMIN\n      - SYS_PTRACE\n      - CAP_NET_RAW\n  # Seeded from fixtures\n  db:\n    image: postgres:16\n    cap_add: [NET_ADMIN]\n",
+        )
+        .unwrap();
+
+        let customizer = DefaultDevcontainerCustomizer::new(temp_dir.path().to_path_buf(), false);
+        let result = customizer
+            .strip_firewall_features(&devcontainer_path)
+            .unwrap();
+
+        assert_eq!(
+            result.compose_changes,
+            vec![
+                "docker-compose.yml: Removed NET_ADMIN, CAP_NET_RAW from cap_add of service 'app'",
+                "docker-compose.yml: Removed NET_ADMIN from cap_add of service 'db'",
+            ]
+        );
+        assert_eq!(result.files_modified, vec![compose_path.clone()]);
+        assert!(result
+            .warnings
+            .iter()
+            .any(|w| w.code == "dropped-comments" && w.message.contains("docker-compose.yml")));
+        let compose: serde_yaml::Value =
+            serde_yaml::from_str(&fs::read_to_string(&compose_path).unwrap()).unwrap();
+        assert_eq!(
+            compose["services"]["app"]["cap_add"],
+            serde_yaml::from_str::<serde_yaml::Value>("[SYS_PTRACE]").unwrap()
+        );
+        assert_eq!(
+            compose["services"]["app"]["image"].as_str(),
+            Some("node:20")
+        );
+        assert!(compose["services"]["db"].get("cap_add").is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_detect_firewall_scripts_skips_symlinked_directories() {
+        let temp_dir = TempDir::new().unwrap();
+        let devcontainer_path = temp_dir.path();
+        let script_path = devcontainer_path.join("init-firewall.sh");
+        fs::write(&script_path, "#!/bin/bash\niptables -F\n").unwrap();
+        // A link back to the devcontainer itself would otherwise be walked forever
+        std::os::unix::fs::symlink(devcontainer_path, devcontainer_path.join("loop")).unwrap();
+
+        let customizer = DefaultDevcontainerCustomizer::new(temp_dir.path().to_path_buf(), false);
+        let scripts = customizer
+            .detect_firewall_scripts(devcontainer_path)
+            .unwrap();
+
+        assert_eq!(scripts, vec![script_path]);
+    }
+
+    #[test]
+    fn test_detect_firewall_scripts_with_invalid_utf8() {
+        let temp_dir = TempDir::new().unwrap();
+        let devcontainer_path = temp_dir.path();
+
+        let script_path = devcontainer_path.join("setup.sh");
+        let mut content = b"#!/bin/bash\n# \xff\xfe latin-1 comment\n".to_vec();
+        content.extend_from_slice(b"iptables -A INPUT -j DROP\n");
+        fs::write(&script_path, content).unwrap();
+
+        let customizer = DefaultDevcontainerCustomizer::new(temp_dir.path().to_path_buf(), false);
+        let scripts = customizer
+            .detect_firewall_scripts(devcontainer_path)
+            .unwrap();
+
+        assert_eq!(scripts, vec![script_path]);
+    }
+
+    #[test]
+    fn test_no_firewall_scripts() {
+        let temp_dir = TempDir::new().unwrap();
+        let devcontainer_path = temp_dir.path();
+
+        // Create a non-firewall script
+        let script_path = devcontainer_path.join("setup.sh");
+        fs::write(&script_path, "#!/bin/bash\necho 'Hello world'\n").unwrap();
+
+        let customizer = DefaultDevcontainerCustomizer::new(temp_dir.path().to_path_buf(), false);
+        let scripts = customizer
+            .detect_firewall_scripts(devcontainer_path)
+            .unwrap();
+
+        assert_eq!(scripts.len(), 0);
+    }
+
+    #[test]
+    fn test_dockerfile_trailing_newline_preserved() {
+        let temp_dir = TempDir::new().unwrap();
+        let dockerfile_path = temp_dir.path().join("Dockerfile");
+        fs::write(
+            &dockerfile_path,
+            "FROM node:18\nRUN apt-get update && apt-get install -y git iptables\n",
+        )
+        .unwrap();
+
+        let customizer = DefaultDevcontainerCustomizer::new(temp_dir.path().to_path_buf(), false);
+        customizer
+            .strip_dockerfile_firewall(&dockerfile_path)
+            .unwrap();
+
+        let content = fs::read_to_string(&dockerfile_path).unwrap();
+        assert!(!content.contains("iptables"));
+        assert!(content.ends_with("git\n"));
+    }
+
+    #[test]
+    fn test_json_trailing_newline_preserved() {
+        let temp_dir = TempDir::new().unwrap();
+        let json_path = temp_dir.path().join("devcontainer.json");
+        fs::write(&json_path, "{\"runArgs\": [\"--cap-add=NET_ADMIN\"]}\n").unwrap();
+
+        let customizer = DefaultDevcontainerCustomizer::new(temp_dir.path().to_path_buf(), false);
+        customizer
+            .strip_devcontainer_json_firewall(&json_path)
+            .unwrap();
+
+        let content = fs::read_to_string(&json_path).unwrap();
+        assert!(content.ends_with("}\n"));
+        assert!(!content.ends_with("\n\n"));
+    }
+
+    #[test]
+    fn test_strip_changed_firewall_features_leaves_other_files_alone() {
+        let temp_dir = TempDir::new().unwrap();
+        let devcontainer_path = temp_dir.path();
+        let dockerfile = "FROM node:18\nRUN apt-get install -y git iptables\n";
+        fs::write(devcontainer_path.join("Dockerfile"), dockerfile).unwrap();
+        let json = "{\"runArgs\": [\"--cap-add=NET_ADMIN\"]}\n";
+        fs::write(devcontainer_path.join("devcontainer.json"), json).unwrap();
+        fs::write(devcontainer_path.join("init-firewall.sh"), "iptables -F\n").unwrap();
+        let new_script = devcontainer_path.join("egress-firewall.sh");
+        fs::write(&new_script, "iptables -A OUTPUT -j DROP\n").unwrap();
+
+        let result = DefaultDevcontainerCustomizer::new(devcontainer_path.to_path_buf(), false)
+            .strip_changed_firewall_features(devcontainer_path, std::slice::from_ref(&new_script))
+            .unwrap();
+
+        assert_eq!(result.files_removed, vec![new_script.clone()]);
+        assert!(result.files_modified.is_empty());
+        assert!(!new_script.exists());
+        assert!(devcontainer_path.join("init-firewall.sh").exists());
+        assert_eq!(
+            fs::read_to_string(devcontainer_path.join("Dockerfile")).unwrap(),
+            dockerfile
+        );
+        assert_eq!(
+            fs::read_to_string(devcontainer_path.join("devcontainer.json")).unwrap(),
+            json
+        );
+        assert!(result
+            .warnings
+            .iter()
+            .all(|warning| warning.severity < Severity::Warn));
+    }
+
+    #[test]
+    fn test_select_scripts_returns_only_the_chosen_scripts() {
+        let root = Path::new("/repo");
+        let scripts: Vec<PathBuf> = ["init-firewall.sh", "egress.sh", "dns-firewall.sh"]
+            .iter()
+            .map(|name| root.join(".devcontainer").join(name))
+            .collect();
+        let mut prompt = Vec::new();
+
+        // An out-of-range answer asks again
+        let selected = select_scripts(
+            &scripts,
+            root,
+            std::io::Cursor::new("4\n1, 3\n"),
+            &mut prompt,
+        )
+        .unwrap();
+
+        assert_eq!(selected, vec![scripts[0].clone(), scripts[2].clone()]);
+        let prompt = String::from_utf8(prompt).unwrap();
+        assert!(prompt.contains("  2) .devcontainer/egress.sh"));
+        assert!(prompt.contains("Please enter numbers between 1 and 3"));
+    }
+
+    #[test]
+    fn test_select_scripts_answers() {
+        let root = Path::new("/repo");
+        let scripts = vec![root.join("a.sh"), root.join("b.sh")];
+        let select = |input: &'static str| {
+            select_scripts(&scripts, root, std::io::Cursor::new(input), std::io::sink()).unwrap()
+        };
+
+        assert_eq!(select("none\n"), Vec::<PathBuf>::new());
+        assert_eq!(select("ALL\n"), scripts);
+        assert_eq!(select("\n"), scripts);
+        // Closed input falls back to removing everything
+        assert_eq!(select(""), scripts);
+        assert_eq!(select("2\n"), vec![scripts[1].clone()]);
+    }
+
+    #[test]
+    fn test_render_diff() {
+        let original = "FROM node:20\nRUN apt-get install -y \\\n  git \\\n  iptables\n";
+        let modified = "FROM node:20\nRUN apt-get install -y \\\n  git\n";
+
+        let diff = render_diff(".devcontainer/Dockerfile", original, Some(modified), false);
+        assert_eq!(
+            diff,
+            "--- a/.devcontainer/Dockerfile\n+++ b/.devcontainer/Dockerfile\n@@ -1,4 +1,3 @@\n FROM node:20\n RUN apt-get install -y \\\n-  git \\\n-  iptables\n+  git\n"
+        );
+
+        let deleted = render_diff("init-firewall.sh", "iptables -F\n", None, true);
+        assert!(deleted.contains("+++ /dev/null"));
+        assert!(deleted.contains("\x1b[31m-iptables -F\x1b[0m"));
+    }
+
+    #[test]
+    fn test_confirm_changes_answers() {
+        let confirm =
+            |input: &'static str| confirm_changes(std::io::Cursor::new(input), std::io::sink());
+
+        assert!(confirm("y\n").unwrap());
+        assert!(confirm("YES\n").unwrap());
+        assert!(!confirm("\n").unwrap());
+        assert!(!confirm("n\n").unwrap());
+        // Closed input keeps the files as they are
+        assert!(!confirm("").unwrap());
+    }
+
+    #[test]
+    fn test_repo_relative() {
+        let root = Path::new("/home/user/project");
+        assert_eq!(
+            repo_relative(&root.join(".devcontainer/init-firewall.sh"), root),
+            ".devcontainer/init-firewall.sh"
+        );
+        assert_eq!(
+            repo_relative(Path::new("/elsewhere/file"), root),
+            "/elsewhere/file"
+        );
+        assert_eq!(
+            repo_relative(Path::new("./.devcontainer/Dockerfile"), Path::new(".")),
+            ".devcontainer/Dockerfile"
+        );
+    }
+
+    #[test]
+    fn test_strip_jsonc() {
+        let plain = strip_jsonc(
+            "// leading\n{\n  \"a\": \"http://x/*y*/\", /* inline */\n  \"b\": [1, 2,],\n}\n",
+        );
+        assert!(plain.had_comments);
+        assert_eq!(
+            plain.json,
+            "\n{\n  \"a\": \"http://x/*y*/\", \n  \"b\": [1, 2]\n}\n"
+        );
+        assert_eq!(plain.repairs, vec!["trailing commas"]);
+
+        // Commas, slashes and escaped quotes inside strings are left alone
+        let plain = strip_jsonc(r#"{"a": "x,]", "b": "say \"//hi\",}"}"#);
+        assert!(!plain.had_comments);
+        assert_eq!(plain.json, r#"{"a": "x,]", "b": "say \"//hi\",}"}"#);
+        assert!(plain.repairs.is_empty());
+
+        let plain = strip_jsonc("{\"a\": 1 /* multi\nline */,\n}");
+        assert_eq!(plain.json, "{\"a\": 1 \n\n}");
+
+        // An apostrophe in a comment or string doesn't start a single-quoted string
+        let plain = strip_jsonc(r#"{'a': 'it\'s "x" //', "b": "don't"} // it's"#);
+        assert_eq!(plain.json, r#"{"a": "it's \"x\" //", "b": "don't"} "#);
+        assert_eq!(plain.repairs, vec!["single-quoted strings"]);
+    }
+
+    #[test]
+    fn test_strip_devcontainer_json_with_comments_and_trailing_commas() {
+        let temp_dir = TempDir::new().unwrap();
+        let json_path = temp_dir.path().join("devcontainer.json");
+        let json_content = r#"// For format details, see https://aka.ms/devcontainer.json
+{
+  "name": "Test Container",
+  "runArgs": [
+    "--cap-add=NET_ADMIN",
+    "--cap-add=NET_RAW",
+    "--init",
+  ],
+  /* Starts the firewall */
+  "postStartCommand": "sudo /usr/local/bin/init-firewall.sh",
+}
+"#;
+        fs::write(&json_path, json_content).unwrap();
+
+        let customizer = DefaultDevcontainerCustomizer::new(temp_dir.path().to_path_buf(), false);
+        let rewrite = customizer
+            .propose_json_rewrite(&json_path, FeatureProfile::Firewall)
+            .unwrap()
+            .unwrap();
+        assert!(rewrite
+            .warnings
+            .iter()
+            .any(|w| w.code == "dropped-comments" && w.message.contains("devcontainer.json")));
+        assert!(!rewrite.changes.iter().any(|c| c.contains("comments")));
+
+        let changes = customizer
+            .strip_devcontainer_json_firewall(&json_path)
+            .unwrap();
+
+        assert!(changes.iter().any(|c| c.contains("NET_ADMIN")));
+        assert!(changes.iter().any(|c| c.contains("postStartCommand")));
+        let modified: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&json_path).unwrap()).unwrap();
+        assert_eq!(modified["runArgs"], serde_json::json!(["--init"]));
+        assert!(modified.get("postStartCommand").is_none());
+        assert_eq!(modified["name"], "Test Container");
+    }
+
+    #[test]
+    fn test_config_file_name_variants() {
+        for name in [
+            "Dockerfile",
+            "dockerfile.dev",
+            "app.Dockerfile",
+            "Containerfile",
+        ] {
+            assert!(is_dockerfile_name(name), "{} is a Dockerfile", name);
+        }
+        for name in [
+            "devcontainer.json",
+            "devcontainer.dev.json",
+            ".DevContainer.json",
+        ] {
+            assert!(
+                is_devcontainer_json_name(name),
+                "{} is a devcontainer.json",
+                name
+            );
+        }
+        assert!(!is_dockerfile_name("docker-compose.yml"));
+        assert!(!is_devcontainer_json_name("devcontainer-lock.json"));
+        assert!(!is_devcontainer_json_name("package.json"));
+    }
+
+    #[test]
+    fn test_strip_covers_renamed_config_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let devcontainer = temp_dir.path().join(".devcontainer");
+        fs::create_dir(&devcontainer).unwrap();
+        fs::write(
+            devcontainer.join("devcontainer.dev.json"),
+            "{\"name\": \"Dev\", \"runArgs\": [\"--cap-add=NET_ADMIN\"]}\n",
+        )
+        .unwrap();
+        fs::write(
+            devcontainer.join("Dockerfile.dev"),
+            "FROM node:20\nRUN apt-get install -y \\\n  git \\\n  iptables\n",
+        )
+        .unwrap();
+
+        let customizer = DefaultDevcontainerCustomizer::new(temp_dir.path().to_path_buf(), false);
+        let result = customizer.strip_firewall_features(&devcontainer).unwrap();
+
+        let json = fs::read_to_string(devcontainer.join("devcontainer.dev.json")).unwrap();
+        assert!(!json.contains("NET_ADMIN"));
+        let dockerfile = fs::read_to_string(devcontainer.join("Dockerfile.dev")).unwrap();
+        assert!(!dockerfile.contains("iptables"));
+        assert!(result
+            .dockerfile_changes
+            .contains(&"Dockerfile.dev: Removed firewall packages from apt install".to_string()));
+        assert!(result
+            .json_changes
+            .iter()
+            .all(|change| change.starts_with("devcontainer.dev.json: ")));
+        assert!(!result
+            .warnings
+            .iter()
+            .any(|warning| warning.code.starts_with("missing-")));
+    }
+
+    #[test]
+    fn test_strip_json_removes_only_firewall_features() {
+        let temp_dir = TempDir::new().unwrap();
+        let json_path = temp_dir.path().join("devcontainer.json");
+        fs::write(
+            &json_path,
+            r#"{
+  "name": "Test",
+  "features": {
+    "ghcr.io/devcontainers/features/node:1": {"version": "20"},
+    "ghcr.io/example/features/iptables:1": {}
+  }
+}
+"#,
+        )
+        .unwrap();
+
+        let customizer = DefaultDevcontainerCustomizer::new(temp_dir.path().to_path_buf(), false);
+        let changes = customizer
+            .strip_devcontainer_json_firewall(&json_path)
             .unwrap();
 
-        assert_eq!(scripts.len(), 1);
-        assert_eq!(scripts[0], script_path);
+        assert_eq!(
+            changes,
+            vec!["Removed firewall feature ghcr.io/example/features/iptables:1"]
+        );
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&json_path).unwrap()).unwrap();
+        let features = json["features"].as_object().unwrap();
+        assert_eq!(
+            features.keys().collect::<Vec<_>>(),
+            vec!["ghcr.io/devcontainers/features/node:1"]
+        );
+        assert_eq!(
+            features["ghcr.io/devcontainers/features/node:1"]["version"],
+            "20"
+        );
     }
 
     #[test]
-    fn test_detect_firewall_scripts_by_content() {
+    fn test_strip_normalizes_minor_json_issues_with_a_warning() {
         let temp_dir = TempDir::new().unwrap();
-        let devcontainer_path = temp_dir.path();
-
-        // Create a script with firewall content but different name
-        let script_path = devcontainer_path.join("setup.sh");
+        let devcontainer = temp_dir.path().join(".devcontainer");
+        fs::create_dir(&devcontainer).unwrap();
         fs::write(
-            &script_path,
-            "#!/bin/bash\necho 'Setting up iptables'\niptables -A INPUT -j DROP\n",
+            devcontainer.join("devcontainer.json"),
+            "{\n  'name': 'Test',\n  \"runArgs\": [\"--cap-add=NET_ADMIN\", \"--init\",],\n}\n",
         )
         .unwrap();
 
         let customizer = DefaultDevcontainerCustomizer::new(temp_dir.path().to_path_buf(), false);
-        let scripts = customizer
-            .detect_firewall_scripts(devcontainer_path)
+        let result = customizer
+            .strip_features(&devcontainer, FeatureProfile::Firewall)
             .unwrap();
 
-        assert_eq!(scripts.len(), 1);
-        assert_eq!(scripts[0], script_path);
+        let warning = result
+            .warnings
+            .iter()
+            .find(|warning| warning.code == "normalized-json")
+            .expect("normalized-json warning");
+        assert_eq!(warning.severity, Severity::Warn);
+        assert!(warning
+            .message
+            .contains("Normalized minor JSON issues in .devcontainer/devcontainer.json"));
+        assert!(warning.message.contains("trailing commas"));
+        assert!(warning.message.contains("single-quoted strings"));
+        let modified: serde_json::Value = serde_json::from_str(
+            &fs::read_to_string(devcontainer.join("devcontainer.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(modified["name"], "Test");
+        assert_eq!(modified["runArgs"], serde_json::json!(["--init"]));
     }
 
     #[test]
-    fn test_no_firewall_scripts() {
+    fn test_preserved_settings() {
+        let settings = preserved_settings(
+            r#"{
+  // Kept after stripping
+  "name": "Test",
+  "build": { "dockerfile": "Dockerfile" },
+  "runArgs": ["--privileged"],
+  "customizations": { "vscode": { "extensions": ["a", "b"] } },
+  "postCreateCommand": "npm install",
+}"#,
+        );
+
+        assert_eq!(
+            settings,
+            vec![
+                "name: Test",
+                "Dockerfile: Dockerfile",
+                "runArgs: --privileged",
+                "2 VS Code extension(s)",
+                "postCreateCommand",
+            ]
+        );
+        assert!(preserved_settings("not json").is_empty());
+    }
+
+    #[test]
+    fn test_match_trailing_newline() {
+        assert_eq!(match_trailing_newline("a\n", "b".to_string()), "b\n");
+        assert_eq!(match_trailing_newline("a", "b\n".to_string()), "b");
+        assert_eq!(match_trailing_newline("a\n", "b\n".to_string()), "b\n");
+    }
+
+    #[test]
+    fn test_warning_severities() {
         let temp_dir = TempDir::new().unwrap();
         let devcontainer_path = temp_dir.path();
-
-        // Create a non-firewall script
-        let script_path = devcontainer_path.join("setup.sh");
-        fs::write(&script_path, "#!/bin/bash\necho 'Hello world'\n").unwrap();
+        fs::write(
+            devcontainer_path.join("Dockerfile"),
+            "FROM node:18\nRUN echo \"node ALL=(root) NOPASSWD: /usr/local/bin/init-firewall.sh\" > /etc/sudoers.d/node-firewall\n",
+        )
+        .unwrap();
+        fs::write(devcontainer_path.join("devcontainer.json"), "{}").unwrap();
 
         let customizer = DefaultDevcontainerCustomizer::new(temp_dir.path().to_path_buf(), false);
-        let scripts = customizer
-            .detect_firewall_scripts(devcontainer_path)
+        let result = customizer
+            .strip_firewall_features(devcontainer_path)
             .unwrap();
 
-        assert_eq!(scripts.len(), 0);
+        let nothing_found = result
+            .warnings
+            .iter()
+            .find(|w| w.message == "No firewall configurations found in Dockerfile")
+            .expect("Expected a nothing-found warning");
+        assert_eq!(nothing_found.severity, Severity::Info);
+
+        let sudoers = result
+            .warnings
+            .iter()
+            .find(|w| w.code == "leftover-sudoers")
+            .expect("Expected a leftover sudoers warning");
+        assert_eq!(sudoers.severity, Severity::Warn);
+
+        let serious = result.warnings_at_least(Severity::Warn);
+        assert_eq!(serious, vec![sudoers]);
     }
 
     #[test]
@@ -545,7 +2677,7 @@ mod tests {
 
         result.add_modified_file(PathBuf::from("test.json"));
         result.add_removed_file(PathBuf::from("firewall.sh"));
-        result.add_warning("Test warning".to_string());
+        result.add_warning(Warning::warn("test", "Test warning"));
 
         assert!(result.has_changes());
         assert!(result.has_warnings());
@@ -614,6 +2746,55 @@ mod tests {
         assert!(modified_json.get("customizations").is_some());
     }
 
+    #[test]
+    fn test_strip_devcontainer_json_preserves_schema_and_key_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let json_path = temp_dir.path().join("devcontainer.json");
+
+        let json_content = r#"{
+  "$schema": "https://raw.githubusercontent.com/devcontainers/spec/main/schemas/devContainer.schema.json",
+  "name": "Test Container",
+  "runArgs": [
+    "--cap-add=NET_ADMIN",
+    "--cap-add=NET_RAW"
+  ],
+  "postStartCommand": "sudo /usr/local/bin/init-firewall.sh",
+  "waitFor": "postStartCommand",
+  "remoteUser": "node",
+  "containerEnv": {
+    "ZSH": "/bin/zsh",
+    "CLAUDE_CONFIG_DIR": "/home/node/.claude"
+  }
+}
+"#;
+        fs::write(&json_path, json_content).unwrap();
+
+        let customizer = DefaultDevcontainerCustomizer::new(temp_dir.path().to_path_buf(), false);
+        let changes = customizer
+            .strip_devcontainer_json_firewall(&json_path)
+            .unwrap();
+        assert!(!changes.is_empty());
+
+        let modified_content = fs::read_to_string(&json_path).unwrap();
+        let modified_json: serde_json::Value = serde_json::from_str(&modified_content).unwrap();
+        let keys: Vec<&String> = modified_json.as_object().unwrap().keys().collect();
+        assert_eq!(
+            keys,
+            ["$schema", "name", "runArgs", "remoteUser", "containerEnv"]
+        );
+        assert_eq!(
+            modified_json["$schema"],
+            "https://raw.githubusercontent.com/devcontainers/spec/main/schemas/devContainer.schema.json"
+        );
+
+        let env_keys: Vec<&String> = modified_json["containerEnv"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .collect();
+        assert_eq!(env_keys, ["ZSH", "CLAUDE_CONFIG_DIR"]);
+    }
+
     #[test]
     fn test_strip_devcontainer_json_no_firewall() {
         let temp_dir = TempDir::new().unwrap();
@@ -739,6 +2920,182 @@ ENV NPM_CONFIG_PREFIX=/usr/local/share/npm-global
         assert!(modified_content.contains("NPM_CONFIG_PREFIX"));
     }
 
+    #[test]
+    fn test_strip_records_line_numbers_of_removed_dockerfile_lines() {
+        let temp_dir = TempDir::new().unwrap();
+        let dockerfile_path = temp_dir.path().join("Dockerfile");
+        fs::write(
+            &dockerfile_path,
+            r#"FROM node:20
+
+RUN apt-get update && apt-get install -y --no-install-recommends \
+  git \
+  iptables \
+  ipset \
+  jq \
+  && apt-get clean
+
+# Copy and set up firewall script
+COPY init-firewall.sh /usr/local/bin/
+USER root
+RUN chmod +x /usr/local/bin/init-firewall.sh && \
+  echo "node ALL=(root) NOPASSWD: /usr/local/bin/init-firewall.sh" > /etc/sudoers.d/node-firewall
+USER node
+"#,
+        )
+        .unwrap();
+
+        let customizer = DefaultDevcontainerCustomizer::new(temp_dir.path().to_path_buf(), false);
+        let result = customizer.strip_firewall_features(temp_dir.path()).unwrap();
+
+        let removed: Vec<(usize, &str)> = result
+            .changes
+            .iter()
+            .filter(|change| change.path == dockerfile_path)
+            .map(|change| (change.line, change.removed.as_str()))
+            .collect();
+        assert_eq!(
+            removed,
+            vec![
+                (5, "  iptables \\"),
+                (6, "  ipset \\"),
+                (9, ""),
+                (10, "# Copy and set up firewall script"),
+                (11, "COPY init-firewall.sh /usr/local/bin/"),
+                (12, "USER root"),
+                (13, "RUN chmod +x /usr/local/bin/init-firewall.sh && \\"),
+                (14, "  echo \"node ALL=(root) NOPASSWD: /usr/local/bin/init-firewall.sh\" > /etc/sudoers.d/node-firewall"),
+                (15, "USER node"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_strip_dockerfile_keeps_requested_packages() {
+        let temp_dir = TempDir::new().unwrap();
+        let dockerfile_path = temp_dir.path().join("Dockerfile");
+
+        let dockerfile_content = r#"FROM node:20
+RUN apt-get update && apt-get install -y --no-install-recommends \
+  git \
+  iptables \
+  ipset \
+  iproute2 \
+  dnsutils \
+  aggregate \
+  && apt-get clean
+"#;
+        fs::write(&dockerfile_path, dockerfile_content).unwrap();
+
+        let customizer = DefaultDevcontainerCustomizer::new(temp_dir.path().to_path_buf(), false)
+            .with_kept_packages(&["iproute2".to_string()]);
+        let changes = customizer
+            .strip_dockerfile_firewall(&dockerfile_path)
+            .unwrap();
+        assert!(changes.iter().any(|c| c.contains("firewall packages")));
+
+        let modified_content = fs::read_to_string(&dockerfile_path).unwrap();
+        assert!(modified_content.contains("iproute2"));
+        for package in ["iptables", "ipset", "dnsutils", "aggregate"] {
+            assert!(
+                !modified_content.contains(package),
+                "{} should be removed",
+                package
+            );
+        }
+    }
+
+    #[test]
+    fn test_strip_dockerfile_scans_every_apt_install_block() {
+        let temp_dir = TempDir::new().unwrap();
+        let dockerfile_path = temp_dir.path().join("Dockerfile");
+
+        let first_block = "RUN apt-get update && apt-get install -y \\\n  git \\\n  # editors\n  vim \\\n  && apt-get clean";
+        let dockerfile_content = format!(
+            "FROM node:20\n{}\n\nRUN apt-get install -y \\\n  curl \\\n  iptables\nUSER node\n",
+            first_block
+        );
+        fs::write(&dockerfile_path, &dockerfile_content).unwrap();
+
+        let customizer = DefaultDevcontainerCustomizer::new(temp_dir.path().to_path_buf(), false);
+        let changes = customizer
+            .strip_dockerfile_firewall(&dockerfile_path)
+            .unwrap();
+
+        assert!(changes.iter().any(|c| c.contains("firewall packages")));
+        let modified_content = fs::read_to_string(&dockerfile_path).unwrap();
+        assert!(modified_content.contains(first_block));
+        assert!(!modified_content.contains("iptables"));
+        assert!(modified_content.contains("RUN apt-get install -y \\\n  curl\nUSER node\n"));
+    }
+
+    #[test]
+    fn test_strip_dockerfile_strips_each_build_stage() {
+        let temp_dir = TempDir::new().unwrap();
+        let dockerfile_path = temp_dir.path().join("Dockerfile");
+
+        // The build stage's apt block ends on a dangling continuation, which must not
+        // swallow the runtime stage
+        let dockerfile_content = "FROM node:20 AS build\nRUN apt-get install -y \\\n  git \\\n  iptables \\\n\nFROM node:20-slim\nRUN apt-get install -y \\\n  iptables \\\n  jq\nUSER node\n";
+        fs::write(&dockerfile_path, dockerfile_content).unwrap();
+
+        let customizer = DefaultDevcontainerCustomizer::new(temp_dir.path().to_path_buf(), false);
+        let changes = customizer
+            .strip_dockerfile_firewall(&dockerfile_path)
+            .unwrap();
+
+        assert_eq!(
+            changes,
+            vec![
+                "Removed firewall packages from apt install in stage 'build'",
+                "Removed firewall packages from apt install in stage 2",
+            ]
+        );
+        let modified_content = fs::read_to_string(&dockerfile_path).unwrap();
+        assert!(!modified_content.contains("iptables"));
+        assert!(modified_content.contains("FROM node:20-slim\nRUN apt-get install -y \\\n  jq\n"));
+    }
+
+    #[test]
+    fn test_strip_dockerfile_removes_extra_packages() {
+        let temp_dir = TempDir::new().unwrap();
+        let dockerfile_path = temp_dir.path().join("Dockerfile");
+
+        let dockerfile_content = r#"FROM node:20
+RUN apt-get update && apt-get install -y --no-install-recommends \
+  git \
+  iptables \
+  nftables \
+  conntrack \
+  && apt-get clean
+"#;
+        fs::write(&dockerfile_path, dockerfile_content).unwrap();
+
+        let customizer = DefaultDevcontainerCustomizer::new(temp_dir.path().to_path_buf(), false)
+            .with_extra_packages(&["nftables".to_string()]);
+        customizer
+            .strip_dockerfile_firewall(&dockerfile_path)
+            .unwrap();
+
+        let modified_content = fs::read_to_string(&dockerfile_path).unwrap();
+        assert!(!modified_content.contains("iptables"));
+        assert!(!modified_content.contains("nftables"));
+        assert!(modified_content.contains("conntrack"));
+        assert!(modified_content.contains("git"));
+    }
+
+    #[test]
+    fn test_kept_packages_override_extra_packages() {
+        let customizer = DefaultDevcontainerCustomizer::new(PathBuf::from("/tmp"), false)
+            .with_extra_packages(&["nftables".to_string()])
+            .with_kept_packages(&["nftables".to_string(), "dnsutils".to_string()]);
+
+        let packages = customizer.firewall_packages();
+        assert!(packages.contains(&"iptables".to_string()));
+        assert!(!packages.contains(&"nftables".to_string()));
+        assert!(!packages.contains(&"dnsutils".to_string()));
+    }
+
     #[test]
     fn test_strip_dockerfile_no_firewall() {
         let temp_dir = TempDir::new().unwrap();
@@ -799,13 +3156,15 @@ WORKDIR /app
         // Test with no changes
         let empty_result = FirewallRemovalResult::new();
         let warnings = customizer.validate_firewall_removal(&empty_result);
-        assert!(warnings.iter().any(|w| w.contains("No firewall scripts")));
-        assert!(warnings
-            .iter()
-            .any(|w| w.contains("No firewall configurations found in Dockerfile")));
         assert!(warnings
             .iter()
-            .any(|w| w.contains("No firewall configurations found in devcontainer.json")));
+            .any(|w| w.message.contains("No firewall scripts")));
+        assert!(warnings.iter().any(|w| w
+            .message
+            .contains("No firewall configurations found in Dockerfile")));
+        assert!(warnings.iter().any(|w| w
+            .message
+            .contains("No firewall configurations found in devcontainer.json")));
 
         // Test with changes
         let mut result_with_changes = FirewallRemovalResult::new();
@@ -923,7 +3282,7 @@ USER node
         assert!(result.has_changes());
 
         // Test adding warnings
-        result.add_warning("Test warning".to_string());
+        result.add_warning(Warning::warn("test", "Test warning"));
         assert!(result.has_warnings());
 
         result.add_pattern_not_found("missing pattern".to_string());
@@ -940,6 +3299,186 @@ USER node
         assert_eq!(result.warnings.len(), 1);
         assert_eq!(result.patterns_not_found.len(), 1);
     }
+
+    fn git(args: &[&str], dir: &Path) -> String {
+        let output = std::process::Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .expect("Failed to run git");
+        String::from_utf8_lossy(&output.stdout).to_string()
+    }
+
+    fn create_test_git_repo_with_devcontainer() -> (TempDir, PathBuf) {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().to_path_buf();
+
+        git(&["init"], &path);
+        git(&["config", "user.name", "Test User"], &path);
+        git(&["config", "user.email", "test@example.com"], &path);
+
+        let devcontainer_path = path.join(".devcontainer");
+        fs::create_dir_all(&devcontainer_path).unwrap();
+        fs::write(
+            devcontainer_path.join("devcontainer.json"),
+            r#"{"name": "Test", "runArgs": ["--cap-add=NET_ADMIN", "--privileged"]}"#,
+        )
+        .unwrap();
+        fs::write(
+            devcontainer_path.join("init-firewall.sh"),
+            "#!/bin/bash\niptables -F\n",
+        )
+        .unwrap();
+        fs::write(devcontainer_path.join("notes.txt"), "original notes\n").unwrap();
+
+        git(&["add", "."], &path);
+        git(&["commit", "-m", "Initial commit"], &path);
+
+        (temp_dir, path)
+    }
+
+    #[test]
+    fn test_commit_customizations_excludes_unrelated_changes() {
+        let (_temp_dir, repo_path) = create_test_git_repo_with_devcontainer();
+        let devcontainer_path = repo_path.join(".devcontainer");
+
+        // An unrelated local edit the user hasn't committed yet
+        fs::write(devcontainer_path.join("notes.txt"), "work in progress\n").unwrap();
+
+        let customizer = DefaultDevcontainerCustomizer::new(repo_path.clone(), false);
+        let result = customizer
+            .strip_firewall_features(&devcontainer_path)
+            .unwrap();
+        customizer
+            .commit_customizations(&result, "Strip firewall configurations")
+            .unwrap();
+
+        let committed = git(&["show", "--name-only", "--format=", "HEAD"], &repo_path);
+        assert!(committed.contains(".devcontainer/devcontainer.json"));
+        assert!(committed.contains(".devcontainer/init-firewall.sh"));
+        assert!(!committed.contains("notes.txt"));
+
+        // The unrelated edit is still an uncommitted, unstaged modification
+        let status = git(&["status", "--porcelain"], &repo_path);
+        assert!(status.contains(" M .devcontainer/notes.txt"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_commit_customizations_honours_the_timeout() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (_temp_dir, repo_path) = create_test_git_repo_with_devcontainer();
+        let devcontainer_path = repo_path.join(".devcontainer");
+
+        // A hook that stalls the commit well past the timeout
+        let hook_path = repo_path.join(".git/hooks/pre-commit");
+        fs::write(&hook_path, "#!/bin/sh\nsleep 5\n").unwrap();
+        fs::set_permissions(&hook_path, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let customizer = DefaultDevcontainerCustomizer::new(repo_path.clone(), false)
+            .with_timeout(std::time::Duration::from_millis(200));
+        let result = customizer
+            .strip_firewall_features(&devcontainer_path)
+            .unwrap();
+
+        let started = std::time::Instant::now();
+        let error = customizer
+            .commit_customizations(&result, "Strip firewall configurations")
+            .unwrap_err();
+
+        assert!(started.elapsed() < std::time::Duration::from_secs(3));
+        assert!(error.to_string().contains("timed out after 0.2s"));
+    }
+
+    #[test]
+    fn test_commit_customizations_appends_to_blame_ignore_revs() {
+        let (_temp_dir, repo_path) = create_test_git_repo_with_devcontainer();
+        let devcontainer_path = repo_path.join(".devcontainer");
+        let revs_path = repo_path.join(".git-blame-ignore-revs");
+        fs::write(
+            &revs_path,
+            "# Reformat\n0123456789abcdef0123456789abcdef01234567",
+        )
+        .unwrap();
+        git(&["add", "."], &repo_path);
+        git(&["commit", "-m", "Add blame ignore revs"], &repo_path);
+
+        let customizer =
+            DefaultDevcontainerCustomizer::new(repo_path.clone(), false).with_blame_ignore(true);
+        let result = customizer
+            .strip_firewall_features(&devcontainer_path)
+            .unwrap();
+        customizer
+            .commit_customizations(&result, "Strip firewall configurations")
+            .unwrap();
+
+        let strip_sha = git(&["rev-parse", "HEAD~1"], &repo_path);
+        let revs = fs::read_to_string(&revs_path).unwrap();
+        assert_eq!(
+            revs,
+            format!(
+                "# Reformat\n0123456789abcdef0123456789abcdef01234567\n# Strip firewall configurations\n{}\n",
+                strip_sha.trim()
+            )
+        );
+
+        let committed = git(&["show", "--name-only", "--format=", "HEAD"], &repo_path);
+        assert_eq!(committed.trim(), ".git-blame-ignore-revs");
+    }
+
+    #[test]
+    fn test_strip_features_gpu_profile_leaves_firewall_intact() {
+        let temp_dir = TempDir::new().unwrap();
+        let devcontainer_path = temp_dir.path();
+
+        fs::write(
+            devcontainer_path.join("devcontainer.json"),
+            r#"{
+  "name": "Test",
+  "runArgs": ["--gpus", "all", "--cap-add=NET_ADMIN", "--cap-add=NET_RAW"],
+  "hostRequirements": { "gpu": true },
+  "postStartCommand": "sudo /usr/local/bin/init-firewall.sh"
+}"#,
+        )
+        .unwrap();
+        fs::write(
+            devcontainer_path.join("Dockerfile"),
+            "FROM node:20\nRUN apt-get update && apt-get install -y git nvidia-container-toolkit iptables\n",
+        )
+        .unwrap();
+        fs::write(
+            devcontainer_path.join("install-cuda.sh"),
+            "#!/bin/bash\nnvidia-smi\n",
+        )
+        .unwrap();
+        fs::write(
+            devcontainer_path.join("init-firewall.sh"),
+            "#!/bin/bash\niptables -F\n",
+        )
+        .unwrap();
+
+        let customizer = DefaultDevcontainerCustomizer::new(temp_dir.path().to_path_buf(), false);
+        let result = customizer
+            .strip_features(devcontainer_path, FeatureProfile::Gpu)
+            .unwrap();
+
+        assert_eq!(result.files_removed.len(), 1);
+        assert!(!devcontainer_path.join("install-cuda.sh").exists());
+        assert!(devcontainer_path.join("init-firewall.sh").exists());
+
+        let json = fs::read_to_string(devcontainer_path.join("devcontainer.json")).unwrap();
+        assert!(!json.contains("--gpus"));
+        assert!(!json.contains("\"all\""));
+        assert!(!json.contains("hostRequirements"));
+        assert!(json.contains("--cap-add=NET_ADMIN"));
+        assert!(json.contains("init-firewall.sh"));
+
+        let dockerfile = fs::read_to_string(devcontainer_path.join("Dockerfile")).unwrap();
+        assert!(!dockerfile.contains("nvidia-container-toolkit"));
+        assert!(dockerfile.contains("iptables"));
+        assert!(dockerfile.contains("git"));
+    }
 }
 
 #[cfg(test)]
@@ -1137,7 +3676,7 @@ WORKDIR /app
             let result = customizer.strip_firewall_features(&devcontainer_path).unwrap();
 
             // Property: Tool should continue processing and report what wasn't found rather than failing
-            prop_assert!(result.warnings.iter().any(|w| w.contains("No firewall")));
+            prop_assert!(result.warnings.iter().any(|w| w.message.contains("No firewall")));
             prop_assert_eq!(result.files_removed.len(), 0);
             prop_assert_eq!(result.json_changes.len(), 0);
             prop_assert_eq!(result.dockerfile_changes.len(), 0);
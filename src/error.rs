@@ -1,3 +1,5 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -6,15 +8,38 @@ pub enum CliError {
     Repository { message: String, suggestion: String },
 
     #[error("Network error: {message}")]
-    Network { message: String, suggestion: String },
+    Network {
+        message: String,
+        suggestion: String,
+        /// The remote could not be reached or stopped responding, so trying again may
+        /// succeed; refused credentials would only fail again
+        retryable: bool,
+    },
 
     #[error("Git operation error: {message}")]
     GitOperation { message: String, suggestion: String },
 
     #[error("File system error: {message}")]
     FileSystem { message: String, suggestion: String },
+
+    #[error("Interrupted: {message}")]
+    Interrupted { message: String, suggestion: String },
+
+    #[error("Merge conflict: {message}")]
+    Conflict {
+        message: String,
+        suggestion: String,
+        conflicting_paths: Vec<PathBuf>,
+    },
 }
 
+/// Exit code after Ctrl-C, following the shell convention of 128 + SIGINT
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// Exit code of an `update --check` that found upstream commits not synced yet. The
+/// check itself succeeded, so this is not an error.
+pub const UPDATES_AVAILABLE_EXIT_CODE: i32 = 10;
+
 impl CliError {
     pub fn exit_code(&self) -> i32 {
         match self {
@@ -22,6 +47,8 @@ impl CliError {
             CliError::Network { .. } => 2,
             CliError::GitOperation { .. } => 3,
             CliError::FileSystem { .. } => 4,
+            CliError::Interrupted { .. } => INTERRUPTED_EXIT_CODE,
+            CliError::Conflict { .. } => 5,
         }
     }
 
@@ -31,9 +58,22 @@ impl CliError {
             CliError::Network { suggestion, .. } => suggestion,
             CliError::GitOperation { suggestion, .. } => suggestion,
             CliError::FileSystem { suggestion, .. } => suggestion,
+            CliError::Interrupted { suggestion, .. } => suggestion,
+            CliError::Conflict { suggestion, .. } => suggestion,
         }
     }
 
+    /// Whether running the same command again may succeed, as after a dropped connection
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            CliError::Network {
+                retryable: true,
+                ..
+            }
+        )
+    }
+
     // Convenience constructors
     pub fn not_git_repository() -> Self {
         CliError::Repository {
@@ -50,4 +90,330 @@ impl CliError {
             suggestion: "Make at least one commit before running this command".to_string(),
         }
     }
+
+    pub fn shallow_repository() -> Self {
+        CliError::Repository {
+            message: "Repository is a shallow clone; git subtree split needs full history"
+                .to_string(),
+            suggestion: "Run 'git fetch --unshallow' to fetch the complete history, then retry"
+                .to_string(),
+        }
+    }
+
+    pub fn git_not_found(git_path: &Path) -> Self {
+        CliError::GitOperation {
+            message: format!("git not found (tried '{}')", git_path.display()),
+            suggestion: "Install git (https://git-scm.com/downloads) and make sure it is on your PATH, or pass --git-path".to_string(),
+        }
+    }
+
+    pub fn unsupported_git_version(version: &str, minimum: (u32, u32)) -> Self {
+        CliError::GitOperation {
+            message: format!(
+                "{} is too old; devcontainer-sync needs git {}.{} or newer",
+                version, minimum.0, minimum.1
+            ),
+            suggestion:
+                "Upgrade git (https://git-scm.com/downloads), or pass --git-path to use a newer one"
+                    .to_string(),
+        }
+    }
+
+    /// Init was pointed at a directory where a devcontainer and remote would be a surprise
+    pub fn unsafe_sync_location(path: &Path, description: &str) -> Self {
+        CliError::Repository {
+            message: format!(
+                "Refusing to sync into {} ({})",
+                description,
+                path.display()
+            ),
+            suggestion: "Run devcontainer-sync from inside a project repository, or pass --force if this really is where you want the devcontainer".to_string(),
+        }
+    }
+
+    /// Init would force-reset a branch that shares history with the user's own work
+    pub fn branch_in_use(branch: &str, flag: &str) -> Self {
+        CliError::Repository {
+            message: format!(
+                "Branch '{}' already exists and shares history with the current branch",
+                branch
+            ),
+            suggestion: format!(
+                "Pick another name with {} <NAME>, or pass --force to reset '{}' anyway",
+                flag, branch
+            ),
+        }
+    }
+
+    pub fn remote_exists(remote: &str) -> Self {
+        CliError::Repository {
+            message: format!("Remote '{}' already exists", remote),
+            suggestion:
+                "Run 'devcontainer-sync update' instead, or 'devcontainer-sync remove' first"
+                    .to_string(),
+        }
+    }
+
+    pub fn remote_name_taken(remote: &str, url: &str) -> Self {
+        CliError::Repository {
+            message: format!(
+                "Remote '{}' already exists and points at {}, not the upstream repository",
+                remote, url
+            ),
+            suggestion: "Pick another name for the upstream remote with --remote-name <NAME>"
+                .to_string(),
+        }
+    }
+
+    pub fn foreign_tracking_branch(branch: &str, remote: &str, upstream: Option<&str>) -> Self {
+        let tracked = match upstream {
+            Some(upstream) => format!("tracks '{}'", upstream),
+            None => "tracks no remote branch".to_string(),
+        };
+        CliError::Repository {
+            message: format!(
+                "Branch '{}' already exists and {}, not a branch of remote '{}'",
+                branch, tracked, remote
+            ),
+            suggestion: format!(
+                "Pick another name with --tracking-branch <NAME>, or pass --force to reset '{}' anyway",
+                branch
+            ),
+        }
+    }
+
+    /// The upstream branch being synced is gone, usually because upstream renamed it
+    pub fn upstream_branch_missing(remote: &str, branch: &str) -> Self {
+        CliError::Repository {
+            message: format!(
+                "Branch '{}' no longer exists on remote '{}'; upstream may have deleted or renamed it",
+                branch, remote
+            ),
+            suggestion: format!(
+                "Set default_branch in .devcontainer-sync.toml to the new branch (see 'git ls-remote --symref {} HEAD'), or run 'devcontainer-sync remove' and 'devcontainer-sync init' again",
+                remote
+            ),
+        }
+    }
+
+    pub fn uncommitted_devcontainer_changes(prefix: &str, files: &[String]) -> Self {
+        CliError::Repository {
+            message: format!(
+                "Uncommitted changes in {} would conflict with the update: {}",
+                prefix,
+                files.join(", ")
+            ),
+            suggestion:
+                "Commit or stash these changes first, or re-run with --force to update anyway"
+                    .to_string(),
+        }
+    }
+
+    /// `git subtree add` found something already at the prefix
+    pub fn subtree_prefix_exists(prefix: &str) -> Self {
+        CliError::Repository {
+            message: format!(
+                "Cannot add the devcontainer subtree: {} already exists",
+                prefix
+            ),
+            suggestion: format!(
+                "Remove {} and commit the removal, or sync into another directory with --prefix <PATH>",
+                prefix
+            ),
+        }
+    }
+
+    /// `git subtree add` refused to run over uncommitted changes
+    pub fn subtree_add_dirty(prefix: &str) -> Self {
+        CliError::Repository {
+            message: format!(
+                "Cannot add the devcontainer subtree at {}: the working tree or index has uncommitted changes",
+                prefix
+            ),
+            suggestion:
+                "Commit or stash your changes before adding the subtree, or re-run with --autostash"
+                    .to_string(),
+        }
+    }
+
+    pub fn dirty_working_tree(files: &[String]) -> Self {
+        CliError::Repository {
+            message: format!("Uncommitted changes in the working tree: {}", files.join(", ")),
+            suggestion:
+                "Commit or stash these changes first, or re-run with --allow-dirty to continue anyway"
+                    .to_string(),
+        }
+    }
+
+    /// The subtree merge stopped on conflicts and was aborted
+    pub fn merge_conflict(prefix: &str, files: &[String]) -> Self {
+        CliError::Conflict {
+            message: format!(
+                "Upstream changes to {} conflict with local changes: {}",
+                prefix,
+                files.join(", ")
+            ),
+            suggestion: "The merge was aborted; re-run with --force to take the upstream version of the conflicting files".to_string(),
+            conflicting_paths: files.iter().map(PathBuf::from).collect(),
+        }
+    }
+
+    /// A git command killed for running past `timeout`; commands that talk to a remote
+    /// are reported as network errors since a stalled connection is the usual cause
+    pub fn git_timed_out(args: &[&str], timeout: Duration) -> Self {
+        let message = format!(
+            "git {} timed out after {}s",
+            args.join(" "),
+            timeout.as_secs_f64()
+        );
+        if crate::git::executor::talks_to_remote(args) {
+            CliError::Network {
+                message,
+                suggestion:
+                    "Check your network connection; the remote may be unreachable or stalled"
+                        .to_string(),
+                retryable: true,
+            }
+        } else {
+            CliError::GitOperation {
+                message,
+                suggestion: "Check that the repository is not locked by another git process"
+                    .to_string(),
+            }
+        }
+    }
+
+    /// A git command that exited unsuccessfully; authentication failures (typically a
+    /// missing SSH key for a private fork) and dropped connections are network errors
+    /// rather than git misuse
+    pub fn git_command_failed(args: &[&str], stderr: &str) -> Self {
+        const CONNECTION_FAILURES: [&str; 6] = [
+            "Could not resolve host",
+            "Failed to connect to",
+            "Connection timed out",
+            "Connection reset",
+            "The remote end hung up unexpectedly",
+            "early EOF",
+        ];
+        // curl prefixes every HTTP transport failure with this, but when the server did
+        // answer (a 404 for a mistyped URL, say) the connection itself was fine
+        let unreachable_over_http = stderr.contains("unable to access")
+            && !stderr.contains("The requested URL returned error");
+
+        if unreachable_over_http
+            || CONNECTION_FAILURES
+                .iter()
+                .any(|marker| stderr.contains(marker))
+        {
+            return CliError::Network {
+                message: format!(
+                    "Lost the connection to the remote: git {}\nError: {}",
+                    args.join(" "),
+                    stderr
+                ),
+                suggestion:
+                    "Check your network connection; the remote may be unreachable or stalled"
+                        .to_string(),
+                retryable: true,
+            };
+        }
+
+        // git runs with GIT_TERMINAL_PROMPT=0, so missing HTTPS credentials fail rather than prompt
+        const HTTPS_AUTH_FAILURES: [&str; 2] =
+            ["terminal prompts disabled", "Authentication failed for"];
+        if HTTPS_AUTH_FAILURES
+            .iter()
+            .any(|marker| stderr.contains(marker))
+        {
+            return CliError::Network {
+                message: format!(
+                    "Could not authenticate to the remote: git {}\nError: {}",
+                    args.join(" "),
+                    stderr
+                ),
+                suggestion: "The repository needs credentials: pass a token with access to it via --token or DEVCONTAINER_SYNC_TOKEN, or set up a git credential helper".to_string(),
+                retryable: false,
+            };
+        }
+
+        // git follows every failed SSH connection with "Could not read from remote
+        // repository", including a mistyped path, so that alone doesn't mean the key was refused
+        let ssh_key_refused = stderr.contains("Permission denied")
+            && !stderr.contains("does not appear to be a git repository");
+        if ssh_key_refused {
+            return CliError::Network {
+                message: format!(
+                    "Could not authenticate to the remote: git {}\nError: {}",
+                    args.join(" "),
+                    stderr
+                ),
+                suggestion: "Check that your SSH key is loaded ('ssh-add -l') and has access to the repository ('ssh -T git@github.com')".to_string(),
+                retryable: false,
+            };
+        }
+
+        CliError::GitOperation {
+            message: format!(
+                "Git command failed: git {}\nError: {}",
+                args.join(" "),
+                stderr
+            ),
+            suggestion: format!(
+                "Check the git command syntax and repository state. Command: git {}",
+                args.join(" ")
+            ),
+        }
+    }
+
+    /// Some repositories in a `batch` run failed; each failure was reported as it happened
+    pub fn batch_failed(operation: &str, failed: &[String], total: usize) -> Self {
+        CliError::Repository {
+            message: format!(
+                "{} failed in {} of {} repositories: {}",
+                operation,
+                failed.len(),
+                total,
+                failed.join(", ")
+            ),
+            suggestion: format!(
+                "Fix the errors above, then re-run 'devcontainer-sync {}' in each failed repository",
+                operation
+            ),
+        }
+    }
+
+    /// A git command killed, or never started, because the user pressed Ctrl-C
+    pub fn interrupted(args: &[&str]) -> Self {
+        CliError::Interrupted {
+            message: format!("git {} was interrupted", args.join(" ")),
+            suggestion: "Run 'git status' to check the repository state, then re-run the command"
+                .to_string(),
+        }
+    }
+
+    /// `error` ended a command the user had pressed Ctrl-C in, most likely because of it
+    pub fn interrupted_by_user(error: CliError) -> Self {
+        match error {
+            CliError::Interrupted { .. } => error,
+            other => CliError::Interrupted {
+                message: format!("stopped after Ctrl-C: {}", other),
+                suggestion:
+                    "Run 'git status' to check the repository state, then re-run the command"
+                        .to_string(),
+            },
+        }
+    }
+
+    pub fn working_dir_not_writable(path: &Path, error: &std::io::Error) -> Self {
+        CliError::FileSystem {
+            message: format!(
+                "Working directory is not writable: {} ({})",
+                path.display(),
+                error
+            ),
+            suggestion:
+                "Check the directory permissions and that the filesystem is not mounted read-only"
+                    .to_string(),
+        }
+    }
 }
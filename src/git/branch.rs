@@ -3,11 +3,15 @@ use crate::git::GitExecutor;
 
 #[derive(Debug, Clone)]
 pub struct Branch {
+    /// The branch name, or `DETACHED_HEAD` for a detached HEAD
     pub name: String,
     pub is_current: bool,
     pub upstream: Option<String>,
 }
 
+/// Name given to the entry `git branch` lists for a detached HEAD
+pub const DETACHED_HEAD: &str = "HEAD (detached)";
+
 pub trait BranchManager {
     fn create_branch(&self, name: &str, source: &str) -> Result<(), CliError>;
     fn delete_branch(&self, name: &str) -> Result<(), CliError>;
@@ -66,42 +70,58 @@ impl<T: GitExecutor> BranchManager for GitBranchManager<T> {
             .executor
             .execute_git_command(&["branch", "-vv"], &self.working_dir)?;
 
-        let mut branches = Vec::new();
+        Ok(parse_branches(&output))
+    }
+}
+
+/// Parse `git branch -vv` output. A detached HEAD, which git lists as
+/// `* (HEAD detached at abc123)` (or `(no branch, rebasing main)` mid-rebase), becomes a
+/// current branch named `DETACHED_HEAD` without an upstream.
+fn parse_branches(output: &str) -> Vec<Branch> {
+    let mut branches = Vec::new();
 
-        for line in output.lines() {
-            if line.trim().is_empty() {
-                continue;
-            }
+    for line in output.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
 
-            let line = line.trim();
-            let is_current = line.starts_with('*');
+        let line = line.trim();
+        let is_current = line.starts_with('*');
+
+        // Remove the * prefix if present
+        let line = if is_current {
+            line.strip_prefix('*').unwrap_or(line).trim()
+        } else {
+            line
+        };
+
+        if line.starts_with('(') {
+            branches.push(Branch {
+                name: DETACHED_HEAD.to_string(),
+                is_current,
+                upstream: None,
+            });
+            continue;
+        }
 
-            // Remove the * prefix if present
-            let line = if is_current {
-                line.strip_prefix('*').unwrap_or(line).trim()
+        // Parse branch name (first word)
+        if let Some(name) = line.split_whitespace().next() {
+            // Extract upstream info if present (between square brackets)
+            let upstream = if let Some(start) = line.find('[') {
+                line.find(']').map(|end| line[start + 1..end].to_string())
             } else {
-                line
+                None
             };
 
-            // Parse branch name (first word)
-            if let Some(name) = line.split_whitespace().next() {
-                // Extract upstream info if present (between square brackets)
-                let upstream = if let Some(start) = line.find('[') {
-                    line.find(']').map(|end| line[start + 1..end].to_string())
-                } else {
-                    None
-                };
-
-                branches.push(Branch {
-                    name: name.to_string(),
-                    is_current,
-                    upstream,
-                });
-            }
+            branches.push(Branch {
+                name: name.to_string(),
+                is_current,
+                upstream,
+            });
         }
-
-        Ok(branches)
     }
+
+    branches
 }
 
 #[cfg(test)]
@@ -168,6 +188,29 @@ mod tests {
         assert!(current_branch.is_some());
     }
 
+    #[test]
+    fn test_parse_branches_names_a_detached_head() {
+        let output = "* (HEAD detached at 1a2b3c4) 1a2b3c4 Add devcontainer
+  claude-main 5d6e7f8 [claude/main] Update firewall [skip ci]
+  master      1a2b3c4 Add devcontainer
+";
+
+        let branches = parse_branches(output);
+
+        let summary: Vec<(&str, bool, Option<&str>)> = branches
+            .iter()
+            .map(|b| (b.name.as_str(), b.is_current, b.upstream.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (DETACHED_HEAD, true, None),
+                ("claude-main", false, Some("claude/main")),
+                ("master", false, None),
+            ]
+        );
+    }
+
     #[test]
     fn test_create_branch() {
         let (_temp_dir, repo_path) = create_test_git_repo();
@@ -1,7 +1,42 @@
+use crate::config::{default_timeout, DEFAULT_GIT_PROGRAM, MIN_GIT_VERSION};
 use crate::error::CliError;
-use std::path::Path;
+use std::io::{ErrorKind, Read};
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Longest pause between checks on a running git command
+const MAX_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Set on Ctrl-C; the running git command is killed the next time it is polled, or,
+/// between commands, the next one fails before it starts
+static INTERRUPT_REQUESTED: AtomicBool = AtomicBool::new(false);
+/// Stays set once Ctrl-C was pressed, after the request itself has been consumed
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Ask the command to stop at its next git command, so it can unwind and clean up after
+/// itself. Returns false when an earlier request is still pending, which a caller can
+/// take as a request to stop straight away.
+pub fn request_interrupt() -> bool {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+    !INTERRUPT_REQUESTED.swap(true, Ordering::SeqCst)
+}
+
+/// Whether Ctrl-C was pressed at any point, so an error that wraps or replaces the
+/// interrupted git command's own can still be reported as an interruption
+pub fn was_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Everything a successful git command printed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GitOutput {
+    pub stdout: String,
+    /// Progress and warnings, which git writes to stderr even when it succeeds
+    pub stderr: String,
+}
 
 pub trait GitExecutor {
     fn execute_git_command(&self, args: &[&str], working_dir: &Path) -> Result<String, CliError>;
@@ -11,58 +46,311 @@ pub trait GitExecutor {
         working_dir: &Path,
         timeout: Duration,
     ) -> Result<String, CliError>;
+
+    /// Run a git command and keep its stderr as well as its stdout. Executors that don't
+    /// capture stderr report it as empty.
+    fn execute_git_command_with_output(
+        &self,
+        args: &[&str],
+        working_dir: &Path,
+    ) -> Result<GitOutput, CliError> {
+        Ok(GitOutput {
+            stdout: self.execute_git_command(args, working_dir)?,
+            stderr: String::new(),
+        })
+    }
+}
+
+/// Lets one executor be shared by reference between the managers of a command
+impl<T: GitExecutor + ?Sized> GitExecutor for &T {
+    fn execute_git_command(&self, args: &[&str], working_dir: &Path) -> Result<String, CliError> {
+        (**self).execute_git_command(args, working_dir)
+    }
+
+    fn execute_git_command_with_timeout(
+        &self,
+        args: &[&str],
+        working_dir: &Path,
+        timeout: Duration,
+    ) -> Result<String, CliError> {
+        (**self).execute_git_command_with_timeout(args, working_dir, timeout)
+    }
+
+    fn execute_git_command_with_output(
+        &self,
+        args: &[&str],
+        working_dir: &Path,
+    ) -> Result<GitOutput, CliError> {
+        (**self).execute_git_command_with_output(args, working_dir)
+    }
 }
 
-pub struct SystemGitExecutor;
+pub struct SystemGitExecutor {
+    git_path: PathBuf,
+    /// Applied to every command not given its own timeout
+    timeout: Duration,
+    /// An HTTPS URL and the token sent with every request to it
+    auth: Option<(String, String)>,
+}
 
 impl SystemGitExecutor {
     pub fn new() -> Self {
-        Self
+        Self::with_git_path(DEFAULT_GIT_PROGRAM)
+    }
+
+    pub fn with_git_path(git_path: impl Into<PathBuf>) -> Self {
+        Self {
+            git_path: git_path.into(),
+            timeout: default_timeout(),
+            auth: None,
+        }
+    }
+
+    /// Send `token` with HTTPS requests to `url`, as an `http.<url>.extraHeader` passed
+    /// through the environment of commands that talk to the remote (git 2.31 or later).
+    /// It is kept off the command line, where other local users could read it, out of the
+    /// remote URL and `.git/config`, and is redacted from any git output that is kept.
+    pub fn with_token(mut self, url: &str, token: &str) -> Self {
+        self.auth = Some((url.to_string(), token.to_string()));
+        self
+    }
+
+    fn redact(&self, text: &str) -> String {
+        match &self.auth {
+            Some((_, token)) if !token.is_empty() => text
+                .replace(token.as_str(), "***")
+                .replace(&basic_credentials(token), "***"),
+            _ => text.to_string(),
+        }
+    }
+
+    /// Kill commands that run longer than `timeout`; zero lets them run indefinitely
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+/// Run `git --version` to confirm the git binary can be executed, returning the version string
+pub fn ensure_git_available(git_path: &Path) -> Result<String, CliError> {
+    let output = Command::new(git_path)
+        .arg("--version")
+        .output()
+        .map_err(|e| spawn_error(git_path, e))?;
+
+    if !output.status.success() {
+        return Err(CliError::GitOperation {
+            message: format!(
+                "'{} --version' failed: {}",
+                git_path.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+            suggestion: "Check that your git installation is working".to_string(),
+        });
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Major, minor and patch numbers from `git --version` output such as
+/// "git version 2.39.5" or "git version 2.39.3 (Apple Git-145)"
+pub fn parse_git_version(version: &str) -> Option<(u32, u32, u32)> {
+    let number = version
+        .strip_prefix("git version ")?
+        .split_whitespace()
+        .next()?;
+    let mut parts = number.split('.').map(|part| part.parse::<u32>().ok());
+    let major = parts.next()??;
+    let minor = parts.next()??;
+    // Release candidates ("2.45.0.rc1") and Windows builds ("2.41.0.windows.1") add more
+    let patch = parts.next().flatten().unwrap_or(0);
+    Some((major, minor, patch))
+}
+
+/// Confirm the git binary runs and is at least `MIN_GIT_VERSION`, returning the version string
+pub fn ensure_supported_git(git_path: &Path) -> Result<String, CliError> {
+    let version = ensure_git_available(git_path)?;
+    match parse_git_version(&version) {
+        Some((major, minor, _)) if (major, minor) < MIN_GIT_VERSION => {
+            Err(CliError::unsupported_git_version(&version, MIN_GIT_VERSION))
+        }
+        // An unrecognised format is more likely a new git than an old one
+        _ => Ok(version),
+    }
+}
+
+/// Whether a git subcommand connects to a remote repository
+pub(crate) fn talks_to_remote(args: &[&str]) -> bool {
+    matches!(
+        args.first().copied(),
+        Some("fetch" | "ls-remote" | "pull" | "push" | "clone")
+    )
+}
+
+/// GitHub takes a token as the password of basic auth with any user name
+fn basic_credentials(token: &str) -> String {
+    base64(format!("x-access-token:{}", token).as_bytes())
+}
+
+/// Standard, padded base64, for the basic auth header
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, &byte)| {
+            group | (byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Drain a child's pipe on a separate thread so a chatty command can't fill the pipe
+/// buffer and block while we wait for it to exit
+fn read_in_background(pipe: Option<impl Read + Send + 'static>) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buffer);
+        }
+        buffer
+    })
+}
+
+fn spawn_error(git_path: &Path, error: std::io::Error) -> CliError {
+    if error.kind() == ErrorKind::NotFound {
+        return CliError::git_not_found(git_path);
+    }
+
+    CliError::GitOperation {
+        message: format!("Failed to execute git command: {}", error),
+        suggestion: "Make sure git is installed and available in PATH".to_string(),
     }
 }
 
 impl GitExecutor for SystemGitExecutor {
     fn execute_git_command(&self, args: &[&str], working_dir: &Path) -> Result<String, CliError> {
-        self.execute_git_command_with_timeout(args, working_dir, Duration::from_secs(30))
+        self.execute_git_command_with_timeout(args, working_dir, self.timeout)
     }
 
     fn execute_git_command_with_timeout(
         &self,
         args: &[&str],
         working_dir: &Path,
-        _timeout: Duration,
+        timeout: Duration,
     ) -> Result<String, CliError> {
-        let mut command = Command::new("git");
+        self.run(args, working_dir, timeout)
+            .map(|output| output.stdout)
+    }
+
+    fn execute_git_command_with_output(
+        &self,
+        args: &[&str],
+        working_dir: &Path,
+    ) -> Result<GitOutput, CliError> {
+        self.run(args, working_dir, self.timeout)
+    }
+}
+
+impl SystemGitExecutor {
+    fn run(
+        &self,
+        args: &[&str],
+        working_dir: &Path,
+        timeout: Duration,
+    ) -> Result<GitOutput, CliError> {
+        log::trace!(
+            "{} {} (in {})",
+            self.git_path.display(),
+            args.join(" "),
+            working_dir.display()
+        );
+        let mut command = Command::new(&self.git_path);
+        if let (Some((url, token)), true) = (&self.auth, talks_to_remote(args)) {
+            // Appended after any GIT_CONFIG_KEY_<n> entries the caller already set
+            let index = std::env::var("GIT_CONFIG_COUNT")
+                .ok()
+                .and_then(|count| count.parse::<usize>().ok())
+                .unwrap_or(0);
+            command
+                .env("GIT_CONFIG_COUNT", (index + 1).to_string())
+                .env(
+                    format!("GIT_CONFIG_KEY_{}", index),
+                    format!("http.{}.extraheader", url),
+                )
+                .env(
+                    format!("GIT_CONFIG_VALUE_{}", index),
+                    format!("AUTHORIZATION: basic {}", basic_credentials(token)),
+                );
+        }
         command
             .args(args)
             .current_dir(working_dir)
+            // git asks for HTTPS credentials on the terminal rather than stdin, which would
+            // block forever; credential helpers still run. GIT_ASKPASS is left alone so a
+            // desktop askpass program the user set up keeps working.
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .stdin(Stdio::null())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped());
 
-        // Execute the command
-        let output = command.output().map_err(|e| CliError::GitOperation {
-            message: format!("Failed to execute git command: {}", e),
-            suggestion: "Make sure git is installed and available in PATH".to_string(),
-        })?;
+        // Consumed here, like the check below, so the cleanup that follows can still run git
+        if INTERRUPT_REQUESTED.swap(false, Ordering::SeqCst) {
+            return Err(CliError::interrupted(args));
+        }
+        let mut child = command
+            .spawn()
+            .map_err(|e| spawn_error(&self.git_path, e))?;
+        let stdout_reader = read_in_background(child.stdout.take());
+        let stderr_reader = read_in_background(child.stderr.take());
+
+        // Poll with a growing interval so quick commands return quickly
+        let started = Instant::now();
+        let mut interval = Duration::from_millis(1);
+        let status = loop {
+            if let Some(status) = child
+                .try_wait()
+                .map_err(|e| spawn_error(&self.git_path, e))?
+            {
+                break status;
+            }
+            // Consumed here, so the cleanup that follows can still run git
+            if INTERRUPT_REQUESTED.swap(false, Ordering::SeqCst) {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(CliError::interrupted(args));
+            }
+            let no_timeout = timeout.is_zero();
+            if !no_timeout && started.elapsed() >= timeout {
+                let _ = child.kill();
+                let _ = child.wait();
+                // Grandchildren (e.g. git-remote-https) may still hold the pipes open,
+                // so the reader threads are left to finish on their own
+                return Err(CliError::git_timed_out(args, timeout));
+            }
+            if !no_timeout {
+                interval = interval.min(timeout.saturating_sub(started.elapsed()));
+            }
+            thread::sleep(interval);
+            interval = (interval * 2).min(MAX_POLL_INTERVAL);
+        };
 
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        let stdout = String::from_utf8_lossy(&stdout_reader.join().unwrap_or_default()).to_string();
+        let stderr = self.redact(&String::from_utf8_lossy(
+            &stderr_reader.join().unwrap_or_default(),
+        ));
 
-        if !output.status.success() {
-            return Err(CliError::GitOperation {
-                message: format!(
-                    "Git command failed: git {}\nError: {}",
-                    args.join(" "),
-                    stderr
-                ),
-                suggestion: format!(
-                    "Check the git command syntax and repository state. Command: git {}",
-                    args.join(" ")
-                ),
-            });
+        if !status.success() {
+            return Err(CliError::git_command_failed(args, &stderr));
         }
 
-        Ok(stdout)
+        Ok(GitOutput { stdout, stderr })
     }
 }
 
@@ -75,6 +363,7 @@ impl Default for SystemGitExecutor {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::INTERRUPTED_EXIT_CODE;
     use std::fs;
     use tempfile::TempDir;
 
@@ -170,4 +459,350 @@ mod tests {
         assert!(result.is_ok());
         assert!(result.unwrap().contains("Initial commit"));
     }
+
+    #[test]
+    fn test_ensure_git_available_reports_version() {
+        let version = ensure_git_available(Path::new(DEFAULT_GIT_PROGRAM)).unwrap();
+        assert!(version.starts_with("git version"));
+    }
+
+    #[test]
+    fn test_missing_git_binary_is_reported_as_not_found() {
+        let missing = Path::new("/nonexistent/bin/git");
+
+        match ensure_git_available(missing) {
+            Err(CliError::GitOperation { message, .. }) => {
+                assert!(message.contains("git not found"));
+            }
+            other => panic!("Expected git not found error, got {:?}", other),
+        }
+
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let executor = SystemGitExecutor::with_git_path(missing);
+        match executor.execute_git_command(&["status"], temp_dir.path()) {
+            Err(CliError::GitOperation { message, .. }) => {
+                assert!(message.contains("git not found"));
+            }
+            other => panic!("Expected git not found error, got {:?}", other),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_slow_command_is_killed_after_timeout() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        // Stand-in for a git command stalled on the network
+        let executor = SystemGitExecutor::with_git_path("sleep");
+
+        let started = Instant::now();
+        let result = executor.execute_git_command_with_timeout(
+            &["5"],
+            temp_dir.path(),
+            Duration::from_millis(200),
+        );
+
+        assert!(started.elapsed() < Duration::from_secs(2));
+        match result {
+            Err(CliError::GitOperation { message, .. }) => {
+                assert_eq!(message, "git 5 timed out after 0.2s");
+            }
+            other => panic!("Expected timeout error, got {:?}", other),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_zero_timeout_waits_for_the_command() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let executor = SystemGitExecutor::with_git_path("sleep").with_timeout(Duration::ZERO);
+
+        let result = executor.execute_git_command(&["0.2"], temp_dir.path());
+
+        assert!(result.is_ok(), "{:?}", result);
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(
+            base64(b"x-access-token:ghp_1"),
+            "eC1hY2Nlc3MtdG9rZW46Z2hwXzE="
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_token_is_sent_through_the_environment_of_remote_commands_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        // Stand-in for git that reports its arguments and the config it was given
+        let fake_git = temp_dir.path().join("git");
+        fs::write(
+            &fake_git,
+            "#!/bin/sh\necho \"args: $*\"\nenv | grep '^GIT_CONFIG_' | sort\n",
+        )
+        .unwrap();
+        fs::set_permissions(&fake_git, fs::Permissions::from_mode(0o755)).unwrap();
+        let url = "https://127.0.0.1:9/private/claude-code.git";
+        let executor = SystemGitExecutor::with_git_path(&fake_git).with_token(url, "ghp_s3cr3t");
+
+        let remote = executor
+            .execute_git_command(&["ls-remote", url], temp_dir.path())
+            .unwrap();
+        let credentials = base64(b"x-access-token:ghp_s3cr3t");
+        assert!(remote.contains(&format!("GIT_CONFIG_KEY_0=http.{}.extraheader", url)));
+        assert!(remote.contains(&format!(
+            "GIT_CONFIG_VALUE_0=AUTHORIZATION: basic {}",
+            credentials
+        )));
+        assert!(remote.contains(&format!("args: ls-remote {}\n", url)));
+
+        let local = executor
+            .execute_git_command(&["status"], temp_dir.path())
+            .unwrap();
+        assert!(!local.contains(&credentials), "{}", local);
+    }
+
+    #[test]
+    fn test_token_is_kept_out_of_errors() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let url = "https://127.0.0.1:9/private/claude-code.git";
+        let token = "ghp_s3cr3t";
+        let executor = SystemGitExecutor::new().with_token(url, token);
+
+        // Nothing listens on the discard port, so this fails straight away
+        let error = executor
+            .execute_git_command(&["ls-remote", url], temp_dir.path())
+            .unwrap_err();
+        let rendered = format!("{} {}", error, error.suggestion());
+        assert!(rendered.contains("git ls-remote https://127.0.0.1:9/private/claude-code.git"));
+        assert!(!rendered.contains(token));
+        assert!(!rendered.contains(&base64(b"x-access-token:ghp_s3cr3t")));
+    }
+
+    #[test]
+    fn test_redact_hides_the_token_and_its_encoded_credentials() {
+        let executor = SystemGitExecutor::new().with_token("https://example.com/", "ghp_s3cr3t");
+        let credentials = base64(b"x-access-token:ghp_s3cr3t");
+
+        assert_eq!(
+            executor.redact(&format!("token ghp_s3cr3t, header basic {}\n", credentials)),
+            "token ***, header basic ***\n"
+        );
+    }
+
+    #[test]
+    fn test_errors_after_ctrl_c_are_reported_as_interruptions() {
+        let wrapped = CliError::interrupted_by_user(CliError::GitOperation {
+            message: "Failed to add remote 'claude'".to_string(),
+            suggestion: String::new(),
+        });
+        assert_eq!(wrapped.exit_code(), INTERRUPTED_EXIT_CODE);
+        assert!(wrapped
+            .to_string()
+            .contains("Failed to add remote 'claude'"));
+
+        let direct = CliError::interrupted_by_user(CliError::interrupted(&["fetch", "claude"]));
+        assert_eq!(
+            direct.to_string(),
+            "Interrupted: git fetch claude was interrupted"
+        );
+    }
+
+    #[test]
+    fn test_remote_command_timeout_is_a_network_error() {
+        let error = CliError::git_timed_out(&["fetch", "claude"], Duration::from_secs(30));
+
+        assert!(
+            matches!(
+                error,
+                CliError::Network {
+                    retryable: true,
+                    ..
+                }
+            ),
+            "{:?}",
+            error
+        );
+        assert_eq!(error.exit_code(), 2);
+        assert!(error
+            .to_string()
+            .contains("git fetch claude timed out after 30s"));
+    }
+
+    #[test]
+    fn test_ssh_authentication_failures_are_network_errors() {
+        for stderr in [
+            "git@github.com: Permission denied (publickey).\nfatal: Could not read from remote repository.\n",
+            "git@github.com: Permission denied (publickey,keyboard-interactive).\r\nfatal: Could not read from remote repository.\n\nPlease make sure you have the correct access rights\n",
+        ] {
+            let error = CliError::git_command_failed(&["fetch", "claude"], stderr);
+
+            assert!(matches!(error, CliError::Network { .. }), "{:?}", error);
+            assert!(!error.is_retryable());
+            assert!(error.suggestion().contains("SSH key"));
+        }
+    }
+
+    #[test]
+    fn test_missing_repository_is_not_an_authentication_error() {
+        for stderr in [
+            "fatal: '/nonexistent/repo.git' does not appear to be a git repository\nfatal: Could not read from remote repository.\n\nPlease make sure you have the correct access rights\nand the repository exists.\n",
+            "ERROR: Repository not found.\nfatal: Could not read from remote repository.\n",
+        ] {
+            let error = CliError::git_command_failed(&["fetch", "claude"], stderr);
+
+            assert!(matches!(error, CliError::GitOperation { .. }), "{:?}", error);
+            assert!(!error.suggestion().contains("SSH key"));
+        }
+    }
+
+    #[test]
+    fn test_connection_failures_are_network_errors() {
+        let error = CliError::git_command_failed(
+            &["fetch", "claude"],
+            "fatal: unable to access 'https://github.com/anthropics/claude-code.git/': Could not resolve host: github.com\n",
+        );
+
+        assert!(
+            matches!(
+                error,
+                CliError::Network {
+                    retryable: true,
+                    ..
+                }
+            ),
+            "{:?}",
+            error
+        );
+        assert_eq!(error.exit_code(), 2);
+    }
+
+    #[test]
+    fn test_connection_failure_variants_are_network_errors() {
+        for stderr in [
+            "fatal: unable to access 'https://github.com/anthropics/claude-code.git/': Failed to connect to github.com port 443 after 2 ms: Couldn't connect to server\n",
+            "fatal: unable to access 'https://github.com/anthropics/claude-code.git/': Connection timed out after 300001 milliseconds\n",
+            "fatal: unable to access 'https://proxy.internal/claude-code.git/': SSL certificate problem: unable to get local issuer certificate\n",
+            "ssh: connect to host github.com port 22: Connection timed out\nfatal: Could not read from remote repository.\n",
+        ] {
+            let error = CliError::git_command_failed(&["fetch", "claude"], stderr);
+
+            assert!(matches!(error, CliError::Network { retryable: true, .. }), "{:?}", error);
+            assert!(error.suggestion().contains("network connection"));
+        }
+    }
+
+    #[test]
+    fn test_missing_https_credentials_are_authentication_errors() {
+        for stderr in [
+            "fatal: could not read Username for 'https://github.com': terminal prompts disabled\n",
+            "remote: Invalid username or token.\nfatal: Authentication failed for 'https://github.com/myorg/claude-code.git/'\n",
+        ] {
+            let error = CliError::git_command_failed(&["fetch", "claude"], stderr);
+
+            assert!(matches!(error, CliError::Network { .. }), "{:?}", error);
+            assert!(!error.is_retryable());
+            assert!(error.to_string().contains("Could not authenticate"));
+            assert!(error.suggestion().contains("--token"));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_git_runs_with_terminal_prompts_disabled() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        // Stand-in for git that reports the environment it was given
+        let executor = SystemGitExecutor::with_git_path("sh");
+
+        let output = executor
+            .execute_git_command(&["-c", "echo \"$GIT_TERMINAL_PROMPT\""], temp_dir.path())
+            .unwrap();
+
+        assert_eq!(output.trim(), "0");
+    }
+
+    #[test]
+    #[ignore = "needs network access to github.com"]
+    fn test_fetch_from_private_looking_url_fails_instead_of_prompting() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let executor = SystemGitExecutor::new();
+
+        let started = Instant::now();
+        let result = executor.execute_git_command(
+            &[
+                "ls-remote",
+                "https://github.com/devcontainer-sync-test/private-claude-code.git",
+            ],
+            temp_dir.path(),
+        );
+
+        assert!(
+            matches!(result, Err(CliError::Network { .. })),
+            "{:?}",
+            result
+        );
+        assert!(started.elapsed() < default_timeout());
+    }
+
+    #[test]
+    fn test_http_error_responses_are_not_connection_failures() {
+        let error = CliError::git_command_failed(
+            &["fetch", "claude"],
+            "remote: Not Found\nfatal: unable to access 'https://example.com/typo.git/': The requested URL returned error: 404\n",
+        );
+
+        assert!(
+            matches!(error, CliError::GitOperation { .. }),
+            "{:?}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_other_failures_are_git_operation_errors() {
+        let error = CliError::git_command_failed(
+            &["checkout", "missing"],
+            "error: pathspec 'missing' did not match any file(s) known to git\n",
+        );
+
+        assert!(matches!(error, CliError::GitOperation { .. }));
+        assert_eq!(error.exit_code(), 3);
+    }
+
+    #[test]
+    fn test_parse_git_version() {
+        assert_eq!(parse_git_version("git version 2.39.5"), Some((2, 39, 5)));
+        assert_eq!(
+            parse_git_version("git version 2.39.3 (Apple Git-145)"),
+            Some((2, 39, 3))
+        );
+        assert_eq!(
+            parse_git_version("git version 2.41.0.windows.1"),
+            Some((2, 41, 0))
+        );
+        assert_eq!(parse_git_version("git version 2.45"), Some((2, 45, 0)));
+        assert_eq!(parse_git_version("hub version 2.14.2"), None);
+        assert_eq!(parse_git_version("git version unknown"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_ensure_supported_git_rejects_old_versions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new().unwrap();
+        let fake_git = dir.path().join("git");
+        std::fs::write(&fake_git, "#!/bin/sh\necho 'git version 1.8.3.1'\n").unwrap();
+        std::fs::set_permissions(&fake_git, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let error = ensure_supported_git(&fake_git).unwrap_err();
+        assert!(error.to_string().contains("git version 1.8.3.1 is too old"));
+        assert!(error.suggestion().contains("Upgrade git"));
+        assert!(ensure_supported_git(Path::new("git")).is_ok());
+    }
 }
@@ -4,8 +4,11 @@ pub mod remote;
 pub mod subtree;
 pub mod validator;
 
-pub use branch::{Branch, BranchManager, GitBranchManager};
-pub use executor::{GitExecutor, SystemGitExecutor};
+pub use branch::{Branch, BranchManager, GitBranchManager, DETACHED_HEAD};
+pub use executor::{
+    ensure_git_available, ensure_supported_git, parse_git_version, request_interrupt,
+    was_interrupted, GitExecutor, GitOutput, SystemGitExecutor,
+};
 pub use remote::{GitRemoteManager, Remote, RemoteManager};
-pub use subtree::{GitSubtreeManager, SubtreeManager};
+pub use subtree::{GitSubtreeManager, Split, SubtreeManager};
 pub use validator::{GitRepositoryValidator, RepositoryValidator};
@@ -1,3 +1,4 @@
+use crate::config::{branch_fetch_refspec, CLAUDE_UPSTREAM_BRANCH};
 use crate::error::CliError;
 use crate::git::GitExecutor;
 
@@ -10,8 +11,26 @@ pub struct Remote {
 pub trait RemoteManager {
     fn add_remote(&self, name: &str, url: &str) -> Result<(), CliError>;
     fn remove_remote(&self, name: &str) -> Result<(), CliError>;
-    fn fetch_remote(&self, name: &str) -> Result<(), CliError>;
+    /// Fetch from `name`; with `branch`, only that branch's remote-tracking ref is updated
+    fn fetch_remote(&self, name: &str, branch: Option<&str>) -> Result<(), CliError>;
+    /// Like `fetch_remote`, but only the tip commit and none of its history
+    fn fetch_remote_shallow(&self, name: &str, branch: Option<&str>) -> Result<(), CliError>;
     fn list_remotes(&self) -> Result<Vec<Remote>, CliError>;
+    /// Confirm `url` is reachable and has `branch`, without adding a remote
+    fn check_remote_branch(&self, url: &str, branch: &str) -> Result<(), CliError>;
+    /// Default branch advertised by `remote` (a remote name or URL)
+    fn default_branch(&self, remote: &str) -> Result<String, CliError>;
+}
+
+/// Branch that `HEAD` points at in `git ls-remote --symref <remote> HEAD` output
+fn parse_symref_head(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let (target, name) = line.strip_prefix("ref: ")?.split_once('\t')?;
+        if name.trim() != "HEAD" {
+            return None;
+        }
+        target.strip_prefix("refs/heads/").map(str::to_string)
+    })
 }
 
 pub struct GitRemoteManager<T: GitExecutor> {
@@ -26,6 +45,32 @@ impl<T: GitExecutor> GitRemoteManager<T> {
             working_dir,
         }
     }
+
+    fn fetch(&self, name: &str, branch: Option<&str>, options: &[&str]) -> Result<(), CliError> {
+        // Check if remote exists first
+        if self
+            .executor
+            .execute_git_command(&["remote", "get-url", name], &self.working_dir)
+            .is_err()
+        {
+            return Err(CliError::GitOperation {
+                message: format!("Remote '{}' does not exist", name),
+                suggestion: "Add the remote first using 'git remote add'".to_string(),
+            });
+        }
+
+        let mut args = vec!["fetch"];
+        args.extend_from_slice(options);
+        args.push(name);
+        let refspec = branch.map(|branch| branch_fetch_refspec(name, branch));
+        if let Some(refspec) = &refspec {
+            args.push(refspec);
+        }
+        self.executor
+            .execute_git_command(&args, &self.working_dir)?;
+
+        Ok(())
+    }
 }
 
 impl<T: GitExecutor> RemoteManager for GitRemoteManager<T> {
@@ -68,23 +113,12 @@ impl<T: GitExecutor> RemoteManager for GitRemoteManager<T> {
         Ok(())
     }
 
-    fn fetch_remote(&self, name: &str) -> Result<(), CliError> {
-        // Check if remote exists first
-        if self
-            .executor
-            .execute_git_command(&["remote", "get-url", name], &self.working_dir)
-            .is_err()
-        {
-            return Err(CliError::GitOperation {
-                message: format!("Remote '{}' does not exist", name),
-                suggestion: "Add the remote first using 'git remote add'".to_string(),
-            });
-        }
-
-        self.executor
-            .execute_git_command(&["fetch", name], &self.working_dir)?;
+    fn fetch_remote(&self, name: &str, branch: Option<&str>) -> Result<(), CliError> {
+        self.fetch(name, branch, &[])
+    }
 
-        Ok(())
+    fn fetch_remote_shallow(&self, name: &str, branch: Option<&str>) -> Result<(), CliError> {
+        self.fetch(name, branch, &["--depth=1"])
     }
 
     fn list_remotes(&self) -> Result<Vec<Remote>, CliError> {
@@ -114,6 +148,63 @@ impl<T: GitExecutor> RemoteManager for GitRemoteManager<T> {
 
         Ok(remotes)
     }
+
+    fn check_remote_branch(&self, url: &str, branch: &str) -> Result<(), CliError> {
+        let output = self
+            .executor
+            .execute_git_command(&["ls-remote", "--heads", url, branch], &self.working_dir)
+            .map_err(|e| CliError::Network {
+                message: format!("Cannot reach '{}': {}", url, e),
+                suggestion: "Check your network connection and that the repository URL is correct"
+                    .to_string(),
+                retryable: e.is_retryable(),
+            })?;
+
+        if output.trim().is_empty() {
+            return Err(CliError::Repository {
+                message: format!("Branch '{}' not found at '{}'", branch, url),
+                suggestion: "Check that the repository URL points at the Claude Code repository or a fork of it".to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn default_branch(&self, remote: &str) -> Result<String, CliError> {
+        let output = self
+            .executor
+            .execute_git_command(
+                &["ls-remote", "--symref", remote, "HEAD"],
+                &self.working_dir,
+            )
+            .map_err(|e| CliError::Network {
+                message: format!("Cannot reach '{}': {}", remote, e),
+                suggestion: "Check your network connection and that the repository URL is correct"
+                    .to_string(),
+                retryable: e.is_retryable(),
+            })?;
+
+        // Servers that don't advertise HEAD get the historical default
+        let branch =
+            parse_symref_head(&output).unwrap_or_else(|| CLAUDE_UPSTREAM_BRANCH.to_string());
+
+        let full_ref = format!("refs/heads/{}", branch);
+        if self
+            .executor
+            .execute_git_command(&["check-ref-format", &full_ref], &self.working_dir)
+            .is_err()
+        {
+            return Err(CliError::Repository {
+                message: format!(
+                    "Upstream default branch '{}' is not a valid branch name",
+                    branch
+                ),
+                suggestion: "Check the upstream repository's default branch setting".to_string(),
+            });
+        }
+
+        Ok(branch)
+    }
 }
 
 #[cfg(test)]
@@ -212,7 +303,7 @@ mod tests {
         let executor = SystemGitExecutor::new();
         let manager = GitRemoteManager::new(executor, repo_path);
 
-        let result = manager.fetch_remote("nonexistent");
+        let result = manager.fetch_remote("nonexistent", None);
         assert!(result.is_err());
 
         if let Err(CliError::GitOperation { message, .. }) = result {
@@ -221,4 +312,62 @@ mod tests {
             panic!("Expected GitOperation error");
         }
     }
+
+    #[test]
+    fn test_check_remote_branch() {
+        let (_upstream_dir, upstream_path) = create_test_git_repo();
+        let (_temp_dir, repo_path) = create_test_git_repo();
+        let executor = SystemGitExecutor::new();
+        let manager = GitRemoteManager::new(executor, repo_path);
+        let url = upstream_path.to_string_lossy().to_string();
+        let branch = String::from_utf8_lossy(
+            &Command::new("git")
+                .args(["branch", "--show-current"])
+                .current_dir(&upstream_path)
+                .output()
+                .expect("Failed to read current branch")
+                .stdout,
+        )
+        .trim()
+        .to_string();
+
+        assert!(manager.check_remote_branch(&url, &branch).is_ok());
+        assert!(matches!(
+            manager.check_remote_branch(&url, "no-such-branch"),
+            Err(CliError::Repository { .. })
+        ));
+        assert!(matches!(
+            manager.check_remote_branch("/nonexistent/upstream", &branch),
+            Err(CliError::Network { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_symref_head() {
+        let output =
+            "ref: refs/heads/release/v2\tHEAD\n0123456789abcdef0123456789abcdef01234567\tHEAD\n";
+        assert_eq!(parse_symref_head(output), Some("release/v2".to_string()));
+        assert_eq!(
+            parse_symref_head("0123456789abcdef0123456789abcdef01234567\tHEAD\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_default_branch_with_slash() {
+        let (_upstream_dir, upstream_path) = create_test_git_repo();
+        Command::new("git")
+            .args(["branch", "-M", "release/v2.x"])
+            .current_dir(&upstream_path)
+            .output()
+            .expect("Failed to rename branch");
+        let (_temp_dir, repo_path) = create_test_git_repo();
+        let executor = SystemGitExecutor::new();
+        let manager = GitRemoteManager::new(executor, repo_path);
+
+        let branch = manager
+            .default_branch(&upstream_path.to_string_lossy())
+            .unwrap();
+        assert_eq!(branch, "release/v2.x");
+    }
 }
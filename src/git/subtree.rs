@@ -1,11 +1,55 @@
+use crate::config::REINIT_REMOVE_COMMIT_MESSAGE;
 use crate::error::CliError;
 use crate::git::GitExecutor;
+use std::path::{Path, PathBuf};
+
+/// What `git subtree split` produced
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Split {
+    /// SHA of the produced subtree commit
+    pub commit: String,
+    /// Warnings git printed while rewriting commits, e.g. about a message that isn't UTF-8
+    pub warnings: Vec<String>,
+}
 
 pub trait SubtreeManager {
-    fn split_subtree(&self, prefix: &str, branch: &str) -> Result<(), CliError>;
-    fn add_subtree(&self, prefix: &str, branch: &str, squash: bool) -> Result<(), CliError>;
-    fn update_subtree(&self, prefix: &str, branch: &str) -> Result<(), CliError>;
+    /// Split `prefix` into `branch`, returning the produced subtree commit
+    fn split_subtree(&self, prefix: &str, branch: &str) -> Result<Split, CliError>;
+    /// Add `branch` at `prefix`; `message` replaces git's generated commit message
+    fn add_subtree(
+        &self,
+        prefix: &str,
+        branch: &str,
+        squash: bool,
+        message: Option<&str>,
+    ) -> Result<(), CliError>;
+    fn update_subtree(
+        &self,
+        prefix: &str,
+        branch: &str,
+        message: Option<&str>,
+    ) -> Result<(), CliError>;
     fn remove_subtree(&self, prefix: &str) -> Result<(), CliError>;
+    /// Prefixes recorded by squashed subtree commits in HEAD's history that still exist on disk
+    fn subtree_prefixes(&self) -> Result<Vec<String>, CliError>;
+    /// Move the subtree at `old_prefix` to `new_prefix`, re-recording its squash metadata so
+    /// later `git subtree merge --prefix=<new_prefix>` calls keep working
+    fn migrate_subtree(&self, old_prefix: &str, new_prefix: &str) -> Result<(), CliError>;
+    /// Remove the subtree at `prefix` and add `branch` there afresh, for when the squash
+    /// history that `git subtree merge` builds on is no longer reachable
+    fn reinit_subtree(
+        &self,
+        prefix: &str,
+        branch: &str,
+        message: Option<&str>,
+    ) -> Result<(), CliError>;
+    /// Paths left unmerged by a `git subtree merge` that stopped on conflicts
+    fn conflicted_paths(&self) -> Result<Vec<String>, CliError>;
+    /// Settle the conflicts of a stopped merge in favour of the incoming upstream side
+    /// and commit the merge
+    fn resolve_conflicts_with_upstream(&self, paths: &[String]) -> Result<(), CliError>;
+    /// Abandon a merge that stopped on conflicts, restoring the pre-merge state
+    fn abort_merge(&self) -> Result<(), CliError>;
 }
 
 pub struct GitSubtreeManager<T: GitExecutor> {
@@ -22,42 +66,265 @@ impl<T: GitExecutor> GitSubtreeManager<T> {
     }
 }
 
-impl<T: GitExecutor> SubtreeManager for GitSubtreeManager<T> {
-    fn split_subtree(&self, prefix: &str, branch: &str) -> Result<(), CliError> {
-        // git subtree split --prefix=<prefix> -b <branch> <source>
-        // For our use case, we'll split from the current branch
-        let prefix_arg = format!("--prefix={}", prefix);
-        self.executor.execute_git_command(
-            &["subtree", "split", &prefix_arg, "-b", branch],
-            &self.working_dir,
-        )?;
+/// Build the `--prefix=` argument, normalizing Windows separators to the
+/// forward slashes git subtree expects
+fn prefix_arg(prefix: &str) -> String {
+    format!("--prefix={}", git_prefix(prefix))
+}
 
-        Ok(())
+/// Arguments for `git subtree split`, extracting `prefix` history into `branch`
+pub fn split_args(prefix: &str, branch: &str) -> Vec<String> {
+    vec![
+        "subtree".to_string(),
+        "split".to_string(),
+        prefix_arg(prefix),
+        "-b".to_string(),
+        branch.to_string(),
+    ]
+}
+
+/// Arguments for `git subtree add`, placing `branch` at `prefix`
+pub fn add_args(prefix: &str, branch: &str, squash: bool, message: Option<&str>) -> Vec<String> {
+    let mut args = vec!["subtree".to_string(), "add".to_string(), prefix_arg(prefix)];
+    if squash {
+        args.push("--squash".to_string());
+    }
+    push_message(&mut args, message);
+    args.push(branch.to_string());
+    args
+}
+
+/// Arguments for a squashed `git subtree merge` of `branch` into `prefix`
+pub fn merge_args(prefix: &str, branch: &str, message: Option<&str>) -> Vec<String> {
+    let mut args = vec![
+        "subtree".to_string(),
+        "merge".to_string(),
+        prefix_arg(prefix),
+        "--squash".to_string(),
+    ];
+    push_message(&mut args, message);
+    args.push(branch.to_string());
+    args
+}
+
+/// Pass `-m <message>`, leaving git's generated message when none is given
+fn push_message(args: &mut Vec<String>, message: Option<&str>) {
+    if let Some(message) = message {
+        args.push("-m".to_string());
+        args.push(message.to_string());
+    }
+}
+
+/// Whether a failed `git subtree merge` means the subtree's recorded base is gone, as
+/// after a history rewrite, rather than an ordinary conflict
+pub fn is_lost_merge_base(error: &CliError) -> bool {
+    let message = error.to_string();
+    [
+        "was never added",
+        "could not rev-parse split hash",
+        "refusing to merge unrelated histories",
+    ]
+    .iter()
+    .any(|symptom| message.contains(symptom))
+}
+
+/// Turn the two ways `git subtree add` refuses to start into errors that say what to
+/// do: the prefix directory is already there, or the working tree or index is dirty
+fn classify_add_failure(prefix: &str, error: CliError) -> CliError {
+    let message = error.to_string();
+    if message.contains("already exists") {
+        CliError::subtree_prefix_exists(prefix)
+    } else if message.contains("has modifications") {
+        CliError::subtree_add_dirty(prefix)
+    } else {
+        error
+    }
+}
+
+/// Borrow owned arguments in the form `GitExecutor` takes
+pub(crate) fn as_strs(args: &[String]) -> Vec<&str> {
+    args.iter().map(String::as_str).collect()
+}
+
+/// Prefix in git's pathspec form (forward slashes)
+fn git_prefix(prefix: &str) -> String {
+    prefix.replace('\\', "/")
+}
+
+/// Prefix resolved against the working directory using native separators
+fn prefix_path(working_dir: &Path, prefix: &str) -> PathBuf {
+    working_dir.join(
+        prefix
+            .split(['/', '\\'])
+            .filter(|component| !component.is_empty())
+            .collect::<PathBuf>(),
+    )
+}
+
+/// Fail unless a subtree at `old_prefix` can be moved to `new_prefix`: the old directory
+/// has to exist and the new one must not
+pub fn check_migration(
+    working_dir: &Path,
+    old_prefix: &str,
+    new_prefix: &str,
+) -> Result<(), CliError> {
+    let old_dir = git_prefix(old_prefix).trim_end_matches('/').to_string();
+    let new_dir = git_prefix(new_prefix).trim_end_matches('/').to_string();
+
+    if !prefix_path(working_dir, &old_dir).exists() {
+        return Err(CliError::FileSystem {
+            message: format!("Subtree prefix '{}' does not exist", old_dir),
+            suggestion: "Pass the directory the devcontainer was previously synced to".to_string(),
+        });
     }
+    if prefix_path(working_dir, &new_dir).exists() {
+        return Err(CliError::FileSystem {
+            message: format!("Cannot migrate subtree: '{}' already exists", new_dir),
+            suggestion: format!("Remove '{}' or choose a different --prefix", new_dir),
+        });
+    }
+    Ok(())
+}
 
-    fn add_subtree(&self, prefix: &str, branch: &str, squash: bool) -> Result<(), CliError> {
-        let prefix_arg = format!("--prefix={}", prefix);
-        let mut args = vec!["subtree", "add", &prefix_arg];
+/// Read a `git-subtree-*` trailer value from a commit message
+fn subtree_trailer<'a>(message: &'a str, key: &str) -> Option<&'a str> {
+    message.lines().find_map(|line| {
+        line.strip_prefix(key)
+            .and_then(|rest| rest.strip_prefix(':'))
+            .map(str::trim)
+    })
+}
 
-        if squash {
-            args.push("--squash");
+/// Collect the distinct `git-subtree-dir` values from `git log` output, without trailing slashes
+fn parse_subtree_dirs(log: &str) -> Vec<String> {
+    let mut dirs: Vec<String> = Vec::new();
+    for line in log.lines() {
+        if let Some(dir) = subtree_trailer(line, "git-subtree-dir") {
+            let dir = dir.trim_end_matches('/').to_string();
+            if !dir.is_empty() && !dirs.contains(&dir) {
+                dirs.push(dir);
+            }
         }
+    }
+    dirs
+}
 
-        args.push(branch);
+/// Extract the produced commit SHA, which git subtree split prints as its last line
+fn parse_split_output(output: &str) -> Option<String> {
+    output
+        .lines()
+        .map(str::trim)
+        .rfind(|line| !line.is_empty())
+        .filter(|line| line.len() == 40 && line.chars().all(|c| c.is_ascii_hexdigit()))
+        .map(str::to_string)
+}
+
+/// The `1/12 (0) [0]` counter git subtree split redraws as it walks the history
+fn is_split_progress(line: &str) -> bool {
+    let mut parts = line.split_whitespace();
+    let counter = parts.next().and_then(|part| part.split_once('/'));
+    let created = parts.next();
+    let extra = parts.next();
+    let is_number = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    counter.is_some_and(|(done, total)| is_number(done) && is_number(total))
+        && created.is_some_and(|part| {
+            part.strip_prefix('(')
+                .and_then(|part| part.strip_suffix(')'))
+                .is_some_and(is_number)
+        })
+        && extra.is_some_and(|part| {
+            part.strip_prefix('[')
+                .and_then(|part| part.strip_suffix(']'))
+                .is_some_and(is_number)
+        })
+        && parts.next().is_none()
+}
 
+/// Warnings in the stderr of a successful split, leaving out the progress counter and the
+/// "Created branch" note. Lines following a `warning:` line are joined onto it.
+fn parse_split_warnings(stderr: &str) -> Vec<String> {
+    let mut warnings: Vec<String> = Vec::new();
+    let mut continues_warning = false;
+    for line in stderr.split(['\n', '\r']).map(str::trim) {
+        if line.is_empty() || is_split_progress(line) {
+            continue;
+        }
+        if line.starts_with("Created branch '") || line.starts_with("Updated branch '") {
+            continues_warning = false;
+            continue;
+        }
+        let starts_warning = line.to_lowercase().starts_with("warning:");
+        match warnings.last_mut() {
+            Some(warning) if continues_warning && !starts_warning => {
+                warning.push(' ');
+                warning.push_str(line);
+            }
+            _ => {
+                warnings.push(line.to_string());
+                continues_warning = starts_warning;
+            }
+        }
+    }
+    warnings
+}
+
+impl<T: GitExecutor> SubtreeManager for GitSubtreeManager<T> {
+    fn split_subtree(&self, prefix: &str, branch: &str) -> Result<Split, CliError> {
+        // git subtree split --prefix=<prefix> -b <branch> <source>
+        // For our use case, we'll split from the current branch
+        let args = split_args(prefix, branch);
+        let output = self
+            .executor
+            .execute_git_command_with_output(&as_strs(&args), &self.working_dir)?;
+
+        let commit = parse_split_output(&output.stdout).ok_or_else(|| CliError::GitOperation {
+            message: format!(
+                "git subtree split did not report a commit for prefix '{}'",
+                prefix
+            ),
+            suggestion: format!(
+                "Check that '{}' exists and has history on the current branch",
+                prefix
+            ),
+        })?;
+        Ok(Split {
+            commit,
+            warnings: parse_split_warnings(&output.stderr),
+        })
+    }
+
+    fn add_subtree(
+        &self,
+        prefix: &str,
+        branch: &str,
+        squash: bool,
+        message: Option<&str>,
+    ) -> Result<(), CliError> {
+        let args = add_args(prefix, branch, squash, message);
         self.executor
-            .execute_git_command(&args, &self.working_dir)?;
+            .execute_git_command(&as_strs(&args), &self.working_dir)
+            .map_err(|e| classify_add_failure(prefix, e))?;
 
         Ok(())
     }
 
-    fn update_subtree(&self, prefix: &str, branch: &str) -> Result<(), CliError> {
+    fn update_subtree(
+        &self,
+        prefix: &str,
+        branch: &str,
+        message: Option<&str>,
+    ) -> Result<(), CliError> {
         // Use subtree pull to update an existing subtree
-        let prefix_arg = format!("--prefix={}", prefix);
-        self.executor.execute_git_command(
-            &["subtree", "pull", &prefix_arg, "--squash", branch],
-            &self.working_dir,
-        )?;
+        let mut args = vec![
+            "subtree".to_string(),
+            "pull".to_string(),
+            prefix_arg(prefix),
+            "--squash".to_string(),
+        ];
+        push_message(&mut args, message);
+        args.push(branch.to_string());
+        self.executor
+            .execute_git_command(&as_strs(&args), &self.working_dir)?;
 
         Ok(())
     }
@@ -67,7 +334,7 @@ impl<T: GitExecutor> SubtreeManager for GitSubtreeManager<T> {
         // We'll remove the directory and commit the change
         use std::fs;
 
-        let subtree_path = self.working_dir.join(prefix);
+        let subtree_path = prefix_path(&self.working_dir, prefix);
 
         if subtree_path.exists() {
             fs::remove_dir_all(&subtree_path).map_err(|e| CliError::FileSystem {
@@ -78,11 +345,173 @@ impl<T: GitExecutor> SubtreeManager for GitSubtreeManager<T> {
 
             // Stage the removal
             self.executor
-                .execute_git_command(&["add", prefix], &self.working_dir)?;
+                .execute_git_command(&["add", &git_prefix(prefix)], &self.working_dir)?;
         }
 
         Ok(())
     }
+
+    fn subtree_prefixes(&self) -> Result<Vec<String>, CliError> {
+        let log = self.executor.execute_git_command(
+            &["log", "--grep=^git-subtree-dir:", "--format=%B", "HEAD"],
+            &self.working_dir,
+        )?;
+
+        Ok(parse_subtree_dirs(&log)
+            .into_iter()
+            .filter(|dir| prefix_path(&self.working_dir, dir).exists())
+            .collect())
+    }
+
+    fn migrate_subtree(&self, old_prefix: &str, new_prefix: &str) -> Result<(), CliError> {
+        check_migration(&self.working_dir, old_prefix, new_prefix)?;
+        let old_dir = git_prefix(old_prefix).trim_end_matches('/').to_string();
+        let new_dir = git_prefix(new_prefix).trim_end_matches('/').to_string();
+        let new_path = prefix_path(&self.working_dir, &new_dir);
+
+        // The latest squash commit holds the pristine upstream tree that subtree merges diff against
+        let grep = format!("--grep=^git-subtree-dir: {}/*$", old_dir);
+        let candidates = self
+            .executor
+            .execute_git_command(&["log", &grep, "--format=%H", "HEAD"], &self.working_dir)?;
+        let mut squash = None;
+        for sha in candidates
+            .lines()
+            .map(str::trim)
+            .filter(|sha| !sha.is_empty())
+        {
+            let message = self
+                .executor
+                .execute_git_command(&["show", "-s", "--format=%B", sha], &self.working_dir)?;
+            if subtree_trailer(&message, "git-subtree-mainline").is_some() {
+                continue;
+            }
+            if let Some(split) = subtree_trailer(&message, "git-subtree-split") {
+                squash = Some((sha.to_string(), split.to_string()));
+                break;
+            }
+        }
+        let (squash, split) = squash.ok_or_else(|| CliError::Repository {
+            message: format!("No squashed subtree found at prefix '{}'", old_dir),
+            suggestion: "Only subtrees added with 'git subtree add --squash' can be migrated"
+                .to_string(),
+        })?;
+
+        if let Some(parent) = new_path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| CliError::FileSystem {
+                message: format!("Failed to create directory for '{}': {}", new_dir, e),
+                suggestion: "Check file permissions in the repository".to_string(),
+            })?;
+        }
+        self.executor
+            .execute_git_command(&["mv", &old_dir, &new_dir], &self.working_dir)?;
+        let tree = self
+            .executor
+            .execute_git_command(&["write-tree"], &self.working_dir)?;
+
+        // Re-record the squash under the new prefix and join it into history, like subtree add does
+        let squash_message = format!(
+            "Squashed '{}/' content from commit {}\n\ngit-subtree-dir: {}\ngit-subtree-split: {}",
+            new_dir,
+            &split[..split.len().min(7)],
+            new_dir,
+            split
+        );
+        let squash_tree = format!("{}^{{tree}}", squash);
+        let new_squash = self.executor.execute_git_command(
+            &[
+                "commit-tree",
+                &squash_tree,
+                "-p",
+                &squash,
+                "-m",
+                &squash_message,
+            ],
+            &self.working_dir,
+        )?;
+        let move_message = format!(
+            "Move devcontainer subtree from '{}' to '{}'",
+            old_dir, new_dir
+        );
+        let merge = self.executor.execute_git_command(
+            &[
+                "commit-tree",
+                tree.trim(),
+                "-p",
+                "HEAD",
+                "-p",
+                new_squash.trim(),
+                "-m",
+                &move_message,
+            ],
+            &self.working_dir,
+        )?;
+        self.executor.execute_git_command(
+            &["update-ref", "-m", &move_message, "HEAD", merge.trim()],
+            &self.working_dir,
+        )?;
+
+        Ok(())
+    }
+
+    fn reinit_subtree(
+        &self,
+        prefix: &str,
+        branch: &str,
+        message: Option<&str>,
+    ) -> Result<(), CliError> {
+        // git subtree add refuses an existing prefix or a dirty index, so the removal
+        // has to be committed first
+        self.remove_subtree(prefix)?;
+        self.executor.execute_git_command(
+            &["commit", "-m", REINIT_REMOVE_COMMIT_MESSAGE],
+            &self.working_dir,
+        )?;
+
+        self.add_subtree(prefix, branch, true, message)
+    }
+
+    fn conflicted_paths(&self) -> Result<Vec<String>, CliError> {
+        let output = self.executor.execute_git_command(
+            &["diff", "--name-only", "--diff-filter=U"],
+            &self.working_dir,
+        )?;
+
+        Ok(output
+            .lines()
+            .map(str::trim)
+            .filter(|path| !path.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    fn resolve_conflicts_with_upstream(&self, paths: &[String]) -> Result<(), CliError> {
+        for path in paths {
+            let theirs = self
+                .executor
+                .execute_git_command(&["checkout", "--theirs", "--", path], &self.working_dir);
+            if theirs.is_ok() {
+                self.executor
+                    .execute_git_command(&["add", "--", path], &self.working_dir)?;
+            } else {
+                // Upstream deleted the file, so there is no version of theirs to take
+                self.executor
+                    .execute_git_command(&["rm", "--quiet", "--", path], &self.working_dir)?;
+            }
+        }
+
+        self.executor
+            .execute_git_command(&["commit", "--no-edit"], &self.working_dir)?;
+
+        Ok(())
+    }
+
+    fn abort_merge(&self) -> Result<(), CliError> {
+        self.executor
+            .execute_git_command(&["merge", "--abort"], &self.working_dir)?;
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -147,6 +576,17 @@ mod tests {
         let result = manager.split_subtree("subdir", "subtree-branch");
         assert!(result.is_ok());
 
+        // The returned SHA is the split commit the branch points at
+        let sha = result.unwrap().commit;
+        assert_eq!(sha.len(), 40);
+        assert!(sha.chars().all(|c| c.is_ascii_hexdigit()));
+        let branch_sha = Command::new("git")
+            .args(["rev-parse", "subtree-branch"])
+            .current_dir(&repo_path)
+            .output()
+            .expect("Failed to resolve branch");
+        assert_eq!(String::from_utf8_lossy(&branch_sha.stdout).trim(), sha);
+
         // Verify the branch was created
         let output = Command::new("git")
             .args(["branch", "--list", "subtree-branch"])
@@ -182,7 +622,232 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_parse_split_warnings() {
+        let stderr = "1/3 (0) [0]\r2/3 (1) [0]\r3/3 (2) [0]\rWarning: commit message did not conform to UTF-8.\nYou may want to amend it after fixing the message, or set the config\nvariable i18n.commitEncoding to the encoding your project uses.\nCreated branch 'devcontainer'\n";
+
+        assert_eq!(
+            parse_split_warnings(stderr),
+            vec!["Warning: commit message did not conform to UTF-8. You may want to amend it after fixing the message, or set the config variable i18n.commitEncoding to the encoding your project uses."]
+        );
+        assert!(parse_split_warnings("1/1 (0) [0]\rCreated branch 'devcontainer'\n").is_empty());
+    }
+
+    #[test]
+    fn test_parse_split_output() {
+        let sha = "0123456789abcdef0123456789abcdef01234567";
+        assert_eq!(
+            parse_split_output(&format!("{}\n", sha)),
+            Some(sha.to_string())
+        );
+        assert_eq!(
+            parse_split_output(&format!("progress\n{}\n\n", sha)),
+            Some(sha.to_string())
+        );
+        assert_eq!(parse_split_output("Created branch 'x'\n"), None);
+        assert_eq!(parse_split_output(""), None);
+    }
+
+    #[test]
+    fn test_is_lost_merge_base() {
+        let lost = CliError::GitOperation {
+            message: "Git command failed: git subtree merge\nError: Can't squash-merge: '.devcontainer' was never added.".to_string(),
+            suggestion: String::new(),
+        };
+        let conflict = CliError::GitOperation {
+            message: "Git command failed: git subtree merge\nError: CONFLICT (content)".to_string(),
+            suggestion: String::new(),
+        };
+
+        assert!(is_lost_merge_base(&lost));
+        assert!(!is_lost_merge_base(&conflict));
+    }
+
+    #[test]
+    fn test_classify_add_failure() {
+        let args = ["subtree", "add", "--prefix=.devcontainer", "devcontainer"];
+        let failure = |stderr: &str| {
+            classify_add_failure(".devcontainer", CliError::git_command_failed(&args, stderr))
+        };
+
+        let exists = failure("fatal: prefix '.devcontainer' already exists.");
+        assert!(exists.to_string().contains(".devcontainer already exists"));
+        assert!(exists.suggestion().contains("Remove .devcontainer"));
+
+        for stderr in [
+            "fatal: working tree has modifications.  Cannot add.",
+            "fatal: index has modifications.  Cannot add.",
+        ] {
+            let dirty = failure(stderr);
+            assert!(dirty.to_string().contains("uncommitted changes"));
+            assert!(dirty.suggestion().contains("Commit or stash"));
+        }
+
+        let other = failure("fatal: ambiguous argument 'devcontainer'");
+        assert!(other.to_string().contains("ambiguous argument"));
+    }
+
+    #[test]
+    fn test_subtree_message_precedes_branch() {
+        assert_eq!(
+            add_args(".devcontainer", "devcontainer", true, Some("Sync OPS-42")),
+            vec![
+                "subtree",
+                "add",
+                "--prefix=.devcontainer",
+                "--squash",
+                "-m",
+                "Sync OPS-42",
+                "devcontainer"
+            ]
+        );
+        assert_eq!(
+            merge_args(".devcontainer", "devcontainer-updated", None),
+            vec![
+                "subtree",
+                "merge",
+                "--prefix=.devcontainer",
+                "--squash",
+                "devcontainer-updated"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_prefix_arg_normalizes_windows_separators() {
+        assert_eq!(
+            prefix_arg(".devcontainer\\sub"),
+            "--prefix=.devcontainer/sub"
+        );
+        assert_eq!(
+            prefix_arg(".devcontainer/sub"),
+            "--prefix=.devcontainer/sub"
+        );
+    }
+
+    #[test]
+    fn test_prefix_path_uses_native_separators() {
+        let working_dir = PathBuf::from("repo");
+        let expected = working_dir.join(".devcontainer").join("sub");
+
+        assert_eq!(prefix_path(&working_dir, ".devcontainer\\sub"), expected);
+        assert_eq!(prefix_path(&working_dir, ".devcontainer/sub"), expected);
+    }
+
+    #[test]
+    fn test_remove_subtree_with_backslash_prefix() {
+        let (_temp_dir, repo_path) = create_test_git_repo_with_subtree();
+        fs::create_dir_all(repo_path.join("subdir/nested")).expect("Failed to create nested dir");
+        fs::write(repo_path.join("subdir/nested/file.txt"), "nested")
+            .expect("Failed to create nested file");
+        Command::new("git")
+            .args(["add", "."])
+            .current_dir(&repo_path)
+            .output()
+            .expect("Failed to add nested file");
+        Command::new("git")
+            .args(["commit", "-m", "Add nested content"])
+            .current_dir(&repo_path)
+            .output()
+            .expect("Failed to commit nested file");
+        let executor = SystemGitExecutor::new();
+        let manager = GitSubtreeManager::new(executor, repo_path.clone());
+
+        let result = manager.remove_subtree("subdir\\nested");
+        assert!(result.is_ok());
+
+        assert!(!repo_path.join("subdir").join("nested").exists());
+        assert!(repo_path.join("subdir").join("file.txt").exists());
+    }
+
     // Note: add_subtree and update_subtree tests are more complex as they require
     // actual remote repositories or more sophisticated setup. For now, we'll test
     // the basic functionality that doesn't require network access.
+
+    #[test]
+    fn test_parse_subtree_dirs() {
+        let log = "Squashed 'vendor/' content from commit abc1234\n\ngit-subtree-dir: vendor\ngit-subtree-split: abc\n\nMerge commit\n\ngit-subtree-dir: vendor/\ngit-subtree-dir: other\n";
+
+        assert_eq!(parse_subtree_dirs(log), vec!["vendor", "other"]);
+        assert!(parse_subtree_dirs("no trailers here").is_empty());
+    }
+
+    #[test]
+    fn test_migrate_subtree_keeps_subtree_mergeable() {
+        let (_temp_dir, repo_path) = create_test_git_repo_with_subtree();
+        let git = |args: &[&str]| {
+            let output = Command::new("git")
+                .args(args)
+                .current_dir(&repo_path)
+                .output()
+                .expect("Failed to run git");
+            assert!(
+                output.status.success(),
+                "git {:?} failed: {}",
+                args,
+                String::from_utf8_lossy(&output.stderr)
+            );
+        };
+        git(&[
+            "subtree",
+            "split",
+            "--prefix=subdir",
+            "-b",
+            "subtree-branch",
+        ]);
+        git(&[
+            "subtree",
+            "add",
+            "--prefix=vendor",
+            "--squash",
+            "subtree-branch",
+        ]);
+
+        let executor = SystemGitExecutor::new();
+        let manager = GitSubtreeManager::new(executor, repo_path.clone());
+        assert_eq!(manager.subtree_prefixes().unwrap(), vec!["vendor"]);
+
+        manager
+            .migrate_subtree("vendor", "nested/vendor")
+            .expect("Migration should succeed");
+
+        assert!(!repo_path.join("vendor").exists());
+        assert_eq!(
+            fs::read_to_string(repo_path.join("nested/vendor/file.txt")).unwrap(),
+            "subtree content"
+        );
+        assert_eq!(manager.subtree_prefixes().unwrap(), vec!["nested/vendor"]);
+
+        // New upstream content merges into the migrated prefix
+        fs::write(repo_path.join("subdir/file.txt"), "updated content").unwrap();
+        git(&["commit", "-am", "Update subtree content"]);
+        git(&[
+            "subtree",
+            "split",
+            "--prefix=subdir",
+            "-b",
+            "subtree-branch",
+        ]);
+        git(&[
+            "subtree",
+            "merge",
+            "--prefix=nested/vendor",
+            "--squash",
+            "subtree-branch",
+        ]);
+        assert_eq!(
+            fs::read_to_string(repo_path.join("nested/vendor/file.txt")).unwrap(),
+            "updated content"
+        );
+    }
+
+    #[test]
+    fn test_migrate_subtree_refuses_existing_destination() {
+        let (_temp_dir, repo_path) = create_test_git_repo_with_subtree();
+        let executor = SystemGitExecutor::new();
+        let manager = GitSubtreeManager::new(executor, repo_path);
+
+        let result = manager.migrate_subtree("subdir", "subdir");
+        assert!(matches!(result, Err(CliError::FileSystem { .. })));
+    }
 }
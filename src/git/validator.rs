@@ -1,3 +1,4 @@
+use crate::config::DEFAULT_GIT_PROGRAM;
 use crate::error::CliError;
 use std::path::Path;
 use std::process::Command;
@@ -7,15 +8,33 @@ pub trait RepositoryValidator {
     fn check_existing_remote(&self, remote_name: &str) -> Result<bool, CliError>;
     fn check_existing_branch(&self, branch_name: &str) -> Result<bool, CliError>;
     fn validate_has_commits(&self) -> Result<(), CliError>;
+    fn validate_not_shallow(&self) -> Result<(), CliError>;
+    fn validate_subtree_available(&self) -> Result<(), CliError>;
+    fn validate_writable(&self) -> Result<(), CliError>;
+    /// Fail when `prefix` has uncommitted changes, naming the affected files
+    fn validate_clean_path(&self, prefix: &str) -> Result<(), CliError>;
+    /// Fail when anything in the working tree is modified or untracked, naming the files.
+    /// The `<prefix>.backup` and `<prefix>.preserved` directories this tool writes
+    /// itself don't count.
+    fn validate_clean_working_tree(&self, prefix: &str) -> Result<(), CliError>;
 }
 
 pub struct GitRepositoryValidator {
     working_dir: std::path::PathBuf,
+    git_path: std::path::PathBuf,
 }
 
 impl GitRepositoryValidator {
     pub fn new(working_dir: std::path::PathBuf) -> Self {
-        Self { working_dir }
+        Self {
+            working_dir,
+            git_path: std::path::PathBuf::from(DEFAULT_GIT_PROGRAM),
+        }
+    }
+
+    pub fn with_git_path(mut self, git_path: std::path::PathBuf) -> Self {
+        self.git_path = git_path;
+        self
     }
 }
 
@@ -28,7 +47,7 @@ impl RepositoryValidator for GitRepositoryValidator {
         }
 
         // Also check if git command recognizes this as a valid repository
-        let output = Command::new("git")
+        let output = Command::new(&self.git_path)
             .args(["rev-parse", "--git-dir"])
             .current_dir(path)
             .output()
@@ -45,7 +64,7 @@ impl RepositoryValidator for GitRepositoryValidator {
     }
 
     fn check_existing_remote(&self, remote_name: &str) -> Result<bool, CliError> {
-        let output = Command::new("git")
+        let output = Command::new(&self.git_path)
             .args(["remote", "get-url", remote_name])
             .current_dir(&self.working_dir)
             .output()
@@ -58,7 +77,7 @@ impl RepositoryValidator for GitRepositoryValidator {
     }
 
     fn check_existing_branch(&self, branch_name: &str) -> Result<bool, CliError> {
-        let output = Command::new("git")
+        let output = Command::new(&self.git_path)
             .args([
                 "show-ref",
                 "--verify",
@@ -75,7 +94,7 @@ impl RepositoryValidator for GitRepositoryValidator {
     }
 
     fn validate_has_commits(&self) -> Result<(), CliError> {
-        let output = Command::new("git")
+        let output = Command::new(&self.git_path)
             .args(["rev-parse", "HEAD"])
             .current_dir(&self.working_dir)
             .output()
@@ -90,6 +109,128 @@ impl RepositoryValidator for GitRepositoryValidator {
 
         Ok(())
     }
+
+    fn validate_not_shallow(&self) -> Result<(), CliError> {
+        let git = |args: &[&str]| {
+            Command::new(&self.git_path)
+                .args(args)
+                .current_dir(&self.working_dir)
+                .output()
+                .map_err(|e| CliError::GitOperation {
+                    message: format!("Failed to check for shallow clone: {}", e),
+                    suggestion: "Make sure git is installed and available in PATH".to_string(),
+                })
+        };
+
+        let output = git(&["rev-parse", "--is-shallow-repository"])?;
+        if String::from_utf8_lossy(&output.stdout).trim() != "true" {
+            return Ok(());
+        }
+
+        // The upstream is fetched shallowly, so a shallow repository is only a problem
+        // when the cut-off history is the current branch's own
+        let output = git(&["rev-parse", "--git-path", "shallow"])?;
+        let shallow_file = self
+            .working_dir
+            .join(String::from_utf8_lossy(&output.stdout).trim());
+        let boundaries = std::fs::read_to_string(shallow_file).unwrap_or_default();
+        for boundary in boundaries.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            if git(&["merge-base", "--is-ancestor", boundary, "HEAD"])?
+                .status
+                .success()
+            {
+                return Err(CliError::shallow_repository());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_subtree_available(&self) -> Result<(), CliError> {
+        // Bare 'git subtree' prints usage and exits non-zero, so only an unknown-command
+        // error means the subtree contrib script is missing
+        let output = Command::new(&self.git_path)
+            .arg("subtree")
+            .current_dir(&self.working_dir)
+            .output()
+            .map_err(|e| CliError::GitOperation {
+                message: format!("Failed to check for git subtree: {}", e),
+                suggestion: "Make sure git is installed and available in PATH".to_string(),
+            })?;
+
+        if String::from_utf8_lossy(&output.stderr).contains("is not a git command") {
+            return Err(CliError::GitOperation {
+                message: "git subtree is not available".to_string(),
+                suggestion: "Install the git subtree contrib command (often packaged separately, e.g. 'git-subtree')".to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn validate_writable(&self) -> Result<(), CliError> {
+        // Probe with a real file: permission bits alone miss read-only mounts
+        let probe = self.working_dir.join(format!(
+            ".devcontainer-sync-write-check-{}",
+            std::process::id()
+        ));
+
+        std::fs::write(&probe, b"")
+            .and_then(|_| std::fs::remove_file(&probe))
+            .map_err(|e| CliError::working_dir_not_writable(&self.working_dir, &e))
+    }
+
+    fn validate_clean_path(&self, prefix: &str) -> Result<(), CliError> {
+        let files = self.uncommitted_files(&["--", prefix])?;
+        if !files.is_empty() {
+            return Err(CliError::uncommitted_devcontainer_changes(prefix, &files));
+        }
+
+        Ok(())
+    }
+
+    fn validate_clean_working_tree(&self, prefix: &str) -> Result<(), CliError> {
+        let backup = format!(":(exclude){}.backup", prefix);
+        let preserved = format!(":(exclude){}.preserved", prefix);
+        let files = self.uncommitted_files(&["--", ":/", &backup, &preserved])?;
+        if !files.is_empty() {
+            return Err(CliError::dirty_working_tree(&files));
+        }
+
+        Ok(())
+    }
+}
+
+impl GitRepositoryValidator {
+    /// Paths `git status --porcelain` reports, limited by `pathspec`
+    fn uncommitted_files(&self, pathspec: &[&str]) -> Result<Vec<String>, CliError> {
+        let output = Command::new(&self.git_path)
+            .args(["status", "--porcelain"])
+            .args(pathspec)
+            .current_dir(&self.working_dir)
+            .output()
+            .map_err(|e| CliError::GitOperation {
+                message: format!("Failed to check for uncommitted changes: {}", e),
+                suggestion: "Make sure git is installed and available in PATH".to_string(),
+            })?;
+
+        if !output.status.success() {
+            return Err(CliError::GitOperation {
+                message: format!(
+                    "Failed to check for uncommitted changes: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+                suggestion: "Make sure the repository is not corrupted".to_string(),
+            });
+        }
+
+        // Porcelain lines are "XY <path>"
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.get(3..))
+            .map(str::to_string)
+            .collect())
+    }
 }
 
 #[cfg(test)]
@@ -192,6 +333,52 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_validate_not_shallow_success() {
+        let (_temp_dir, repo_path) = create_temp_git_repo(true);
+        let validator = GitRepositoryValidator::new(repo_path);
+
+        assert!(validator.validate_not_shallow().is_ok());
+    }
+
+    #[test]
+    fn test_validate_not_shallow_in_shallow_clone() {
+        let (_temp_dir, repo_path) = create_temp_git_repo(true);
+        fs::write(repo_path.join("test.txt"), "more content").expect("Failed to update file");
+        Command::new("git")
+            .args(["commit", "-am", "Second commit"])
+            .current_dir(&repo_path)
+            .output()
+            .expect("Failed to make second commit");
+
+        let clone_dir = TempDir::new().expect("Failed to create temp directory");
+        let clone_path = clone_dir.path().join("clone");
+        Command::new("git")
+            .args([
+                "clone",
+                "--depth",
+                "1",
+                &format!("file://{}", repo_path.display()),
+                &clone_path.display().to_string(),
+            ])
+            .output()
+            .expect("Failed to create shallow clone");
+
+        let validator = GitRepositoryValidator::new(clone_path);
+        let result = validator.validate_not_shallow();
+
+        if let Err(CliError::Repository {
+            message,
+            suggestion,
+        }) = result
+        {
+            assert!(message.contains("shallow clone"));
+            assert!(suggestion.contains("git fetch --unshallow"));
+        } else {
+            panic!("Expected Repository error");
+        }
+    }
+
     #[test]
     fn test_check_existing_remote_not_exists() {
         let (_temp_dir, repo_path) = create_temp_git_repo(true);
@@ -211,4 +398,113 @@ mod tests {
         assert!(result.is_ok());
         assert!(!result.unwrap());
     }
+
+    #[test]
+    fn test_validate_subtree_available() {
+        let (_temp_dir, path) = create_temp_git_repo(true);
+        let validator = GitRepositoryValidator::new(path);
+
+        assert!(validator.validate_subtree_available().is_ok());
+    }
+
+    #[test]
+    fn test_validate_clean_path_names_modified_files() {
+        let (_temp_dir, path) = create_temp_git_repo(true);
+        fs::create_dir(path.join(".devcontainer")).unwrap();
+        fs::write(path.join(".devcontainer/devcontainer.json"), "{}\n").unwrap();
+        Command::new("git")
+            .args(["add", "."])
+            .current_dir(&path)
+            .output()
+            .unwrap();
+        Command::new("git")
+            .args(["commit", "-m", "Add devcontainer"])
+            .current_dir(&path)
+            .output()
+            .unwrap();
+        let validator = GitRepositoryValidator::new(path.clone());
+        assert!(validator.validate_clean_path(".devcontainer").is_ok());
+
+        // Changes outside the prefix don't count
+        fs::write(path.join("test.txt"), "changed\n").unwrap();
+        assert!(validator.validate_clean_path(".devcontainer").is_ok());
+
+        fs::write(path.join(".devcontainer/devcontainer.json"), "{\"a\": 1}\n").unwrap();
+        match validator.validate_clean_path(".devcontainer") {
+            Err(CliError::Repository { message, .. }) => {
+                assert!(message.contains(".devcontainer/devcontainer.json"));
+                assert!(!message.contains("test.txt"));
+            }
+            other => panic!("Expected Repository error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_validate_clean_working_tree_ignores_backup_and_preserved_directories() {
+        let (_temp_dir, path) = create_temp_git_repo(true);
+        let validator = GitRepositoryValidator::new(path.clone());
+        for dir in [".devcontainer.backup", ".devcontainer.preserved"] {
+            fs::create_dir(path.join(dir)).unwrap();
+            fs::write(path.join(dir).join("devcontainer.json"), "{}\n").unwrap();
+        }
+        assert!(validator
+            .validate_clean_working_tree(".devcontainer")
+            .is_ok());
+
+        fs::write(path.join("notes.txt"), "draft\n").unwrap();
+        match validator.validate_clean_working_tree(".devcontainer") {
+            Err(CliError::Repository { message, .. }) => {
+                assert!(message.contains("notes.txt"));
+                assert!(!message.contains(".devcontainer.backup"));
+            }
+            other => panic!("Expected Repository error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_uncommitted_files_reports_a_failed_git_status() {
+        let temp_dir = TempDir::new().unwrap();
+        let validator = GitRepositoryValidator::new(temp_dir.path().to_path_buf());
+
+        assert!(matches!(
+            validator.validate_clean_working_tree(".devcontainer"),
+            Err(CliError::GitOperation { .. })
+        ));
+    }
+
+    #[test]
+    fn test_validate_writable_success() {
+        let (_temp_dir, path) = create_temp_git_repo(true);
+        let validator = GitRepositoryValidator::new(path.clone());
+
+        assert!(validator.validate_writable().is_ok());
+        // The probe file is cleaned up
+        assert_eq!(fs::read_dir(&path).unwrap().count(), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_validate_writable_read_only_directory() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (_temp_dir, path) = create_temp_git_repo(true);
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o555)).unwrap();
+
+        // Root ignores permission bits, so the check must agree with what a real write
+        // does rather than with the mode
+        let writable_anyway = fs::write(path.join("root-check"), b"").is_ok();
+        let result = GitRepositoryValidator::new(path.clone()).validate_writable();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        if writable_anyway {
+            assert!(result.is_ok(), "writable directory rejected: {:?}", result);
+            return;
+        }
+
+        match result {
+            Err(CliError::FileSystem { message, .. }) => {
+                assert!(message.contains("not writable"));
+            }
+            other => panic!("Expected FileSystem error, got {:?}", other),
+        }
+    }
 }
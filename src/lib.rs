@@ -3,10 +3,15 @@ pub mod config;
 pub mod customizer;
 pub mod error;
 pub mod git;
+pub mod lockfile;
 pub mod types;
 
 pub use customizer::{
-    DefaultDevcontainerCustomizer, DevcontainerCustomizer, FirewallRemovalResult,
+    Change, CustomizationResult, DefaultDevcontainerCustomizer, DevcontainerCustomizer,
+    FeatureProfile, FirewallRemovalResult,
 };
 pub use error::CliError;
-pub use types::{CommandContext, GitCommand, OperationResult};
+pub use types::{
+    BatchEntry, ColorChoice, CommandContext, GitCommand, InitReport, OperationResult, Severity,
+    UpdateSummary, Warning,
+};
@@ -0,0 +1,139 @@
+use crate::error::CliError;
+use std::path::{Path, PathBuf};
+
+pub const LOCKFILE_NAME: &str = ".devcontainer-sync.lock";
+pub const LOCKFILE_COMMIT_MESSAGE: &str = "Record synced devcontainer revision";
+
+const UPSTREAM_COMMIT_KEY: &str = "upstream-commit";
+const SUBTREE_SPLIT_KEY: &str = "git-subtree-split";
+
+/// Pinned upstream revision recorded after a successful init/update
+///
+/// The lockfile is committed alongside the devcontainer so every checkout of
+/// the repository can sync exactly the same upstream content with `update --locked`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lockfile {
+    /// Upstream commit the tracking branch pointed at
+    pub upstream_commit: String,
+    /// Commit produced by `git subtree split` for that upstream commit
+    pub subtree_split: String,
+}
+
+impl Lockfile {
+    pub fn new(upstream_commit: String, subtree_split: String) -> Self {
+        Self {
+            upstream_commit,
+            subtree_split,
+        }
+    }
+
+    pub fn path(working_dir: &Path) -> PathBuf {
+        working_dir.join(LOCKFILE_NAME)
+    }
+
+    /// Read the lockfile from the repository root, if one exists
+    pub fn read(working_dir: &Path) -> Result<Option<Self>, CliError> {
+        let path = Self::path(working_dir);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(&path).map_err(|e| CliError::FileSystem {
+            message: format!("Failed to read {}: {}", LOCKFILE_NAME, e),
+            suggestion: "Check file permissions".to_string(),
+        })?;
+
+        Self::parse(&content).map(Some)
+    }
+
+    /// Write the lockfile to the repository root
+    pub fn write(&self, working_dir: &Path) -> Result<(), CliError> {
+        std::fs::write(Self::path(working_dir), self.render()).map_err(|e| CliError::FileSystem {
+            message: format!("Failed to write {}: {}", LOCKFILE_NAME, e),
+            suggestion: "Check file permissions and available disk space".to_string(),
+        })
+    }
+
+    pub fn parse(content: &str) -> Result<Self, CliError> {
+        let mut upstream_commit = None;
+        let mut subtree_split = None;
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            match line.split_once(':') {
+                Some((key, value)) if key.trim() == UPSTREAM_COMMIT_KEY => {
+                    upstream_commit = Some(value.trim().to_string());
+                }
+                Some((key, value)) if key.trim() == SUBTREE_SPLIT_KEY => {
+                    subtree_split = Some(value.trim().to_string());
+                }
+                _ => {}
+            }
+        }
+
+        match (upstream_commit, subtree_split) {
+            (Some(upstream_commit), Some(subtree_split))
+                if is_commit_sha(&upstream_commit) && is_commit_sha(&subtree_split) =>
+            {
+                Ok(Self::new(upstream_commit, subtree_split))
+            }
+            _ => Err(CliError::FileSystem {
+                message: format!(
+                    "{} is malformed: expected '{}' and '{}' commit SHAs",
+                    LOCKFILE_NAME, UPSTREAM_COMMIT_KEY, SUBTREE_SPLIT_KEY
+                ),
+                suggestion: format!(
+                    "Delete {} and run 'devcontainer-sync update' to regenerate it",
+                    LOCKFILE_NAME
+                ),
+            }),
+        }
+    }
+
+    pub fn render(&self) -> String {
+        format!(
+            "# Generated by devcontainer-sync. Do not edit by hand.\n{}: {}\n{}: {}\n",
+            UPSTREAM_COMMIT_KEY, self.upstream_commit, SUBTREE_SPLIT_KEY, self.subtree_split
+        )
+    }
+}
+
+fn is_commit_sha(value: &str) -> bool {
+    value.len() == 40 && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const UPSTREAM: &str = "0123456789abcdef0123456789abcdef01234567";
+    const SPLIT: &str = "fedcba9876543210fedcba9876543210fedcba98";
+
+    #[test]
+    fn test_lockfile_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let lockfile = Lockfile::new(UPSTREAM.to_string(), SPLIT.to_string());
+
+        lockfile.write(temp_dir.path()).unwrap();
+        let read_back = Lockfile::read(temp_dir.path()).unwrap();
+
+        assert_eq!(read_back, Some(lockfile));
+    }
+
+    #[test]
+    fn test_lockfile_missing() {
+        let temp_dir = TempDir::new().unwrap();
+        assert_eq!(Lockfile::read(temp_dir.path()).unwrap(), None);
+    }
+
+    #[test]
+    fn test_lockfile_malformed() {
+        let result = Lockfile::parse("upstream-commit: not-a-sha\n");
+        assert!(matches!(result, Err(CliError::FileSystem { .. })));
+    }
+}
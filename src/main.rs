@@ -1,6 +1,20 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use devcontainer_sync_cli::cli::explain::Operation;
+use devcontainer_sync_cli::cli::output::{
+    json_result, porcelain_record, print_result, OutputFormat,
+};
 use devcontainer_sync_cli::cli::CliApp;
+use devcontainer_sync_cli::config::{
+    fork_repo_url, load_config, validate_prefix, validate_repo_url, SyncConfig,
+    DEFAULT_FETCH_RETRIES, DEFAULT_TIMEOUT_SECS, TOKEN_ENV_VAR,
+};
+use devcontainer_sync_cli::error::{INTERRUPTED_EXIT_CODE, UPDATES_AVAILABLE_EXIT_CODE};
+use devcontainer_sync_cli::git::{request_interrupt, was_interrupted};
+use devcontainer_sync_cli::{CliError, ColorChoice, OperationResult};
+use std::io::Write;
+use std::path::PathBuf;
 use std::process;
+use std::time::Duration;
 
 #[derive(Parser)]
 #[command(name = "devcontainer-sync")]
@@ -13,6 +27,116 @@ struct Cli {
     /// Enable verbose output
     #[arg(short, long, global = true)]
     verbose: bool,
+
+    /// Sync from the same-named claude-code fork under this GitHub organization
+    #[arg(long, global = true, value_name = "ORG")]
+    fork: Option<String>,
+
+    /// Sync from this repository URL instead of the Anthropic claude-code repository
+    #[arg(long, global = true, value_name = "URL", conflicts_with = "fork")]
+    repo_url: Option<String>,
+
+    /// Token for an HTTPS upstream such as a private fork [env: DEVCONTAINER_SYNC_TOKEN]
+    #[arg(long, global = true, value_name = "TOKEN")]
+    token: Option<String>,
+
+    /// Path to the git binary to use instead of the one on PATH
+    #[arg(long, global = true, value_name = "PATH")]
+    git_path: Option<PathBuf>,
+
+    /// Directory in this repository that holds the synced devcontainer
+    #[arg(long, global = true, value_name = "PATH")]
+    prefix: Option<String>,
+
+    /// Directory in the upstream repository to sync the devcontainer from (default: .devcontainer)
+    #[arg(long, global = true, value_name = "PATH")]
+    upstream_prefix: Option<String>,
+
+    /// Name of the git remote added for the upstream repository (default: claude)
+    #[arg(long, global = true, value_name = "NAME")]
+    remote_name: Option<String>,
+
+    /// Name of the branch that tracks upstream (default: claude-main)
+    #[arg(long, global = true, value_name = "NAME")]
+    tracking_branch: Option<String>,
+
+    /// Name of the branch the devcontainer subtree is split into (default: devcontainer)
+    #[arg(long, global = true, value_name = "NAME")]
+    work_branch: Option<String>,
+
+    /// Seconds a git command may run before it is killed; 0 for no limit
+    #[arg(long, global = true, value_name = "SECONDS", default_value_t = DEFAULT_TIMEOUT_SECS)]
+    timeout: u64,
+
+    /// Times to retry a fetch that fails on the network, backing off between attempts
+    #[arg(long, global = true, value_name = "N", default_value_t = DEFAULT_FETCH_RETRIES)]
+    retries: u32,
+
+    /// Leave out the per-step progress lines, keeping the final summary
+    #[arg(long, global = true)]
+    no_progress: bool,
+
+    /// When to color output: always, auto (only on a terminal, unless NO_COLOR is set) or never
+    #[arg(long, global = true, value_enum, default_value_t = Color::Auto)]
+    color: Color,
+
+    /// Output format for progress and results
+    #[arg(long, global = true, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Print one stable, space-separated result record instead of progress (format v1)
+    #[arg(long, global = true, conflicts_with = "format")]
+    porcelain: bool,
+
+    /// Print a single JSON object describing the result instead of progress
+    #[arg(long, global = true, conflicts_with_all = ["format", "porcelain"])]
+    json: bool,
+}
+
+impl Cli {
+    fn output_format(&self) -> OutputFormat {
+        if self.porcelain {
+            OutputFormat::Porcelain
+        } else if self.json {
+            OutputFormat::Json
+        } else {
+            self.format.into()
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Color {
+    Always,
+    Auto,
+    Never,
+}
+
+impl From<Color> for ColorChoice {
+    fn from(color: Color) -> Self {
+        match color {
+            Color::Always => ColorChoice::Always,
+            Color::Auto => ColorChoice::Auto,
+            Color::Never => ColorChoice::Never,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Text,
+    Ndjson,
+    Json,
+}
+
+impl From<Format> for OutputFormat {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Text => OutputFormat::Text,
+            Format::Ndjson => OutputFormat::Ndjson,
+            Format::Json => OutputFormat::Json,
+        }
+    }
 }
 
 #[derive(Subcommand)]
@@ -22,6 +146,50 @@ enum Commands {
         /// Remove firewall configurations from devcontainer files
         #[arg(long)]
         strip_firewall: bool,
+        /// Keep this package when stripping firewall packages (repeatable)
+        #[arg(long = "keep-package", alias = "exclude-package", value_name = "NAME")]
+        keep_packages: Vec<String>,
+        /// Also strip this package from apt install commands (repeatable)
+        #[arg(long = "extra-package", value_name = "NAME")]
+        extra_packages: Vec<String>,
+        /// Record the firewall strip commit in .git-blame-ignore-revs
+        #[arg(long)]
+        blame_ignore: bool,
+        /// Commit message for the subtree add, instead of git's generated one
+        #[arg(long, value_name = "MSG")]
+        subtree_message: Option<String>,
+        /// Fetch every upstream branch and tag, not just the branch being synced
+        #[arg(long)]
+        fetch_all: bool,
+        /// Fetch the full upstream history instead of only the latest commit
+        #[arg(long)]
+        full_history: bool,
+        /// With --strip-firewall, ask which detected firewall scripts to remove
+        #[arg(long, requires = "strip_firewall")]
+        select_scripts: bool,
+        /// With --strip-firewall, show the changes as a diff and ask before applying them
+        #[arg(long, requires = "strip_firewall")]
+        preview_diff: bool,
+        /// Only check that init would succeed, without changing the repository
+        #[arg(long)]
+        check: bool,
+        /// Show what init would do, including the firewall strip commit message, without
+        /// changing the repository
+        #[arg(long, conflicts_with = "check")]
+        dry_run: bool,
+        /// Overwrite an existing .devcontainer without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
+        /// Initialize even in your home directory or the filesystem root, or over
+        /// existing branches with the tracking or work branch names
+        #[arg(long)]
+        force: bool,
+        /// Run even with uncommitted changes in the working tree
+        #[arg(long)]
+        allow_dirty: bool,
+        /// Stash uncommitted changes while init runs and restore them afterwards
+        #[arg(long)]
+        autostash: bool,
     },
     /// Update existing devcontainer configurations
     Update {
@@ -31,41 +199,463 @@ enum Commands {
         /// Force update even if conflicts exist
         #[arg(long)]
         force: bool,
+        /// Run even with uncommitted changes outside the devcontainer
+        #[arg(long)]
+        allow_dirty: bool,
+        /// Stash uncommitted changes while update runs and restore them afterwards
+        #[arg(long)]
+        autostash: bool,
         /// Remove firewall configurations from devcontainer files
         #[arg(long)]
         strip_firewall: bool,
+        /// With --strip-firewall, strip only the files this update changed
+        #[arg(long, requires = "strip_firewall")]
+        incremental_strip: bool,
+        /// With --strip-firewall, ask which detected firewall scripts to remove
+        #[arg(long, requires = "strip_firewall")]
+        select_scripts: bool,
+        /// With --strip-firewall, show the changes as a diff and ask before applying them
+        #[arg(long, requires = "strip_firewall")]
+        preview_diff: bool,
+        /// Sync exactly the upstream revision recorded in .devcontainer-sync.lock
+        #[arg(long)]
+        locked: bool,
+        /// Move a devcontainer previously synced to this directory to --prefix first
+        #[arg(long, value_name = "OLD")]
+        migrate_prefix: Option<String>,
+        /// Re-add the subtree from scratch if its merge base was lost to a history rewrite
+        #[arg(long)]
+        reinit_subtree: bool,
+        /// Keep this package when stripping firewall packages (repeatable)
+        #[arg(long = "keep-package", alias = "exclude-package", value_name = "NAME")]
+        keep_packages: Vec<String>,
+        /// Also strip this package from apt install commands (repeatable)
+        #[arg(long = "extra-package", value_name = "NAME")]
+        extra_packages: Vec<String>,
+        /// Record the firewall strip commit in .git-blame-ignore-revs
+        #[arg(long)]
+        blame_ignore: bool,
+        /// Commit message for the subtree merge, instead of git's generated one
+        #[arg(long, value_name = "MSG")]
+        subtree_message: Option<String>,
+        /// Fetch every upstream branch and tag, not just the branch being synced
+        #[arg(long)]
+        fetch_all: bool,
+        /// Fetch the full upstream history instead of only the latest commit
+        #[arg(long)]
+        full_history: bool,
+        /// Only report how many upstream commits are waiting, exiting 10 if there are any
+        #[arg(long)]
+        check: bool,
+        /// Show the upstream commits update would merge and the changes it would make,
+        /// without changing the repository
+        #[arg(long, conflicts_with = "check")]
+        dry_run: bool,
     },
     /// Remove devcontainer tracking and cleanup
     Remove {
         /// Keep devcontainer files when removing tracking
         #[arg(long)]
         keep_files: bool,
+        /// Stage the removal without committing it
+        #[arg(long)]
+        no_commit: bool,
+        /// Move devcontainer files matching this glob to .devcontainer.preserved/
+        /// instead of deleting them (repeatable)
+        #[arg(long, value_name = "GLOB", conflicts_with = "keep_files")]
+        preserve: Vec<String>,
+        /// Skip a remote, tracking branch or devcontainer that is already gone
+        #[arg(long)]
+        ignore_missing: bool,
+        /// Show the changes remove would make, without changing the repository
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Init if nothing is set up, update if upstream has moved on, otherwise do nothing
+    Ensure {
+        /// Remove firewall configurations from devcontainer files
+        #[arg(long)]
+        strip_firewall: bool,
+        /// Overwrite an existing .devcontainer on init without asking
+        #[arg(long, short = 'y')]
+        yes: bool,
+    },
+    /// Report whether devcontainer sync is set up and whether upstream has moved on
+    Status,
+    /// Diagnose git, repository and remote problems before filing a bug
+    Doctor,
+    /// Check that the upstream devcontainer still has the layout syncing and stripping expect
+    CheckUpstream,
+    /// Restore the devcontainer from the backup made by `update --backup`
+    Restore {
+        /// Copy the backup into place instead of moving it, so it survives
+        #[arg(long)]
+        keep_backup: bool,
+    },
+    /// Strip firewall configurations from an existing devcontainer, offline
+    StripFirewall {
+        /// Leave the stripped files uncommitted for review
+        #[arg(long)]
+        no_commit: bool,
+        /// Keep this package when stripping firewall packages (repeatable)
+        #[arg(long = "keep-package", alias = "exclude-package", value_name = "NAME")]
+        keep_packages: Vec<String>,
+        /// Also strip this package from apt install commands (repeatable)
+        #[arg(long = "extra-package", value_name = "NAME")]
+        extra_packages: Vec<String>,
+        /// Record the firewall strip commit in .git-blame-ignore-revs
+        #[arg(long)]
+        blame_ignore: bool,
+        /// Ask which detected firewall scripts to remove instead of removing them all
+        #[arg(long)]
+        select_scripts: bool,
+        /// Show the changes as a diff and ask before applying them
+        #[arg(long)]
+        preview_diff: bool,
+    },
+    /// Show devcontainer changes between two upstream refs, or between the local
+    /// devcontainer and the latest upstream
+    Diff {
+        /// Upstream branch, tag or commit to diff from (default: the local devcontainer)
+        #[arg(long, value_name = "REF")]
+        from: Option<String>,
+        /// Upstream branch, tag or commit to diff to (default: the upstream default branch)
+        #[arg(long, value_name = "REF")]
+        to: Option<String>,
+    },
+    /// List upstream devcontainer commits since a release, flagging firewall changes
+    Changelog {
+        /// Upstream release tag the local devcontainer was synced from
+        #[arg(long, value_name = "TAG")]
+        since_version: String,
+    },
+    /// List the firewall detection patterns in effect and where each comes from
+    ListPatterns,
+    /// Describe the built-in defaults: upstream, branches, prefix and what gets stripped
+    Info,
+    /// Print the git commands a command would run, as a shell script
+    Explain {
+        #[arg(value_enum)]
+        operation: OperationArg,
+    },
+    /// Run init, update or remove in every repository listed in a manifest
+    Batch {
+        #[arg(value_enum)]
+        operation: OperationArg,
+        /// File listing repository paths, one per line or as a TOML `repos` array
+        #[arg(long, value_name = "FILE")]
+        manifest: PathBuf,
+        /// With init or update, remove firewall configurations in each repository
+        #[arg(long)]
+        strip_firewall: bool,
     },
 }
 
-fn main() {
-    let cli = Cli::parse();
+impl Commands {
+    fn name(&self) -> &'static str {
+        match self {
+            Commands::Init { .. } => "init",
+            Commands::Update { .. } => "update",
+            Commands::Remove { .. } => "remove",
+            Commands::Ensure { .. } => "ensure",
+            Commands::Status => "status",
+            Commands::Doctor => "doctor",
+            Commands::CheckUpstream => "check-upstream",
+            Commands::Restore { .. } => "restore",
+            Commands::StripFirewall { .. } => "strip-firewall",
+            Commands::Diff { .. } => "diff",
+            Commands::Changelog { .. } => "changelog",
+            Commands::ListPatterns => "list-patterns",
+            Commands::Info => "info",
+            Commands::Explain { .. } => "explain",
+            Commands::Batch { .. } => "batch",
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OperationArg {
+    Init,
+    Update,
+    Remove,
+}
 
-    let app = CliApp::new(cli.verbose);
+impl From<OperationArg> for Operation {
+    fn from(operation: OperationArg) -> Self {
+        match operation {
+            OperationArg::Init => Operation::Init,
+            OperationArg::Update => Operation::Update,
+            OperationArg::Remove => Operation::Remove,
+        }
+    }
+}
 
-    let result = match cli.command {
-        Commands::Init { strip_firewall } => app.init(strip_firewall),
+/// Run the command, returning the exit status it finished with
+fn run(cli: &Cli) -> Result<i32, CliError> {
+    let mut app = CliApp::new(cli.verbose)
+        .with_format(cli.output_format())
+        .with_timeout(Duration::from_secs(cli.timeout))
+        .with_retries(cli.retries)
+        .with_no_progress(cli.no_progress)
+        .with_color(cli.color.into());
+    // Flags override the repository's config file, which overrides the built-in defaults.
+    // batch reads each listed repository's config file instead of this one.
+    let fork_url = cli.fork.as_deref().map(fork_repo_url).transpose()?;
+    let flags = SyncConfig {
+        repo_url: match &cli.repo_url {
+            Some(repo_url) => Some(validate_repo_url(repo_url)?),
+            None => fork_url,
+        },
+        remote_name: cli.remote_name.clone(),
+        tracking_branch: cli.tracking_branch.clone(),
+        prefix: cli
+            .prefix
+            .as_deref()
+            .map(|prefix| validate_prefix(prefix, "--prefix"))
+            .transpose()?,
+        default_branch: None,
+    };
+    let config = match cli.command {
+        Commands::Batch { .. } => SyncConfig::default(),
+        _ => load_config(app.working_dir())?,
+    };
+    app = app.with_config(&config).with_config(&flags);
+    if let Some(token) = cli
+        .token
+        .clone()
+        .or_else(|| std::env::var(TOKEN_ENV_VAR).ok())
+        .filter(|token| !token.is_empty())
+    {
+        app = app.with_token(token);
+    }
+    if let Some(git_path) = &cli.git_path {
+        app = app.with_git_path(git_path.clone());
+    }
+    if let Some(upstream_prefix) = &cli.upstream_prefix {
+        app = app.with_upstream_prefix(validate_prefix(upstream_prefix, "--upstream-prefix")?);
+    }
+    if let Some(work_branch) = &cli.work_branch {
+        app = app.with_work_branch(work_branch.clone());
+    }
+    // explain and info only print, so they work even where git is missing, and doctor
+    // reports a missing git itself
+    if !matches!(
+        cli.command,
+        Commands::Explain { .. } | Commands::Info | Commands::Doctor
+    ) {
+        app.ensure_git_available()?;
+    }
+
+    match &cli.command {
+        Commands::Init {
+            check: true,
+            allow_dirty,
+            ..
+        } => app.with_allow_dirty(*allow_dirty).check_init(),
+        Commands::Init {
+            strip_firewall,
+            keep_packages,
+            extra_packages,
+            blame_ignore,
+            subtree_message,
+            fetch_all,
+            full_history,
+            select_scripts,
+            preview_diff,
+            yes,
+            force,
+            allow_dirty,
+            autostash,
+            dry_run,
+            ..
+        } => app
+            .with_dry_run(*dry_run)
+            .with_autostash(*autostash)
+            .with_select_scripts(*select_scripts)
+            .with_preview_diff(*preview_diff)
+            .with_allow_dirty(*allow_dirty)
+            .with_keep_packages(keep_packages.clone())
+            .with_extra_packages(extra_packages.clone())
+            .with_blame_ignore(*blame_ignore)
+            .with_subtree_message(subtree_message.clone())
+            .with_fetch_all(*fetch_all)
+            .with_full_history(*full_history)
+            .run_init(*strip_firewall, *yes, *force)
+            .map(|result| print_result("init", &result, cli.output_format())),
         Commands::Update {
             backup,
             force,
             strip_firewall,
-        } => app.update(backup, force, strip_firewall),
-        Commands::Remove { keep_files } => app.remove(keep_files),
+            incremental_strip,
+            select_scripts,
+            preview_diff,
+            locked,
+            migrate_prefix,
+            reinit_subtree,
+            keep_packages,
+            extra_packages,
+            blame_ignore,
+            subtree_message,
+            fetch_all,
+            full_history,
+            allow_dirty,
+            autostash,
+            check,
+            dry_run,
+        } => {
+            let migrate_prefix = migrate_prefix
+                .as_deref()
+                .map(|prefix| validate_prefix(prefix, "--migrate-prefix"))
+                .transpose()?;
+            let result = app
+                .with_allow_dirty(*allow_dirty)
+                .with_autostash(*autostash)
+                .with_keep_packages(keep_packages.clone())
+                .with_extra_packages(extra_packages.clone())
+                .with_blame_ignore(*blame_ignore)
+                .with_subtree_message(subtree_message.clone())
+                .with_fetch_all(*fetch_all)
+                .with_full_history(*full_history)
+                .with_incremental_strip(*incremental_strip)
+                .with_select_scripts(*select_scripts)
+                .with_preview_diff(*preview_diff)
+                .with_check_updates(*check)
+                .with_dry_run(*dry_run)
+                .run_update(
+                    *backup,
+                    *force,
+                    *strip_firewall,
+                    *locked,
+                    migrate_prefix.as_deref(),
+                    *reinit_subtree,
+                )?;
+            print_result("update", &result, cli.output_format());
+            // A check succeeds either way, but its exit status tells scripts whether
+            // there is anything to sync
+            return Ok(match result.upstream_commits {
+                Some(behind) if behind > 0 => UPDATES_AVAILABLE_EXIT_CODE,
+                _ => 0,
+            });
+        }
+        Commands::Remove {
+            keep_files,
+            no_commit,
+            preserve,
+            ignore_missing,
+            dry_run,
+        } => app
+            .with_ignore_missing(*ignore_missing)
+            .with_dry_run(*dry_run)
+            .run_remove(*keep_files, *no_commit, preserve)
+            .map(|result| print_result("remove", &result, cli.output_format())),
+        Commands::Ensure {
+            strip_firewall,
+            yes,
+        } => app.ensure(*strip_firewall, *yes).map(|_| ()),
+        Commands::Status => app.status(),
+        Commands::Doctor => app.doctor(),
+        Commands::CheckUpstream => app.check_upstream(),
+        Commands::Restore { keep_backup } => app.restore(*keep_backup),
+        Commands::StripFirewall {
+            no_commit,
+            keep_packages,
+            extra_packages,
+            blame_ignore,
+            select_scripts,
+            preview_diff,
+        } => app
+            .with_keep_packages(keep_packages.clone())
+            .with_extra_packages(extra_packages.clone())
+            .with_blame_ignore(*blame_ignore)
+            .with_select_scripts(*select_scripts)
+            .with_preview_diff(*preview_diff)
+            .strip_firewall(*no_commit),
+        Commands::Diff { from, to } => app.diff(from.as_deref(), to.as_deref()),
+        Commands::Changelog { since_version } => app.changelog(since_version),
+        Commands::ListPatterns => app.list_patterns(),
+        Commands::Info => app.info(),
+        Commands::Explain { operation } => app.explain((*operation).into()),
+        Commands::Batch {
+            operation,
+            manifest,
+            strip_firewall,
+        } => {
+            let result = app.batch(manifest, (*operation).into(), *strip_firewall, &flags)?;
+            // The summary already reported each failure
+            return Ok(result.errors.first().map_or(0, CliError::exit_code));
+        }
+    }
+    .map(|()| 0)
+}
+
+/// Send log records to stdout as plain lines, so verbose output reads the same as
+/// before. Warnings show by default, `--verbose` adds debug detail, and `RUST_LOG`
+/// overrides both, e.g. `RUST_LOG=devcontainer_sync=trace` shows every git command.
+/// Machine-readable formats keep stdout for themselves, so their logs go to stderr
+/// and `--verbose` is ignored as it is for the command context.
+fn init_logging(verbose: bool, format: OutputFormat) {
+    let text = format == OutputFormat::Text;
+    let default_filter = if verbose && text { "debug" } else { "warn" };
+    let target = if text {
+        env_logger::Target::Stdout
+    } else {
+        env_logger::Target::Stderr
     };
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(default_filter))
+        .target(target)
+        .write_style(env_logger::WriteStyle::Never)
+        .format(|buf, record| writeln!(buf, "{}", record.args()))
+        .init();
+}
+
+fn main() {
+    let cli = Cli::parse();
+    init_logging(cli.verbose, cli.output_format());
+
+    // Ctrl-C stops the command at its current or next git command, so it fails with an
+    // interrupted error and restores the branch, stash or half-made init on the way out.
+    // A second Ctrl-C before that happens (while waiting at a prompt, say) exits at once.
+    let _ = ctrlc::set_handler(|| {
+        if !request_interrupt() {
+            process::exit(INTERRUPTED_EXIT_CODE);
+        }
+    });
 
-    match result {
-        Ok(_) => process::exit(0),
+    match run(&cli) {
+        Ok(code) => process::exit(code),
         Err(e) => {
+            let e = if was_interrupted() {
+                CliError::interrupted_by_user(e)
+            } else {
+                e
+            };
+            match cli.output_format() {
+                OutputFormat::Ndjson => println!(
+                    "{}",
+                    serde_json::json!({
+                        "event": "result",
+                        "success": false,
+                        "error": e.to_string(),
+                        "exit_code": e.exit_code(),
+                    })
+                ),
+                OutputFormat::Porcelain => println!(
+                    "{}",
+                    porcelain_record(cli.command.name(), "ERR", &[&e.exit_code().to_string()])
+                ),
+                OutputFormat::Json | OutputFormat::Text | OutputFormat::Silent => {}
+            }
             eprintln!("Error: {}", e);
             if cli.verbose {
                 eprintln!("Suggestion: {}", e.suggestion());
             }
-            process::exit(e.exit_code());
+            let exit_code = e.exit_code();
+            if cli.output_format() == OutputFormat::Json {
+                let name = cli.command.name();
+                let result = OperationResult::failure(format!("{} failed", name), e);
+                println!("{}", json_result(name, &result));
+            }
+            process::exit(exit_code);
         }
     }
 }
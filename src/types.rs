@@ -1,7 +1,49 @@
+use crate::customizer::FirewallRemovalResult;
 use crate::error::CliError;
+use serde::{Serialize, Serializer};
+use std::fmt;
 use std::path::PathBuf;
 use std::time::Duration;
 
+/// How command progress is reported on stdout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// Human-readable step lines and summaries
+    #[default]
+    Text,
+    /// One JSON event object per line, for CI dashboards
+    Ndjson,
+    /// A single stable, space-separated result record per command, for shell scripts
+    Porcelain,
+    /// A single JSON object describing the command's result, printed when it finishes
+    Json,
+    /// Nothing at all; library callers render the returned result themselves
+    Silent,
+}
+
+/// When human-readable output is colored, following git and cargo's `--color`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorChoice {
+    Always,
+    /// Color a terminal, unless `NO_COLOR` is set
+    #[default]
+    Auto,
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether to color output written to a stream that is or isn't a terminal
+    pub fn enabled(&self, is_terminal: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                is_terminal && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+            }
+        }
+    }
+}
+
 /// Context for command execution
 #[derive(Debug, Clone)]
 pub struct CommandContext {
@@ -9,6 +51,56 @@ pub struct CommandContext {
     pub verbose: bool,
     pub strip_firewall: bool,
     pub timeout: Duration,
+    /// Times a fetch that failed on the network is retried
+    pub retries: u32,
+    /// Wait before the first fetch retry; each later retry waits twice as long
+    pub retry_delay: Duration,
+    pub repo_url: String,
+    pub keep_packages: Vec<String>,
+    pub extra_packages: Vec<String>,
+    pub git_path: PathBuf,
+    /// Token for an HTTPS upstream, sent as a header rather than stored in the remote URL
+    pub token: Option<String>,
+    pub prefix: String,
+    /// Directory in the upstream repository the devcontainer is synced from
+    pub upstream_prefix: String,
+    pub format: OutputFormat,
+    /// Leave out the per-step progress lines of text output, keeping the summary
+    pub no_progress: bool,
+    pub color: ColorChoice,
+    pub blame_ignore: bool,
+    /// Commit message for the subtree add/merge, instead of git's generated one
+    pub subtree_message: Option<String>,
+    /// Fetch every upstream ref instead of only the branch being synced
+    pub fetch_all: bool,
+    /// Fetch upstream history in full rather than only the tip commit
+    pub full_history: bool,
+    /// On update, strip only the files the merge changed rather than the whole directory
+    pub incremental_strip: bool,
+    /// On remove, skip a missing remote, tracking branch or devcontainer instead of failing
+    pub ignore_missing: bool,
+    /// On update, only report how many upstream commits are waiting, changing nothing
+    pub check_updates: bool,
+    /// Running one of the `--dry-run` previews, which only print what they would do
+    pub dry_run: bool,
+    /// The user's home directory, which init refuses to sync into without --force
+    pub home_dir: Option<PathBuf>,
+    /// Ask which detected firewall scripts to remove instead of removing them all
+    pub select_scripts: bool,
+    /// Show the strip changes as a diff and ask before applying them
+    pub preview_diff: bool,
+    /// Branch that tracks the upstream repository
+    pub tracking_branch: String,
+    /// Branch the devcontainer subtree is split into
+    pub work_branch: String,
+    /// Let init and update run with uncommitted changes elsewhere in the working tree
+    pub allow_dirty: bool,
+    /// Stash uncommitted changes while init and update run, and restore them afterwards
+    pub autostash: bool,
+    /// Name of the git remote pointing at the upstream repository
+    pub remote_name: String,
+    /// Upstream branch to sync; when unset, the upstream's default branch is looked up
+    pub upstream_branch: Option<String>,
 }
 
 impl CommandContext {
@@ -18,23 +110,347 @@ impl CommandContext {
             verbose,
             strip_firewall: false,
             timeout: crate::config::default_timeout(),
+            retries: crate::config::DEFAULT_FETCH_RETRIES,
+            retry_delay: crate::config::FETCH_RETRY_DELAY,
+            repo_url: crate::config::CLAUDE_REPO_URL.to_string(),
+            keep_packages: Vec::new(),
+            extra_packages: Vec::new(),
+            git_path: PathBuf::from(crate::config::DEFAULT_GIT_PROGRAM),
+            token: None,
+            prefix: crate::config::DEVCONTAINER_PREFIX.to_string(),
+            upstream_prefix: crate::config::DEVCONTAINER_PREFIX.to_string(),
+            format: OutputFormat::default(),
+            no_progress: false,
+            color: ColorChoice::Auto,
+            blame_ignore: false,
+            subtree_message: None,
+            fetch_all: false,
+            full_history: false,
+            incremental_strip: false,
+            check_updates: false,
+            dry_run: false,
+            ignore_missing: false,
+            home_dir: std::env::var_os("HOME")
+                .or_else(|| std::env::var_os("USERPROFILE"))
+                .map(PathBuf::from),
+            select_scripts: false,
+            preview_diff: false,
+            tracking_branch: crate::config::CLAUDE_BRANCH_NAME.to_string(),
+            work_branch: crate::config::DEVCONTAINER_BRANCH.to_string(),
+            allow_dirty: false,
+            autostash: false,
+            remote_name: crate::config::CLAUDE_REMOTE_NAME.to_string(),
+            upstream_branch: None,
         }
     }
 
+    /// Branch `update` splits the refreshed subtree into, derived from the work branch
+    pub fn updated_branch(&self) -> String {
+        format!("{}-updated", self.work_branch)
+    }
+
+    pub fn with_repo_url(mut self, repo_url: String) -> Self {
+        self.repo_url = repo_url;
+        self
+    }
+
+    pub fn with_keep_packages(mut self, keep_packages: Vec<String>) -> Self {
+        self.keep_packages = keep_packages;
+        self
+    }
+
+    pub fn with_extra_packages(mut self, extra_packages: Vec<String>) -> Self {
+        self.extra_packages = extra_packages;
+        self
+    }
+
+    pub fn with_git_path(mut self, git_path: PathBuf) -> Self {
+        self.git_path = git_path;
+        self
+    }
+
+    pub fn with_token(mut self, token: String) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    pub fn with_prefix(mut self, prefix: String) -> Self {
+        self.prefix = prefix;
+        self
+    }
+
+    pub fn with_upstream_prefix(mut self, upstream_prefix: String) -> Self {
+        self.upstream_prefix = upstream_prefix;
+        self
+    }
+
+    /// Machine-readable formats also silence verbose output so stdout stays parseable
+    pub fn with_format(mut self, format: OutputFormat) -> Self {
+        self.format = format;
+        if format != OutputFormat::Text {
+            self.verbose = false;
+        }
+        self
+    }
+
     pub fn with_strip_firewall(mut self, strip_firewall: bool) -> Self {
         self.strip_firewall = strip_firewall;
         self
     }
+
+    pub fn with_blame_ignore(mut self, blame_ignore: bool) -> Self {
+        self.blame_ignore = blame_ignore;
+        self
+    }
+
+    pub fn with_fetch_all(mut self, fetch_all: bool) -> Self {
+        self.fetch_all = fetch_all;
+        self
+    }
+
+    pub fn with_full_history(mut self, full_history: bool) -> Self {
+        self.full_history = full_history;
+        self
+    }
+
+    pub fn with_subtree_message(mut self, subtree_message: Option<String>) -> Self {
+        self.subtree_message = subtree_message;
+        self
+    }
+
+    pub fn with_incremental_strip(mut self, incremental_strip: bool) -> Self {
+        self.incremental_strip = incremental_strip;
+        self
+    }
+
+    pub fn with_ignore_missing(mut self, ignore_missing: bool) -> Self {
+        self.ignore_missing = ignore_missing;
+        self
+    }
+
+    pub fn with_check_updates(mut self, check_updates: bool) -> Self {
+        self.check_updates = check_updates;
+        self
+    }
+
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn with_select_scripts(mut self, select_scripts: bool) -> Self {
+        self.select_scripts = select_scripts;
+        self
+    }
+
+    pub fn with_preview_diff(mut self, preview_diff: bool) -> Self {
+        self.preview_diff = preview_diff;
+        self
+    }
+
+    pub fn with_tracking_branch(mut self, tracking_branch: String) -> Self {
+        self.tracking_branch = tracking_branch;
+        self
+    }
+
+    pub fn with_work_branch(mut self, work_branch: String) -> Self {
+        self.work_branch = work_branch;
+        self
+    }
+
+    pub fn with_allow_dirty(mut self, allow_dirty: bool) -> Self {
+        self.allow_dirty = allow_dirty;
+        self
+    }
+
+    pub fn with_autostash(mut self, autostash: bool) -> Self {
+        self.autostash = autostash;
+        self
+    }
+
+    pub fn with_no_progress(mut self, no_progress: bool) -> Self {
+        self.no_progress = no_progress;
+        self
+    }
+
+    pub fn with_color(mut self, color: ColorChoice) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_remote_name(mut self, remote_name: String) -> Self {
+        self.remote_name = remote_name;
+        self
+    }
+
+    pub fn with_upstream_branch(mut self, upstream_branch: String) -> Self {
+        self.upstream_branch = Some(upstream_branch);
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    pub fn with_retry_delay(mut self, retry_delay: Duration) -> Self {
+        self.retry_delay = retry_delay;
+        self
+    }
+}
+
+/// How serious a reported warning is, ordered from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warn,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Severity::Info => "info",
+            Severity::Warn => "warning",
+            Severity::Error => "error",
+        };
+        f.write_str(label)
+    }
+}
+
+/// A warning with a stable machine-readable code, so consumers can filter by severity
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub severity: Severity,
+    pub code: String,
+    pub message: String,
+}
+
+impl Warning {
+    pub fn new(severity: Severity, code: &str, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            code: code.to_string(),
+            message: message.into(),
+        }
+    }
+
+    pub fn info(code: &str, message: impl Into<String>) -> Self {
+        Self::new(Severity::Info, code, message)
+    }
+
+    pub fn warn(code: &str, message: impl Into<String>) -> Self {
+        Self::new(Severity::Warn, code, message)
+    }
+
+    pub fn error(code: &str, message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, code, message)
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Everything a successful `init` produced, for the CLI to render and libraries to consume
+#[derive(Debug, Clone)]
+pub struct InitReport {
+    /// Whether the upstream remote was added by this run
+    pub remote_added: bool,
+    /// Local branch tracking the upstream default branch
+    pub tracking_branch: String,
+    /// Commit produced by `git subtree split`, which the subtree was added from
+    pub subtree_commit: String,
+    /// Directory the devcontainer files were added to
+    pub devcontainer_path: PathBuf,
+    /// Firewall stripping outcome, when `--strip-firewall` was requested and succeeded
+    pub firewall: Option<FirewallRemovalResult>,
+}
+
+/// What `update` changed, so CI can tell a substantive update from a no-op
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct UpdateSummary {
+    /// Upstream commit the tracking branch pointed at before the update
+    pub old_upstream: String,
+    /// Upstream commit it points at now
+    pub new_upstream: String,
+    /// Devcontainer files the merge added, modified and deleted, relative to the repository root
+    pub added: Vec<String>,
+    pub modified: Vec<String>,
+    pub deleted: Vec<String>,
+    /// Whether the merge conflicted and `--force` took the upstream version
+    pub conflict: bool,
 }
 
 /// Result of a command operation
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct OperationResult {
     pub success: bool,
     pub message: String,
     pub changes: Vec<String>,
     pub warnings: Vec<String>,
+    #[serde(serialize_with = "serialize_errors")]
     pub errors: Vec<CliError>,
+    /// Set by `update`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update: Option<UpdateSummary>,
+    /// Set by `init` and `update`: the branch tracking upstream and the subtree split
+    /// commit now synced
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tracking_branch: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subtree_split: Option<String>,
+    /// Set by a dry run: its plan, which `changes` also lists line by line
+    #[serde(skip)]
+    pub plan: Option<DryRunPlan>,
+    /// Set by `update --check`: upstream commits since the last sync
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upstream_commits: Option<usize>,
+    /// Set by `batch`: each repository's own result, in manifest order
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub repos: Vec<BatchEntry>,
+}
+
+/// What a dry run would do: the commands in order, then the firewall strip it would commit
+#[derive(Debug, Default)]
+pub struct DryRunPlan {
+    pub planned: Vec<String>,
+    pub strip: Option<StripPreview>,
+}
+
+/// The firewall strip a dry run would commit
+#[derive(Debug)]
+pub struct StripPreview {
+    /// The files it would change, relative to the repository
+    pub files: Vec<String>,
+    /// The commit message, with its list of changes, or `None` when nothing would be stripped
+    pub message: Option<String>,
+}
+
+/// One repository's result in a `batch` run
+#[derive(Debug, Serialize)]
+pub struct BatchEntry {
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub result: OperationResult,
+}
+
+/// Errors serialize as their message, suggestion and exit code
+fn serialize_errors<S: Serializer>(errors: &[CliError], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(errors.iter().map(|error| {
+        serde_json::json!({
+            "message": error.to_string(),
+            "suggestion": error.suggestion(),
+            "exit_code": error.exit_code(),
+        })
+    }))
 }
 
 impl OperationResult {
@@ -45,6 +461,12 @@ impl OperationResult {
             changes: Vec::new(),
             warnings: Vec::new(),
             errors: Vec::new(),
+            update: None,
+            tracking_branch: None,
+            subtree_split: None,
+            plan: None,
+            upstream_commits: None,
+            repos: Vec::new(),
         }
     }
 
@@ -55,6 +477,12 @@ impl OperationResult {
             changes: Vec::new(),
             warnings: Vec::new(),
             errors: vec![error],
+            update: None,
+            tracking_branch: None,
+            subtree_split: None,
+            plan: None,
+            upstream_commits: None,
+            repos: Vec::new(),
         }
     }
 
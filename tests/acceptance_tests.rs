@@ -118,6 +118,108 @@ fn temp_git_repo_with_commits() -> (TempDir, PathBuf) {
     (temp_dir, path)
 }
 
+/// Fixture that creates a local stand-in for the Claude Code repository, with a
+/// `.devcontainer` on `main` that carries the same firewall setup as upstream
+#[fixture]
+fn fixture_upstream() -> (TempDir, PathBuf) {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let path = temp_dir.path().to_path_buf();
+
+    run_git(&path, &["init", "--initial-branch=main"]);
+    run_git(&path, &["config", "user.name", "Upstream User"]);
+    run_git(&path, &["config", "user.email", "upstream@example.com"]);
+
+    write_firewall_devcontainer(&path);
+    std::fs::write(path.join("README.md"), "# Claude Code\n").expect("Failed to write README");
+
+    run_git(&path, &["add", "."]);
+    run_git(&path, &["commit", "-m", "Add devcontainer"]);
+
+    (temp_dir, path)
+}
+
+/// Write a `.devcontainer` under `dir` with the same firewall setup as upstream
+fn write_firewall_devcontainer(dir: &Path) {
+    let devcontainer = dir.join(".devcontainer");
+    std::fs::create_dir_all(&devcontainer).expect("Failed to create .devcontainer");
+    std::fs::write(
+        devcontainer.join("devcontainer.json"),
+        r#"{
+  "name": "Claude Code Sandbox",
+  "build": {
+    "dockerfile": "Dockerfile"
+  },
+  "runArgs": [
+    "--cap-add=NET_ADMIN",
+    "--cap-add=NET_RAW"
+  ],
+  "customizations": {
+    "vscode": {
+      "extensions": ["dbaeumer.vscode-eslint"]
+    }
+  },
+  "remoteUser": "node",
+  "postStartCommand": "sudo /usr/local/bin/init-firewall.sh",
+  "waitFor": "postStartCommand"
+}
+"#,
+    )
+    .expect("Failed to write devcontainer.json");
+    std::fs::write(
+        devcontainer.join("Dockerfile"),
+        r#"FROM node:20
+
+RUN apt-get update && apt-get install -y --no-install-recommends \
+  less \
+  git \
+  iptables \
+  ipset \
+  iproute2 \
+  dnsutils \
+  aggregate \
+  jq \
+  && apt-get clean
+
+# Copy and set up firewall script
+COPY init-firewall.sh /usr/local/bin/
+USER root
+RUN chmod +x /usr/local/bin/init-firewall.sh && \
+  echo "node ALL=(root) NOPASSWD: /usr/local/bin/init-firewall.sh" > /etc/sudoers.d/node-firewall
+USER node
+"#,
+    )
+    .expect("Failed to write Dockerfile");
+    std::fs::write(
+        devcontainer.join("init-firewall.sh"),
+        "#!/bin/bash\nset -euo pipefail\niptables -F\nipset create allowed-domains hash:net\n",
+    )
+    .expect("Failed to write init-firewall.sh");
+}
+
+/// Fixture that creates a git repository with commits whose Claude Code remote
+/// URL is transparently redirected to a local fixture upstream
+#[fixture]
+fn temp_git_repo_with_fixture_upstream(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    fixture_upstream: (TempDir, PathBuf),
+) -> FixtureRepo {
+    let (repo_dir, path) = temp_git_repo_with_commits;
+    let (upstream_dir, upstream) = fixture_upstream;
+
+    redirect_upstream(
+        &path,
+        "https://github.com/anthropics/claude-code.git",
+        &upstream,
+    );
+
+    FixtureRepo {
+        _repo_dir: repo_dir,
+        path,
+        _upstream_dir: upstream_dir,
+        upstream,
+    }
+}
+
 /// Fixture that compiles the binary and returns its path
 #[fixture]
 fn compiled_binary() -> PathBuf {
@@ -170,6 +272,83 @@ pub fn run_command(binary_path: &Path, args: &[&str], working_dir: &Path) -> Com
     }
 }
 
+/// Like `run_command`, but with `input` written to the child's stdin
+pub fn run_command_with_input(
+    binary_path: &Path,
+    args: &[&str],
+    working_dir: &Path,
+    input: &str,
+) -> CommandResult {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let mut child = Command::new(binary_path)
+        .args(args)
+        .current_dir(working_dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute command");
+    child
+        .stdin
+        .take()
+        .expect("Failed to open stdin")
+        .write_all(input.as_bytes())
+        .expect("Failed to write stdin");
+    let output = child
+        .wait_with_output()
+        .expect("Failed to wait for command");
+
+    CommandResult {
+        exit_code: output.status.code().unwrap_or(-1),
+        stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+        stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+    }
+}
+
+/// A consumer repository paired with the local upstream its remote resolves to
+pub struct FixtureRepo {
+    _repo_dir: TempDir,
+    pub path: PathBuf,
+    _upstream_dir: TempDir,
+    pub upstream: PathBuf,
+}
+
+/// Commit a change to a file in the fixture upstream, returning the new commit SHA
+pub fn commit_upstream_change(upstream: &Path, file: &str, content: &str) -> String {
+    std::fs::write(upstream.join(file), content).expect("Failed to write upstream file");
+    run_git(upstream, &["add", "."]);
+    run_git(upstream, &["commit", "-m", &format!("Update {}", file)]);
+    run_git(upstream, &["rev-parse", "HEAD"]).trim().to_string()
+}
+
+/// Helper function to run git in a directory, panicking on failure
+pub fn run_git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .expect("Failed to run git");
+
+    if !output.status.success() {
+        panic!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+/// Rewrite fetches of `url` in `repo` to the local `upstream` repository, so
+/// tests exercise the real remote URL without network access
+pub fn redirect_upstream(repo: &Path, url: &str, upstream: &Path) {
+    let key = format!("url.{}.insteadOf", upstream.display());
+    run_git(repo, &["config", "--add", &key, url]);
+}
+
 /// Additional assertion helpers for more readable tests
 impl CommandResult {
     pub fn should_succeed(&self) -> &Self {
@@ -292,6 +471,22 @@ fn should_show_minimal_output_without_verbose_flag(
     assert!(!result.stdout.contains("Adding Claude Code remote..."));
 }
 
+#[rstest]
+fn should_hide_step_progress_with_no_progress_flag(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+
+    let result = run_command(&compiled_binary, &["init", "--no-progress"], &repo.path);
+
+    result
+        .should_succeed()
+        .should_contain_in_stdout("Successfully initialized devcontainer sync!");
+    assert!(!result.stdout.contains("Fetching repository..."));
+    assert!(!result.stdout.contains("✓"));
+}
+
 #[rstest]
 fn should_show_detailed_output_with_verbose_flag(
     temp_git_repo_with_commits: (TempDir, PathBuf),
@@ -591,6 +786,58 @@ fn should_show_version_information(
     result.should_contain_in_stdout("devcontainer-sync");
 }
 
+#[rstest]
+fn should_report_closed_stdin_at_overwrite_prompt(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, repo_path) = temp_git_repo_with_commits;
+    std::fs::create_dir(repo_path.join(".devcontainer")).expect("Failed to create .devcontainer");
+
+    // run_command gives the child an already-closed stdin
+    let result = run_command(&compiled_binary, &["init", "--verbose"], &repo_path);
+
+    result.should_fail();
+    result.should_contain_in_stderr("confirm overwriting .devcontainer (stdin is closed)");
+    result.should_contain_in_stderr("--yes");
+    assert_that(&result.stderr.contains("cancelled by user")).is_false();
+}
+
+#[rstest]
+fn should_name_the_prefix_when_stdin_is_closed_at_overwrite_prompt(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, repo_path) = temp_git_repo_with_commits;
+    std::fs::create_dir_all(repo_path.join("tools/devcontainer"))
+        .expect("Failed to create tools/devcontainer");
+
+    let result = run_command(
+        &compiled_binary,
+        &["init", "--prefix", "tools/devcontainer"],
+        &repo_path,
+    );
+
+    result.should_fail();
+    result.should_contain_in_stderr("confirm overwriting tools/devcontainer (stdin is closed)");
+}
+
+#[rstest]
+fn should_show_overwrite_prompt_on_stderr_when_stdout_is_redirected(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, repo_path) = temp_git_repo_with_commits;
+    std::fs::create_dir(repo_path.join(".devcontainer")).expect("Failed to create .devcontainer");
+
+    let result = run_command_with_input(&compiled_binary, &["init"], &repo_path, "n\n");
+
+    result.should_fail();
+    result.should_contain_in_stderr("Continue? (y/N)");
+    result.should_not_contain_in_stdout("Continue?");
+    result.should_contain_in_stderr("cancelled by user");
+}
+
 // Firewall stripping acceptance tests
 
 #[rstest]
@@ -781,3 +1028,2216 @@ fn should_handle_graceful_degradation_with_partial_patterns(
     assert_that(&json_path.exists()).is_true();
     assert_that(&dockerfile_path.exists()).is_true();
 }
+
+// Upstream fork tests
+
+#[rstest]
+fn should_use_fork_shorthand_for_remote_url(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    fixture_upstream: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, repo_path) = temp_git_repo_with_commits;
+    let (_upstream_dir, upstream_path) = fixture_upstream;
+    redirect_upstream(
+        &repo_path,
+        "https://github.com/myorg/claude-code.git",
+        &upstream_path,
+    );
+
+    let result = run_command(&compiled_binary, &["init", "--fork", "myorg"], &repo_path);
+    result
+        .should_succeed()
+        .should_contain_in_stdout("https://github.com/myorg/claude-code.git");
+
+    let remote_url = run_git(&repo_path, &["config", "--get", "remote.claude.url"]);
+    assert_that(&remote_url.trim()).is_equal_to("https://github.com/myorg/claude-code.git");
+    assert_that(&repo_path.join(".devcontainer/devcontainer.json").exists()).is_true();
+}
+
+#[rstest]
+fn should_reject_fork_names_that_look_like_paths(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, repo_path) = temp_git_repo_with_commits;
+
+    let result = run_command(
+        &compiled_binary,
+        &["init", "--fork", "myorg/other"],
+        &repo_path,
+    );
+
+    result
+        .should_fail()
+        .should_contain_in_stderr("Invalid fork organization name");
+}
+
+#[rstest]
+fn should_sync_from_custom_repo_url(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    fixture_upstream: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, repo_path) = temp_git_repo_with_commits;
+    let (_upstream_dir, upstream_path) = fixture_upstream;
+    let repo_url = format!("file://{}", upstream_path.display());
+
+    let result = run_command(
+        &compiled_binary,
+        &["init", "--repo-url", &repo_url],
+        &repo_path,
+    );
+    result.should_succeed();
+
+    let remote_url = run_git(&repo_path, &["config", "--get", "remote.claude.url"]);
+    assert_that(&remote_url.trim()).is_equal_to(repo_url.as_str());
+    assert_that(&repo_path.join(".devcontainer/devcontainer.json").exists()).is_true();
+}
+
+#[rstest]
+fn should_reject_repo_url_without_scheme(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, repo_path) = temp_git_repo_with_commits;
+
+    let result = run_command(
+        &compiled_binary,
+        &["init", "--repo-url", "github.com/myorg/claude-code"],
+        &repo_path,
+    );
+
+    result
+        .should_fail()
+        .should_contain_in_stderr("Invalid repository URL");
+    assert_that(&repo_path.join(".devcontainer").exists()).is_false();
+}
+
+#[rstest]
+fn should_report_missing_git_binary(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, repo_path) = temp_git_repo_with_commits;
+
+    let result = run_command(
+        &compiled_binary,
+        &["init", "--git-path", "/nonexistent/bin/git"],
+        &repo_path,
+    );
+
+    result
+        .should_fail()
+        .should_contain_in_stderr("git not found");
+}
+
+// Explain tests
+
+#[rstest]
+fn should_explain_init_as_ordered_git_commands(compiled_binary: PathBuf) {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+
+    let result = run_command(
+        &compiled_binary,
+        &["explain", "init", "--fork", "myorg"],
+        temp_dir.path(),
+    );
+    result.should_succeed();
+
+    let expected = [
+        "git remote add claude https://github.com/myorg/claude-code.git",
+        "git fetch --depth=1 claude",
+        "git branch -f claude-main refs/remotes/claude/main",
+        "git checkout claude-main",
+        "git subtree split --prefix=.devcontainer -b devcontainer",
+        "git checkout -\n",
+        "git subtree add --prefix=.devcontainer --squash devcontainer",
+    ];
+    let positions: Vec<usize> = expected
+        .iter()
+        .map(|command| {
+            result
+                .stdout
+                .find(command)
+                .unwrap_or_else(|| panic!("Missing '{}' in:\n{}", command, result.stdout))
+        })
+        .collect();
+    let mut sorted = positions.clone();
+    sorted.sort();
+    assert_that(&positions).is_equal_to(sorted);
+
+    // Nothing was executed
+    assert_that(&temp_dir.path().join(".git").exists()).is_false();
+}
+
+// Init check tests
+
+#[rstest]
+fn should_pass_init_check_without_changing_repository(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    let head_before = run_git(&repo.path, &["rev-parse", "HEAD"]);
+
+    run_command(&compiled_binary, &["init", "--check"], &repo.path)
+        .should_succeed()
+        .should_contain_in_stdout("✓ upstream branch 'main' reachable")
+        .should_contain_in_stdout("All checks passed");
+
+    assert_that(&run_git(&repo.path, &["rev-parse", "HEAD"])).is_equal_to(&head_before);
+    assert_that(&run_git(&repo.path, &["remote"]).trim()).is_equal_to("");
+    assert_that(&repo.path.join(".devcontainer").exists()).is_false();
+}
+
+#[rstest]
+fn should_report_unreachable_remote_in_init_check(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, repo_path) = temp_git_repo_with_commits;
+    let missing_upstream = repo_path.join("no-such-upstream");
+    redirect_upstream(
+        &repo_path,
+        "https://github.com/anthropics/claude-code.git",
+        &missing_upstream,
+    );
+    let head_before = run_git(&repo_path, &["rev-parse", "HEAD"]);
+
+    let result = run_command(&compiled_binary, &["init", "--check"], &repo_path);
+
+    result
+        .should_fail()
+        .should_contain_in_stdout("✗ upstream reachable")
+        .should_contain_in_stderr("Network error");
+    assert_that(&result.exit_code).is_equal_to(2);
+
+    assert_that(&run_git(&repo_path, &["rev-parse", "HEAD"])).is_equal_to(&head_before);
+    assert_that(&run_git(&repo_path, &["remote"]).trim()).is_equal_to("");
+    assert_that(&run_git(&repo_path, &["branch", "--list"]).lines().count()).is_equal_to(1);
+    assert_that(&repo_path.join(".devcontainer").exists()).is_false();
+}
+
+// Doctor tests
+
+#[rstest]
+fn should_pass_doctor_and_report_advisory_problems(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, repo_path) = temp_git_repo_with_commits;
+    let missing_upstream = repo_path.join("no-such-upstream");
+    run_git(
+        &repo_path,
+        &[
+            "remote",
+            "add",
+            "claude",
+            missing_upstream.to_str().unwrap(),
+        ],
+    );
+    std::fs::write(repo_path.join("notes.txt"), "work in progress\n").unwrap();
+
+    let result = run_command(&compiled_binary, &["doctor"], &repo_path);
+
+    result
+        .should_succeed()
+        .should_contain_in_stdout("is installed and supported")
+        .should_contain_in_stdout("✓ inside a git repository")
+        .should_contain_in_stdout("✓ repository has commits")
+        .should_contain_in_stdout("✗ 'claude' remote is reachable (advisory)")
+        .should_contain_in_stdout("✗ working tree is clean (advisory): ")
+        .should_contain_in_stdout("notes.txt")
+        .should_contain_in_stdout("Commit or stash these changes");
+}
+
+#[rstest]
+fn should_fail_doctor_outside_a_git_repository(
+    temp_non_git_dir: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, dir_path) = temp_non_git_dir;
+
+    let result = run_command(&compiled_binary, &["doctor"], &dir_path);
+
+    result
+        .should_fail()
+        .should_contain_in_stdout("✗ inside a git repository")
+        .should_contain_in_stdout("1 required check(s) failed")
+        .should_contain_in_stderr("Current directory is not a git repository");
+    assert_that(
+        &result
+            .stdout
+            .contains("Current directory is not a git repository"),
+    )
+    .is_false();
+    assert_that(&result.stdout.contains("working tree is clean")).is_false();
+    assert_that(&result.exit_code).is_equal_to(1);
+}
+
+#[rstest]
+fn should_fail_doctor_when_git_is_missing(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, repo_path) = temp_git_repo_with_commits;
+    let missing_git = repo_path.join("no-such-git");
+
+    let result = run_command(
+        &compiled_binary,
+        &["doctor", "--git-path", missing_git.to_str().unwrap()],
+        &repo_path,
+    );
+
+    result
+        .should_fail()
+        .should_contain_in_stdout("✗ git is installed and supported\n")
+        .should_contain_in_stdout("Install git")
+        .should_contain_in_stderr("git not found");
+    // The error itself is printed once, by main on stderr
+    assert_that(&result.stdout.contains("git not found")).is_false();
+}
+
+// Lockfile tests
+
+#[rstest]
+fn should_write_lockfile_on_init_and_sync_it_with_locked_update(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    let initial_upstream = run_git(&repo.upstream, &["rev-parse", "HEAD"]);
+
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+
+    let lockfile_path = repo.path.join(".devcontainer-sync.lock");
+    let lockfile = std::fs::read_to_string(&lockfile_path).expect("Lockfile should exist");
+    let split = run_git(&repo.path, &["rev-parse", "devcontainer"]);
+    assert_that(&lockfile)
+        .contains(format!("upstream-commit: {}", initial_upstream.trim()).as_str());
+    assert_that(&lockfile).contains(format!("git-subtree-split: {}", split.trim()).as_str());
+
+    // The lockfile is committed so the working tree stays clean
+    let status = run_git(&repo.path, &["status", "--porcelain"]);
+    assert_that(&status.trim()).is_equal_to("");
+
+    // Upstream moves on, but a locked update stays on the recorded revision
+    commit_upstream_change(
+        &repo.upstream,
+        ".devcontainer/devcontainer.json",
+        r#"{"name": "Claude Code Sandbox v2"}"#,
+    );
+
+    run_command(&compiled_binary, &["update", "--locked"], &repo.path)
+        .should_succeed()
+        .should_contain_in_stdout("Merged locked upstream revision");
+
+    let json = std::fs::read_to_string(repo.path.join(".devcontainer/devcontainer.json")).unwrap();
+    assert_that(&json.contains("v2")).is_false();
+    let locked_tracking = run_git(&repo.path, &["rev-parse", "claude-main"]);
+    assert_that(&locked_tracking).is_equal_to(&initial_upstream);
+    assert_that(&std::fs::read_to_string(&lockfile_path).unwrap()).is_equal_to(&lockfile);
+
+    // A regular update picks up the new revision and refreshes the lockfile
+    run_command(&compiled_binary, &["update"], &repo.path).should_succeed();
+
+    let json = std::fs::read_to_string(repo.path.join(".devcontainer/devcontainer.json")).unwrap();
+    assert_that(&json.contains("v2")).is_true();
+    assert_that(&std::fs::read_to_string(&lockfile_path).unwrap()).is_not_equal_to(&lockfile);
+}
+
+#[rstest]
+fn should_fail_locked_update_without_lockfile(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, repo_path) = temp_git_repo_with_commits;
+
+    let result = run_command(&compiled_binary, &["update", "--locked"], &repo_path);
+
+    result
+        .should_fail()
+        .should_contain_in_stderr("No .devcontainer-sync.lock found");
+}
+
+// Firewall package selection tests
+
+#[rstest]
+fn should_keep_requested_package_when_stripping_firewall(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+
+    let result = run_command(
+        &compiled_binary,
+        &["init", "--strip-firewall", "--keep-package", "iproute2"],
+        &repo.path,
+    );
+    result.should_succeed();
+
+    let dockerfile = std::fs::read_to_string(repo.path.join(".devcontainer/Dockerfile")).unwrap();
+    assert_that(&dockerfile).contains("iproute2");
+    assert_that(&dockerfile.contains("iptables")).is_false();
+    assert_that(&dockerfile.contains("ipset")).is_false();
+    assert_that(&dockerfile.contains("dnsutils")).is_false();
+}
+
+// Prefix migration tests
+
+#[rstest]
+fn should_migrate_existing_subtree_to_new_prefix(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+
+    // Without migrating, the old copy is flagged
+    run_command(
+        &compiled_binary,
+        &["update", "--prefix", "tools/devcontainer"],
+        &repo.path,
+    )
+    .should_contain_in_stdout("a devcontainer subtree already exists at '.devcontainer'");
+
+    commit_upstream_change(
+        &repo.upstream,
+        ".devcontainer/devcontainer.json",
+        r#"{"name": "Claude Code Sandbox v2"}"#,
+    );
+
+    run_command(
+        &compiled_binary,
+        &[
+            "update",
+            "--prefix",
+            "tools/devcontainer",
+            "--migrate-prefix",
+            ".devcontainer",
+        ],
+        &repo.path,
+    )
+    .should_succeed()
+    .should_contain_in_stdout(
+        "Moved devcontainer subtree from .devcontainer to tools/devcontainer",
+    );
+
+    assert_that(&repo.path.join(".devcontainer").exists()).is_false();
+    assert_that(&repo.path.join("tools/devcontainer/Dockerfile").exists()).is_true();
+    let json =
+        std::fs::read_to_string(repo.path.join("tools/devcontainer/devcontainer.json")).unwrap();
+    assert_that(&json.contains("v2")).is_true();
+
+    let status = run_git(&repo.path, &["status", "--porcelain"]);
+    assert_that(&status.trim()).is_equal_to("");
+}
+
+// Custom prefix tests
+
+#[rstest]
+fn should_sync_from_non_standard_upstream_prefix(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    std::fs::create_dir_all(repo.upstream.join("tools")).unwrap();
+    run_git(
+        &repo.upstream,
+        &["mv", ".devcontainer", "tools/devcontainer"],
+    );
+    run_git(&repo.upstream, &["commit", "-m", "Move devcontainer"]);
+
+    run_command(
+        &compiled_binary,
+        &[
+            "init",
+            "--upstream-prefix",
+            "tools/devcontainer/",
+            "--prefix",
+            "ci/devcontainer",
+        ],
+        &repo.path,
+    )
+    .should_succeed();
+
+    assert_that(&repo.path.join("ci/devcontainer/devcontainer.json").exists()).is_true();
+    assert_that(&repo.path.join(".devcontainer").exists()).is_false();
+}
+
+#[rstest]
+#[case(&["--prefix", "../outside"])]
+#[case(&["--upstream-prefix", "/etc/devcontainer"])]
+fn should_reject_prefix_outside_repository(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+    #[case] flag: &[&str],
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    let mut args = vec!["init"];
+    args.extend_from_slice(flag);
+
+    let result = run_command(&compiled_binary, &args, &repo.path);
+
+    result
+        .should_fail()
+        .should_contain_in_stderr("must be a relative path inside the repository");
+    assert_that(&result.exit_code).is_equal_to(4);
+    assert_that(&run_git(&repo.path, &["remote"]).trim().is_empty()).is_true();
+}
+
+// Starting branch tests
+
+#[rstest]
+fn should_return_to_feature_branch_after_update(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_git(&repo.path, &["branch", "-m", "main"]);
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+    run_git(&repo.path, &["checkout", "-b", "feature/sandbox"]);
+
+    commit_upstream_change(
+        &repo.upstream,
+        ".devcontainer/devcontainer.json",
+        r#"{"name": "Claude Code Sandbox v2"}"#,
+    );
+    run_command(&compiled_binary, &["update"], &repo.path).should_succeed();
+
+    let current = run_git(&repo.path, &["symbolic-ref", "--short", "HEAD"]);
+    assert_that(&current.trim()).is_equal_to("feature/sandbox");
+    let json = std::fs::read_to_string(repo.path.join(".devcontainer/devcontainer.json")).unwrap();
+    assert_that(&json.contains("v2")).is_true();
+    let main_json = run_git(
+        &repo.path,
+        &["show", "main:.devcontainer/devcontainer.json"],
+    );
+    assert_that(&main_json.contains("v2")).is_false();
+}
+
+// Upstream default branch tests
+
+#[rstest]
+fn should_init_from_upstream_default_branch_with_slash(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_git(&repo.upstream, &["branch", "-m", "main", "release/v2"]);
+    let upstream_head = run_git(&repo.upstream, &["rev-parse", "HEAD"]);
+
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+
+    run_git(&repo.path, &["check-ref-format", "--branch", "claude-main"]);
+    assert_that(&run_git(&repo.path, &["rev-parse", "claude-main"])).is_equal_to(&upstream_head);
+    assert_that(&repo.path.join(".devcontainer/devcontainer.json").exists()).is_true();
+
+    commit_upstream_change(
+        &repo.upstream,
+        ".devcontainer/devcontainer.json",
+        r#"{"name": "Claude Code Sandbox v2"}"#,
+    );
+    run_command(&compiled_binary, &["update"], &repo.path).should_succeed();
+    let json = std::fs::read_to_string(repo.path.join(".devcontainer/devcontainer.json")).unwrap();
+    assert_that(&json.contains("v2")).is_true();
+}
+
+// Timing tests
+
+#[rstest]
+fn should_show_step_timings_in_verbose_output(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+
+    let result = run_command(&compiled_binary, &["init", "--verbose"], &repo.path);
+
+    result
+        .should_succeed()
+        .should_contain_in_stdout("Fetching from Claude Code repository...\n")
+        .should_contain_in_stdout("Total time: ");
+    let timings = result
+        .stdout
+        .lines()
+        .filter(|line| line.trim_start().starts_with("done in ") && line.ends_with('s'))
+        .count();
+    assert_that(&timings).is_greater_than_or_equal_to(7);
+
+    // Compact output stays untimed
+    let remove_result = run_command(&compiled_binary, &["remove"], &repo.path);
+    remove_result
+        .should_succeed()
+        .should_not_contain_in_stdout("done in")
+        .should_not_contain_in_stdout("Total time");
+}
+
+// Remove tests
+
+#[rstest]
+fn should_stage_removal_without_committing_when_no_commit(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+    let head_before = run_git(&repo.path, &["rev-parse", "HEAD"]);
+
+    run_command(&compiled_binary, &["remove", "--no-commit"], &repo.path)
+        .should_succeed()
+        .should_contain_in_stdout("staged but not committed");
+
+    assert_that(&repo.path.join(".devcontainer").exists()).is_false();
+    assert_that(&run_git(&repo.path, &["rev-parse", "HEAD"])).is_equal_to(&head_before);
+    let staged = run_git(&repo.path, &["diff", "--cached", "--name-status"]);
+    assert_that(&staged.contains("D\t.devcontainer/devcontainer.json")).is_true();
+    let log = run_git(&repo.path, &["log", "--format=%s"]);
+    assert_that(&log.contains("Remove devcontainer configuration")).is_false();
+}
+
+#[rstest]
+fn should_succeed_without_a_commit_when_devcontainer_was_already_removed(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+    run_git(&repo.path, &["rm", "-rq", ".devcontainer"]);
+    run_git(&repo.path, &["commit", "-qm", "Drop devcontainer"]);
+    let head_before = run_git(&repo.path, &["rev-parse", "HEAD"]);
+
+    run_command(&compiled_binary, &["remove"], &repo.path)
+        .should_succeed()
+        .should_contain_in_stdout("Nothing to commit");
+
+    assert_that(&run_git(&repo.path, &["rev-parse", "HEAD"])).is_equal_to(&head_before);
+    assert_that(&run_git(&repo.path, &["remote"]).trim().is_empty()).is_true();
+}
+
+#[rstest]
+fn should_leave_unrelated_staged_changes_out_of_the_removal_commit(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+    std::fs::write(repo.path.join("notes.txt"), "work in progress\n").unwrap();
+    run_git(&repo.path, &["add", "notes.txt"]);
+
+    run_command(&compiled_binary, &["remove"], &repo.path).should_succeed();
+
+    let committed = run_git(&repo.path, &["show", "--name-only", "--format=", "HEAD"]);
+    assert_that(&committed.contains(".devcontainer/")).is_true();
+    assert_that(&committed.contains("notes.txt")).is_false();
+    assert_that(&run_git(&repo.path, &["diff", "--cached", "--name-only"]).trim())
+        .is_equal_to("notes.txt");
+}
+
+#[rstest]
+fn should_not_commit_unrelated_staged_changes_when_devcontainer_was_already_removed(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+    run_git(&repo.path, &["rm", "-rq", ".devcontainer"]);
+    run_git(&repo.path, &["commit", "-qm", "Drop devcontainer"]);
+    std::fs::write(repo.path.join("notes.txt"), "work in progress\n").unwrap();
+    run_git(&repo.path, &["add", "notes.txt"]);
+    let head_before = run_git(&repo.path, &["rev-parse", "HEAD"]);
+
+    run_command(&compiled_binary, &["remove"], &repo.path)
+        .should_succeed()
+        .should_contain_in_stdout("Nothing to commit");
+
+    assert_that(&run_git(&repo.path, &["rev-parse", "HEAD"])).is_equal_to(&head_before);
+    assert_that(&run_git(&repo.path, &["diff", "--cached", "--name-only"]).trim())
+        .is_equal_to("notes.txt");
+}
+
+#[rstest]
+fn should_keep_user_scripts_matching_preserve_glob_on_remove(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+    let scripts = repo.path.join(".devcontainer/scripts");
+    std::fs::create_dir_all(&scripts).unwrap();
+    std::fs::write(scripts.join("my-setup.sh"), "#!/bin/sh\necho mine\n").unwrap();
+    std::fs::write(repo.path.join(".devcontainer/other.sh"), "#!/bin/sh\n").unwrap();
+    run_git(&repo.path, &["add", "."]);
+    run_git(&repo.path, &["commit", "-m", "Add my scripts"]);
+
+    run_command(
+        &compiled_binary,
+        &["remove", "--preserve", "my-*.sh"],
+        &repo.path,
+    )
+    .should_succeed()
+    .should_contain_in_stdout("Preserved 1 file(s) in .devcontainer.preserved")
+    .should_contain_in_stdout(".devcontainer.preserved/scripts/my-setup.sh");
+
+    assert_that(&repo.path.join(".devcontainer").exists()).is_false();
+    let preserved = repo
+        .path
+        .join(".devcontainer.preserved/scripts/my-setup.sh");
+    assert_that(&std::fs::read_to_string(preserved).unwrap())
+        .is_equal_to("#!/bin/sh\necho mine\n".to_string());
+    assert_that(&repo.path.join(".devcontainer.preserved/other.sh").exists()).is_false();
+    let tracked = run_git(&repo.path, &["ls-files", ".devcontainer"]);
+    assert_that(&tracked.trim()).is_equal_to("");
+}
+
+#[rstest]
+fn should_reject_invalid_preserve_glob_before_removing_anything(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+
+    run_command(
+        &compiled_binary,
+        &["remove", "--preserve", "[oops"],
+        &repo.path,
+    )
+    .should_fail()
+    .should_contain_in_stderr("Invalid --preserve pattern '[oops'");
+
+    assert_that(&run_git(&repo.path, &["remote"]).trim()).is_equal_to("claude");
+    assert_that(&repo.path.join(".devcontainer").exists()).is_true();
+}
+
+#[rstest]
+fn should_fail_preserve_clash_before_removing_anything(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+    std::fs::write(repo.path.join(".devcontainer/a-mine.sh"), "#!/bin/sh\n").unwrap();
+    std::fs::write(repo.path.join(".devcontainer/b-mine.sh"), "#!/bin/sh\n").unwrap();
+    run_git(&repo.path, &["add", "."]);
+    run_git(&repo.path, &["commit", "-m", "Add my scripts"]);
+    std::fs::create_dir(repo.path.join(".devcontainer.preserved")).unwrap();
+    std::fs::write(
+        repo.path.join(".devcontainer.preserved/b-mine.sh"),
+        "older\n",
+    )
+    .unwrap();
+
+    run_command(
+        &compiled_binary,
+        &["remove", "--preserve", "*-mine.sh"],
+        &repo.path,
+    )
+    .should_fail()
+    .should_contain_in_stderr("already exists");
+
+    assert_that(&run_git(&repo.path, &["remote"]).trim()).is_equal_to("claude");
+    assert_that(&run_git(&repo.path, &["branch", "--list", "claude-main"]).is_empty()).is_false();
+    assert_that(&repo.path.join(".devcontainer/a-mine.sh").exists()).is_true();
+    assert_that(&repo.path.join(".devcontainer.preserved/a-mine.sh").exists()).is_false();
+}
+
+#[rstest]
+fn should_clean_up_leftover_branches_when_remote_is_already_gone(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+    run_git(&repo.path, &["remote", "remove", "claude"]);
+
+    run_command(&compiled_binary, &["remove", "--keep-files"], &repo.path).should_fail();
+
+    run_command(
+        &compiled_binary,
+        &["remove", "--ignore-missing", "--keep-files"],
+        &repo.path,
+    )
+    .should_succeed()
+    .should_contain_in_stdout("No 'claude' remote to remove");
+
+    let branches = run_git(
+        &repo.path,
+        &["branch", "--list", "claude-main", "devcontainer"],
+    );
+    assert_that(&branches.trim()).is_equal_to("");
+    assert_that(&repo.path.join(".devcontainer/devcontainer.json").exists()).is_true();
+
+    // Running it again finds nothing left to clean up and still succeeds
+    run_command(
+        &compiled_binary,
+        &["remove", "--ignore-missing", "--keep-files"],
+        &repo.path,
+    )
+    .should_succeed();
+}
+
+// Standalone strip tests
+
+#[rstest]
+fn should_strip_firewall_offline_without_claude_remote(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, repo_path) = temp_git_repo_with_commits;
+    write_firewall_devcontainer(&repo_path);
+    run_git(&repo_path, &["add", "."]);
+    run_git(&repo_path, &["commit", "-m", "Add hand-made devcontainer"]);
+
+    run_command(&compiled_binary, &["strip-firewall"], &repo_path)
+        .should_succeed()
+        .should_contain_in_stdout("Stripped firewall configurations");
+
+    assert_that(&run_git(&repo_path, &["remote"]).trim()).is_equal_to("");
+    assert_that(&repo_path.join(".devcontainer/init-firewall.sh").exists()).is_false();
+    let dockerfile = std::fs::read_to_string(repo_path.join(".devcontainer/Dockerfile")).unwrap();
+    assert_that(&dockerfile.contains("iptables")).is_false();
+    let status = run_git(&repo_path, &["status", "--porcelain"]);
+    assert_that(&status.trim()).is_equal_to("");
+}
+
+#[rstest]
+fn should_record_strip_commit_in_blame_ignore_revs(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, repo_path) = temp_git_repo_with_commits;
+    write_firewall_devcontainer(&repo_path);
+    run_git(&repo_path, &["add", "."]);
+    run_git(&repo_path, &["commit", "-m", "Add hand-made devcontainer"]);
+
+    run_command(
+        &compiled_binary,
+        &["strip-firewall", "--blame-ignore"],
+        &repo_path,
+    )
+    .should_succeed();
+
+    let strip_sha = run_git(
+        &repo_path,
+        &["log", "-1", "--format=%H", "--grep", "Strip firewall"],
+    );
+    let revs = std::fs::read_to_string(repo_path.join(".git-blame-ignore-revs"))
+        .expect("Failed to read .git-blame-ignore-revs");
+    assert_that(&revs.lines().any(|line| line == strip_sha.trim())).is_true();
+    let status = run_git(&repo_path, &["status", "--porcelain"]);
+    assert_that(&status.trim()).is_equal_to("");
+}
+
+#[rstest]
+fn should_leave_stripped_files_uncommitted_with_no_commit(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, repo_path) = temp_git_repo_with_commits;
+    write_firewall_devcontainer(&repo_path);
+    run_git(&repo_path, &["add", "."]);
+    run_git(&repo_path, &["commit", "-m", "Add hand-made devcontainer"]);
+    let head_before = run_git(&repo_path, &["rev-parse", "HEAD"]);
+
+    run_command(
+        &compiled_binary,
+        &["strip-firewall", "--no-commit"],
+        &repo_path,
+    )
+    .should_succeed();
+
+    assert_that(&run_git(&repo_path, &["rev-parse", "HEAD"])).is_equal_to(&head_before);
+    let status = run_git(&repo_path, &["status", "--porcelain"]);
+    assert_that(&status.contains(".devcontainer/Dockerfile")).is_true();
+}
+
+#[rstest]
+fn should_report_repo_relative_paths_when_stripping_firewall(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, repo_path) = temp_git_repo_with_commits;
+    write_firewall_devcontainer(&repo_path);
+    run_git(&repo_path, &["add", "."]);
+    run_git(&repo_path, &["commit", "-m", "Add hand-made devcontainer"]);
+
+    let result = run_command(
+        &compiled_binary,
+        &["--verbose", "strip-firewall"],
+        &repo_path,
+    );
+
+    result.should_succeed();
+    result.should_contain_in_stdout("Removed .devcontainer/init-firewall.sh");
+    result.should_not_contain_in_stdout(&repo_path.display().to_string());
+}
+
+#[rstest]
+fn should_summarize_preserved_configuration_when_verbose(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, repo_path) = temp_git_repo_with_commits;
+    write_firewall_devcontainer(&repo_path);
+    let json_path = repo_path.join(".devcontainer/devcontainer.json");
+    let json = std::fs::read_to_string(&json_path).unwrap().replace(
+        "\"--cap-add=NET_RAW\"",
+        "\"--cap-add=NET_RAW\",\n    \"--privileged\"",
+    );
+    std::fs::write(&json_path, json).unwrap();
+    run_git(&repo_path, &["add", "."]);
+    run_git(&repo_path, &["commit", "-m", "Add hand-made devcontainer"]);
+
+    let result = run_command(
+        &compiled_binary,
+        &["--verbose", "strip-firewall"],
+        &repo_path,
+    );
+
+    result
+        .should_succeed()
+        .should_contain_in_stdout("Preserved configuration:")
+        .should_contain_in_stdout("  - name: Claude Code Sandbox")
+        .should_contain_in_stdout("  - runArgs: --privileged")
+        .should_contain_in_stdout("  - 1 VS Code extension(s)");
+}
+
+#[rstest]
+fn should_remove_every_script_when_select_scripts_has_no_terminal(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, repo_path) = temp_git_repo_with_commits;
+    write_firewall_devcontainer(&repo_path);
+    std::fs::write(
+        repo_path.join(".devcontainer/egress-firewall.sh"),
+        "iptables -A OUTPUT -j DROP\n",
+    )
+    .unwrap();
+    run_git(&repo_path, &["add", "."]);
+    run_git(&repo_path, &["commit", "-m", "Add hand-made devcontainer"]);
+
+    let result = run_command_with_input(
+        &compiled_binary,
+        &["strip-firewall", "--select-scripts"],
+        &repo_path,
+        "1\n",
+    );
+
+    result.should_succeed();
+    assert_that(&result.stderr.contains("Remove which scripts?")).is_false();
+    assert_that(&repo_path.join(".devcontainer/init-firewall.sh").exists()).is_false();
+    assert_that(&repo_path.join(".devcontainer/egress-firewall.sh").exists()).is_false();
+}
+
+#[rstest]
+fn should_show_strip_changes_as_a_diff_with_preview_diff(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, repo_path) = temp_git_repo_with_commits;
+    write_firewall_devcontainer(&repo_path);
+    run_git(&repo_path, &["add", "."]);
+    run_git(&repo_path, &["commit", "-m", "Add hand-made devcontainer"]);
+
+    let result = run_command(
+        &compiled_binary,
+        &["strip-firewall", "--preview-diff"],
+        &repo_path,
+    );
+
+    // Without a terminal to answer, the diff is shown and the changes are applied
+    result
+        .should_succeed()
+        .should_contain_in_stderr("--- a/.devcontainer/Dockerfile")
+        .should_contain_in_stderr("+++ b/.devcontainer/devcontainer.json")
+        .should_contain_in_stderr("-    \"--cap-add=NET_ADMIN\",")
+        .should_contain_in_stderr("-  iptables")
+        .should_contain_in_stderr("+++ /dev/null");
+    assert_that(&result.stderr.contains("Apply these changes?")).is_false();
+    assert_that(&repo_path.join(".devcontainer/init-firewall.sh").exists()).is_false();
+}
+
+#[rstest]
+fn should_fail_strip_firewall_without_devcontainer(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, repo_path) = temp_git_repo_with_commits;
+
+    run_command(&compiled_binary, &["strip-firewall"], &repo_path)
+        .should_fail()
+        .should_contain_in_stderr("No .devcontainer directory found");
+}
+
+// NDJSON output tests
+
+#[rstest]
+fn should_stream_init_progress_as_ndjson(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+
+    let result = run_command(
+        &compiled_binary,
+        &["init", "--format", "ndjson", "--verbose"],
+        &repo.path,
+    );
+    result.should_succeed();
+
+    let events: Vec<serde_json::Value> = result
+        .stdout
+        .lines()
+        .map(|line| {
+            serde_json::from_str(line)
+                .unwrap_or_else(|e| panic!("Invalid JSON line '{}': {}", line, e))
+        })
+        .collect();
+    let names: Vec<&str> = events
+        .iter()
+        .map(|event| event["event"].as_str().expect("event name"))
+        .collect();
+
+    let mut expected = Vec::new();
+    for step in [
+        "add_remote",
+        "fetch",
+        "create_branch",
+        "checkout_tracking",
+        "split",
+        "checkout_master",
+        "add_subtree",
+        "record_lockfile",
+    ] {
+        expected.push(format!("{}_started", step));
+        expected.push(format!("{}_done", step));
+    }
+    expected.push("result".to_string());
+    assert_that(&names).is_equal_to(expected.iter().map(String::as_str).collect::<Vec<_>>());
+
+    assert_that(&events[1]["duration_ms"].is_u64()).is_true();
+    let result_event = events.last().unwrap();
+    assert_that(&result_event["success"]).is_equal_to(serde_json::json!(true));
+    assert_that(&result_event["command"]).is_equal_to(serde_json::json!("init"));
+}
+
+// Diff tests
+
+#[rstest]
+fn should_diff_devcontainer_between_two_upstream_tags(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_git(&repo.upstream, &["tag", "v1"]);
+    commit_upstream_change(
+        &repo.upstream,
+        ".devcontainer/post-create.sh",
+        "#!/bin/bash\necho ready\n",
+    );
+    run_git(&repo.upstream, &["tag", "-a", "v2", "-m", "Release v2"]);
+
+    let result = run_command(
+        &compiled_binary,
+        &["diff", "--from", "v1", "--to", "v2"],
+        &repo.path,
+    );
+
+    result.should_succeed();
+    result.should_contain_in_stdout("post-create.sh");
+    result.should_contain_in_stdout("+echo ready");
+    result.should_not_contain_in_stdout("Dockerfile");
+
+    let leftover = run_git(&repo.path, &["for-each-ref", "refs/devcontainer-sync"]);
+    assert_that(&leftover.trim()).is_equal_to("");
+    let remotes = run_git(&repo.path, &["remote"]);
+    assert_that(&remotes.trim()).is_equal_to("");
+}
+
+// Upstream layout check tests
+
+#[rstest]
+fn should_report_matching_upstream_layout(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+
+    run_command(&compiled_binary, &["check-upstream"], &repo.path)
+        .should_succeed()
+        .should_contain_in_stdout("Upstream layout matches expectations");
+
+    let temporary_refs = run_git(&repo.path, &["for-each-ref", "refs/devcontainer-sync"]);
+    assert_that(&temporary_refs.trim()).is_equal_to("");
+    assert_that(&repo.path.join(".devcontainer").exists()).is_false();
+}
+
+#[rstest]
+fn should_list_deviations_from_expected_upstream_layout(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_git(
+        &repo.upstream,
+        &[
+            "rm",
+            "-q",
+            ".devcontainer/Dockerfile",
+            ".devcontainer/init-firewall.sh",
+        ],
+    );
+    commit_upstream_change(
+        &repo.upstream,
+        ".devcontainer/devcontainer.json",
+        "{\"name\": \"Claude Code\", \"image\": \"node:20\"}\n",
+    );
+
+    let result = run_command(&compiled_binary, &["check-upstream"], &repo.path);
+
+    result
+        .should_fail()
+        .should_contain_in_stdout("Upstream layout deviates from expectations")
+        .should_contain_in_stdout("- no .devcontainer/Dockerfile")
+        .should_contain_in_stdout("- no firewall script")
+        .should_contain_in_stdout("- devcontainer.json has none of the known firewall settings")
+        .should_contain_in_stderr("Upstream layout has 3 deviation(s)");
+}
+
+// Changelog tests
+
+#[rstest]
+fn should_list_devcontainer_changes_since_release_flagging_firewall(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_git(
+        &repo.upstream,
+        &["tag", "-a", "v1.0.0", "-m", "Release v1.0.0"],
+    );
+    commit_upstream_change(
+        &repo.upstream,
+        ".devcontainer/post-create.sh",
+        "#!/bin/bash\necho ready\n",
+    );
+    commit_upstream_change(
+        &repo.upstream,
+        ".devcontainer/init-firewall.sh",
+        "#!/bin/bash\niptables -A OUTPUT -j DROP\n",
+    );
+    commit_upstream_change(&repo.upstream, "README.md", "# Claude Code v2\n");
+    run_git(&repo.upstream, &["tag", "v1.1.0"]);
+
+    let result = run_command(
+        &compiled_binary,
+        &["changelog", "--since-version", "v1.0.0"],
+        &repo.path,
+    );
+
+    result.should_succeed();
+    result.should_contain_in_stdout("(2 commits)");
+    result.should_not_contain_in_stdout("README.md");
+    let stdout = &result.stdout;
+    let firewall = stdout
+        .find("Affecting firewall")
+        .expect("no firewall section");
+    let other = stdout.find("Other changes").expect("no other section");
+    let firewall_commit = stdout
+        .find("Update .devcontainer/init-firewall.sh")
+        .unwrap();
+    let other_commit = stdout.find("Update .devcontainer/post-create.sh").unwrap();
+    assert_that(&(firewall < firewall_commit && firewall_commit < other)).is_true();
+    assert_that(&(other < other_commit)).is_true();
+}
+
+#[rstest]
+fn should_fail_changelog_for_unknown_release_tag(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+
+    run_command(
+        &compiled_binary,
+        &["changelog", "--since-version", "v9.9.9"],
+        &repo.path,
+    )
+    .should_fail()
+    .should_contain_in_stderr("Release tag 'v9.9.9' not found");
+}
+
+// Pattern listing tests
+
+#[rstest]
+fn should_list_builtin_and_custom_patterns_with_their_sources(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, repo_path) = temp_git_repo_with_commits;
+    std::fs::write(
+        repo_path.join(".devcontainer-sync-patterns"),
+        "# Our egress rules use nftables\nnft add rule\n\n",
+    )
+    .expect("Failed to write patterns file");
+
+    let result = run_command(&compiled_binary, &["list-patterns"], &repo_path);
+
+    result.should_succeed();
+    result.should_contain_in_stdout("built-in  iptables");
+    result.should_contain_in_stdout("config    nft add rule");
+    result.should_not_contain_in_stdout("nftables");
+}
+
+#[rstest]
+fn should_list_patterns_that_came_with_the_synced_devcontainer(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    commit_upstream_change(
+        &repo.upstream,
+        ".devcontainer/.devcontainer-sync-patterns",
+        "# Upstream's egress rules\nufw allow\n",
+    );
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+
+    run_command(&compiled_binary, &["list-patterns"], &repo.path)
+        .should_succeed()
+        .should_contain_in_stdout("built-in  iptables")
+        .should_contain_in_stdout("synced    ufw allow");
+}
+
+// Color tests
+
+#[rstest]
+fn should_color_warnings_only_when_asked_to(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, repo_path) = temp_git_repo_with_commits;
+    write_firewall_devcontainer(&repo_path);
+    std::fs::remove_file(repo_path.join(".devcontainer/Dockerfile")).unwrap();
+    run_git(&repo_path, &["add", "."]);
+    run_git(
+        &repo_path,
+        &["commit", "-m", "Add devcontainer without Dockerfile"],
+    );
+
+    // stdout is a pipe here, so auto leaves color off
+    run_command(
+        &compiled_binary,
+        &["strip-firewall", "--no-commit", "--color", "never"],
+        &repo_path,
+    )
+    .should_succeed()
+    .should_contain_in_stdout("warning: Dockerfile not found")
+    .should_not_contain_in_stdout("\x1b[");
+    run_command(
+        &compiled_binary,
+        &["strip-firewall", "--no-commit"],
+        &repo_path,
+    )
+    .should_succeed()
+    .should_not_contain_in_stdout("\x1b[");
+
+    run_command(
+        &compiled_binary,
+        &["strip-firewall", "--no-commit", "--color", "always"],
+        &repo_path,
+    )
+    .should_succeed()
+    .should_contain_in_stdout("\x1b[33mwarning: Dockerfile not found\x1b[0m");
+}
+
+// Info tests
+
+#[rstest]
+fn should_describe_default_upstream_and_strip_targets(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, repo_path) = temp_git_repo_with_commits;
+
+    let result = run_command(&compiled_binary, &["info"], &repo_path);
+
+    result
+        .should_succeed()
+        .should_contain_in_stdout(
+            "Upstream repository: https://github.com/anthropics/claude-code.git",
+        )
+        .should_contain_in_stdout("Packages: iptables, ipset");
+
+    let result = run_command(&compiled_binary, &["info", "--format", "json"], &repo_path);
+
+    result.should_succeed();
+    let info: serde_json::Value = serde_json::from_str(&result.stdout).unwrap();
+    assert_that(&info["repo_url"].as_str())
+        .is_equal_to(Some("https://github.com/anthropics/claude-code.git"));
+    assert_that(&info["strip_firewall"]["packages"][0].as_str()).is_equal_to(Some("iptables"));
+}
+
+// Subtree message tests
+
+#[rstest]
+fn should_use_custom_subtree_message_for_init(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+
+    run_command(
+        &compiled_binary,
+        &["init", "--subtree-message", "Sync devcontainer for OPS-42"],
+        &repo.path,
+    )
+    .should_succeed();
+
+    // The lockfile commit sits on top of the subtree add's merge commit
+    let subject = run_git(&repo.path, &["log", "-1", "--format=%s", "HEAD~1"]);
+    assert_that(&subject.trim()).is_equal_to("Sync devcontainer for OPS-42");
+    let parents = run_git(&repo.path, &["rev-list", "--parents", "-1", "HEAD~1"]);
+    assert_that(&parents.split_whitespace().count()).is_equal_to(3);
+}
+
+// Dirty devcontainer tests
+
+#[rstest]
+fn should_refuse_update_with_uncommitted_devcontainer_changes(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+    commit_upstream_change(&repo.upstream, ".devcontainer/post-create.sh", "echo hi\n");
+
+    let json_path = repo.path.join(".devcontainer/devcontainer.json");
+    let edited = std::fs::read_to_string(&json_path)
+        .unwrap()
+        .replace("Sandbox", "Local");
+    std::fs::write(&json_path, edited).unwrap();
+    let head_before = run_git(&repo.path, &["rev-parse", "HEAD"]);
+
+    let result = run_command(&compiled_binary, &["update"], &repo.path);
+
+    result.should_fail();
+    result.should_contain_in_stderr(".devcontainer/devcontainer.json");
+    assert_that(&run_git(&repo.path, &["rev-parse", "HEAD"])).is_equal_to(head_before);
+    assert_that(&repo.path.join(".devcontainer/post-create.sh").exists()).is_false();
+}
+
+// JSON output tests
+
+#[rstest]
+fn should_print_single_json_result_for_init_and_remove(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+
+    let result = run_command(
+        &compiled_binary,
+        &["--json", "init", "--strip-firewall"],
+        &repo.path,
+    );
+    result.should_succeed();
+    let init: serde_json::Value =
+        serde_json::from_str(result.stdout.trim()).expect("stdout should be one JSON object");
+    assert_that(&init["command"]).is_equal_to(serde_json::json!("init"));
+    assert_that(&init["success"]).is_equal_to(serde_json::json!(true));
+    let changes = init["changes"].as_array().expect("changes array");
+    assert_that(&changes.iter().any(|change| {
+        change
+            .as_str()
+            .unwrap()
+            .contains("Removed .devcontainer/init-firewall.sh")
+    }))
+    .is_true();
+
+    let result = run_command(&compiled_binary, &["--json", "remove"], &repo.path);
+    result.should_succeed();
+    let remove: serde_json::Value =
+        serde_json::from_str(result.stdout.trim()).expect("stdout should be one JSON object");
+    assert_that(&remove["command"]).is_equal_to(serde_json::json!("remove"));
+    assert_that(&remove["errors"]).is_equal_to(serde_json::json!([]));
+}
+
+#[rstest]
+fn should_print_json_failure_with_errors(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, repo_path) = temp_git_repo_with_commits;
+
+    let result = run_command(&compiled_binary, &["--json", "update"], &repo_path);
+
+    result.should_fail();
+    let update: serde_json::Value =
+        serde_json::from_str(result.stdout.trim()).expect("stdout should be one JSON object");
+    assert_that(&update["success"]).is_equal_to(serde_json::json!(false));
+    assert_that(&update["errors"][0]["exit_code"]).is_equal_to(serde_json::json!(result.exit_code));
+}
+
+// Porcelain output tests
+
+#[rstest]
+fn should_print_stable_porcelain_record_for_init(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+
+    let result = run_command(&compiled_binary, &["init", "--porcelain"], &repo.path);
+
+    result.should_succeed();
+    let split = run_git(&repo.path, &["rev-parse", "devcontainer"]);
+    assert_that(&result.stdout).is_equal_to(format!("INIT OK claude-main {}\n", split.trim()));
+}
+
+#[rstest]
+fn should_print_porcelain_error_record_with_exit_code(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, repo_path) = temp_git_repo_with_commits;
+
+    let result = run_command(&compiled_binary, &["remove", "--porcelain"], &repo_path);
+
+    result.should_fail();
+    assert_that(&result.stdout).is_equal_to(format!("REMOVE ERR {}\n", result.exit_code));
+}
+
+// Narrowed fetch tests
+
+#[rstest]
+fn should_fetch_only_the_synced_branch_unless_fetch_all(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_git(&repo.upstream, &["branch", "experimental"]);
+    run_git(&repo.upstream, &["branch", "release/v1"]);
+
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+
+    let fetched = run_git(
+        &repo.path,
+        &["for-each-ref", "--format=%(refname)", "refs/remotes/claude"],
+    );
+    assert_that(&fetched.trim()).is_equal_to("refs/remotes/claude/main");
+
+    run_command(&compiled_binary, &["update", "--fetch-all"], &repo.path).should_succeed();
+
+    let fetched = run_git(
+        &repo.path,
+        &["for-each-ref", "--format=%(refname)", "refs/remotes/claude"],
+    );
+    assert_that(&fetched).contains("refs/remotes/claude/experimental");
+    assert_that(&fetched).contains("refs/remotes/claude/release/v1");
+}
+
+// Lost subtree base tests
+
+#[rstest]
+fn should_reinit_subtree_after_history_rewrite(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+
+    // Collapse history into a single root commit, dropping the subtree's squash commits
+    run_git(&repo.path, &["checkout", "-q", "--orphan", "rewritten"]);
+    run_git(&repo.path, &["commit", "-q", "-m", "Rewritten history"]);
+    run_git(&repo.path, &["branch", "-M", "rewritten", "master"]);
+    commit_upstream_change(&repo.upstream, ".devcontainer/post-create.sh", "echo hi\n");
+
+    let result = run_command(&compiled_binary, &["update"], &repo.path);
+    result.should_fail();
+    result.should_contain_in_stderr("no longer in this repository's history");
+
+    run_command(
+        &compiled_binary,
+        &["update", "--reinit-subtree"],
+        &repo.path,
+    )
+    .should_succeed();
+    assert_that(&repo.path.join(".devcontainer/post-create.sh").exists()).is_true();
+    let status = run_git(&repo.path, &["status", "--porcelain"]);
+    assert_that(&status.trim()).is_equal_to("");
+
+    // The re-added subtree merges normally again
+    commit_upstream_change(&repo.upstream, ".devcontainer/post-start.sh", "echo up\n");
+    run_command(&compiled_binary, &["update"], &repo.path).should_succeed();
+    assert_that(&repo.path.join(".devcontainer/post-start.sh").exists()).is_true();
+}
+
+// Backup tests
+
+#[rstest]
+fn should_fail_backup_before_changing_repository_when_path_is_a_file(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+    commit_upstream_change(&repo.upstream, ".devcontainer/post-create.sh", "echo hi\n");
+    std::fs::write(repo.path.join(".devcontainer.backup"), "blocking file")
+        .expect("Failed to create blocking file");
+    let head_before = run_git(&repo.path, &["rev-parse", "HEAD", "claude-main"]);
+
+    let result = run_command(
+        &compiled_binary,
+        &["update", "--backup", "--allow-dirty"],
+        &repo.path,
+    );
+
+    result.should_fail();
+    result.should_contain_in_stderr(".devcontainer.backup exists and is not a directory");
+    assert_that(&run_git(&repo.path, &["rev-parse", "HEAD", "claude-main"]))
+        .is_equal_to(head_before);
+}
+
+#[rstest]
+fn should_back_up_devcontainer_before_update(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+    commit_upstream_change(&repo.upstream, ".devcontainer/post-create.sh", "echo hi\n");
+
+    run_command(&compiled_binary, &["update", "--backup"], &repo.path).should_succeed();
+
+    let backup = repo.path.join(".devcontainer.backup");
+    assert_that(&backup.join("devcontainer.json").exists()).is_true();
+    assert_that(&backup.join("post-create.sh").exists()).is_false();
+    assert_that(&repo.path.join(".devcontainer/post-create.sh").exists()).is_true();
+}
+
+#[rstest]
+fn should_update_again_after_a_backup_was_left_behind(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+    commit_upstream_change(&repo.upstream, ".devcontainer/post-create.sh", "echo hi\n");
+    run_command(&compiled_binary, &["update", "--backup"], &repo.path).should_succeed();
+
+    // The untracked backup directory doesn't make the working tree dirty
+    commit_upstream_change(&repo.upstream, ".devcontainer/post-create.sh", "echo bye\n");
+    run_command(&compiled_binary, &["update", "--backup"], &repo.path).should_succeed();
+    run_command(&compiled_binary, &["update"], &repo.path).should_succeed();
+
+    let backup = repo.path.join(".devcontainer.backup");
+    assert_that(&std::fs::read_to_string(backup.join("post-create.sh")).unwrap())
+        .is_equal_to("echo hi\n".to_string());
+}
+
+// Status tests
+
+#[rstest]
+fn should_report_not_initialized_status_successfully(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, repo_path) = temp_git_repo_with_commits;
+
+    run_command(&compiled_binary, &["status"], &repo_path)
+        .should_succeed()
+        .should_contain_in_stdout("not initialized")
+        .should_contain_in_stdout("'claude' remote: missing");
+}
+
+#[rstest]
+fn should_report_upstream_commits_since_last_sync(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+
+    run_command(&compiled_binary, &["status"], &repo.path)
+        .should_succeed()
+        .should_contain_in_stdout("Up to date with upstream");
+
+    commit_upstream_change(&repo.upstream, ".devcontainer/post-create.sh", "echo hi\n");
+    commit_upstream_change(&repo.upstream, "README.md", "# Claude Code v2\n");
+
+    run_command(&compiled_binary, &["status"], &repo.path)
+        .should_succeed()
+        .should_contain_in_stdout("2 upstream commits since the last sync");
+    run_command(&compiled_binary, &["--porcelain", "status"], &repo.path)
+        .should_succeed()
+        .should_contain_in_stdout("STATUS OK initialized 2");
+}
+
+// Ensure tests
+
+#[rstest]
+fn should_init_once_and_then_leave_an_up_to_date_repository_alone(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+
+    run_command(&compiled_binary, &["ensure"], &repo.path)
+        .should_succeed()
+        .should_contain_in_stdout("not initialized; running init");
+    assert_that(&repo.path.join(".devcontainer/devcontainer.json").exists()).is_true();
+    let head = run_git(&repo.path, &["rev-parse", "HEAD"]);
+
+    run_command(&compiled_binary, &["ensure"], &repo.path)
+        .should_succeed()
+        .should_contain_in_stdout("Already up to date; nothing to do");
+    assert_that(&run_git(&repo.path, &["rev-parse", "HEAD"])).is_equal_to(head);
+
+    commit_upstream_change(&repo.upstream, ".devcontainer/post-create.sh", "echo hi\n");
+    run_command(&compiled_binary, &["ensure"], &repo.path)
+        .should_succeed()
+        .should_contain_in_stdout("1 upstream commit(s) since the last sync; running update");
+    assert_that(&repo.path.join(".devcontainer/post-create.sh").exists()).is_true();
+}
+
+// Remote name tests
+
+#[rstest]
+fn should_use_custom_remote_name_for_init_update_and_remove(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+
+    run_command(
+        &compiled_binary,
+        &["init", "--remote-name", "claude-code-upstream"],
+        &repo.path,
+    )
+    .should_succeed()
+    .should_contain_in_stdout("Added 'claude-code-upstream' remote");
+    assert_that(&run_git(&repo.path, &["remote"]).trim()).is_equal_to("claude-code-upstream");
+
+    commit_upstream_change(&repo.upstream, ".devcontainer/post-create.sh", "echo hi\n");
+    run_command(
+        &compiled_binary,
+        &["update", "--remote-name", "claude-code-upstream"],
+        &repo.path,
+    )
+    .should_succeed();
+    assert_that(&repo.path.join(".devcontainer/post-create.sh").exists()).is_true();
+
+    run_command(
+        &compiled_binary,
+        &["remove", "--remote-name", "claude-code-upstream"],
+        &repo.path,
+    )
+    .should_succeed();
+    assert_that(&run_git(&repo.path, &["remote"]).trim()).is_equal_to("");
+}
+
+// Config file tests
+
+#[rstest]
+fn should_read_settings_from_config_file_and_let_flags_override_them(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    std::fs::write(
+        repo.path.join(".devcontainer-sync.toml"),
+        "remote_name = \"upstream\"\ntracking_branch = \"upstream-main\"\n",
+    )
+    .unwrap();
+    run_git(&repo.path, &["add", ".devcontainer-sync.toml"]);
+    run_git(&repo.path, &["commit", "-m", "Add sync settings"]);
+
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+    assert_that(&run_git(&repo.path, &["remote"]).trim()).is_equal_to("upstream");
+    run_git(&repo.path, &["rev-parse", "--verify", "upstream-main"]);
+
+    run_command(
+        &compiled_binary,
+        &["explain", "init", "--remote-name", "other"],
+        &repo.path,
+    )
+    .should_succeed()
+    .should_contain_in_stdout("git remote add other")
+    .should_contain_in_stdout("git branch -f upstream-main");
+}
+
+#[rstest]
+fn should_reject_malformed_config_file(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, repo_path) = temp_git_repo_with_commits;
+    std::fs::write(
+        repo_path.join(".devcontainer-sync.toml"),
+        "remote_name = upstream\n",
+    )
+    .unwrap();
+
+    run_command(&compiled_binary, &["status"], &repo_path)
+        .should_fail()
+        .should_contain_in_stderr("Invalid .devcontainer-sync.toml")
+        .should_contain_in_stderr("remote_name = upstream");
+}
+
+// Restore tests
+
+#[rstest]
+fn should_restore_devcontainer_from_backup(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+    commit_upstream_change(&repo.upstream, ".devcontainer/post-create.sh", "echo hi\n");
+    run_command(&compiled_binary, &["update", "--backup"], &repo.path).should_succeed();
+
+    let result = run_command(&compiled_binary, &["restore"], &repo.path);
+
+    result.should_succeed();
+    result.should_contain_in_stdout("Restored .devcontainer");
+    assert_that(&repo.path.join(".devcontainer/post-create.sh").exists()).is_false();
+    assert_that(&repo.path.join(".devcontainer.backup").exists()).is_false();
+    let staged = run_git(&repo.path, &["diff", "--cached", "--name-status"]);
+    assert_that(&staged.contains(".devcontainer/post-create.sh")).is_true();
+}
+
+#[rstest]
+fn should_keep_backup_when_restoring_with_keep_backup(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+    commit_upstream_change(&repo.upstream, ".devcontainer/post-create.sh", "echo hi\n");
+    run_command(&compiled_binary, &["update", "--backup"], &repo.path).should_succeed();
+
+    run_command(&compiled_binary, &["restore", "--keep-backup"], &repo.path).should_succeed();
+
+    assert_that(&repo.path.join(".devcontainer/post-create.sh").exists()).is_false();
+    assert_that(
+        &repo
+            .path
+            .join(".devcontainer.backup/devcontainer.json")
+            .exists(),
+    )
+    .is_true();
+}
+
+#[rstest]
+fn should_fail_restore_without_backup(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, repo_path) = temp_git_repo_with_commits;
+
+    let result = run_command(&compiled_binary, &["--verbose", "restore"], &repo_path);
+
+    result.should_fail();
+    result.should_contain_in_stderr("No backup found");
+    result.should_contain_in_stderr("update --backup");
+}
+
+#[rstest]
+fn should_not_leave_empty_merge_commit_when_upstream_change_is_a_no_op(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+    let head_after_init = run_git(&repo.path, &["rev-parse", "HEAD"]);
+    let original = std::fs::read_to_string(repo.upstream.join(".devcontainer/devcontainer.json"))
+        .expect("Failed to read upstream devcontainer.json");
+    commit_upstream_change(&repo.upstream, ".devcontainer/devcontainer.json", "{}\n");
+    commit_upstream_change(&repo.upstream, ".devcontainer/devcontainer.json", &original);
+
+    run_command(&compiled_binary, &["update"], &repo.path).should_succeed();
+
+    let merges = run_git(
+        &repo.path,
+        &[
+            "rev-list",
+            "--merges",
+            &format!("{}..HEAD", head_after_init.trim()),
+        ],
+    );
+    assert_that(&merges.as_str()).is_equal_to("");
+    assert_that(&run_git(&repo.path, &["status", "--porcelain"]).as_str()).is_equal_to("");
+}
+
+// Interruption tests
+
+#[cfg(unix)]
+#[rstest]
+fn should_kill_git_and_restore_branch_on_sigint(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::{Duration, Instant};
+
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+    commit_upstream_change(&repo.upstream, ".devcontainer/post-create.sh", "echo hi\n");
+
+    // A git that hangs in subtree split, which update runs on the tracking branch
+    let scripts = TempDir::new().expect("Failed to create temp directory");
+    let pid_file = scripts.path().join("split.pid");
+    let fake_git = scripts.path().join("git");
+    std::fs::write(
+        &fake_git,
+        format!(
+            "#!/bin/sh\nif [ \"$1\" = subtree ] && [ \"$2\" = split ]; then\n  echo $$ > '{}'\n  exec sleep 20\nfi\nexec git \"$@\"\n",
+            pid_file.display()
+        ),
+    )
+    .expect("Failed to write fake git");
+    std::fs::set_permissions(&fake_git, std::fs::Permissions::from_mode(0o755))
+        .expect("Failed to make fake git executable");
+
+    let child = Command::new(&compiled_binary)
+        .args(["update", "--git-path", fake_git.to_str().unwrap()])
+        .current_dir(&repo.path)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to start update");
+
+    let deadline = Instant::now() + Duration::from_secs(15);
+    let split_pid = loop {
+        if let Ok(pid) = std::fs::read_to_string(&pid_file) {
+            if !pid.trim().is_empty() {
+                break pid.trim().to_string();
+            }
+        }
+        assert!(Instant::now() < deadline, "subtree split never started");
+        std::thread::sleep(Duration::from_millis(20));
+    };
+    assert_that(&run_git(&repo.path, &["branch", "--show-current"]).trim())
+        .is_equal_to("claude-main");
+
+    let killed = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .expect("Failed to send SIGINT");
+    assert!(killed.success());
+    let output = child.wait_with_output().expect("Failed to wait for update");
+
+    assert_that(&output.status.code()).is_equal_to(Some(130));
+    assert_that(&String::from_utf8_lossy(&output.stderr).contains("interrupted")).is_true();
+    let alive = Command::new("kill")
+        .args(["-0", &split_pid])
+        .stderr(std::process::Stdio::null())
+        .status()
+        .expect("Failed to probe split process");
+    assert_that(&alive.success()).is_false();
+    assert_that(&run_git(&repo.path, &["branch", "--show-current"]).trim()).is_equal_to("master");
+}
+
+// Incremental strip tests
+
+#[rstest]
+fn should_strip_only_files_changed_by_update_with_incremental_strip(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_command(&compiled_binary, &["init", "--strip-firewall"], &repo.path).should_succeed();
+
+    // A deliberate local change that a full re-strip would undo
+    let json = "{\n  \"runArgs\": [\"--cap-add=NET_ADMIN\"]\n}\n";
+    std::fs::write(repo.path.join(".devcontainer/devcontainer.json"), json)
+        .expect("Failed to write devcontainer.json");
+    run_git(
+        &repo.path,
+        &["commit", "-am", "Keep NET_ADMIN for local debugging"],
+    );
+
+    commit_upstream_change(
+        &repo.upstream,
+        ".devcontainer/egress-firewall.sh",
+        "#!/bin/bash\niptables -A OUTPUT -j DROP\n",
+    );
+
+    run_command(
+        &compiled_binary,
+        &["update", "--strip-firewall", "--incremental-strip"],
+        &repo.path,
+    )
+    .should_succeed();
+
+    assert_that(&repo.path.join(".devcontainer/egress-firewall.sh").exists()).is_false();
+    let kept = std::fs::read_to_string(repo.path.join(".devcontainer/devcontainer.json"))
+        .expect("Failed to read devcontainer.json");
+    assert_that(&kept.as_str()).is_equal_to(json);
+    let strip_commit = run_git(
+        &repo.path,
+        &[
+            "log",
+            "-1",
+            "--format=",
+            "--name-only",
+            "--grep=Strip firewall configurations from updated devcontainer",
+        ],
+    );
+    assert_that(&strip_commit.trim()).is_equal_to(".devcontainer/egress-firewall.sh");
+}
+
+// Batch tests
+
+#[rstest]
+fn should_run_batch_in_every_manifest_repo_and_tally_failures(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    let (_broken_dir, broken) = temp_git_repo_with_commits;
+    // The broken repo's upstream can't be fetched, so init fails there
+    redirect_upstream(
+        &broken,
+        "https://github.com/anthropics/claude-code.git",
+        &broken.join("missing-upstream"),
+    );
+    let manifest_dir = TempDir::new().unwrap();
+    let manifest = manifest_dir.path().join("repos.txt");
+    std::fs::write(
+        &manifest,
+        format!("{}\n{}\n", broken.display(), repo.path.display()),
+    )
+    .unwrap();
+
+    let result = run_command(
+        &compiled_binary,
+        &["batch", "init", "--manifest", manifest.to_str().unwrap()],
+        manifest_dir.path(),
+    );
+
+    result
+        .should_fail()
+        .should_contain_in_stdout(&format!("==> {}", broken.display()))
+        .should_contain_in_stdout(&format!("==> {}", repo.path.display()))
+        .should_contain_in_stdout("Batch init: 1 succeeded, 1 failed")
+        .should_contain_in_stdout(&format!("  ❌ {}: ", broken.display()))
+        .should_contain_in_stdout(&format!("  ✅ {}", repo.path.display()))
+        .should_contain_in_stderr(&format!("Error in {}", broken.display()));
+    assert_that(&repo.path.join(".devcontainer/devcontainer.json").exists()).is_true();
+    assert_that(&broken.join(".devcontainer").exists()).is_false();
+}
+
+#[rstest]
+fn should_apply_each_repo_config_and_report_batch_results_as_json(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    std::fs::write(
+        repo.path.join(".devcontainer-sync.toml"),
+        "prefix = \"tools/devcontainer\"\n",
+    )
+    .unwrap();
+    run_git(&repo.path, &["add", ".devcontainer-sync.toml"]);
+    run_git(&repo.path, &["commit", "-m", "Add sync settings"]);
+    let manifest_dir = TempDir::new().unwrap();
+    // The directory batch runs from has its own config, which must not reach the repos
+    std::fs::write(
+        manifest_dir.path().join(".devcontainer-sync.toml"),
+        "prefix = \"elsewhere\"\n",
+    )
+    .unwrap();
+    let manifest = manifest_dir.path().join("repos.txt");
+    std::fs::write(&manifest, format!("{}\n", repo.path.display())).unwrap();
+
+    let result = run_command(
+        &compiled_binary,
+        &[
+            "--format",
+            "json",
+            "batch",
+            "init",
+            "--manifest",
+            manifest.to_str().unwrap(),
+        ],
+        manifest_dir.path(),
+    );
+
+    result.should_succeed();
+    let summary: serde_json::Value = serde_json::from_str(result.stdout.trim()).unwrap();
+    assert_that(&summary["command"]).is_equal_to(serde_json::json!("batch"));
+    assert_that(&summary["success"]).is_equal_to(serde_json::json!(true));
+    assert_that(&summary["repos"][0]["path"])
+        .is_equal_to(serde_json::json!(repo.path.display().to_string()));
+    assert_that(&summary["repos"][0]["success"]).is_equal_to(serde_json::json!(true));
+    assert_that(
+        &repo
+            .path
+            .join("tools/devcontainer/devcontainer.json")
+            .exists(),
+    )
+    .is_true();
+    assert_that(&repo.path.join(".devcontainer").exists()).is_false();
+}
+
+#[rstest]
+fn should_reject_strip_firewall_with_batch_remove(compiled_binary: PathBuf) {
+    let manifest_dir = TempDir::new().unwrap();
+    let manifest = manifest_dir.path().join("repos.txt");
+    std::fs::write(&manifest, "service-a\n").unwrap();
+
+    run_command(
+        &compiled_binary,
+        &[
+            "batch",
+            "remove",
+            "--strip-firewall",
+            "--manifest",
+            manifest.to_str().unwrap(),
+        ],
+        manifest_dir.path(),
+    )
+    .should_fail()
+    .should_contain_in_stderr("--strip-firewall can't be used with 'batch remove'");
+}
+
+// Merge conflict tests
+
+#[rstest]
+fn should_exit_with_conflict_code_when_update_conflicts(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+
+    std::fs::write(
+        repo.path.join(".devcontainer/devcontainer.json"),
+        r#"{"name": "Our Sandbox"}"#,
+    )
+    .unwrap();
+    run_git(&repo.path, &["commit", "-am", "Rename sandbox"]);
+    commit_upstream_change(
+        &repo.upstream,
+        ".devcontainer/devcontainer.json",
+        r#"{"name": "Claude Code Sandbox v2"}"#,
+    );
+
+    let result = run_command(&compiled_binary, &["update"], &repo.path);
+
+    result
+        .should_fail()
+        .should_contain_in_stderr("Merge conflict: Upstream changes to .devcontainer conflict");
+    assert_that(&result.exit_code).is_equal_to(5);
+    let status = run_git(&repo.path, &["status", "--porcelain"]);
+    assert_that(&status.trim()).is_equal_to("");
+}
+
+// Update check tests
+
+#[rstest]
+fn should_report_pending_upstream_commits_on_update_check(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+
+    run_command(&compiled_binary, &["update", "--check"], &repo.path)
+        .should_succeed()
+        .should_contain_in_stdout("Up to date with upstream");
+
+    commit_upstream_change(
+        &repo.upstream,
+        ".devcontainer/devcontainer.json",
+        r#"{"name": "Claude Code Sandbox v2"}"#,
+    );
+    let head_before = run_git(&repo.path, &["rev-parse", "HEAD"]);
+
+    let result = run_command(&compiled_binary, &["update", "--check"], &repo.path);
+
+    result
+        .should_fail()
+        .should_contain_in_stdout("1 upstream commit since the last sync");
+    assert_that(&result.exit_code).is_equal_to(10);
+    assert_that(&result.stderr.contains("Error:")).is_false();
+
+    let result = run_command(
+        &compiled_binary,
+        &["--format", "json", "update", "--check"],
+        &repo.path,
+    );
+    assert_that(&result.exit_code).is_equal_to(10);
+    let report: serde_json::Value = serde_json::from_str(result.stdout.trim()).unwrap();
+    assert_that(&report["success"]).is_equal_to(serde_json::json!(true));
+    assert_that(&report["upstream_commits"]).is_equal_to(serde_json::json!(1));
+    assert_that(&run_git(&repo.path, &["rev-parse", "HEAD"])).is_equal_to(head_before);
+}
+
+// Dry run tests
+
+#[rstest]
+fn should_print_planned_strip_commit_on_init_dry_run(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    let head_before = run_git(&repo.path, &["rev-parse", "HEAD"]);
+
+    let result = run_command(
+        &compiled_binary,
+        &["init", "--dry-run", "--strip-firewall"],
+        &repo.path,
+    );
+
+    result
+        .should_succeed()
+        .should_contain_in_stdout("Would run:")
+        .should_contain_in_stdout("git subtree add --prefix=.devcontainer --squash devcontainer")
+        .should_contain_in_stdout("  .devcontainer/Dockerfile")
+        .should_contain_in_stdout(
+            "Would commit:\n  Strip firewall configurations from devcontainer\n\n  Changes made:\n  - ",
+        );
+    assert_that(&run_git(&repo.path, &["rev-parse", "HEAD"])).is_equal_to(&head_before);
+    assert_that(&run_git(&repo.path, &["remote"]).trim().is_empty()).is_true();
+    assert_that(&run_git(&repo.path, &["for-each-ref"]).contains("devcontainer-sync")).is_false();
+    assert_that(&repo.path.join(".devcontainer").exists()).is_false();
+}
+
+#[rstest]
+fn should_leave_branches_and_remotes_alone_on_update_and_remove_dry_run(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+    commit_upstream_change(
+        &repo.upstream,
+        ".devcontainer/devcontainer.json",
+        r#"{"name": "Claude Code Sandbox v2"}"#,
+    );
+    let refs_before = run_git(&repo.path, &["for-each-ref"]);
+
+    run_command(&compiled_binary, &["update", "--dry-run"], &repo.path)
+        .should_succeed()
+        .should_contain_in_stdout("Dry run: update would merge 1 upstream commit(s)")
+        .should_contain_in_stdout(
+            "  git subtree merge --prefix=.devcontainer --squash devcontainer-updated",
+        );
+    run_command(&compiled_binary, &["remove", "--dry-run"], &repo.path)
+        .should_succeed()
+        .should_contain_in_stdout("  git remote remove claude")
+        .should_contain_in_stdout("  git branch -D claude-main")
+        .should_contain_in_stdout("  rm -rf .devcontainer");
+
+    assert_that(&run_git(&repo.path, &["for-each-ref"])).is_equal_to(&refs_before);
+    assert_that(&run_git(&repo.path, &["remote"]).trim()).is_equal_to("claude");
+    let devcontainer =
+        std::fs::read_to_string(repo.path.join(".devcontainer/devcontainer.json")).unwrap();
+    assert_that(&devcontainer.contains("v2")).is_false();
+}
+
+#[rstest]
+fn should_plan_backup_autostash_and_locked_reset_on_update_dry_run(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+    std::fs::write(repo.path.join("notes.txt"), "work in progress\n").unwrap();
+    let refs_before = run_git(&repo.path, &["for-each-ref"]);
+
+    run_command(
+        &compiled_binary,
+        &["update", "--dry-run", "--backup", "--autostash", "--locked"],
+        &repo.path,
+    )
+    .should_succeed()
+    .should_contain_in_stdout("Dry run: update would merge 0 upstream commit(s)")
+    .should_contain_in_stdout("(locked revision ")
+    .should_contain_in_stdout(
+        "  git stash push --include-untracked -m 'devcontainer-sync autostash'",
+    )
+    .should_contain_in_stdout("  cp -R .devcontainer .devcontainer.backup")
+    .should_contain_in_stdout("  git stash pop");
+
+    assert_that(&run_git(&repo.path, &["for-each-ref"])).is_equal_to(&refs_before);
+    assert_that(&run_git(&repo.path, &["stash", "list"]).trim().is_empty()).is_true();
+    assert_that(&repo.path.join(".devcontainer.backup").exists()).is_false();
+    assert_that(&repo.path.join("notes.txt").exists()).is_true();
+}
+
+#[rstest]
+fn should_print_planned_strip_commit_on_update_dry_run(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+    let head_before = run_git(&repo.path, &["rev-parse", "HEAD"]);
+
+    run_command(
+        &compiled_binary,
+        &["update", "--dry-run", "--strip-firewall"],
+        &repo.path,
+    )
+    .should_succeed()
+    .should_contain_in_stdout("  .devcontainer/Dockerfile")
+    .should_contain_in_stdout(
+        "Would commit:\n  Strip firewall configurations from updated devcontainer\n\n  Changes made:\n  - ",
+    );
+
+    assert_that(&run_git(&repo.path, &["rev-parse", "HEAD"])).is_equal_to(&head_before);
+    assert_that(
+        &run_git(&repo.path, &["status", "--porcelain"])
+            .trim()
+            .is_empty(),
+    )
+    .is_true();
+}
+
+#[rstest]
+fn should_plan_preserved_files_on_remove_dry_run(
+    temp_git_repo_with_fixture_upstream: FixtureRepo,
+    compiled_binary: PathBuf,
+) {
+    let repo = temp_git_repo_with_fixture_upstream;
+    run_command(&compiled_binary, &["init"], &repo.path).should_succeed();
+
+    run_command(
+        &compiled_binary,
+        &["remove", "--dry-run", "--preserve", "devcontainer.json"],
+        &repo.path,
+    )
+    .should_succeed()
+    .should_contain_in_stdout(
+        "  mv .devcontainer/devcontainer.json .devcontainer.preserved/devcontainer.json",
+    )
+    .should_contain_in_stdout(
+        "  git commit -m 'Remove devcontainer configuration' -- .devcontainer",
+    );
+
+    assert_that(&repo.path.join(".devcontainer/devcontainer.json").exists()).is_true();
+    assert_that(&repo.path.join(".devcontainer.preserved").exists()).is_false();
+}
+
+#[rstest]
+fn should_fail_update_dry_run_without_remote(
+    temp_git_repo_with_commits: (TempDir, PathBuf),
+    compiled_binary: PathBuf,
+) {
+    let (_temp_dir, repo_path) = temp_git_repo_with_commits;
+
+    run_command(&compiled_binary, &["update", "--dry-run"], &repo_path)
+        .should_fail()
+        .should_contain_in_stderr("Remote 'claude' does not exist");
+    run_command(&compiled_binary, &["remove", "--dry-run"], &repo_path)
+        .should_fail()
+        .should_contain_in_stderr("Remote 'claude' does not exist");
+}